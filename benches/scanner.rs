@@ -0,0 +1,39 @@
+use std::sync::Arc;
+
+use criterion::criterion_group;
+use criterion::criterion_main;
+use criterion::BatchSize;
+use criterion::Criterion;
+use criterion::Throughput;
+use flexi_parse::SourceFile;
+use flexi_parse::TokenStream;
+
+const SNIPPET: &str = "fn add_one(value: u32) -> u32 {\n\tlet result = value + 1;\n  \
+                       return result; // 'done' \"ok\"\n}\n\n";
+
+fn source(len: usize) -> String {
+    SNIPPET.repeat(len / SNIPPET.len() + 1)
+}
+
+fn scan(c: &mut Criterion) {
+    let mut group = c.benchmark_group("scan");
+    for megabytes in [1, 4] {
+        let contents = source(megabytes * 1024 * 1024);
+        group.throughput(Throughput::Bytes(contents.len() as u64));
+        group.bench_function(format!("{megabytes}MB"), |b| {
+            b.iter_batched(
+                || Arc::new(SourceFile::new("bench".to_string(), contents.clone())),
+                |source| TokenStream::try_from(source).unwrap(),
+                BatchSize::LargeInput,
+            );
+        });
+    }
+    group.finish();
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(10);
+    targets = scan
+}
+criterion_main!(benches);
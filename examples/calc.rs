@@ -1,3 +1,5 @@
+use flexi_parse::expr::Associativity;
+use flexi_parse::expr::ExprParser;
 use flexi_parse::group::Group;
 use flexi_parse::group::Parentheses;
 use flexi_parse::parse;
@@ -37,45 +39,24 @@ impl Expr {
 
 impl Parse for Expr {
     fn parse(input: ParseStream<'_>) -> Result<Self> {
-        addition(input)
-    }
-}
-
-fn addition(input: ParseStream<'_>) -> Result<Expr> {
-    let mut expr = factor(input)?;
-    loop {
-        if input.parse::<Option<Punct!["+"]>>()?.is_some() {
-            expr = Expr::Add(Box::new(expr), Box::new(factor(input)?));
-        } else if input.parse::<Option<Punct!["-"]>>()?.is_some() {
-            expr = Expr::Sub(Box::new(expr), Box::new(factor(input)?));
-        } else {
-            break;
-        }
-    }
-    Ok(expr)
-}
-
-fn factor(input: ParseStream<'_>) -> Result<Expr> {
-    let mut expr: Expr = unary(input)?;
-    loop {
-        if input.parse::<Option<Punct!["*"]>>()?.is_some() {
-            expr = Expr::Mul(Box::new(expr), Box::new(unary(input)?));
-        } else if input.parse::<Option<Punct!["/"]>>()?.is_some() {
-            expr = Expr::Div(Box::new(expr), Box::new(unary(input)?));
-        } else if input.parse::<Option<Punct!["%"]>>()?.is_some() {
-            expr = Expr::Mod(Box::new(expr), Box::new(unary(input)?));
-        } else {
-            break;
-        }
-    }
-    Ok(expr)
-}
-
-fn unary(input: ParseStream<'_>) -> Result<Expr> {
-    if input.parse::<Option<Punct!["-"]>>()?.is_some() {
-        Ok(Expr::Neg(Box::new(unary(input)?)))
-    } else {
-        primary(input)
+        ExprParser::new(primary)
+            .infix(1, Associativity::Left, |l, _: Punct!["+"], r| {
+                Expr::Add(Box::new(l), Box::new(r))
+            })
+            .infix(1, Associativity::Left, |l, _: Punct!["-"], r| {
+                Expr::Sub(Box::new(l), Box::new(r))
+            })
+            .infix(2, Associativity::Left, |l, _: Punct!["*"], r| {
+                Expr::Mul(Box::new(l), Box::new(r))
+            })
+            .infix(2, Associativity::Left, |l, _: Punct!["/"], r| {
+                Expr::Div(Box::new(l), Box::new(r))
+            })
+            .infix(2, Associativity::Left, |l, _: Punct!["%"], r| {
+                Expr::Mod(Box::new(l), Box::new(r))
+            })
+            .prefix(3, |_: Punct!["-"], expr| Expr::Neg(Box::new(expr)))
+            .parse(input)
     }
 }
 
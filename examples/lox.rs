@@ -1,12 +1,14 @@
 use flexi_parse::group::Braces;
 use flexi_parse::group::Group;
 use flexi_parse::group::Parentheses;
+use flexi_parse::group::Parenthesized;
 use flexi_parse::parse;
 use flexi_parse::parse_repeated;
 use flexi_parse::parse_string;
-use flexi_parse::peek2_any;
 use flexi_parse::pretty_unwrap;
 use flexi_parse::punctuated::Punctuated;
+use flexi_parse::punctuated::Terminated;
+use flexi_parse::recover::Recover;
 use flexi_parse::token::Ident;
 use flexi_parse::token::LitFloat;
 use flexi_parse::token::LitInt;
@@ -21,8 +23,8 @@ mod kw {
     use flexi_parse::keywords_prefixed;
 
     keywords_prefixed![
-        "and", "class", "else", "false", "for", "fun", "if", "nil", "or", "print", "return",
-        "super", "this", "true", "var", "while"
+        "and", "else", "false", "nil", "or", "super", "this", "true",
+        statement: ["class", "for", "fun", "if", "print", "return", "var", "while"],
     ];
 }
 
@@ -521,8 +523,7 @@ impl Stmt {
 
     fn if_statement(input: ParseStream<'_>) -> Result<Self> {
         let _: kw::keyword_if = input.parse()?;
-        let condition: Group<Parentheses> = input.parse()?;
-        let condition = parse(condition.into_token_stream())?;
+        let condition = input.parse::<Parenthesized<Expr>>()?.into_value();
 
         let then_branch = Box::new(Stmt::statement(input)?);
         let else_branch = if input.peek(kw::keyword_else) {
@@ -540,9 +541,8 @@ impl Stmt {
 
     fn print_statement(input: ParseStream<'_>) -> Result<Self> {
         let _: kw::keyword_print = input.parse()?;
-        let value = input.parse()?;
-        let _: Punct![";"] = input.parse()?;
-        Ok(Self::Print(value))
+        let value: Terminated<Expr, Punct![";"]> = input.parse()?;
+        Ok(Self::Print(value.into_value()))
     }
 
     fn return_statement(input: ParseStream<'_>) -> Result<Self> {
@@ -558,17 +558,15 @@ impl Stmt {
 
     fn while_statement(input: ParseStream<'_>) -> Result<Self> {
         let _: kw::keyword_while = input.parse()?;
-        let condition: Group<Parentheses> = input.parse()?;
-        let condition = parse(condition.into_token_stream())?;
+        let condition = input.parse::<Parenthesized<Expr>>()?.into_value();
         let body = Box::new(Stmt::statement(input)?);
 
         Ok(Stmt::While { condition, body })
     }
 
     fn expression_statement(input: ParseStream<'_>) -> Result<Self> {
-        let expr = input.parse()?;
-        let _: Punct![";"] = input.parse()?;
-        Ok(Self::Expr(expr))
+        let expr: Terminated<Expr, Punct![";"]> = input.parse()?;
+        Ok(Self::Expr(expr.into_value()))
     }
 }
 
@@ -578,47 +576,23 @@ impl Parse for Stmt {
     }
 }
 
+#[allow(dead_code)]
 struct Ast(Vec<Stmt>);
 
 impl Ast {
-    fn synchronise(input: ParseStream<'_>) {
-        input.synchronise(|input| {
-            use kw::*;
-            input.peek(Punct![";"])
-                || peek2_any!(
-                    input,
-                    keyword_class,
-                    keyword_for,
-                    keyword_fun,
-                    keyword_if,
-                    keyword_print,
-                    keyword_return,
-                    keyword_var,
-                    keyword_while
-                )
-        });
+    fn is_boundary(input: ParseStream<'_>) -> bool {
+        input.peek(Punct![";"]) || kw::peek2_any_statement_keyword(input)
     }
 }
 
 impl Parse for Ast {
     fn parse(input: ParseStream<'_>) -> Result<Self> {
-        let mut stmts = vec![];
-        let mut errors = vec![];
-
-        while !input.is_empty() {
-            match input.parse() {
-                Ok(stmt) => stmts.push(stmt),
-                Err(err) => {
-                    Ast::synchronise(input);
-                    errors.push(err);
-                }
-            }
-        }
+        input.set_recovery(Ast::is_boundary);
+        let stmts: Vec<Recover<Stmt>> = parse_repeated(input)?;
 
-        if let Some(error) = errors.into_iter().reduce(|acc, err| acc.with(err)) {
-            Err(error)
-        } else {
-            Ok(Ast(stmts))
+        match input.get_error() {
+            Some(error) => Err(error),
+            None => Ok(Ast(stmts.into_iter().filter_map(Recover::ok).collect())),
         }
     }
 }
@@ -0,0 +1,124 @@
+use syn::meta::ParseNestedMeta;
+use syn::Attribute;
+use syn::Expr;
+use syn::LitStr;
+use syn::Path;
+use syn::Result;
+
+/// Parses the value of an option naming a function, given either as a path or
+/// as a string containing one.
+fn parse_function(meta: &ParseNestedMeta<'_>) -> Result<Path> {
+    let value = meta.value()?;
+    if value.peek(LitStr) {
+        value.parse::<LitStr>()?.parse()
+    } else {
+        value.parse()
+    }
+}
+
+/// The options given in `#[parse(...)]` attributes on a struct or enum.
+#[derive(Default)]
+pub(crate) struct TypeAttrs {
+    /// The token skipped to when a `Recover` field fails to parse.
+    pub(crate) recover_to: Option<Expr>,
+}
+
+impl TypeAttrs {
+    pub(crate) fn parse(attrs: &[Attribute]) -> Result<TypeAttrs> {
+        let mut options = TypeAttrs::default();
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("parse")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("recover_to") {
+                    options.recover_to = Some(meta.value()?.parse()?);
+                } else {
+                    return Err(meta.error("unknown parse option"));
+                }
+                Ok(())
+            })?;
+        }
+        Ok(options)
+    }
+}
+
+/// The options given in `#[parse(...)]` attributes on an enum variant.
+#[derive(Default)]
+pub(crate) struct VariantAttrs {
+    /// The token that selects this variant.
+    pub(crate) peek: Option<Expr>,
+    /// A function that returns true if the input starts with this variant.
+    pub(crate) peek_func: Option<Path>,
+    /// The name of the variant in errors, when selected with `peek_func`.
+    pub(crate) name: Option<LitStr>,
+}
+
+impl VariantAttrs {
+    pub(crate) fn parse(attrs: &[Attribute]) -> Result<VariantAttrs> {
+        let mut options = VariantAttrs::default();
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("parse")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("peek") {
+                    options.peek = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("peek_func") {
+                    options.peek_func = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("name") {
+                    options.name = Some(meta.value()?.parse()?);
+                } else {
+                    return Err(meta.error("unknown parse option"));
+                }
+                Ok(())
+            })?;
+        }
+        Ok(options)
+    }
+}
+
+/// How a field holding a `Punctuated` collection is parsed.
+pub(crate) enum Collection {
+    /// Parsed with `Punctuated::parse_separated`.
+    Separated,
+    /// Parsed with `Punctuated::parse_terminated`.
+    Terminated,
+}
+
+/// The options given in `#[parse(...)]` attributes on a field.
+#[derive(Default)]
+pub(crate) struct FieldAttrs {
+    /// A function used to parse the field, or its elements for a collection.
+    pub(crate) with: Option<Path>,
+    /// A function that returns true if an optional field is present.
+    pub(crate) parse_if: Option<Path>,
+    /// How the field is parsed if it is a collection.
+    pub(crate) collection: Option<Collection>,
+    /// Whether the field is set to its default value instead of being parsed.
+    pub(crate) skip: bool,
+}
+
+impl FieldAttrs {
+    pub(crate) fn parse(attrs: &[Attribute]) -> Result<FieldAttrs> {
+        let mut options = FieldAttrs::default();
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("parse")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("with") {
+                    options.with = Some(parse_function(&meta)?);
+                } else if meta.path.is_ident("parse_if") {
+                    options.parse_if = Some(parse_function(&meta)?);
+                } else if meta.path.is_ident("separated") || meta.path.is_ident("terminated") {
+                    if options.collection.is_some() {
+                        return Err(meta.error("`separated` and `terminated` cannot both be given"));
+                    }
+                    options.collection = Some(if meta.path.is_ident("separated") {
+                        Collection::Separated
+                    } else {
+                        Collection::Terminated
+                    });
+                } else if meta.path.is_ident("skip") {
+                    options.skip = true;
+                } else {
+                    return Err(meta.error("unknown parse option"));
+                }
+                Ok(())
+            })?;
+        }
+        Ok(options)
+    }
+}
@@ -0,0 +1,104 @@
+//! Derive macros for [flexi-parse](https://docs.rs/flexi-parse).
+//!
+//! These macros are re-exported by flexi-parse when its `derive` feature is
+//! enabled, and should be used from there.
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use syn::parse_macro_input;
+use syn::DeriveInput;
+use syn::Fields;
+use syn::Generics;
+use syn::Ident;
+use syn::TypeParamBound;
+
+mod attr;
+mod parse;
+mod spanned;
+mod to_tokens;
+mod visit;
+
+/// Adds `bound` to every type parameter in `generics`.
+fn add_bounds(generics: &Generics, bound: TypeParamBound) -> Generics {
+    let mut generics = generics.clone();
+    for param in generics.type_params_mut() {
+        param.bounds.push(bound.clone());
+    }
+    generics
+}
+
+/// Returns a pattern binding each of `fields` to a variable, along with the
+/// variables in order.
+///
+/// The pattern can also be used as an expression constructing the fields from
+/// the variables.
+fn bind(fields: &Fields) -> (proc_macro2::TokenStream, Vec<Ident>) {
+    let names: Vec<_> = (0..fields.len())
+        .map(|i| Ident::new(&format!("field{i}"), Span::call_site()))
+        .collect();
+    let pattern = match fields {
+        Fields::Named(fields) => {
+            let fields = fields.named.iter().map(|field| &field.ident);
+            quote!({ #( #fields: #names ),* })
+        }
+        Fields::Unnamed(_) => quote!(( #( #names ),* )),
+        Fields::Unit => quote!(),
+    };
+    (pattern, names)
+}
+
+/// Derives `flexi_parse::Parse`.
+///
+/// See the documentation of `flexi_parse::Parse` for details.
+#[proc_macro_derive(Parse, attributes(parse))]
+pub fn derive_parse(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    parse::derive(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Derives `flexi_parse::Spanned`.
+///
+/// See the documentation of `flexi_parse::Spanned` for details.
+#[proc_macro_derive(Spanned)]
+pub fn derive_spanned(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    spanned::derive(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Derives `flexi_parse::ToTokens`.
+///
+/// See the documentation of `flexi_parse::ToTokens` for details.
+#[proc_macro_derive(ToTokens)]
+pub fn derive_to_tokens(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    to_tokens::derive(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Derives `flexi_parse::visit::Visit`.
+///
+/// See the documentation of `flexi_parse::visit::Visit` for details.
+#[proc_macro_derive(Visit)]
+pub fn derive_visit(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    visit::derive_visit(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Derives `flexi_parse::visit::Fold`.
+///
+/// See the documentation of `flexi_parse::visit::Fold` for details.
+#[proc_macro_derive(Fold)]
+pub fn derive_fold(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    visit::derive_fold(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
@@ -0,0 +1,158 @@
+use crate::add_bounds;
+use crate::attr::Collection;
+use crate::attr::FieldAttrs;
+use crate::attr::TypeAttrs;
+use crate::attr::VariantAttrs;
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::parse_quote;
+use syn::Data;
+use syn::DeriveInput;
+use syn::Error;
+use syn::Expr;
+use syn::Field;
+use syn::Fields;
+use syn::LitStr;
+use syn::Result;
+use syn::Type;
+
+/// Returns true if `ty` is written as `Recover<...>`.
+fn is_recover(ty: &Type) -> bool {
+    matches!(
+        ty,
+        Type::Path(ty) if ty.qself.is_none()
+            && ty.path.segments.last().is_some_and(|segment| segment.ident == "Recover")
+    )
+}
+
+/// Returns an expression parsing `field` from `input`.
+///
+/// `Recover` fields skip to `recover_to` when they fail to parse, if given.
+fn parse_field(field: &Field, recover_to: Option<&Expr>) -> Result<TokenStream> {
+    let options = FieldAttrs::parse(&field.attrs)?;
+    if options.skip {
+        if options.with.is_some() || options.parse_if.is_some() || options.collection.is_some() {
+            return Err(Error::new_spanned(
+                field,
+                "`skip` cannot be combined with other parse options",
+            ));
+        }
+        return Ok(quote!(::std::default::Default::default()));
+    }
+
+    let punctuated = quote!(::flexi_parse::punctuated::Punctuated);
+    let value = match (options.collection, options.with) {
+        (Some(Collection::Separated), Some(function)) => {
+            quote!(#punctuated::parse_separated_with(input, #function)?)
+        }
+        (Some(Collection::Separated), None) => quote!(#punctuated::parse_separated(input)?),
+        (Some(Collection::Terminated), Some(function)) => {
+            quote!(#punctuated::parse_terminated_with(input, #function)?)
+        }
+        (Some(Collection::Terminated), None) => quote!(#punctuated::parse_terminated(input)?),
+        (None, Some(function)) => quote!(#function(input)?),
+        (None, None) => match recover_to {
+            Some(token) if is_recover(&field.ty) => {
+                quote!(input.recover(|input| input.peek(#token)))
+            }
+            _ => quote!(input.parse()?),
+        },
+    };
+
+    Ok(match options.parse_if {
+        Some(condition) => quote! {
+            if #condition(input) {
+                ::std::option::Option::Some(#value)
+            } else {
+                ::std::option::Option::None
+            }
+        },
+        None => value,
+    })
+}
+
+/// Returns an expression constructing `path` with each of `fields` parsed in
+/// order from `input`.
+fn construct(path: TokenStream, fields: &Fields, recover_to: Option<&Expr>) -> Result<TokenStream> {
+    let values = fields
+        .iter()
+        .map(|field| parse_field(field, recover_to))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(match fields {
+        Fields::Named(fields) => {
+            let names = fields.named.iter().map(|field| &field.ident);
+            quote!(#path { #( #names: #values, )* })
+        }
+        Fields::Unnamed(_) => quote!(#path( #( #values, )* )),
+        Fields::Unit => path,
+    })
+}
+
+pub(crate) fn derive(input: &DeriveInput) -> Result<TokenStream> {
+    let options = TypeAttrs::parse(&input.attrs)?;
+    let recover_to = options.recover_to.as_ref();
+    let body = match &input.data {
+        Data::Struct(data) => {
+            let value = construct(quote!(Self), &data.fields, recover_to)?;
+            quote!(::std::result::Result::Ok(#value))
+        }
+        Data::Enum(data) => {
+            let mut branches = vec![];
+            for variant in &data.variants {
+                let options = VariantAttrs::parse(&variant.attrs)?;
+                let ident = &variant.ident;
+                let condition = match (options.peek, options.peek_func) {
+                    (Some(peek), None) => quote!(lookahead.peek(#peek)),
+                    (None, Some(function)) => {
+                        let name = options
+                            .name
+                            .unwrap_or_else(|| LitStr::new(&ident.to_string(), ident.span()));
+                        quote!(lookahead.peek_func(#function, #name))
+                    }
+                    (Some(_), Some(_)) => {
+                        return Err(Error::new_spanned(
+                            variant,
+                            "`peek` and `peek_func` cannot both be given",
+                        ))
+                    }
+                    (None, None) => {
+                        return Err(Error::new_spanned(
+                            variant,
+                            "enum variants must be given `#[parse(peek = ...)]` or \
+                             `#[parse(peek_func = ...)]`",
+                        ))
+                    }
+                };
+                let value = construct(quote!(Self::#ident), &variant.fields, recover_to)?;
+                branches.push(quote! {
+                    if #condition {
+                        return ::std::result::Result::Ok(#value);
+                    }
+                });
+            }
+            quote! {
+                let lookahead = input.lookahead();
+                #( #branches )*
+                ::std::result::Result::Err(lookahead.error())
+            }
+        }
+        Data::Union(_) => {
+            return Err(Error::new_spanned(
+                input,
+                "`Parse` cannot be derived for unions",
+            ))
+        }
+    };
+
+    let name = &input.ident;
+    let generics = add_bounds(&input.generics, parse_quote!(::flexi_parse::Parse));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::flexi_parse::Parse for #name #ty_generics #where_clause {
+            fn parse(input: ::flexi_parse::ParseStream<'_>) -> ::flexi_parse::Result<Self> {
+                #body
+            }
+        }
+    })
+}
@@ -0,0 +1,79 @@
+use crate::add_bounds;
+use crate::bind;
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::parse_quote;
+use syn::Data;
+use syn::DeriveInput;
+use syn::Error;
+use syn::Fields;
+use syn::Result;
+
+/// Returns a pattern binding each of `fields`, and statements evaluating to
+/// the span from the first non-empty field to the last, or returning `None`
+/// if every field is empty. Returns `None` if there are no fields.
+fn try_span(fields: &Fields) -> Option<(TokenStream, TokenStream)> {
+    if fields.is_empty() {
+        return None;
+    }
+    let (pattern, names) = bind(fields);
+    let len = names.len();
+    let body = quote! {
+        let fields: [&dyn ::flexi_parse::Spanned; #len] = [ #( #names ),* ];
+        let first = fields.iter().find_map(|field| field.try_span())?;
+        let last = fields.iter().rev().find_map(|field| field.try_span())?;
+        ::std::option::Option::Some(::flexi_parse::Span::across(&first, &last))
+    };
+    Some((pattern, body))
+}
+
+pub(crate) fn derive(input: &DeriveInput) -> Result<TokenStream> {
+    let no_fields = || Error::new_spanned(input, "`Spanned` cannot be derived without fields");
+    let body = match &input.data {
+        Data::Struct(data) => {
+            let (pattern, body) = try_span(&data.fields).ok_or_else(no_fields)?;
+            quote! {
+                let Self #pattern = self;
+                #body
+            }
+        }
+        Data::Enum(data) => {
+            let mut arms = vec![];
+            for variant in &data.variants {
+                let ident = &variant.ident;
+                let (pattern, body) = try_span(&variant.fields).ok_or_else(|| {
+                    Error::new_spanned(variant, "`Spanned` cannot be derived for unit variants")
+                })?;
+                arms.push(quote!(Self::#ident #pattern => { #body }));
+            }
+            quote! {
+                match self {
+                    #( #arms )*
+                }
+            }
+        }
+        Data::Union(_) => {
+            return Err(Error::new_spanned(
+                input,
+                "`Spanned` cannot be derived for unions",
+            ))
+        }
+    };
+
+    let name = &input.ident;
+    let message = format!("`{name}` contains no tokens");
+    let generics = add_bounds(&input.generics, parse_quote!(::flexi_parse::Spanned));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::flexi_parse::Spanned for #name #ty_generics #where_clause {
+            fn span(&self) -> ::flexi_parse::Span {
+                ::flexi_parse::Spanned::try_span(self).expect(#message)
+            }
+
+            fn try_span(&self) -> ::std::option::Option<::flexi_parse::Span> {
+                #body
+            }
+        }
+    })
+}
@@ -0,0 +1,55 @@
+use crate::add_bounds;
+use crate::bind;
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::parse_quote;
+use syn::Data;
+use syn::DeriveInput;
+use syn::Error;
+use syn::Result;
+
+pub(crate) fn derive(input: &DeriveInput) -> Result<TokenStream> {
+    let body = match &input.data {
+        Data::Struct(data) => {
+            let (pattern, names) = bind(&data.fields);
+            quote! {
+                let Self #pattern = self;
+                #( ::flexi_parse::ToTokens::to_tokens(#names, tokens); )*
+            }
+        }
+        Data::Enum(data) => {
+            let arms = data.variants.iter().map(|variant| {
+                let ident = &variant.ident;
+                let (pattern, names) = bind(&variant.fields);
+                quote! {
+                    Self::#ident #pattern => {
+                        #( ::flexi_parse::ToTokens::to_tokens(#names, tokens); )*
+                    }
+                }
+            });
+            quote! {
+                match self {
+                    #( #arms )*
+                }
+            }
+        }
+        Data::Union(_) => {
+            return Err(Error::new_spanned(
+                input,
+                "`ToTokens` cannot be derived for unions",
+            ))
+        }
+    };
+
+    let name = &input.ident;
+    let generics = add_bounds(&input.generics, parse_quote!(::flexi_parse::ToTokens));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::flexi_parse::ToTokens for #name #ty_generics #where_clause {
+            fn to_tokens(&self, tokens: &mut ::flexi_parse::TokenStream) {
+                #body
+            }
+        }
+    })
+}
@@ -0,0 +1,127 @@
+use crate::add_bounds;
+use crate::bind;
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::parse_quote;
+use syn::Data;
+use syn::DeriveInput;
+use syn::Error;
+use syn::Generics;
+use syn::Result;
+
+/// Returns `hooks` if `input` has no lifetime parameters, along with
+/// `generics` with a `'static` bound added to each type parameter, so that the
+/// type is known to be `'static`.
+///
+/// Types with lifetime parameters get no hooks, and their generics are
+/// returned unchanged.
+fn any_hooks(
+    input: &DeriveInput,
+    generics: Generics,
+    hooks: TokenStream,
+) -> (Generics, TokenStream) {
+    if input.generics.lifetimes().next().is_some() {
+        (generics, TokenStream::new())
+    } else {
+        (add_bounds(&generics, parse_quote!('static)), hooks)
+    }
+}
+
+pub(crate) fn derive_visit(input: &DeriveInput) -> Result<TokenStream> {
+    let body = match &input.data {
+        Data::Struct(data) => {
+            let (pattern, names) = bind(&data.fields);
+            quote! {
+                let Self #pattern = self;
+                #( ::flexi_parse::visit::Visit::visit_with(#names, visitor); )*
+            }
+        }
+        Data::Enum(data) => {
+            let arms = data.variants.iter().map(|variant| {
+                let ident = &variant.ident;
+                let (pattern, names) = bind(&variant.fields);
+                quote! {
+                    Self::#ident #pattern => {
+                        #( ::flexi_parse::visit::Visit::visit_with(#names, visitor); )*
+                    }
+                }
+            });
+            quote! {
+                match self {
+                    #( #arms )*
+                }
+            }
+        }
+        Data::Union(_) => {
+            return Err(Error::new_spanned(
+                input,
+                "`Visit` cannot be derived for unions",
+            ))
+        }
+    };
+
+    let name = &input.ident;
+    let generics = add_bounds(&input.generics, parse_quote!(::flexi_parse::visit::Visit));
+    let (generics, any) = any_hooks(input, generics, quote!(::flexi_parse::__visit_any!();));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::flexi_parse::visit::Visit for #name #ty_generics #where_clause {
+            fn visit_children<V: ::flexi_parse::visit::Visitor>(&self, visitor: &mut V) {
+                #body
+            }
+
+            #any
+        }
+    })
+}
+
+pub(crate) fn derive_fold(input: &DeriveInput) -> Result<TokenStream> {
+    let body = match &input.data {
+        Data::Struct(data) => {
+            let (pattern, names) = bind(&data.fields);
+            quote! {
+                let Self #pattern = self;
+                #( let #names = ::flexi_parse::visit::Fold::fold_with(#names, folder); )*
+                Self #pattern
+            }
+        }
+        Data::Enum(data) => {
+            let arms = data.variants.iter().map(|variant| {
+                let ident = &variant.ident;
+                let (pattern, names) = bind(&variant.fields);
+                quote! {
+                    Self::#ident #pattern => {
+                        #( let #names = ::flexi_parse::visit::Fold::fold_with(#names, folder); )*
+                        Self::#ident #pattern
+                    }
+                }
+            });
+            quote! {
+                match self {
+                    #( #arms )*
+                }
+            }
+        }
+        Data::Union(_) => {
+            return Err(Error::new_spanned(
+                input,
+                "`Fold` cannot be derived for unions",
+            ))
+        }
+    };
+
+    let name = &input.ident;
+    let generics = add_bounds(&input.generics, parse_quote!(::flexi_parse::visit::Fold));
+    let (generics, any) = any_hooks(input, generics, quote!(::flexi_parse::__fold_any!();));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::flexi_parse::visit::Fold for #name #ty_generics #where_clause {
+            fn fold_children<F: ::flexi_parse::visit::Folder>(self, folder: &mut F) -> Self {
+                #body
+            }
+
+            #any
+        }
+    })
+}
@@ -0,0 +1,292 @@
+//! A compact, versioned binary encoding of token streams.
+//!
+//! An encoded stream starts with the bytes `FPTS` and a little-endian `u16`
+//! format version, followed by the stream's source files, a table of the
+//! distinct identifiers in the stream, and each token in turn. All integers
+//! are little-endian, and strings are stored as a `u32` length followed by
+//! their UTF-8 bytes.
+
+use crate::line_starts;
+use crate::token::Interner;
+use crate::token::PunctKind;
+use crate::token::Spacing;
+use crate::PackedEntry;
+use crate::PackedKind;
+use crate::SourceFile;
+use crate::TokenStream;
+
+use std::collections::HashMap;
+use std::io;
+use std::sync::Arc;
+
+const MAGIC: &[u8; 4] = b"FPTS";
+
+const ERROR: u8 = 0;
+const IDENT: u8 = 1;
+const PUNCT: u8 = 2;
+const SPACE2: u8 = 3;
+const TAB: u8 = 4;
+const NEW_LINE: u8 = 5;
+const CARRIAGE_RETURN: u8 = 6;
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn write_u32(bytes: &mut Vec<u8>, value: usize) {
+    let value = u32::try_from(value).expect("value is too large to encode");
+    bytes.extend_from_slice(&value.to_le_bytes());
+}
+
+fn write_str(bytes: &mut Vec<u8>, string: &str) {
+    write_u32(bytes, string.len());
+    bytes.extend_from_slice(string.as_bytes());
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        Ok(self.slice(N)?.try_into().unwrap())
+    }
+
+    fn slice(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        if self.bytes.len() < len {
+            return Err(invalid(
+                "unexpected end of encoded token stream".to_string(),
+            ));
+        }
+        let (slice, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(slice)
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    fn u16(&mut self) -> io::Result<u16> {
+        Ok(u16::from_le_bytes(self.take()?))
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        Ok(u32::from_le_bytes(self.take()?))
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        let len = self.u32()? as usize;
+        String::from_utf8(self.slice(len)?.to_vec()).map_err(|err| invalid(err.to_string()))
+    }
+}
+
+impl TokenStream {
+    /// The version of the format written by [`TokenStream::to_bytes`].
+    ///
+    /// This changes whenever the format does, and streams encoded with a
+    /// different version are rejected by [`TokenStream::from_bytes`].
+    pub const FORMAT_VERSION: u16 = 1;
+
+    /// Encodes this stream and its source files in a compact binary format.
+    ///
+    /// Only tokens that haven't been removed are included. The origins of
+    /// files created with [`SourceFile::new_virtual`] are not included.
+    ///
+    /// ## Example
+    /// ```
+    /// # use flexi_parse::SourceFile;
+    /// # use flexi_parse::TokenStream;
+    /// # use std::sync::Arc;
+    /// let source = SourceFile::new("example".to_string(), "a + b".to_string());
+    /// let tokens = TokenStream::try_from(Arc::new(source)).unwrap();
+    /// let bytes = tokens.to_bytes();
+    /// let decoded = TokenStream::from_bytes(&bytes).unwrap();
+    /// assert_eq!(decoded.to_string(), "a + b");
+    /// ```
+    ///
+    /// ## Panics
+    /// Panics if the stream has more than `u32::MAX` tokens.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&TokenStream::FORMAT_VERSION.to_le_bytes());
+
+        let mut sources: Vec<&Arc<SourceFile>> = self.sources.iter().collect();
+        let source = self
+            .sources
+            .iter()
+            .position(|source| Arc::ptr_eq(source, &self.source))
+            .unwrap_or_else(|| {
+                sources.push(&self.source);
+                sources.len() - 1
+            });
+        write_u32(&mut bytes, sources.len());
+        for file in sources {
+            write_str(&mut bytes, &file.name);
+            match &file.path {
+                Some(path) => {
+                    bytes.push(1);
+                    write_str(&mut bytes, path);
+                }
+                None => bytes.push(0),
+            }
+            write_str(&mut bytes, &file.contents);
+        }
+        write_u32(&mut bytes, source);
+
+        let mut idents = HashMap::new();
+        let mut strings = vec![];
+        for entry in self.packed() {
+            if entry.kind == PackedKind::Ident {
+                idents.entry(entry.symbol).or_insert_with(|| {
+                    strings.push(self.symbols.get(entry.symbol).string());
+                    strings.len() - 1
+                });
+            }
+        }
+        write_u32(&mut bytes, strings.len());
+        for string in strings {
+            write_str(&mut bytes, string);
+        }
+
+        write_u32(&mut bytes, self.tokens.len() - 1);
+        for entry in self.packed() {
+            let (tag, value) = match entry.kind {
+                PackedKind::Error => (ERROR, 0),
+                PackedKind::Ident => (IDENT, idents[&entry.symbol]),
+                PackedKind::Punct(kind, spacing) => (
+                    PUNCT,
+                    u32::from(char::from(kind)) as usize
+                        | usize::from(spacing == Spacing::Joint) << 31,
+                ),
+                PackedKind::Space2 => (SPACE2, 0),
+                PackedKind::Tab => (TAB, 0),
+                PackedKind::NewLine => (NEW_LINE, 0),
+                PackedKind::CarriageReturn => (CARRIAGE_RETURN, 0),
+                PackedKind::End => break,
+            };
+            bytes.push(tag);
+            if matches!(tag, IDENT | PUNCT) {
+                write_u32(&mut bytes, value);
+            }
+            bytes.extend_from_slice(&entry.source.to_le_bytes());
+            bytes.extend_from_slice(&entry.start.to_le_bytes());
+            bytes.extend_from_slice(&entry.end.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Decodes a stream encoded with [`TokenStream::to_bytes`].
+    ///
+    /// ## Errors
+    /// Returns an error of kind [`InvalidData`][io::ErrorKind::InvalidData] if
+    /// `bytes` is not a valid encoding, or was encoded with a different
+    /// [format version](TokenStream::FORMAT_VERSION).
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<TokenStream> {
+        let mut reader = Reader { bytes };
+        if &reader.take::<4>()? != MAGIC {
+            return Err(invalid("not an encoded token stream".to_string()));
+        }
+        let version = reader.u16()?;
+        if version != TokenStream::FORMAT_VERSION {
+            return Err(invalid(format!(
+                "unsupported token stream format version {version}"
+            )));
+        }
+
+        let mut sources = vec![];
+        for _ in 0..reader.u32()? {
+            let name = reader.string()?;
+            let path = match reader.u8()? {
+                0 => None,
+                1 => Some(reader.string()?),
+                flag => return Err(invalid(format!("invalid path flag {flag}"))),
+            };
+            let contents = reader.string()?;
+            sources.push(Arc::new(SourceFile {
+                name,
+                path,
+                line_starts: line_starts(&contents),
+                contents,
+                origins: vec![],
+            }));
+        }
+        let file = |index: u32| {
+            sources
+                .get(index as usize)
+                .ok_or_else(|| invalid(format!("no source file with index {index}")))
+        };
+        let source = Arc::clone(file(reader.u32()?)?);
+
+        let mut symbols = Interner::default();
+        let mut indices = vec![];
+        for _ in 0..reader.u32()? {
+            let symbol = symbols
+                .intern(&reader.string()?)
+                .ok_or_else(|| invalid("too many identifiers".to_string()))?;
+            indices.push(symbol);
+        }
+
+        let len = reader.u32()? as usize;
+        let mut arena = Vec::with_capacity(len.min(reader.bytes.len()) + 1);
+        for _ in 0..len {
+            let tag = reader.u8()?;
+            let value = if matches!(tag, IDENT | PUNCT) {
+                reader.u32()?
+            } else {
+                0
+            };
+            let (kind, symbol) = match tag {
+                ERROR => (PackedKind::Error, 0),
+                IDENT => {
+                    let symbol = indices
+                        .get(value as usize)
+                        .ok_or_else(|| invalid(format!("no identifier with index {value}")))?;
+                    (PackedKind::Ident, *symbol)
+                }
+                PUNCT => {
+                    let kind = char::from_u32(value & !(1 << 31))
+                        .and_then(|c| PunctKind::try_from(c).ok())
+                        .ok_or_else(|| invalid(format!("invalid punctuation {value}")))?;
+                    let spacing = if value & 1 << 31 == 0 {
+                        Spacing::Alone
+                    } else {
+                        Spacing::Joint
+                    };
+                    (PackedKind::Punct(kind, spacing), 0)
+                }
+                SPACE2 => (PackedKind::Space2, 0),
+                TAB => (PackedKind::Tab, 0),
+                NEW_LINE => (PackedKind::NewLine, 0),
+                CARRIAGE_RETURN => (PackedKind::CarriageReturn, 0),
+                tag => return Err(invalid(format!("invalid token kind {tag}"))),
+            };
+            let source = reader.u16()?;
+            let (start, end) = (reader.u32()?, reader.u32()?);
+            let contents = &file(u32::from(source))?.contents;
+            if start > end
+                || !contents.is_char_boundary(start as usize)
+                || !contents.is_char_boundary(end as usize)
+            {
+                return Err(invalid(format!("invalid token range {start}..{end}")));
+            }
+            arena.push(PackedEntry {
+                start,
+                end,
+                symbol,
+                source,
+                kind,
+            });
+        }
+        if !reader.bytes.is_empty() {
+            return Err(invalid("trailing bytes after token stream".to_string()));
+        }
+        arena.push(PackedEntry::END);
+        Ok(TokenStream::from_arena(
+            arena,
+            sources,
+            Arc::new(symbols),
+            source,
+        ))
+    }
+}
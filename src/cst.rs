@@ -0,0 +1,388 @@
+//! Lossless concrete syntax trees.
+//!
+//! As well as the syntax tree returned by a parser, [`parse`] builds a
+//! [`SyntaxNode`] containing every token of the source file, including
+//! whitespace, so that the original text can be reproduced exactly. This is
+//! useful for tools such as formatters and language servers, which need to know
+//! where each part of the input came from.
+//!
+//! The nodes of the tree are marked by the parser itself, by calling
+//! [`ParseBuffer::start_node`] before parsing a node and
+//! [`ParseBuffer::finish_node`] after it. Tokens that aren't inside any node
+//! belong to the root of the tree.
+//!
+//! # Example
+//! ```
+//! # use flexi_parse::cst;
+//! # use flexi_parse::cst::SyntaxElement;
+//! # use flexi_parse::token::Ident;
+//! # use flexi_parse::token::LitInt;
+//! # use flexi_parse::ParseStream;
+//! # use flexi_parse::Punct;
+//! # use flexi_parse::Result;
+//! # use flexi_parse::SourceFile;
+//! # use flexi_parse::TokenStream;
+//! # use std::sync::Arc;
+//! fn assignment(input: ParseStream<'_>) -> Result<()> {
+//!     input.start_node("assignment");
+//!     let _: Ident = input.parse()?;
+//!     let _: Punct!["="] = input.parse()?;
+//!     input.start_node("value");
+//!     let _: LitInt = input.parse()?;
+//!     input.finish_node();
+//!     input.finish_node();
+//!     Ok(())
+//! }
+//!
+//! let source = SourceFile::new("example".to_string(), "x  = 1 ".to_string());
+//! let tokens = TokenStream::try_from(Arc::new(source)).unwrap();
+//! let (result, tree) = cst::parse(assignment, &tokens, "file");
+//! assert!(result.is_ok());
+//! assert_eq!(tree.to_string(), "x  = 1 ");
+//!
+//! let SyntaxElement::Node(assignment) = &tree.children()[0] else {
+//!     panic!()
+//! };
+//! assert_eq!(assignment.kind(), "assignment");
+//! assert_eq!(assignment.text(), "x  = 1");
+//! assert_eq!(assignment.nodes().next().unwrap().text(), "1");
+//! ```
+//!
+//! [`ParseBuffer::start_node`]: crate::ParseBuffer::start_node
+//! [`ParseBuffer::finish_node`]: crate::ParseBuffer::finish_node
+
+use crate::PackedKind;
+use crate::ParseOptions;
+use crate::ParseStream;
+use crate::Parser;
+use crate::Result;
+use crate::SourceFile;
+use crate::Span;
+use crate::TokenStream;
+
+use std::fmt;
+use std::mem;
+use std::sync::Arc;
+
+/// A call to [`ParseBuffer::start_node`](crate::ParseBuffer::start_node) or
+/// [`ParseBuffer::finish_node`](crate::ParseBuffer::finish_node), with the
+/// position in the token stream it was made at.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Event {
+    Start(&'static str, Position),
+    Finish(Position),
+}
+
+/// A position in a token stream.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Position {
+    /// The start of the stream.
+    Origin,
+    /// The start of the token at this index in the stream's arena.
+    Before(usize),
+    /// The end of the token at this index in the stream's arena.
+    After(usize),
+}
+
+/// The kind of a token in a concrete syntax tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TokenKind {
+    /// An identifier.
+    Ident,
+    /// A punctuation character.
+    Punct,
+    /// Whitespace, including the single spaces between tokens.
+    WhiteSpace,
+    /// A token that couldn't be scanned.
+    Error,
+}
+
+/// A token in a concrete syntax tree.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxToken {
+    kind: TokenKind,
+    span: Span,
+}
+
+impl SyntaxToken {
+    /// Returns the kind of this token.
+    pub fn kind(&self) -> TokenKind {
+        self.kind
+    }
+
+    /// Returns the span of this token.
+    pub fn span(&self) -> &Span {
+        &self.span
+    }
+
+    /// Returns the source code of this token.
+    pub fn text(&self) -> &str {
+        self.span.source_text()
+    }
+
+    /// Returns true if this token is whitespace.
+    pub fn is_trivia(&self) -> bool {
+        self.kind == TokenKind::WhiteSpace
+    }
+}
+
+/// A node or token in a concrete syntax tree.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SyntaxElement {
+    /// A node.
+    Node(SyntaxNode),
+    /// A token.
+    Token(SyntaxToken),
+}
+
+impl SyntaxElement {
+    /// Returns the span of this element.
+    pub fn span(&self) -> &Span {
+        match self {
+            SyntaxElement::Node(node) => node.span(),
+            SyntaxElement::Token(token) => token.span(),
+        }
+    }
+
+    /// Returns the source code of this element.
+    pub fn text(&self) -> &str {
+        self.span().source_text()
+    }
+}
+
+/// A node in a concrete syntax tree.
+///
+/// The children of a node cover its span exactly, so [`SyntaxNode::text`] is
+/// the concatenation of the text of its children.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxNode {
+    kind: &'static str,
+    span: Span,
+    children: Vec<SyntaxElement>,
+}
+
+impl SyntaxNode {
+    /// Returns the kind given to this node when it was started.
+    pub fn kind(&self) -> &'static str {
+        self.kind
+    }
+
+    /// Returns the span of this node.
+    pub fn span(&self) -> &Span {
+        &self.span
+    }
+
+    /// Returns the source code of this node.
+    pub fn text(&self) -> &str {
+        self.span.source_text()
+    }
+
+    /// Returns the nodes and tokens directly inside this node, in order.
+    pub fn children(&self) -> &[SyntaxElement] {
+        &self.children
+    }
+
+    /// Returns an iterator over the nodes directly inside this node.
+    pub fn nodes(&self) -> impl Iterator<Item = &SyntaxNode> {
+        self.children.iter().filter_map(|child| match child {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_) => None,
+        })
+    }
+}
+
+impl fmt::Display for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.text())
+    }
+}
+
+/// Builds a tree from the tokens of a source file and the events recorded
+/// while parsing it.
+struct Builder {
+    tokens: Vec<(TokenKind, usize, usize)>,
+    /// The byte range of each token in the stream's arena. Tokens from other
+    /// source files are placed at the end of the last token before them from
+    /// this one, which is where the directive that included them was.
+    ranges: Vec<(usize, usize)>,
+    next: usize,
+    position: usize,
+    source: Arc<SourceFile>,
+    /// The kind, start and children of each open node, outermost first.
+    stack: Vec<(&'static str, usize, Vec<SyntaxElement>)>,
+}
+
+impl Builder {
+    fn new(tokens: &TokenStream, root: &'static str) -> Builder {
+        let mut entries: Vec<_> = tokens
+            .arena
+            .iter()
+            .filter(|entry| {
+                entry.kind != PackedKind::End
+                    && Arc::ptr_eq(&tokens.sources[usize::from(entry.source)], &tokens.source)
+            })
+            .map(|entry| {
+                let kind = match entry.kind {
+                    PackedKind::Error => TokenKind::Error,
+                    PackedKind::Ident => TokenKind::Ident,
+                    PackedKind::Punct(_, _) => TokenKind::Punct,
+                    _ => TokenKind::WhiteSpace,
+                };
+                (kind, entry.start as usize, entry.end as usize)
+            })
+            .collect();
+        entries.sort_by_key(|&(_, start, _)| start);
+        let mut end = 0;
+        let ranges = tokens
+            .arena
+            .iter()
+            .map(|entry| {
+                if entry.kind != PackedKind::End
+                    && Arc::ptr_eq(&tokens.sources[usize::from(entry.source)], &tokens.source)
+                {
+                    end = entry.end as usize;
+                    (entry.start as usize, end)
+                } else {
+                    (end, end)
+                }
+            })
+            .collect();
+        Builder {
+            tokens: entries,
+            ranges,
+            next: 0,
+            position: 0,
+            source: Arc::clone(&tokens.source),
+            stack: vec![(root, 0, vec![])],
+        }
+    }
+
+    /// Returns the byte offset in the source file of `position`.
+    fn offset(&self, position: Position) -> usize {
+        match position {
+            Position::Origin => 0,
+            Position::Before(index) => self.ranges[index].0,
+            Position::After(index) => self.ranges[index].1,
+        }
+    }
+
+    fn span(&self, start: usize, end: usize) -> Span {
+        Span::new(start, end, Arc::clone(&self.source))
+    }
+
+    /// Adds the tokens up to `offset` to the innermost open node. Text that
+    /// isn't covered by a token is added as whitespace.
+    fn advance(&mut self, offset: usize) {
+        let offset = offset.min(self.source.contents.len());
+        while self.position < offset {
+            while self
+                .tokens
+                .get(self.next)
+                .is_some_and(|&(_, start, _)| start < self.position)
+            {
+                self.next += 1;
+            }
+            let (kind, end) = match self.tokens.get(self.next) {
+                Some(&(kind, start, end)) if start == self.position => {
+                    self.next += 1;
+                    (kind, end)
+                }
+                Some(&(_, start, _)) => (TokenKind::WhiteSpace, start.min(offset)),
+                None => (TokenKind::WhiteSpace, offset),
+            };
+            let token = SyntaxToken {
+                kind,
+                span: self.span(self.position, end),
+            };
+            self.stack
+                .last_mut()
+                .unwrap()
+                .2
+                .push(SyntaxElement::Token(token));
+            self.position = end;
+        }
+    }
+
+    fn start(&mut self, kind: &'static str, offset: usize) {
+        self.advance(offset);
+        self.stack.push((kind, self.position, vec![]));
+    }
+
+    fn finish(&mut self, offset: usize) {
+        if self.stack.len() == 1 {
+            return;
+        }
+        self.advance(offset);
+        let (kind, start, children) = self.stack.pop().unwrap();
+        let node = SyntaxNode {
+            kind,
+            span: self.span(start, self.position),
+            children,
+        };
+        self.stack
+            .last_mut()
+            .unwrap()
+            .2
+            .push(SyntaxElement::Node(node));
+    }
+
+    fn build(mut self, events: Vec<Event>) -> SyntaxNode {
+        for event in events {
+            match event {
+                Event::Start(kind, position) => self.start(kind, self.offset(position)),
+                Event::Finish(position) => self.finish(self.offset(position)),
+            }
+        }
+        while self.stack.len() > 1 {
+            self.finish(self.position);
+        }
+        self.advance(self.source.contents.len());
+        let (kind, _, children) = self.stack.pop().unwrap();
+        SyntaxNode {
+            kind,
+            span: self.span(0, self.position),
+            children,
+        }
+    }
+}
+
+/// Parses `tokens` with `function`, and builds a concrete syntax tree of their
+/// source file with a root node of kind `root`.
+///
+/// The tree is built even if parsing fails. Nodes that are still unfinished
+/// when `function` returns, such as after an error, end where the last node
+/// was started or finished. Tokens from other
+/// source files, such as those added by
+/// [`TokenStream::expand_includes`], aren't included in the tree, and nodes
+/// starting or finishing at them are placed where they were included.
+///
+/// Whitespace is ignored while parsing, but is still part of the tree.
+pub fn parse<T, F: FnOnce(ParseStream<'_>) -> Result<T>>(
+    function: F,
+    tokens: &TokenStream,
+    root: &'static str,
+) -> (Result<T>, SyntaxNode) {
+    parse_with(function, tokens, root, ParseOptions::new())
+}
+
+/// Parses `tokens` with `function` using the given options, and builds a
+/// concrete syntax tree of their source file.
+///
+/// See [`parse`].
+pub fn parse_with<T, F: FnOnce(ParseStream<'_>) -> Result<T>>(
+    function: F,
+    tokens: &TokenStream,
+    root: &'static str,
+    options: ParseOptions,
+) -> (Result<T>, SyntaxNode) {
+    let mut events = vec![];
+    let result = (|input: ParseStream<'_>| {
+        input.context.record_events.set(true);
+        let result = function(input);
+        events = mem::take(&mut *input.events.borrow_mut());
+        result
+    })
+    .parse_stream_with_options(tokens, options);
+    let tree = Builder::new(tokens, root).build(events);
+    (result, tree)
+}
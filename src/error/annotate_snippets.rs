@@ -0,0 +1,99 @@
+use crate::error::Error;
+use crate::error::ErrorCode;
+use crate::error::ErrorKind;
+use crate::error::Severity;
+use crate::error::SingleError;
+
+use std::ops::Range;
+
+use annotate_snippets::Level;
+use annotate_snippets::Renderer;
+use annotate_snippets::Snippet;
+
+impl From<Severity> for Level {
+    fn from(value: Severity) -> Self {
+        match value {
+            Severity::Error => Level::Error,
+            Severity::Warning => Level::Warning,
+            Severity::Advice => Level::Note,
+        }
+    }
+}
+
+impl SingleError {
+    fn render_snippet(&self, renderer: &Renderer) -> String {
+        let level = Level::from(self.severity);
+        let code = self.code_string();
+        let title = self.kind.message();
+
+        let mut annotations: Vec<(Level, Range<usize>, String)> = vec![];
+        let span = self.span();
+        annotations.push((
+            level,
+            span.start..span.end,
+            self.kind.expected_message().unwrap_or_default(),
+        ));
+        if let Some((open, span)) = self.kind.opener() {
+            annotations.push((
+                Level::Info,
+                span.start..span.end,
+                format!("'{open}' is not closed"),
+            ));
+        }
+        for (span, message) in &self.labels {
+            annotations.push((Level::Info, span.start..span.end, message.clone()));
+        }
+        for suggestion in &self.suggestions {
+            annotations.push((
+                Level::Help,
+                suggestion.span.start..suggestion.span.end,
+                format!("{}: '{}'", suggestion.message, suggestion.replacement),
+            ));
+        }
+
+        let mut footers = vec![];
+        if let Some(help) = &self.help {
+            footers.push((Level::Help, help.clone()));
+        }
+        if let Some(note) = &self.note {
+            footers.push((Level::Note, note.clone()));
+        }
+        if let Some(url) = self.error_code.as_ref().and_then(ErrorCode::url) {
+            footers.push((Level::Note, format!("see {url}")));
+        }
+
+        let snippet = Snippet::source(&self.source.contents)
+            .origin(self.source.id())
+            .fold(true)
+            .annotations(
+                annotations
+                    .iter()
+                    .map(|(level, range, label)| level.span(range.clone()).label(label)),
+            );
+        let message = level
+            .title(&title)
+            .id(&code)
+            .snippet(snippet)
+            .footers(footers.iter().map(|(level, footer)| level.title(footer)));
+        let rendered = renderer.render(message).to_string();
+        rendered
+    }
+}
+
+impl Error {
+    /// Renders this error in the style of `rustc`, using the
+    /// [`annotate-snippets`][annotate-snippets] crate.
+    ///
+    /// Use [`Renderer::styled`] for coloured output, or [`Renderer::plain`]
+    /// otherwise.
+    ///
+    /// [annotate-snippets]: https://docs.rs/annotate-snippets
+    pub fn render_snippets(&self, renderer: &Renderer) -> String {
+        self.errors
+            .iter()
+            .filter(|error| !matches!(error.kind, ErrorKind::Silent))
+            .map(|error| error.render_snippet(renderer))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
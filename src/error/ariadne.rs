@@ -1,15 +1,22 @@
 use crate::error::unexpected_token_message;
+use crate::error::Diagnostic;
+use crate::error::DiagnosticKind;
 use crate::error::Error;
+use crate::error::ErrorCode;
 use crate::error::ErrorKind;
+use crate::error::Severity;
 use crate::error::SingleError;
 use crate::SourceFile;
 use crate::Span;
 
+use std::collections::HashMap;
+use std::env;
 use std::io;
 use std::io::Write;
-use std::rc::Rc;
+use std::sync::Arc;
 
 use ariadne::Color;
+use ariadne::Config;
 use ariadne::Label;
 use ariadne::ReportKind;
 use ariadne::Source;
@@ -30,18 +37,144 @@ impl ariadne::Span for Span {
     }
 }
 
-impl From<&ErrorKind> for ReportKind<'static> {
-    fn from(value: &ErrorKind) -> Self {
+impl From<Severity> for ReportKind<'static> {
+    fn from(value: Severity) -> Self {
         match value {
-            ErrorKind::Silent
-            | ErrorKind::Custom { .. }
-            | ErrorKind::UnknownCharacter(_)
-            | ErrorKind::UnterminatedGroup { .. }
-            | ErrorKind::UnterminatedChar(_)
-            | ErrorKind::LongChar(_)
-            | ErrorKind::UnterminatedString(_)
-            | ErrorKind::UnexpectedToken { .. }
-            | ErrorKind::EndOfFile(_) => ReportKind::Error,
+            Severity::Error => ReportKind::Error,
+            Severity::Warning => ReportKind::Warning,
+            Severity::Advice => ReportKind::Advice,
+        }
+    }
+}
+
+/// The colours and layout used when rendering a [`Report`].
+///
+/// By default, colours are enabled unless the `NO_COLOR` environment variable
+/// is set to a non-empty value.
+///
+/// # Example
+/// ```
+/// # use flexi_parse::error::Color;
+/// # use flexi_parse::error::ReportStyle;
+/// let style = ReportStyle::new().error(Color::Magenta).label(Color::Cyan);
+/// let plain = ReportStyle::new().color(false);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReportStyle {
+    color: bool,
+    error: Color,
+    warning: Color,
+    advice: Color,
+    label: Color,
+    suggestion: Color,
+    severities: HashMap<DiagnosticKind, Severity>,
+    config: Config,
+}
+
+impl ReportStyle {
+    /// Creates the default style.
+    pub fn new() -> ReportStyle {
+        ReportStyle::default()
+    }
+
+    /// Forces colours on or off, overriding `NO_COLOR`.
+    pub fn color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Sets the colour of the primary label of errors.
+    pub fn error(mut self, color: Color) -> Self {
+        self.error = color;
+        self
+    }
+
+    /// Sets the colour of the primary label of warnings.
+    pub fn warning(mut self, color: Color) -> Self {
+        self.warning = color;
+        self
+    }
+
+    /// Sets the colour of the primary label of advice.
+    pub fn advice(mut self, color: Color) -> Self {
+        self.advice = color;
+        self
+    }
+
+    /// Sets the colour of secondary labels.
+    pub fn label(mut self, color: Color) -> Self {
+        self.label = color;
+        self
+    }
+
+    /// Sets the colour of suggestions.
+    pub fn suggestion(mut self, color: Color) -> Self {
+        self.suggestion = color;
+        self
+    }
+
+    /// Sets the [`Config`] passed to `ariadne`, controlling options such as
+    /// compact mode, underlines and the character set.
+    ///
+    /// The colour setting of `config` is ignored in favour of
+    /// [`ReportStyle::color`].
+    ///
+    /// # Example
+    /// ```
+    /// # use flexi_parse::error::CharSet;
+    /// # use flexi_parse::error::Config;
+    /// # use flexi_parse::error::ReportStyle;
+    /// let config = Config::default().with_compact(true).with_char_set(CharSet::Ascii);
+    /// let style = ReportStyle::new().config(config);
+    /// ```
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Reports every diagnostic of the given kind with `severity`, instead of
+    /// the severity it was created with.
+    ///
+    /// # Example
+    /// ```
+    /// # use flexi_parse::error::DiagnosticKind;
+    /// # use flexi_parse::error::ReportStyle;
+    /// # use flexi_parse::error::Severity;
+    /// let lenient = ReportStyle::new().severity(DiagnosticKind::UnknownCharacter, Severity::Warning);
+    /// ```
+    pub fn severity(mut self, kind: DiagnosticKind, severity: Severity) -> Self {
+        self.severities.insert(kind, severity);
+        self
+    }
+
+    fn severity_of(&self, error: &SingleError) -> Severity {
+        let kind = Diagnostic { error }.kind();
+        self.severities
+            .get(&kind)
+            .copied()
+            .unwrap_or(error.severity)
+    }
+
+    fn color_of(&self, severity: Severity) -> Color {
+        match severity {
+            Severity::Error => self.error,
+            Severity::Warning => self.warning,
+            Severity::Advice => self.advice,
+        }
+    }
+}
+
+impl Default for ReportStyle {
+    fn default() -> Self {
+        ReportStyle {
+            color: !matches!(env::var_os("NO_COLOR"), Some(value) if !value.is_empty()),
+            error: Color::Red,
+            warning: Color::Yellow,
+            advice: Color::Fixed(147),
+            label: Color::Yellow,
+            suggestion: Color::Green,
+            severities: HashMap::new(),
+            config: Config::default(),
         }
     }
 }
@@ -54,7 +187,7 @@ impl From<&ErrorKind> for ReportKind<'static> {
 /// [`Error::eprint`] method will suffice.
 pub struct Report {
     report: ariadne::Report<'static, Span>,
-    source: Rc<SourceFile>,
+    source: Arc<SourceFile>,
 }
 
 impl Report {
@@ -84,6 +217,18 @@ impl Report {
         )
     }
 
+    /// Returns this diagnostic as a string, including ANSI colour codes.
+    pub fn to_string_styled(&self) -> String {
+        let mut buf = vec![];
+        self.write(&mut buf).unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    /// Returns this diagnostic as a string, with ANSI colour codes removed.
+    pub fn to_string_plain(&self) -> String {
+        strip_ansi(&self.to_string_styled())
+    }
+
     /// Prints this diagnostic to stderr.
     ///
     /// For more details, see [`ariadne::Report::eprint`].
@@ -106,67 +251,185 @@ impl Report {
     }
 }
 
-impl From<&SingleError> for Report {
-    fn from(value: &SingleError) -> Self {
+/// Removes ANSI escape sequences of the form `ESC [ ... <letter>`.
+fn strip_ansi(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            if chars.next() == Some('[') {
+                for c in chars.by_ref() {
+                    if c.is_ascii_alphabetic() {
+                        break;
+                    }
+                }
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+impl Report {
+    fn new(value: &SingleError, style: &ReportStyle) -> Self {
+        let severity = style.severity_of(value);
         let mut builder =
-            ariadne::Report::build((&value.kind).into(), value.source.id(), value.kind.start())
-                .with_code(value.kind.code());
+            ariadne::Report::build(severity.into(), value.source.id(), value.kind.start())
+                .with_code(value.code_string())
+                .with_config(style.config.with_color(style.color));
+        let color = style.color_of(severity);
         match &value.kind {
             ErrorKind::Silent => unreachable!(),
             ErrorKind::Custom { message, span, .. } => {
                 builder.set_message(message);
-                builder.add_label(Label::new(span.clone()).with_color(Color::Red));
+                builder.add_label(Label::new(span.clone()).with_color(color));
             }
             ErrorKind::UnknownCharacter(span) => {
                 builder.set_message("Unrecognised character");
-                builder.add_label(Label::new(span.clone()).with_color(Color::Red));
+                builder.add_label(Label::new(span.clone()).with_color(color));
             }
-            ErrorKind::UnterminatedGroup { start, span } => {
+            ErrorKind::UnterminatedGroup { start, span, open } => {
                 builder.set_message(format!("Unmatched '{start}'"));
-                builder.add_label(Label::new(span.clone()).with_color(Color::Red));
+                builder.add_label(Label::new(span.clone()).with_color(color));
+                builder.add_label(
+                    Label::new(open.clone())
+                        .with_color(style.label)
+                        .with_message(format!("'{start}' is not closed")),
+                );
             }
             ErrorKind::UnterminatedChar(span) => {
                 builder.set_message("Expect \"'\" after character literal");
-                builder.add_label(Label::new(span.clone()).with_color(Color::Red));
+                builder.add_label(Label::new(span.clone()).with_color(color));
             }
             ErrorKind::LongChar(span) => {
                 builder.set_message("Character literals must be exactly one character long");
-                builder.add_label(Label::new(span.clone()).with_color(Color::Red));
+                builder.add_label(Label::new(span.clone()).with_color(color));
             }
             ErrorKind::UnterminatedString(span) => {
                 builder.set_message("Expect '\"' at end of string literal");
-                builder.add_label(Label::new(span.clone()).with_color(Color::Red));
+                builder.add_label(Label::new(span.clone()).with_color(color));
             }
             ErrorKind::UnexpectedToken { expected, span } => {
                 builder.set_message("Unexpected token");
                 builder.add_label(
                     Label::new(span.clone())
-                        .with_color(Color::Red)
+                        .with_color(color)
                         .with_message(unexpected_token_message(expected)),
                 );
             }
-            ErrorKind::EndOfFile(_) => builder.set_message("Unexpected end of file while parsing"),
+            ErrorKind::EndOfFile { expected, .. } => {
+                builder.set_message("Unexpected end of file while parsing");
+                if !expected.is_empty() {
+                    builder.add_label(
+                        Label::new(value.span())
+                            .with_color(color)
+                            .with_message(unexpected_token_message(expected)),
+                    );
+                }
+            }
+            ErrorKind::RecursionLimit(span) => {
+                builder.set_message("Recursion limit reached");
+                builder.add_label(Label::new(span.clone()).with_color(color));
+            }
+            ErrorKind::UnmatchedDelimiter {
+                delimiter,
+                span,
+                open,
+            } => {
+                builder.set_message(format!("Unmatched '{delimiter}'"));
+                builder.add_label(Label::new(span.clone()).with_color(color));
+                if let Some((open, span)) = open {
+                    builder.add_label(
+                        Label::new(span.clone())
+                            .with_color(style.label)
+                            .with_message(format!("'{open}' is not closed")),
+                    );
+                }
+            }
+            ErrorKind::TooFewElements { min, span } => {
+                builder.set_message(format!("Expected at least {min} elements"));
+                builder.add_label(Label::new(span.clone()).with_color(color));
+            }
+            ErrorKind::TooManyElements { max, span } => {
+                builder.set_message(format!("Expected at most {max} elements"));
+                builder.add_label(Label::new(span.clone()).with_color(color));
+            }
+            ErrorKind::Io { message, .. } => {
+                builder.set_message(format!("Failed to read file: {message}"));
+            }
+            ErrorKind::LimitExceeded { message, span } => {
+                builder.set_message(message);
+                builder.add_label(Label::new(span.clone()).with_color(color));
+            }
+        }
+        for (span, message) in &value.labels {
+            builder.add_label(
+                Label::new(span.clone())
+                    .with_color(style.label)
+                    .with_message(message),
+            );
+        }
+        for suggestion in &value.suggestions {
+            builder.add_label(
+                Label::new(suggestion.span.clone())
+                    .with_color(style.suggestion)
+                    .with_message(format!(
+                        "{}: '{}'",
+                        suggestion.message, suggestion.replacement
+                    )),
+            );
+        }
+        if let Some(help) = &value.help {
+            builder.set_help(help);
+        }
+        let url = value.error_code.as_ref().and_then(ErrorCode::url);
+        match (&value.note, url) {
+            (Some(note), Some(url)) => builder.set_note(format!("{note}\nsee {url}")),
+            (Some(note), None) => builder.set_note(note),
+            (None, Some(url)) => builder.set_note(format!("see {url}")),
+            (None, None) => {}
         }
         Report {
             report: builder.finish(),
-            source: Rc::clone(&value.source),
+            source: Arc::clone(&value.source),
         }
     }
 }
 
+impl From<&SingleError> for Report {
+    fn from(value: &SingleError) -> Self {
+        Report::new(value, &ReportStyle::default())
+    }
+}
+
 impl From<&Error> for Vec<Report> {
     fn from(value: &Error) -> Self {
-        let mut reports = Vec::with_capacity(value.errors.len());
-        for error in &value.errors {
+        value.reports(&ReportStyle::default())
+    }
+}
+
+impl Error {
+    /// Creates a [`Report`] for each diagnostic in this error, using the
+    /// given style.
+    pub fn reports(&self, style: &ReportStyle) -> Vec<Report> {
+        let mut reports = Vec::with_capacity(self.errors.len());
+        for error in &self.errors {
             if !matches!(&error.kind, ErrorKind::Silent) {
-                reports.push(Report::from(error));
+                reports.push(Report::new(error, style));
             }
         }
         reports
     }
-}
 
-impl Error {
+    /// Prints this error to stderr using the given style.
+    pub fn eprint_with_style(&self, style: &ReportStyle) -> io::Result<()> {
+        for report in self.reports(style) {
+            report.eprint()?;
+        }
+        Ok(())
+    }
+
     /// Prints this error to stderr.
     pub fn eprint(&self) -> io::Result<()> {
         let reports: Vec<Report> = self.into();
@@ -175,4 +438,11 @@ impl Error {
         }
         Ok(())
     }
+
+    /// Renders this error as it would be printed by [`Error::eprint`], with
+    /// ANSI colour codes removed.
+    pub fn to_report_string(&self) -> String {
+        let reports: Vec<Report> = self.into();
+        reports.iter().map(Report::to_string_plain).collect()
+    }
 }
@@ -0,0 +1,135 @@
+use crate::error::Report;
+
+use std::fmt::Write;
+
+const COLORS: [&str; 16] = [
+    "#000000", "#cd0000", "#00cd00", "#cdcd00", "#0000ee", "#cd00cd", "#00cdcd", "#e5e5e5",
+    "#7f7f7f", "#ff0000", "#00ff00", "#ffff00", "#5c5cff", "#ff00ff", "#00ffff", "#ffffff",
+];
+
+/// Returns the CSS colour of an entry in the xterm 256-colour palette.
+fn fixed(n: u8) -> String {
+    match n {
+        0..=15 => COLORS[n as usize].to_string(),
+        16..=231 => {
+            let level = |x: u8| if x == 0 { 0 } else { 55 + x * 40 };
+            let n = n - 16;
+            format!(
+                "#{:02x}{:02x}{:02x}",
+                level(n / 36),
+                level(n / 6 % 6),
+                level(n % 6)
+            )
+        }
+        232..=255 => {
+            let grey = 8 + (n - 232) * 10;
+            format!("#{grey:02x}{grey:02x}{grey:02x}")
+        }
+    }
+}
+
+/// Converts the parameters of an ANSI SGR sequence into CSS declarations.
+///
+/// Returns whether the sequence resets the current style, along with the
+/// declarations it adds.
+fn css(params: &str) -> (bool, String) {
+    let mut reset = params.is_empty();
+    let mut style = String::new();
+    let mut codes = params
+        .split(';')
+        .map(|code| code.parse::<u8>().unwrap_or(0));
+    while let Some(code) = codes.next() {
+        match code {
+            0 => {
+                reset = true;
+                style.clear();
+            }
+            1 => style.push_str("font-weight:bold;"),
+            3 => style.push_str("font-style:italic;"),
+            4 => style.push_str("text-decoration:underline;"),
+            30..=37 => {
+                let _ = write!(style, "color:{};", COLORS[code as usize - 30]);
+            }
+            90..=97 => {
+                let _ = write!(style, "color:{};", COLORS[code as usize - 82]);
+            }
+            38 => match codes.next() {
+                Some(5) => {
+                    let _ = write!(style, "color:{};", fixed(codes.next().unwrap_or(0)));
+                }
+                Some(2) => {
+                    let (r, g, b) = (
+                        codes.next().unwrap_or(0),
+                        codes.next().unwrap_or(0),
+                        codes.next().unwrap_or(0),
+                    );
+                    let _ = write!(style, "color:#{r:02x}{g:02x}{b:02x};");
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+    (reset, style)
+}
+
+/// Converts text containing ANSI colour codes into escaped HTML, with each
+/// style applied using a `<span>` element.
+fn ansi_to_html(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut open = 0;
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\x1b' => {
+                if chars.next() != Some('[') {
+                    continue;
+                }
+                let mut params = String::new();
+                let mut command = None;
+                for c in chars.by_ref() {
+                    if c.is_ascii_alphabetic() {
+                        command = Some(c);
+                        break;
+                    }
+                    params.push(c);
+                }
+                if command != Some('m') {
+                    continue;
+                }
+                let (reset, style) = css(&params);
+                if reset {
+                    out.push_str(&"</span>".repeat(open));
+                    open = 0;
+                }
+                if !style.is_empty() {
+                    let _ = write!(out, "<span style=\"{style}\">");
+                    open += 1;
+                }
+            }
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+    out.push_str(&"</span>".repeat(open));
+    out
+}
+
+impl Report {
+    /// Returns this diagnostic as an HTML `<pre>` element, with the source
+    /// excerpt and labels coloured using inline styles.
+    ///
+    /// The colours used are those of the [`ReportStyle`][super::ReportStyle]
+    /// this report was created with, so colours should be enabled with
+    /// [`ReportStyle::color`][super::ReportStyle::color] to highlight the
+    /// output.
+    pub fn to_html(&self) -> String {
+        format!(
+            "<pre class=\"flexi-parse-report\">{}</pre>",
+            ansi_to_html(&self.to_string_styled())
+        )
+    }
+}
@@ -0,0 +1,232 @@
+use crate::error::Applicability;
+use crate::error::Error;
+use crate::error::Severity;
+use crate::error::SingleError;
+use crate::line_starts;
+use crate::SourceFile;
+use crate::Span;
+
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
+use serde::Serializer;
+
+#[derive(Serialize)]
+struct Location {
+    line: usize,
+    column: usize,
+}
+
+#[derive(Serialize)]
+struct Range {
+    start: usize,
+    end: usize,
+}
+
+#[derive(Serialize)]
+struct Region {
+    range: Range,
+    start: Location,
+    end: Location,
+}
+
+impl From<&Span> for Region {
+    fn from(value: &Span) -> Self {
+        let (start_line, start_column) = value.start_location();
+        let (end_line, end_column) = value.end_location();
+        Region {
+            range: Range {
+                start: value.start,
+                end: value.end,
+            },
+            start: Location {
+                line: start_line,
+                column: start_column,
+            },
+            end: Location {
+                line: end_line,
+                column: end_column,
+            },
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Label {
+    message: Option<String>,
+    primary: bool,
+    #[serde(flatten)]
+    region: Region,
+}
+
+#[derive(Serialize)]
+struct Suggestion {
+    message: String,
+    replacement: String,
+    applicability: &'static str,
+    #[serde(flatten)]
+    region: Region,
+}
+
+#[derive(Serialize)]
+struct Diagnostic {
+    code: String,
+    severity: &'static str,
+    message: String,
+    file: String,
+    #[serde(flatten)]
+    region: Option<Region>,
+    labels: Vec<Label>,
+    suggestions: Vec<Suggestion>,
+    help: Option<String>,
+    note: Option<String>,
+    url: Option<String>,
+}
+
+impl From<&SingleError> for Diagnostic {
+    fn from(value: &SingleError) -> Self {
+        let mut labels = vec![];
+        labels.push(Label {
+            message: value.kind.expected_message(),
+            primary: true,
+            region: (&value.span()).into(),
+        });
+        if let Some((open, span)) = value.kind.opener() {
+            labels.push(Label {
+                message: Some(format!("'{open}' is not closed")),
+                primary: false,
+                region: span.into(),
+            });
+        }
+        for (span, message) in &value.labels {
+            labels.push(Label {
+                message: Some(message.clone()),
+                primary: false,
+                region: span.into(),
+            });
+        }
+
+        let suggestions = value
+            .suggestions
+            .iter()
+            .map(|suggestion| Suggestion {
+                message: suggestion.message.clone(),
+                replacement: suggestion.replacement.clone(),
+                applicability: match suggestion.applicability {
+                    Applicability::MachineApplicable => "machine-applicable",
+                    Applicability::MaybeIncorrect => "maybe-incorrect",
+                    Applicability::HasPlaceholders => "has-placeholders",
+                    Applicability::Unspecified => "unspecified",
+                },
+                region: (&suggestion.span).into(),
+            })
+            .collect();
+
+        Diagnostic {
+            code: value.code_string(),
+            severity: match value.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+                Severity::Advice => "advice",
+            },
+            message: value.kind.message(),
+            file: value.source.id().to_owned(),
+            region: Some((&value.span()).into()),
+            labels,
+            suggestions,
+            help: value.help.clone(),
+            note: value.note.clone(),
+            url: value
+                .error_code
+                .as_ref()
+                .and_then(|error_code| error_code.url.clone()),
+        }
+    }
+}
+
+/// Spans are serialized with the id of their file, their byte range, and the
+/// line and column of their start and end.
+impl Serialize for Span {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct SpanData<'a> {
+            file: &'a str,
+            #[serde(flatten)]
+            region: Region,
+        }
+
+        SpanData {
+            file: self.source.id(),
+            region: self.into(),
+        }
+        .serialize(serializer)
+    }
+}
+
+#[derive(Serialize)]
+struct SourceFileRef<'a> {
+    name: &'a str,
+    path: Option<&'a str>,
+    contents: &'a str,
+}
+
+#[derive(Deserialize)]
+struct SourceFileData {
+    name: String,
+    path: Option<String>,
+    contents: String,
+}
+
+/// Source files are serialized with their name, path and contents. The
+/// origins of files created with [`SourceFile::new_virtual`] are not included.
+impl Serialize for SourceFile {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SourceFileRef {
+            name: &self.name,
+            path: self.path.as_deref(),
+            contents: &self.contents,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for SourceFile {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let data = SourceFileData::deserialize(deserializer)?;
+        Ok(SourceFile {
+            name: data.name,
+            path: data.path,
+            line_starts: line_starts(&data.contents),
+            contents: data.contents,
+            origins: vec![],
+        })
+    }
+}
+
+/// Diagnostics are serialized in the format described in [`Error::to_json`].
+impl Serialize for super::Diagnostic<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Diagnostic::from(self.error).serialize(serializer)
+    }
+}
+
+/// Errors are serialized as a sequence of their diagnostics.
+impl Serialize for Error {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.diagnostics())
+    }
+}
+
+impl Error {
+    /// Returns a JSON array describing each diagnostic in this error.
+    ///
+    /// Each diagnostic is an object with the fields `code`, `severity`,
+    /// `message`, `file`, `range` (a byte range), `start` and `end` (each a
+    /// line and column), `labels`, `suggestions`, `help`, `note` and `url`.
+    /// Labels and suggestions also have `range`, `start` and `end` fields.
+    pub fn to_json(&self) -> String {
+        // Diagnostics only contain strings, numbers and string-keyed objects,
+        // which can always be serialized.
+        serde_json::to_string(self).expect("diagnostics are always serializable")
+    }
+}
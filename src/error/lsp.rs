@@ -0,0 +1,140 @@
+use crate::error::Error;
+use crate::error::ErrorKind;
+use crate::error::Severity;
+use crate::error::SingleError;
+use crate::ColumnUnit;
+use crate::SourceFile;
+use crate::Span;
+
+use lsp_types::CodeDescription;
+use lsp_types::Diagnostic;
+use lsp_types::DiagnosticRelatedInformation;
+use lsp_types::DiagnosticSeverity;
+use lsp_types::Location;
+use lsp_types::NumberOrString;
+use lsp_types::Position;
+use lsp_types::Range;
+use lsp_types::Url;
+
+use std::ptr;
+
+/// Converts a byte offset into a zero-based LSP position, with the column
+/// counted in UTF-16 code units.
+fn position(span: &Span, offset: usize) -> Position {
+    let (line, column) = span.location_in(offset, ColumnUnit::Utf16);
+    Position {
+        line: line as u32 - 1,
+        character: column as u32 - 1,
+    }
+}
+
+impl Span {
+    /// Converts this span into an LSP range.
+    ///
+    /// Lines and characters are zero-based, and characters are counted in
+    /// UTF-16 code units, as the Language Server Protocol requires.
+    pub fn to_lsp_range(&self) -> Range {
+        Range {
+            start: position(self, self.start),
+            end: position(self, self.end),
+        }
+    }
+}
+
+impl From<Severity> for DiagnosticSeverity {
+    fn from(value: Severity) -> Self {
+        match value {
+            Severity::Error => DiagnosticSeverity::ERROR,
+            Severity::Warning => DiagnosticSeverity::WARNING,
+            Severity::Advice => DiagnosticSeverity::HINT,
+        }
+    }
+}
+
+impl SingleError {
+    fn lsp_diagnostic<F: Fn(&SourceFile) -> Option<Url>>(&self, uri: &F) -> Diagnostic {
+        let span = self.span();
+
+        let message = self.full_message();
+        let related: Vec<_> = self
+            .related()
+            .into_iter()
+            .filter_map(|(span, message)| {
+                Some(DiagnosticRelatedInformation {
+                    location: Location {
+                        uri: uri(&span.source)?,
+                        range: span.to_lsp_range(),
+                    },
+                    message,
+                })
+            })
+            .collect();
+        let related_information = if related.is_empty() {
+            None
+        } else {
+            Some(related)
+        };
+
+        Diagnostic {
+            range: span.to_lsp_range(),
+            severity: Some(self.severity.into()),
+            code: Some(NumberOrString::String(self.code_string())),
+            code_description: self
+                .error_code
+                .as_ref()
+                .and_then(|error_code| error_code.url.as_ref())
+                .and_then(|url| Url::parse(url).ok())
+                .map(|href| CodeDescription { href }),
+            source: None,
+            message,
+            related_information,
+            tags: None,
+            data: None,
+        }
+    }
+}
+
+impl Error {
+    /// Converts each diagnostic in this error into an LSP [`Diagnostic`] for
+    /// the document at `uri`.
+    ///
+    /// Positions are zero-based, with columns counted in UTF-16 code units as
+    /// the protocol requires. Labels are reported as related information.
+    /// Labels in the diagnostic's own source file are located in `uri`, and
+    /// labels in other files use the `file:` URI of their path; labels in other
+    /// files without an absolute path are left out. Use
+    /// [`Error::to_lsp_diagnostics_with`] to choose the URI of each file.
+    pub fn to_lsp_diagnostics(&self, uri: &Url) -> Vec<Diagnostic> {
+        self.errors
+            .iter()
+            .filter(|error| !matches!(error.kind, ErrorKind::Silent))
+            .map(|error| {
+                error.lsp_diagnostic(&|source: &SourceFile| {
+                    if ptr::eq(source, &*error.source) {
+                        Some(uri.clone())
+                    } else {
+                        Url::from_file_path(source.path()?).ok()
+                    }
+                })
+            })
+            .collect()
+    }
+
+    /// Converts each diagnostic in this error into an LSP [`Diagnostic`],
+    /// using `uri` to find the document of each source file.
+    ///
+    /// This is the same as [`Error::to_lsp_diagnostics`], except that related
+    /// information is located in the document returned by `uri` for the
+    /// label's source file. Labels for which `uri` returns `None` are left
+    /// out.
+    pub fn to_lsp_diagnostics_with<F: Fn(&SourceFile) -> Option<Url>>(
+        &self,
+        uri: F,
+    ) -> Vec<Diagnostic> {
+        self.errors
+            .iter()
+            .filter(|error| !matches!(error.kind, ErrorKind::Silent))
+            .map(|error| error.lsp_diagnostic(&uri))
+            .collect()
+    }
+}
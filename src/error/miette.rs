@@ -0,0 +1,156 @@
+use crate::error::Error;
+use crate::error::ErrorCode;
+use crate::error::ErrorKind;
+use crate::error::Severity;
+use crate::error::SingleError;
+use crate::SourceFile;
+use crate::Span;
+
+use std::fmt;
+
+use miette::Diagnostic;
+use miette::LabeledSpan;
+use miette::MietteError;
+use miette::MietteSpanContents;
+use miette::SourceCode;
+use miette::SourceSpan;
+use miette::SpanContents;
+
+impl SourceCode for SourceFile {
+    fn read_span<'a>(
+        &'a self,
+        span: &SourceSpan,
+        context_lines_before: usize,
+        context_lines_after: usize,
+    ) -> Result<Box<dyn SpanContents<'a> + 'a>, MietteError> {
+        let contents = self
+            .contents
+            .read_span(span, context_lines_before, context_lines_after)?;
+        Ok(Box::new(MietteSpanContents::new_named(
+            self.id().to_owned(),
+            contents.data(),
+            *contents.span(),
+            contents.line(),
+            contents.column(),
+            contents.line_count(),
+        )))
+    }
+}
+
+impl From<&Span> for SourceSpan {
+    fn from(value: &Span) -> Self {
+        (value.start..value.end).into()
+    }
+}
+
+impl From<Severity> for miette::Severity {
+    fn from(value: Severity) -> Self {
+        match value {
+            Severity::Error => miette::Severity::Error,
+            Severity::Warning => miette::Severity::Warning,
+            Severity::Advice => miette::Severity::Advice,
+        }
+    }
+}
+
+impl fmt::Display for SingleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.kind.message())
+    }
+}
+
+impl std::error::Error for SingleError {}
+
+impl Diagnostic for SingleError {
+    fn code<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        Some(Box::new(self.code_string()))
+    }
+
+    fn severity(&self) -> Option<miette::Severity> {
+        Some(self.severity.into())
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        match (&self.help, &self.note) {
+            (Some(help), Some(note)) => Some(Box::new(format!("{help}\nnote: {note}"))),
+            (Some(help), None) => Some(Box::new(help)),
+            (None, Some(note)) => Some(Box::new(format!("note: {note}"))),
+            (None, None) => None,
+        }
+    }
+
+    fn url<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        let url = self.error_code.as_ref().and_then(ErrorCode::url)?;
+        Some(Box::new(url))
+    }
+
+    fn source_code(&self) -> Option<&dyn SourceCode> {
+        Some(&*self.source)
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
+        let mut labels = vec![];
+        match &self.kind {
+            ErrorKind::Silent => {}
+            ErrorKind::EndOfFile { .. } | ErrorKind::UnexpectedToken { .. } => {
+                labels.push(LabeledSpan::new_primary_with_span(
+                    self.kind.expected_message(),
+                    &self.span(),
+                ));
+            }
+            kind => {
+                let span = kind.span().unwrap();
+                labels.push(LabeledSpan::new_primary_with_span(None, span));
+            }
+        }
+        if let Some((open, span)) = self.kind.opener() {
+            labels.push(LabeledSpan::at(span, format!("'{open}' is not closed")));
+        }
+        for (span, message) in &self.labels {
+            labels.push(LabeledSpan::at(span, message));
+        }
+        for suggestion in &self.suggestions {
+            labels.push(LabeledSpan::at(
+                &suggestion.span,
+                format!("{}: '{}'", suggestion.message, suggestion.replacement),
+            ));
+        }
+        Some(Box::new(labels.into_iter()))
+    }
+}
+
+/// The first diagnostic in an [`Error`] is reported directly, with any others
+/// reported as related diagnostics.
+impl Diagnostic for Error {
+    fn code<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        self.diagnostics().next()?.error.code()
+    }
+
+    fn severity(&self) -> Option<miette::Severity> {
+        self.diagnostics().next()?.error.severity()
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        self.diagnostics().next()?.error.help()
+    }
+
+    fn url<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        self.diagnostics().next()?.error.url()
+    }
+
+    fn source_code(&self) -> Option<&dyn SourceCode> {
+        self.diagnostics().next()?.error.source_code()
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
+        self.diagnostics().next()?.error.labels()
+    }
+
+    fn related<'a>(&'a self) -> Option<Box<dyn Iterator<Item = &'a dyn Diagnostic> + 'a>> {
+        Some(Box::new(
+            self.diagnostics()
+                .skip(1)
+                .map(|diagnostic| diagnostic.error as &dyn Diagnostic),
+        ))
+    }
+}
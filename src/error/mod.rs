@@ -12,14 +12,137 @@
 use crate::SourceFile;
 use crate::Span;
 
+use std::any::Any;
+use std::any::TypeId;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::collections::HashSet;
 use std::fmt;
-use std::rc::Rc;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
 
+#[cfg(feature = "annotate-snippets")]
+mod annotate_snippets;
 #[cfg(feature = "ariadne")]
 mod ariadne;
 #[cfg(feature = "ariadne")]
 pub use self::ariadne::Report;
+#[cfg(feature = "ariadne")]
+pub use self::ariadne::ReportStyle;
+#[cfg(feature = "ariadne")]
+pub use ::ariadne::CharSet;
+#[cfg(feature = "ariadne")]
+pub use ::ariadne::Color;
+#[cfg(feature = "ariadne")]
+pub use ::ariadne::Config;
+#[cfg(feature = "ariadne")]
+pub use ::ariadne::LabelAttach;
+#[cfg(feature = "ariadne")]
+mod html;
+#[cfg(feature = "serde")]
+mod json;
+#[cfg(feature = "lsp")]
+mod lsp;
+#[cfg(feature = "miette")]
+mod miette;
+mod plain;
+pub use self::plain::PlainStyle;
+#[cfg(feature = "serde")]
+mod sarif;
+
+/// The tokens expected where an error occurred.
+///
+/// Most errors expect a single token with a fixed name, which is stored without
+/// allocating. A set is only built once more tokens are added, which usually
+/// happens when the error is about to be reported.
+#[derive(Debug, Clone, Default)]
+pub(crate) enum Expected {
+    #[default]
+    None,
+    One(Cow<'static, str>),
+    Many(HashSet<String>),
+}
+
+impl Expected {
+    pub(crate) fn is_empty(&self) -> bool {
+        match self {
+            Expected::None => true,
+            Expected::One(_) => false,
+            Expected::Many(tokens) => tokens.is_empty(),
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Expected::None => 0,
+            Expected::One(_) => 1,
+            Expected::Many(tokens) => tokens.len(),
+        }
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &str> {
+        let (one, many) = match self {
+            Expected::None => (None, None),
+            Expected::One(token) => (Some(&**token), None),
+            Expected::Many(tokens) => (None, Some(tokens)),
+        };
+        one.into_iter()
+            .chain(many.into_iter().flatten().map(String::as_str))
+    }
+
+    pub(crate) fn insert<T: Into<Cow<'static, str>>>(&mut self, token: T) {
+        let token = token.into();
+        match self {
+            Expected::None => *self = Expected::One(token),
+            Expected::One(existing) if *existing == token => {}
+            Expected::One(existing) => {
+                *self = Expected::Many(HashSet::from_iter([
+                    existing.to_string(),
+                    token.into_owned(),
+                ]));
+            }
+            Expected::Many(tokens) => {
+                tokens.insert(token.into_owned());
+            }
+        }
+    }
+
+    pub(crate) fn extend(&mut self, other: Expected) {
+        match other {
+            Expected::None => {}
+            Expected::One(token) => self.insert(token),
+            Expected::Many(tokens) => {
+                for token in tokens {
+                    self.insert(token);
+                }
+            }
+        }
+    }
+
+    pub(crate) fn to_set(&self) -> HashSet<String> {
+        self.iter().map(str::to_string).collect()
+    }
+}
+
+impl From<&'static str> for Expected {
+    fn from(value: &'static str) -> Self {
+        Expected::One(Cow::Borrowed(value))
+    }
+}
+
+impl From<String> for Expected {
+    fn from(value: String) -> Self {
+        Expected::One(Cow::Owned(value))
+    }
+}
+
+impl From<HashSet<String>> for Expected {
+    fn from(value: HashSet<String>) -> Self {
+        Expected::Many(value)
+    }
+}
 
 #[derive(Debug, Clone)]
 #[repr(u16)]
@@ -29,20 +152,46 @@ pub(crate) enum ErrorKind {
     UnterminatedGroup {
         start: String,
         span: Span,
+        open: Span,
     },
     UnterminatedChar(Span),
     LongChar(Span),
     UnterminatedString(Span),
     UnexpectedToken {
-        expected: HashSet<String>,
+        expected: Expected,
         span: Span,
     },
-    EndOfFile(usize),
+    EndOfFile {
+        end: usize,
+        expected: Expected,
+    },
     Custom {
         message: String,
         span: Span,
         code: u16,
     },
+    RecursionLimit(Span),
+    UnmatchedDelimiter {
+        delimiter: String,
+        span: Span,
+        open: Option<(String, Span)>,
+    },
+    TooFewElements {
+        min: usize,
+        span: Span,
+    },
+    TooManyElements {
+        max: usize,
+        span: Span,
+    },
+    Io {
+        message: String,
+        span: Span,
+    },
+    LimitExceeded {
+        message: String,
+        span: Span,
+    },
 }
 
 impl ErrorKind {
@@ -57,6 +206,93 @@ impl ErrorKind {
         }
     }
 
+    /// Returns the span this error is reported at, if it has one.
+    fn span(&self) -> Option<&Span> {
+        match self {
+            ErrorKind::Silent | ErrorKind::EndOfFile { .. } => None,
+            ErrorKind::Custom { span, .. }
+            | ErrorKind::UnknownCharacter(span)
+            | ErrorKind::UnterminatedGroup { span, .. }
+            | ErrorKind::UnterminatedChar(span)
+            | ErrorKind::LongChar(span)
+            | ErrorKind::UnterminatedString(span)
+            | ErrorKind::UnexpectedToken { span, .. }
+            | ErrorKind::RecursionLimit(span)
+            | ErrorKind::UnmatchedDelimiter { span, .. }
+            | ErrorKind::TooFewElements { span, .. }
+            | ErrorKind::TooManyElements { span, .. }
+            | ErrorKind::Io { span, .. }
+            | ErrorKind::LimitExceeded { span, .. } => Some(span),
+        }
+    }
+
+    fn span_mut(&mut self) -> Option<&mut Span> {
+        match self {
+            ErrorKind::Silent | ErrorKind::EndOfFile { .. } => None,
+            ErrorKind::Custom { span, .. }
+            | ErrorKind::UnknownCharacter(span)
+            | ErrorKind::UnterminatedGroup { span, .. }
+            | ErrorKind::UnterminatedChar(span)
+            | ErrorKind::LongChar(span)
+            | ErrorKind::UnterminatedString(span)
+            | ErrorKind::UnexpectedToken { span, .. }
+            | ErrorKind::RecursionLimit(span)
+            | ErrorKind::UnmatchedDelimiter { span, .. }
+            | ErrorKind::TooFewElements { span, .. }
+            | ErrorKind::TooManyElements { span, .. }
+            | ErrorKind::Io { span, .. }
+            | ErrorKind::LimitExceeded { span, .. } => Some(span),
+        }
+    }
+
+    /// Returns the main message of this error.
+    fn message(&self) -> String {
+        match self {
+            ErrorKind::Silent => String::new(),
+            ErrorKind::Custom { message, .. } => message.clone(),
+            ErrorKind::UnknownCharacter(_) => "Unrecognised character".to_string(),
+            ErrorKind::UnterminatedGroup { start, .. } => format!("Unmatched '{start}'"),
+            ErrorKind::UnterminatedChar(_) => "Unterminated character literal".to_string(),
+            ErrorKind::LongChar(_) => {
+                "Character literals must be exactly one character long".to_string()
+            }
+            ErrorKind::UnterminatedString(_) => "Unterminated string literal".to_string(),
+            ErrorKind::UnexpectedToken { .. } => "Unexpected token".to_string(),
+            ErrorKind::EndOfFile { .. } => "Unexpected end of file while parsing".to_string(),
+            ErrorKind::RecursionLimit(_) => "Recursion limit reached".to_string(),
+            ErrorKind::UnmatchedDelimiter { delimiter, .. } => format!("Unmatched '{delimiter}'"),
+            ErrorKind::TooFewElements { min, .. } => format!("Expected at least {min} elements"),
+            ErrorKind::TooManyElements { max, .. } => format!("Expected at most {max} elements"),
+            ErrorKind::Io { message, .. } => format!("Failed to read file: {message}"),
+            ErrorKind::LimitExceeded { message, .. } => message.clone(),
+        }
+    }
+
+    /// Returns a message listing the expected tokens, if there are any.
+    fn expected_message(&self) -> Option<String> {
+        match self {
+            ErrorKind::UnexpectedToken { expected, .. } | ErrorKind::EndOfFile { expected, .. }
+                if !expected.is_empty() =>
+            {
+                Some(unexpected_token_message(expected))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the opening delimiter left unclosed by this error, and its
+    /// span, if there is one.
+    fn opener(&self) -> Option<(&str, &Span)> {
+        match self {
+            ErrorKind::UnterminatedGroup { start, open, .. } => Some((start, open)),
+            ErrorKind::UnmatchedDelimiter {
+                open: Some((open, span)),
+                ..
+            } => Some((open, span)),
+            _ => None,
+        }
+    }
+
     fn start(&self) -> usize {
         match self {
             ErrorKind::Silent => panic!("called `start` on `ErrorKind::Silent`"),
@@ -66,13 +302,19 @@ impl ErrorKind {
             | ErrorKind::UnterminatedChar(span)
             | ErrorKind::LongChar(span)
             | ErrorKind::UnterminatedString(span)
-            | ErrorKind::UnexpectedToken { span, .. } => span.start,
-            ErrorKind::EndOfFile(n) => *n,
+            | ErrorKind::UnexpectedToken { span, .. }
+            | ErrorKind::RecursionLimit(span)
+            | ErrorKind::UnmatchedDelimiter { span, .. }
+            | ErrorKind::TooFewElements { span, .. }
+            | ErrorKind::TooManyElements { span, .. }
+            | ErrorKind::Io { span, .. }
+            | ErrorKind::LimitExceeded { span, .. } => span.start,
+            ErrorKind::EndOfFile { end, .. } => *end,
         }
     }
 }
 
-fn unexpected_token_message(expected: &HashSet<String>) -> String {
+fn unexpected_token_message(expected: &Expected) -> String {
     if expected.len() == 1 {
         format!("Expected {}", expected.iter().next().unwrap())
     } else if expected.len() == 2 {
@@ -94,10 +336,413 @@ fn unexpected_token_message(expected: &HashSet<String>) -> String {
     }
 }
 
+/// The severity of a diagnostic.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Severity {
+    /// An error, which prevents parsing from succeeding.
+    #[default]
+    Error,
+    /// A warning, which is reported but doesn't cause parsing to fail.
+    Warning,
+    /// Advice, such as a suggestion, which is reported but doesn't cause
+    /// parsing to fail.
+    Advice,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "Error"),
+            Severity::Warning => write!(f, "Warning"),
+            Severity::Advice => write!(f, "Advice"),
+        }
+    }
+}
+
+/// A named error code, with a default message and an optional link to its
+/// documentation.
+///
+/// Error codes are registered with [`ParseOptions::error_code`], after which
+/// errors with the code are reported as `E0123`, with a link to the
+/// documentation if there is one.
+///
+/// [`ParseOptions::error_code`]: crate::ParseOptions::error_code
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorCode {
+    code: u16,
+    name: String,
+    message: String,
+    url: Option<String>,
+}
+
+impl ErrorCode {
+    /// Creates a new error code with the given number, name and default
+    /// message.
+    pub fn new(code: u16, name: String, message: String) -> ErrorCode {
+        ErrorCode {
+            code,
+            name,
+            message,
+            url: None,
+        }
+    }
+
+    /// Sets the URL of the documentation for this error code.
+    pub fn with_url(mut self, url: String) -> Self {
+        self.url = Some(url);
+        self
+    }
+
+    /// Returns the number of this error code.
+    pub fn code(&self) -> u16 {
+        self.code
+    }
+
+    /// Returns the name of this error code.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the default message for errors with this code.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Returns the URL of the documentation for this error code, if there is
+    /// one.
+    pub fn url(&self) -> Option<&str> {
+        self.url.as_deref()
+    }
+}
+
+/// How confident a [`Suggestion`] is that its replacement is correct.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Applicability {
+    /// The replacement is definitely correct, and can be applied
+    /// automatically.
+    MachineApplicable,
+    /// The replacement may be correct, but should be checked by the user.
+    MaybeIncorrect,
+    /// The replacement contains placeholders that must be filled in by the
+    /// user.
+    HasPlaceholders,
+    /// The applicability of the replacement is unknown.
+    Unspecified,
+}
+
+/// A suggested fix for a diagnostic, replacing the source code covered by a
+/// span with new text.
+#[derive(Debug, Clone)]
+pub struct Suggestion {
+    message: String,
+    span: Span,
+    replacement: String,
+    applicability: Applicability,
+}
+
+impl Suggestion {
+    /// Returns the message describing this suggestion.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Returns the span of the source code to be replaced.
+    pub fn span(&self) -> &Span {
+        &self.span
+    }
+
+    /// Returns the text to replace the source code covered by
+    /// [`Suggestion::span`] with.
+    pub fn replacement(&self) -> &str {
+        &self.replacement
+    }
+
+    /// Returns how confident this suggestion is.
+    pub fn applicability(&self) -> Applicability {
+        self.applicability
+    }
+}
+
+fn is_identifier(string: &str) -> bool {
+    !string.is_empty() && string.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Returns the Levenshtein distance between `a` and `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a != *b);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Returns the candidate most similar to `actual`, if any is close enough to
+/// be a plausible misspelling.
+fn most_similar<'a, I: Iterator<Item = &'a str>>(actual: &str, candidates: I) -> Option<&'a str> {
+    let max_distance = (actual.chars().count() / 3).max(1);
+    candidates
+        .filter(|candidate| is_identifier(candidate) && *candidate != actual)
+        .map(|candidate| (edit_distance(actual, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+/// The kind of a [`Diagnostic`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum DiagnosticKind {
+    /// A character which doesn't start any token.
+    UnknownCharacter,
+    /// A group which is never closed.
+    UnterminatedGroup,
+    /// A character literal without a closing quote.
+    UnterminatedChar,
+    /// A character literal containing more than one character.
+    LongChar,
+    /// A string literal without a closing quote.
+    UnterminatedString,
+    /// A token other than those expected.
+    UnexpectedToken,
+    /// The end of the input, where more tokens were expected.
+    EndOfFile,
+    /// An error created by [`ParseBuffer::new_error`].
+    ///
+    /// [`ParseBuffer::new_error`]: crate::ParseBuffer::new_error
+    Custom,
+    /// The recursion limit set in [`ParseOptions`] was reached.
+    ///
+    /// [`ParseOptions`]: crate::ParseOptions
+    RecursionLimit,
+    /// A closing delimiter without a matching opening delimiter.
+    UnmatchedDelimiter,
+    /// A repeated item with too few elements.
+    TooFewElements,
+    /// A repeated item with too many elements.
+    TooManyElements,
+    /// A source file that could not be read.
+    Io,
+    /// A token stream that would exceed the limits documented on
+    /// [`TokenStream`].
+    ///
+    /// [`TokenStream`]: crate::TokenStream
+    LimitExceeded,
+}
+
+/// A single diagnostic in an [`Error`].
+///
+/// These are returned by [`Error::diagnostics`].
+#[derive(Debug, Clone, Copy)]
+pub struct Diagnostic<'a> {
+    error: &'a SingleError,
+}
+
+impl<'a> Diagnostic<'a> {
+    /// Returns the kind of this diagnostic.
+    pub fn kind(&self) -> DiagnosticKind {
+        match &self.error.kind {
+            ErrorKind::Silent => unreachable!(),
+            ErrorKind::UnknownCharacter(_) => DiagnosticKind::UnknownCharacter,
+            ErrorKind::UnterminatedGroup { .. } => DiagnosticKind::UnterminatedGroup,
+            ErrorKind::UnterminatedChar(_) => DiagnosticKind::UnterminatedChar,
+            ErrorKind::LongChar(_) => DiagnosticKind::LongChar,
+            ErrorKind::UnterminatedString(_) => DiagnosticKind::UnterminatedString,
+            ErrorKind::UnexpectedToken { .. } => DiagnosticKind::UnexpectedToken,
+            ErrorKind::EndOfFile { .. } => DiagnosticKind::EndOfFile,
+            ErrorKind::Custom { .. } => DiagnosticKind::Custom,
+            ErrorKind::RecursionLimit(_) => DiagnosticKind::RecursionLimit,
+            ErrorKind::UnmatchedDelimiter { .. } => DiagnosticKind::UnmatchedDelimiter,
+            ErrorKind::TooFewElements { .. } => DiagnosticKind::TooFewElements,
+            ErrorKind::TooManyElements { .. } => DiagnosticKind::TooManyElements,
+            ErrorKind::Io { .. } => DiagnosticKind::Io,
+            ErrorKind::LimitExceeded { .. } => DiagnosticKind::LimitExceeded,
+        }
+    }
+
+    /// Returns the severity of this diagnostic.
+    pub fn severity(&self) -> Severity {
+        self.error.severity
+    }
+
+    /// Returns the span this diagnostic is reported at.
+    ///
+    /// For [`DiagnosticKind::EndOfFile`], this is an empty span at the end of
+    /// the input.
+    pub fn span(&self) -> Span {
+        self.error.span()
+    }
+
+    /// Returns the main message of this diagnostic.
+    pub fn message(&self) -> String {
+        self.error.kind.message()
+    }
+
+    /// Returns the tokens that were expected, if this is a
+    /// [`DiagnosticKind::UnexpectedToken`] or [`DiagnosticKind::EndOfFile`]
+    /// diagnostic.
+    pub fn expected(&self) -> Option<HashSet<String>> {
+        match &self.error.kind {
+            ErrorKind::UnexpectedToken { expected, .. } | ErrorKind::EndOfFile { expected, .. } => {
+                Some(expected.to_set())
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the code of this diagnostic, as it is displayed.
+    pub fn code(&self) -> String {
+        self.error.code_string()
+    }
+
+    /// Returns the registered error code of this diagnostic, if it has one.
+    pub fn error_code(&self) -> Option<&'a ErrorCode> {
+        self.error.error_code.as_ref()
+    }
+
+    /// Returns an iterator over the secondary labels of this diagnostic.
+    pub fn labels(&self) -> impl Iterator<Item = (&'a Span, &'a str)> {
+        self.error
+            .labels
+            .iter()
+            .map(|(span, message)| (span, message.as_str()))
+    }
+
+    /// Returns the suggestions attached to this diagnostic.
+    pub fn suggestions(&self) -> &'a [Suggestion] {
+        &self.error.suggestions
+    }
+
+    /// Returns the help message of this diagnostic, if it has one.
+    pub fn help(&self) -> Option<&'a str> {
+        self.error.help.as_deref()
+    }
+
+    /// Returns the note of this diagnostic, if it has one.
+    pub fn note(&self) -> Option<&'a str> {
+        self.error.note.as_deref()
+    }
+
+    /// Returns the value of type `T` attached to this diagnostic with
+    /// [`Error::insert`], if there is one.
+    pub fn get<T: Any + Send + Sync>(&self) -> Option<&'a T> {
+        self.error.get()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub(crate) struct SingleError {
-    source: Rc<SourceFile>,
+    source: Arc<SourceFile>,
     kind: ErrorKind,
+    severity: Severity,
+    labels: Vec<(Span, String)>,
+    help: Option<String>,
+    note: Option<String>,
+    suggestions: Vec<Suggestion>,
+    error_code: Option<ErrorCode>,
+    data: HashMap<TypeId, Arc<dyn Any + Send + Sync>>,
+}
+
+impl SingleError {
+    /// Returns the primary span of this error, which is empty for
+    /// `EndOfFile`.
+    ///
+    /// # Panics
+    /// Panics if this is a `Silent` error.
+    fn span(&self) -> Span {
+        match &self.kind {
+            ErrorKind::EndOfFile { end, .. } => Span::new(*end, *end, Arc::clone(&self.source)),
+            kind => kind.span().unwrap().clone(),
+        }
+    }
+
+    fn get<T: Any + Send + Sync>(&self) -> Option<&T> {
+        self.data.get(&TypeId::of::<T>())?.downcast_ref()
+    }
+
+    fn map_to_origin(&mut self) {
+        if let ErrorKind::EndOfFile { end, .. } = &mut self.kind {
+            let origin = Span::new(*end, *end, Arc::clone(&self.source)).origin();
+            *end = origin.end;
+            self.source = origin.source;
+        }
+        match &mut self.kind {
+            ErrorKind::UnterminatedGroup { open, .. } => *open = open.origin(),
+            ErrorKind::UnmatchedDelimiter {
+                open: Some((_, open)),
+                ..
+            } => *open = open.origin(),
+            _ => {}
+        }
+        if let Some(span) = self.kind.span_mut() {
+            *span = span.origin();
+            self.source = Arc::clone(&span.source);
+        }
+        for (span, _) in &mut self.labels {
+            *span = span.origin();
+        }
+        for suggestion in &mut self.suggestions {
+            suggestion.span = suggestion.span.origin();
+        }
+    }
+
+    fn is_duplicate(&self, other: &SingleError) -> bool {
+        let start = |error: &SingleError| match &error.kind {
+            ErrorKind::EndOfFile { end, .. } => Some(*end),
+            _ => None,
+        };
+        self.kind.code() == other.kind.code()
+            && self.kind.span() == other.kind.span()
+            && start(self) == start(other)
+            && self.kind.message() == other.kind.message()
+            && Arc::ptr_eq(&self.source, &other.source)
+    }
+
+    /// Returns the message of this error, followed by what was expected, with
+    /// any help and note on lines of their own.
+    #[cfg(any(feature = "lsp", feature = "serde"))]
+    fn full_message(&self) -> String {
+        let mut message = self.kind.message();
+        if let Some(expected) = self.kind.expected_message() {
+            message = format!("{message}: {expected}");
+        }
+        if let Some(help) = &self.help {
+            message = format!("{message}\nhelp: {help}");
+        }
+        if let Some(note) = &self.note {
+            message = format!("{message}\nnote: {note}");
+        }
+        message
+    }
+
+    /// Returns the secondary locations of this error: the opening delimiter of
+    /// an unclosed group, followed by each label.
+    #[cfg(any(feature = "lsp", feature = "serde"))]
+    fn related(&self) -> Vec<(&Span, String)> {
+        let mut related = vec![];
+        if let Some((open, span)) = self.kind.opener() {
+            related.push((span, format!("'{open}' is not closed")));
+        }
+        related.extend(
+            self.labels
+                .iter()
+                .map(|(span, message)| (span, message.clone())),
+        );
+        related
+    }
+
+    /// Returns the code of this error as displayed in reports.
+    fn code_string(&self) -> String {
+        match &self.error_code {
+            Some(error_code) => format!("E{:04}", error_code.code),
+            None => format!("E{:02}", self.kind.code()),
+        }
+    }
 }
 
 /// An error or collection of errors raised during parsing.
@@ -113,12 +758,42 @@ pub struct Error {
 }
 
 impl Error {
-    pub(crate) fn new(source: Rc<SourceFile>, kind: ErrorKind) -> Error {
+    pub(crate) fn new(source: &Arc<SourceFile>, kind: ErrorKind) -> Error {
+        // Tokens spliced in from other files carry spans in those files.
+        let source = Arc::clone(kind.span().map_or(source, |span| &span.source));
         Error {
-            errors: vec![SingleError { source, kind }],
+            errors: vec![SingleError {
+                source,
+                kind,
+                severity: Severity::Error,
+                labels: vec![],
+                help: None,
+                note: None,
+                suggestions: vec![],
+                error_code: None,
+                data: HashMap::new(),
+            }],
         }
     }
 
+    pub(crate) fn io(path: &Path, error: &io::Error) -> Error {
+        let path = path.to_string_lossy().into_owned();
+        let source = Arc::new(SourceFile {
+            name: path.clone(),
+            path: Some(path),
+            contents: String::new(),
+            line_starts: vec![0],
+            origins: vec![],
+        });
+        Error::new(
+            &source,
+            ErrorKind::Io {
+                message: error.to_string(),
+                span: Span::new(0, 0, Arc::clone(&source)),
+            },
+        )
+    }
+
     pub(crate) fn empty() -> Error {
         Error { errors: vec![] }
     }
@@ -127,12 +802,14 @@ impl Error {
         self.errors.is_empty()
     }
 
-    pub(crate) fn eof_to_group(&mut self, span: Span, start: String) {
+    pub(crate) fn eof_to_group(&mut self, open: Span, start: String) {
         for error in &mut self.errors {
-            if let ErrorKind::EndOfFile(_) = error.kind {
+            if let ErrorKind::EndOfFile { end, .. } = error.kind {
+                let end = end.max(open.end);
                 error.kind = ErrorKind::UnterminatedGroup {
                     start: start.clone(),
-                    span: span.clone(),
+                    span: Span::new(end, end, Arc::clone(&open.source)),
+                    open: open.clone(),
                 };
             }
         }
@@ -140,8 +817,8 @@ impl Error {
 
     pub(crate) fn group_to_string(&mut self) {
         for error in &mut self.errors {
-            if let ErrorKind::UnterminatedGroup { span, .. } = &error.kind {
-                let span = span.to_owned();
+            if let ErrorKind::UnterminatedGroup { span, open, .. } = &error.kind {
+                let span = Span::new(open.start, span.end, Arc::clone(&open.source));
                 error.kind = ErrorKind::UnterminatedString(span);
             }
         }
@@ -149,24 +826,429 @@ impl Error {
 
     pub(crate) fn group_to_char(&mut self) {
         for error in &mut self.errors {
-            if let ErrorKind::UnterminatedGroup { span, .. } = &error.kind {
-                let span = span.to_owned();
+            if let ErrorKind::UnterminatedGroup { span, open, .. } = &error.kind {
+                let span = Span::new(open.start, span.end, Arc::clone(&open.source));
                 error.kind = ErrorKind::UnterminatedChar(span);
             }
         }
     }
 
+    /// Returns the position and expected tokens of the furthest
+    /// `UnexpectedToken` error in `self`.
+    pub(crate) fn furthest_expected(&self) -> Option<(usize, &Expected)> {
+        self.errors
+            .iter()
+            .filter_map(|error| match &error.kind {
+                ErrorKind::UnexpectedToken { expected, span } => Some((span.start, expected)),
+                ErrorKind::EndOfFile { end, expected } if !expected.is_empty() => {
+                    Some((*end, expected))
+                }
+                _ => None,
+            })
+            .max_by_key(|(start, _)| *start)
+    }
+
+    /// Adds `tokens` to the expected tokens of every `UnexpectedToken` or
+    /// `EndOfFile` error starting at `start`.
+    pub(crate) fn merge_expected(&mut self, start: usize, tokens: &Expected) {
+        for error in &mut self.errors {
+            match &mut error.kind {
+                ErrorKind::UnexpectedToken { expected, span } if span.start == start => {
+                    expected.extend(tokens.clone());
+                }
+                ErrorKind::EndOfFile { end, expected } if *end == start => {
+                    expected.extend(tokens.clone());
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Adds `tokens` to the expected tokens of every `EndOfFile` error.
+    pub(crate) fn expected_at_end<T: Into<Expected>>(mut self, tokens: T) -> Self {
+        let tokens = tokens.into();
+        for error in &mut self.errors {
+            if let ErrorKind::EndOfFile { expected, .. } = &mut error.kind {
+                expected.extend(tokens.clone());
+            }
+        }
+        self
+    }
+
+    /// Keeps only the first `len` reported diagnostics, noting how many were
+    /// dropped on the last one kept.
+    pub(crate) fn truncate(&mut self, len: usize) {
+        let hidden = self.drop_after(len);
+        self.note_hidden(hidden);
+    }
+
+    /// Keeps only the first `len` reported diagnostics, returning how many
+    /// reported diagnostics were dropped.
+    pub(crate) fn drop_after(&mut self, len: usize) -> usize {
+        let mut reported = 0;
+        let Some(index) = self.errors.iter().position(|error| {
+            if !matches!(error.kind, ErrorKind::Silent) {
+                reported += 1;
+            }
+            reported > len
+        }) else {
+            return 0;
+        };
+        let hidden = self.errors[index..]
+            .iter()
+            .filter(|error| !matches!(error.kind, ErrorKind::Silent))
+            .count();
+        self.errors.truncate(index);
+        hidden
+    }
+
+    /// Notes on the last reported diagnostic that `hidden` more were not
+    /// shown.
+    pub(crate) fn note_hidden(&mut self, hidden: usize) {
+        if hidden == 0 {
+            return;
+        }
+        if let Some(error) = self
+            .errors
+            .iter_mut()
+            .rev()
+            .find(|error| !matches!(error.kind, ErrorKind::Silent))
+        {
+            let message = if hidden == 1 {
+                "1 more error not shown".to_string()
+            } else {
+                format!("{hidden} more errors not shown")
+            };
+            error.note = Some(match error.note.take() {
+                Some(note) => format!("{note}\n{message}"),
+                None => message,
+            });
+        }
+    }
+
+    /// Consumes `self`, returning it with the severity of every diagnostic
+    /// set to `severity`.
+    pub fn with_severity(mut self, severity: Severity) -> Self {
+        for error in &mut self.errors {
+            error.severity = severity;
+        }
+        self
+    }
+
+    /// Consumes `self`, returning it with a secondary label at `location`
+    /// attached to the most recently added diagnostic.
+    ///
+    /// ## Example
+    /// ```
+    /// # use flexi_parse::parse_string;
+    /// # use flexi_parse::token::LeftParen;
+    /// # use flexi_parse::token::RightParen;
+    /// # use flexi_parse::Parse;
+    /// # use flexi_parse::ParseStream;
+    /// # use flexi_parse::Result;
+    /// struct Unit;
+    ///
+    /// impl Parse for Unit {
+    ///     fn parse(input: ParseStream<'_>) -> Result<Self> {
+    ///         let open: LeftParen = input.parse()?;
+    ///         input.parse::<RightParen>().map_err(|err| {
+    ///             err.with_label(&open, "to match this '('".to_string())
+    ///         })?;
+    ///         Ok(Unit)
+    ///     }
+    /// }
+    ///
+    /// # fn main() {
+    /// let err = parse_string::<Unit>("( x".to_string()).err().unwrap();
+    /// assert!(err.to_string().contains("to match this '('"));
+    /// # }
+    /// ```
+    pub fn with_label<T: Into<Span>>(mut self, location: T, message: String) -> Self {
+        if let Some(error) = self.errors.last_mut() {
+            error.labels.push((location.into(), message));
+        }
+        self
+    }
+
+    /// Consumes `self`, returning it with help text attached to the most
+    /// recently added diagnostic, replacing any existing help text.
+    ///
+    /// Help text should give actionable advice, such as "wrap the expression
+    /// in parentheses".
+    pub fn with_help(mut self, help: String) -> Self {
+        if let Some(error) = self.errors.last_mut() {
+            error.help = Some(help);
+        }
+        self
+    }
+
+    /// Consumes `self`, returning it with a note attached to the most recently
+    /// added diagnostic, replacing any existing note.
+    pub fn with_note(mut self, note: String) -> Self {
+        if let Some(error) = self.errors.last_mut() {
+            error.note = Some(note);
+        }
+        self
+    }
+
+    /// Attaches `value` to the most recently added diagnostic, replacing any
+    /// value of the same type already attached.
+    ///
+    /// This allows structured information to be passed along with an error,
+    /// and retrieved with [`Error::get`] or [`Diagnostic::get`].
+    ///
+    /// # Example
+    /// ```
+    /// # use flexi_parse::parse_string;
+    /// # use flexi_parse::token::Ident;
+    /// # use flexi_parse::Parse;
+    /// # use flexi_parse::ParseStream;
+    /// # use flexi_parse::Result;
+    /// struct Undefined(String);
+    ///
+    /// # #[derive(Debug)]
+    /// struct Variable(Ident);
+    ///
+    /// impl Parse for Variable {
+    ///     fn parse(input: ParseStream) -> Result<Self> {
+    ///         let ident: Ident = input.parse()?;
+    ///         let mut err = input.new_error("Undefined variable".to_string(), &ident, 0);
+    ///         err.insert(Undefined(ident.string().to_owned()));
+    ///         Err(err)
+    ///     }
+    /// }
+    ///
+    /// let err = parse_string::<Variable>("x".to_string()).unwrap_err();
+    /// assert_eq!(err.get::<Undefined>().unwrap().0, "x");
+    /// ```
+    pub fn insert<T: Any + Send + Sync>(&mut self, value: T) {
+        if let Some(error) = self.errors.last_mut() {
+            error.data.insert(TypeId::of::<T>(), Arc::new(value));
+        }
+    }
+
+    /// Returns the first value of type `T` attached to a diagnostic in `self`
+    /// with [`Error::insert`].
+    pub fn get<T: Any + Send + Sync>(&self) -> Option<&T> {
+        self.errors.iter().find_map(SingleError::get)
+    }
+
+    /// Consumes `self`, returning it with a suggestion to replace the source
+    /// code at `location` with `replacement` attached to the most recently
+    /// added diagnostic.
+    pub fn with_suggestion<T: Into<Span>>(
+        mut self,
+        message: String,
+        location: T,
+        replacement: String,
+        applicability: Applicability,
+    ) -> Self {
+        if let Some(error) = self.errors.last_mut() {
+            error.suggestions.push(Suggestion {
+                message,
+                span: location.into(),
+                replacement,
+                applicability,
+            });
+        }
+        self
+    }
+
+    /// Returns an iterator over the diagnostics in `self`.
+    pub fn diagnostics(&self) -> impl Iterator<Item = Diagnostic<'_>> {
+        self.errors
+            .iter()
+            .filter(|error| !matches!(error.kind, ErrorKind::Silent))
+            .map(|error| Diagnostic { error })
+    }
+
+    /// Prints this error to stderr, returning the number of diagnostics
+    /// printed.
+    ///
+    /// This is useful for command line tools that report errors and then exit
+    /// with a status code, rather than panicking.
+    pub fn eprint_and_count(&self) -> io::Result<usize> {
+        self.eprint()?;
+        Ok(self.diagnostics().count())
+    }
+
+    /// Returns the primary span of the first diagnostic in `self`.
+    ///
+    /// This is the location an editor should highlight for the error. For an
+    /// unexpected end of file, it is an empty span at the end of the input.
+    pub fn first_span(&self) -> Option<Span> {
+        self.diagnostics()
+            .next()
+            .map(|diagnostic| diagnostic.span())
+    }
+
+    /// Returns an iterator over the suggestions attached to every diagnostic
+    /// in `self`.
+    pub fn suggestions(&self) -> impl Iterator<Item = &Suggestion> {
+        self.errors.iter().flat_map(|error| &error.suggestions)
+    }
+
+    /// Attaches `error_code` to every diagnostic in `self`.
+    pub(crate) fn with_error_code(mut self, error_code: ErrorCode) -> Self {
+        for error in &mut self.errors {
+            error.error_code = Some(error_code.clone());
+        }
+        self
+    }
+
+    /// Returns an iterator over the registered error codes of the diagnostics
+    /// in `self`.
+    ///
+    /// Diagnostics without a registered code are skipped.
+    pub fn error_codes(&self) -> impl Iterator<Item = &ErrorCode> {
+        self.errors
+            .iter()
+            .filter_map(|error| error.error_code.as_ref())
+    }
+
+    /// Adds a "did you mean" suggestion to every `UnexpectedToken` error in
+    /// `self` where the unexpected token is an identifier similar to one of the
+    /// expected tokens or `candidates`.
+    pub(crate) fn suggest_similar(&mut self, candidates: &HashSet<String>) {
+        for error in &mut self.errors {
+            let ErrorKind::UnexpectedToken { expected, span } = &error.kind else {
+                continue;
+            };
+            let actual = &span.source.contents[span.start..span.end];
+            if !is_identifier(actual) || error.help.is_some() {
+                continue;
+            }
+            if let Some(best) = most_similar(
+                actual,
+                expected.iter().chain(candidates.iter().map(String::as_str)),
+            ) {
+                error.help = Some(format!("did you mean `{best}`?"));
+                error.suggestions.push(Suggestion {
+                    message: "did you mean".to_string(),
+                    span: span.clone(),
+                    replacement: best.to_string(),
+                    applicability: Applicability::MaybeIncorrect,
+                });
+            }
+        }
+    }
+
+    /// Sets the severity of every diagnostic in `self` that is an error to
+    /// [`Severity::Warning`].
+    pub(crate) fn into_warning(mut self) -> Self {
+        for error in &mut self.errors {
+            if error.severity == Severity::Error {
+                error.severity = Severity::Warning;
+            }
+        }
+        self
+    }
+
     /// Appends the given error to this one.
-    pub fn add(&mut self, mut other: Error) {
-        self.errors.append(&mut other.errors);
+    ///
+    /// Diagnostics of the same kind with the same span and message as one
+    /// already in this error are dropped, except that the expected tokens of
+    /// duplicate unexpected token errors are combined.
+    pub fn add(&mut self, other: Error) {
+        for error in other.errors {
+            let duplicate = if matches!(error.kind, ErrorKind::Silent) {
+                None
+            } else {
+                self.errors
+                    .iter_mut()
+                    .find(|existing| existing.is_duplicate(&error))
+            };
+            match duplicate {
+                None => self.errors.push(error),
+                Some(existing) => {
+                    if let (
+                        ErrorKind::UnexpectedToken {
+                            expected: tokens, ..
+                        },
+                        ErrorKind::UnexpectedToken { expected, .. },
+                    ) = (&mut existing.kind, error.kind)
+                    {
+                        tokens.extend(expected);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Consumes `self`, returning it with its diagnostics ordered by file and
+    /// then by position in the file.
+    ///
+    /// Diagnostics at the same position keep the order they were added in.
+    pub fn sorted(mut self) -> Self {
+        self.sort_by_source(|a, b| a.id().cmp(b.id()));
+        self
+    }
+
+    /// Consumes `self`, returning it with every span in a file created with
+    /// [`SourceFile::new_virtual`] replaced by the span of the code it was
+    /// generated from.
+    ///
+    /// This is done automatically by [`parse_source`][crate::parse_source]
+    /// and related functions.
+    pub fn into_origin(mut self) -> Self {
+        for error in &mut self.errors {
+            error.map_to_origin();
+        }
+        self
+    }
+
+    /// Sorts the diagnostics in `self` by their source files using `compare`,
+    /// and then by position in the file.
+    pub(crate) fn sort_by_source<F: FnMut(&Arc<SourceFile>, &Arc<SourceFile>) -> Ordering>(
+        &mut self,
+        mut compare: F,
+    ) {
+        self.errors.sort_by(|a, b| {
+            let key = |error: &SingleError| match error.kind {
+                ErrorKind::Silent => usize::MAX,
+                ref kind => kind.start(),
+            };
+            compare(&a.source, &b.source).then_with(|| key(a).cmp(&key(b)))
+        });
     }
 
     /// Consumes `self` and `other`, returning a new error with the contents of
     /// both.
+    ///
+    /// Duplicate diagnostics are combined as in [`Error::add`].
     pub fn with(mut self, other: Error) -> Self {
         self.add(other);
         self
     }
+
+    /// Combines `errors` into a single error, returning `None` if there are
+    /// no errors.
+    ///
+    /// Duplicate diagnostics are combined as in [`Error::add`].
+    pub fn combine<I: IntoIterator<Item = Error>>(errors: I) -> Option<Error> {
+        let error: Error = errors.into_iter().collect();
+        if error.is_empty() {
+            None
+        } else {
+            Some(error)
+        }
+    }
+}
+
+impl Extend<Error> for Error {
+    fn extend<T: IntoIterator<Item = Error>>(&mut self, iter: T) {
+        for error in iter {
+            self.add(error);
+        }
+    }
+}
+
+impl FromIterator<Error> for Error {
+    fn from_iter<T: IntoIterator<Item = Error>>(iter: T) -> Self {
+        let mut error = Error::empty();
+        error.extend(iter);
+        error
+    }
 }
 
 impl fmt::Display for Error {
@@ -175,34 +1257,48 @@ impl fmt::Display for Error {
             match &error.kind {
                 ErrorKind::Silent => {}
                 ErrorKind::Custom { message, span, .. } => {
-                    writeln!(f, "[E{:02}] Error: {}", error.kind.code(), message)?;
+                    writeln!(
+                        f,
+                        "[{}] {}: {}",
+                        error.code_string(),
+                        error.severity,
+                        message
+                    )?;
                     let (line, col) = span.start_location();
                     write!(f, "[{}:{}:{}]", error.source.id(), line, col)?;
                 }
                 ErrorKind::UnknownCharacter(span) => {
                     writeln!(
                         f,
-                        "[E{:02}] Error: Unrecognised character",
-                        error.kind.code()
+                        "[{}] {}: Unrecognised character",
+                        error.code_string(),
+                        error.severity
                     )?;
                     let (line, col) = span.start_location();
                     write!(f, "[{}:{}:{}]", error.source.id(), line, col)?;
                 }
-                ErrorKind::UnterminatedGroup { start, span } => {
+                ErrorKind::UnterminatedGroup { start, span, open } => {
                     writeln!(
                         f,
-                        "[E{:02}] Error: Unmatched '{}'",
-                        error.kind.code(),
+                        "[{}] {}: Unmatched '{}'",
+                        error.code_string(),
+                        error.severity,
                         start
                     )?;
                     let (line, col) = span.start_location();
                     write!(f, "[{}:{}:{}]", error.source.id(), line, col)?;
+                    let (line, col) = open.start_location();
+                    write!(
+                        f,
+                        "\nNote: '{start}' opened at [{line}:{col}] is not closed"
+                    )?;
                 }
                 ErrorKind::UnterminatedChar(span) => {
                     writeln!(
                         f,
-                        "[E{:02}] Error: Unterminated character literal",
-                        error.kind.code()
+                        "[{}] {}: Unterminated character literal",
+                        error.code_string(),
+                        error.severity
                     )?;
                     let (line, col) = span.start_location();
                     write!(f, "[{}:{}:{}]", error.source.id(), line, col)?;
@@ -210,8 +1306,9 @@ impl fmt::Display for Error {
                 ErrorKind::LongChar(span) => {
                     writeln!(
                         f,
-                        "[E{:02}] Error: Character literals must be exactly one character long",
-                        error.kind.code()
+                        "[{}] {}: Character literals must be exactly one character long",
+                        error.code_string(),
+                        error.severity
                     )?;
                     let (line, col) = span.start_location();
                     write!(f, "[{}:{}:{}]", error.source.id(), line, col)?;
@@ -219,22 +1316,179 @@ impl fmt::Display for Error {
                 ErrorKind::UnterminatedString(span) => {
                     writeln!(
                         f,
-                        "[E{:02}] Error: Unterminated string literal",
-                        error.kind.code()
+                        "[{}] {}: Unterminated string literal",
+                        error.code_string(),
+                        error.severity
                     )?;
                     let (line, col) = span.start_location();
                     write!(f, "[{}:{}:{}]", error.source.id(), line, col)?;
                 }
                 ErrorKind::UnexpectedToken { expected, span } => {
-                    writeln!(f, "[E{:02}] Error: Unexpected token", error.kind.code())?;
+                    writeln!(
+                        f,
+                        "[{}] {}: Unexpected token",
+                        error.code_string(),
+                        error.severity
+                    )?;
                     let (line, col) = span.start_location();
                     writeln!(f, "[{}:{}:{}]", error.source.id(), line, col)?;
                     write!(f, "{}", unexpected_token_message(expected))?;
                 }
-                ErrorKind::EndOfFile(_) => write!(f, "Unexpected end of file while parsing")?,
+                ErrorKind::EndOfFile { expected, .. } => {
+                    write!(f, "Unexpected end of file while parsing")?;
+                    if !expected.is_empty() {
+                        write!(f, "\n{}", unexpected_token_message(expected))?;
+                    }
+                }
+                ErrorKind::RecursionLimit(span) => {
+                    writeln!(
+                        f,
+                        "[{}] {}: Recursion limit reached",
+                        error.code_string(),
+                        error.severity
+                    )?;
+                    let (line, col) = span.start_location();
+                    write!(f, "[{}:{}:{}]", error.source.id(), line, col)?;
+                }
+                ErrorKind::UnmatchedDelimiter {
+                    delimiter,
+                    span,
+                    open,
+                } => {
+                    writeln!(
+                        f,
+                        "[{}] {}: Unmatched '{}'",
+                        error.code_string(),
+                        error.severity,
+                        delimiter
+                    )?;
+                    let (line, col) = span.start_location();
+                    write!(f, "[{}:{}:{}]", error.source.id(), line, col)?;
+                    if let Some((open, span)) = open {
+                        let (line, col) = span.start_location();
+                        write!(f, "\nNote: '{open}' opened at [{line}:{col}] is not closed")?;
+                    }
+                }
+                ErrorKind::TooFewElements { min, span } => {
+                    writeln!(
+                        f,
+                        "[{}] {}: Expected at least {} elements",
+                        error.code_string(),
+                        error.severity,
+                        min
+                    )?;
+                    let (line, col) = span.start_location();
+                    write!(f, "[{}:{}:{}]", error.source.id(), line, col)?;
+                }
+                ErrorKind::TooManyElements { max, span } => {
+                    writeln!(
+                        f,
+                        "[{}] {}: Expected at most {} elements",
+                        error.code_string(),
+                        error.severity,
+                        max
+                    )?;
+                    let (line, col) = span.start_location();
+                    write!(f, "[{}:{}:{}]", error.source.id(), line, col)?;
+                }
+                ErrorKind::Io { message, .. } => {
+                    writeln!(
+                        f,
+                        "[{}] {}: Failed to read file: {}",
+                        error.code_string(),
+                        error.severity,
+                        message
+                    )?;
+                    write!(f, "[{}]", error.source.id())?;
+                }
+                ErrorKind::LimitExceeded { message, span } => {
+                    writeln!(
+                        f,
+                        "[{}] {}: {}",
+                        error.code_string(),
+                        error.severity,
+                        message
+                    )?;
+                    let (line, col) = span.start_location();
+                    write!(f, "[{}:{}:{}]", error.source.id(), line, col)?;
+                }
+            }
+            for (span, message) in &error.labels {
+                let (line, col) = span.start_location();
+                write!(f, "\nNote: {message} at [{line}:{col}]")?;
+            }
+            for suggestion in &error.suggestions {
+                let (line, col) = suggestion.span.start_location();
+                write!(
+                    f,
+                    "\nSuggestion: {}: '{}' at [{line}:{col}]",
+                    suggestion.message, suggestion.replacement
+                )?;
+            }
+            if let Some(help) = &error.help {
+                write!(f, "\nHelp: {help}")?;
+            }
+            if let Some(url) = error.error_code.as_ref().and_then(ErrorCode::url) {
+                write!(f, "\nSee: {url}")?;
+            }
+            if let Some(note) = &error.note {
+                write!(f, "\nNote: {note}")?;
             }
         }
 
         Ok(())
     }
 }
+
+impl std::error::Error for Error {}
+
+/// The warnings added with [`ParseBuffer::add_warning`] while parsing.
+///
+/// Unlike an [`Error`], these don't mean that parsing failed. They can be
+/// inspected with [`Warnings::diagnostics`], or converted into an [`Error`]
+/// with [`Warnings::into_error`] to be reported in the same way as errors.
+///
+/// [`ParseBuffer::add_warning`]: crate::ParseBuffer::add_warning
+#[derive(Debug, Clone)]
+pub struct Warnings {
+    warnings: Error,
+}
+
+impl Warnings {
+    pub(crate) fn new(warnings: Option<Error>) -> Warnings {
+        Warnings {
+            warnings: warnings.unwrap_or_else(Error::empty),
+        }
+    }
+
+    /// Returns true if there are no warnings.
+    pub fn is_empty(&self) -> bool {
+        self.diagnostics().next().is_none()
+    }
+
+    /// Returns the number of warnings.
+    pub fn len(&self) -> usize {
+        self.diagnostics().count()
+    }
+
+    /// Returns an iterator over the diagnostics of the warnings.
+    pub fn diagnostics(&self) -> impl Iterator<Item = Diagnostic<'_>> {
+        self.warnings.diagnostics()
+    }
+
+    /// Converts these warnings into an [`Error`] for reporting, or returns
+    /// `None` if there are none.
+    pub fn into_error(self) -> Option<Error> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(self.warnings)
+        }
+    }
+}
+
+impl fmt::Display for Warnings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.warnings, f)
+    }
+}
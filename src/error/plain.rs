@@ -0,0 +1,155 @@
+use crate::error::Error;
+use crate::error::ErrorCode;
+use crate::error::ErrorKind;
+use crate::error::Severity;
+use crate::error::SingleError;
+use crate::ColumnUnit;
+
+use std::fmt::Write;
+
+/// Options for [`Error::render_plain_with_style`].
+///
+/// # Example
+/// ```
+/// # use flexi_parse::error::PlainStyle;
+/// let verbose = PlainStyle::new().context_lines(2);
+/// let ci = PlainStyle::new().compact(true);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PlainStyle {
+    context_lines: usize,
+    compact: bool,
+    columns: ColumnUnit,
+}
+
+impl PlainStyle {
+    /// Creates the default style, which shows only the line containing each
+    /// error.
+    pub fn new() -> PlainStyle {
+        PlainStyle::default()
+    }
+
+    /// Sets the number of source lines shown before and after the line
+    /// containing each error.
+    pub fn context_lines(mut self, context_lines: usize) -> Self {
+        self.context_lines = context_lines;
+        self
+    }
+
+    /// Sets the unit that reported columns are measured in.
+    ///
+    /// Defaults to [`ColumnUnit::Char`].
+    pub fn columns(mut self, columns: ColumnUnit) -> Self {
+        self.columns = columns;
+        self
+    }
+
+    /// Writes each diagnostic on a single line, without any source excerpt,
+    /// help or notes.
+    pub fn compact(mut self, compact: bool) -> Self {
+        self.compact = compact;
+        self
+    }
+}
+
+impl SingleError {
+    fn render_plain(&self, out: &mut String, style: &PlainStyle) {
+        let contents = &self.source.contents;
+        let start = self.kind.start().min(contents.len());
+        let end = self
+            .kind
+            .span()
+            .map_or(start, |span| span.end.min(contents.len()));
+
+        let index = self.source.line_index(start);
+        let text = self.source.line(index).unwrap();
+        let line_start = self.source.line_starts[index];
+        let line_end = line_start + text.len();
+        let line = index + 1;
+        let indent = contents[line_start..start].chars().count();
+        let column = style.columns.count(&contents[line_start..start]) + 1;
+
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Advice => "advice",
+        };
+        let mut message = self.kind.message();
+        if let Some(expected) = self.kind.expected_message() {
+            message = format!("{message}: {expected}");
+        }
+        let _ = writeln!(
+            out,
+            "{}:{line}:{column}: {severity}: {message}",
+            self.source.id()
+        );
+        if style.compact {
+            return;
+        }
+
+        for before in index.saturating_sub(style.context_lines)..index {
+            let _ = writeln!(out, "{}", self.source.line(before).unwrap());
+        }
+        let carets = contents[start..end.clamp(start, line_end)]
+            .chars()
+            .count()
+            .max(1);
+        let _ = writeln!(out, "{text}");
+        let _ = writeln!(out, "{}{}", " ".repeat(indent), "^".repeat(carets));
+        for after in index + 1..=index + style.context_lines {
+            if let Some(text) = self.source.line(after) {
+                let _ = writeln!(out, "{text}");
+            }
+        }
+        if let Some((open, span)) = self.kind.opener() {
+            let (line, column) = span.location_in(span.start, style.columns);
+            let _ = writeln!(
+                out,
+                "note: '{open}' opened at {line}:{column} is not closed"
+            );
+        }
+
+        if let Some(help) = &self.help {
+            let _ = writeln!(out, "help: {help}");
+        }
+        if let Some(note) = &self.note {
+            let _ = writeln!(out, "note: {note}");
+        }
+        if let Some(url) = self.error_code.as_ref().and_then(ErrorCode::url) {
+            let _ = writeln!(out, "see: {url}");
+        }
+    }
+}
+
+impl Error {
+    /// Renders this error as plain text, without colours or the `ariadne`
+    /// dependency.
+    ///
+    /// Each diagnostic is written as `file:line:col: severity: message`,
+    /// followed by the offending source line and a line of carets under the
+    /// reported span.
+    pub fn render_plain(&self) -> String {
+        self.render_plain_with_style(&PlainStyle::new())
+    }
+
+    /// Renders this error as plain text, as [`Error::render_plain`] does,
+    /// using the given style.
+    pub fn render_plain_with_style(&self, style: &PlainStyle) -> String {
+        let mut out = String::new();
+        for error in &self.errors {
+            if !matches!(error.kind, ErrorKind::Silent) {
+                error.render_plain(&mut out, style);
+            }
+        }
+        out
+    }
+
+    /// Prints this error to stderr.
+    ///
+    /// Without the `ariadne` feature, this uses [`Error::render_plain`].
+    #[cfg(not(feature = "ariadne"))]
+    pub fn eprint(&self) -> std::io::Result<()> {
+        use std::io::Write as _;
+        std::io::stderr().write_all(self.render_plain().as_bytes())
+    }
+}
@@ -0,0 +1,135 @@
+use crate::error::Error;
+use crate::error::ErrorKind;
+use crate::error::Severity;
+use crate::error::SingleError;
+use crate::Span;
+
+use serde_json::json;
+use serde_json::Value;
+
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+fn region(span: &Span) -> Value {
+    let (start_line, start_column) = span.start_location();
+    let (end_line, end_column) = span.end_location();
+    json!({
+        "startLine": start_line,
+        "startColumn": start_column,
+        "endLine": end_line,
+        "endColumn": end_column,
+        "byteOffset": span.start,
+        "byteLength": span.end - span.start,
+    })
+}
+
+fn physical_location(span: &Span) -> Value {
+    json!({
+        "artifactLocation": { "uri": span.source.id() },
+        "region": region(span),
+    })
+}
+
+impl SingleError {
+    fn sarif_result(&self) -> Value {
+        let span = self.span();
+
+        let message = self.full_message();
+        let related: Vec<Value> = self
+            .related()
+            .into_iter()
+            .enumerate()
+            .map(|(id, (span, message))| {
+                json!({
+                    "id": id,
+                    "physicalLocation": physical_location(span),
+                    "message": { "text": message },
+                })
+            })
+            .collect();
+
+        let fixes: Vec<Value> = self
+            .suggestions
+            .iter()
+            .map(|suggestion| {
+                json!({
+                    "description": { "text": suggestion.message },
+                    "artifactChanges": [{
+                        "artifactLocation": { "uri": suggestion.span.source.id() },
+                        "replacements": [{
+                            "deletedRegion": region(&suggestion.span),
+                            "insertedContent": { "text": suggestion.replacement },
+                        }],
+                    }],
+                })
+            })
+            .collect();
+
+        let mut result = json!({
+            "ruleId": self.code_string(),
+            "level": match self.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+                Severity::Advice => "note",
+            },
+            "message": { "text": message },
+            "locations": [physical_location(&span)],
+        });
+        if !related.is_empty() {
+            result["relatedLocations"] = Value::Array(related);
+        }
+        if !fixes.is_empty() {
+            result["fixes"] = Value::Array(fixes);
+        }
+        result
+    }
+}
+
+impl Error {
+    /// Returns a [SARIF 2.1.0][sarif] log describing each diagnostic in this
+    /// error, reported as a single run of the tool `tool_name`.
+    ///
+    /// This can be uploaded to SARIF consumers such as GitHub code scanning.
+    ///
+    /// [sarif]: https://docs.oasis-open.org/sarif/sarif/v2.1.0/sarif-v2.1.0.html
+    pub fn to_sarif(&self, tool_name: &str) -> String {
+        let errors: Vec<&SingleError> = self
+            .errors
+            .iter()
+            .filter(|error| !matches!(error.kind, ErrorKind::Silent))
+            .collect();
+
+        let mut rules: Vec<Value> = vec![];
+        for error in &errors {
+            let id = error.code_string();
+            if rules.iter().any(|rule| rule["id"] == id) {
+                continue;
+            }
+            let mut rule = json!({ "id": id });
+            if let Some(error_code) = &error.error_code {
+                rule["name"] = json!(error_code.name);
+                rule["shortDescription"] = json!({ "text": error_code.message });
+                if let Some(url) = &error_code.url {
+                    rule["helpUri"] = json!(url);
+                }
+            }
+            rules.push(rule);
+        }
+
+        let log = json!({
+            "$schema": SCHEMA,
+            "version": "2.1.0",
+            "runs": [{
+                "tool": {
+                    "driver": {
+                        "name": tool_name,
+                        "rules": rules,
+                    },
+                },
+                "results": errors.iter().map(|error| error.sarif_result()).collect::<Vec<_>>(),
+            }],
+        });
+        // A `Value` built with `json!` only has string keys, so it can always
+        // be serialized.
+        serde_json::to_string(&log).expect("JSON values are always serializable")
+    }
+}
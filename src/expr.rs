@@ -0,0 +1,640 @@
+//! A precedence-climbing parser for operator expressions.
+//!
+//! The core of this module is the [`ExprParser`] type, which parses a stream
+//! of atoms separated by prefix, infix, and postfix operators, using callbacks
+//! to build the syntax tree.
+//!
+//! Operators are identified by their token type. Each operator has a
+//! precedence, where operators with a higher precedence bind more tightly, and
+//! infix operators also have an [`Associativity`]. Where more than one
+//! operator matches the next tokens, the operator covering the most tokens is
+//! used, so `==` and `=` can be declared in any order.
+//!
+//! Infix operators can also be declared while parsing, such as by a Haskell
+//! style `infixl 6 +++` declaration, by adding them to the stream's
+//! [`OperatorTable`]. These are parsed by the callback given to
+//! [`ExprParser::declared_infix`].
+//!
+//! The [`operators`] macro generates an enum for a set of operators, each with
+//! its own precedence, which can be added with a single callback using
+//! [`ExprParser::infix_operators`].
+//!
+//! # Example
+//! ```
+//! # use flexi_parse::expr::Associativity;
+//! # use flexi_parse::expr::ExprParser;
+//! # use flexi_parse::parse_string;
+//! # use flexi_parse::token::LitInt;
+//! # use flexi_parse::Parse;
+//! # use flexi_parse::ParseStream;
+//! # use flexi_parse::Punct;
+//! # use flexi_parse::Result;
+//! #
+//! struct Value(i64);
+//!
+//! impl Parse for Value {
+//!     fn parse(input: ParseStream<'_>) -> Result<Self> {
+//!         ExprParser::new(|input| Ok(Value(input.parse::<LitInt>()?.value() as i64)))
+//!             .infix(1, Associativity::Left, |l: Value, _: Punct!["+"], r: Value| Value(l.0 + r.0))
+//!             .infix(1, Associativity::Left, |l: Value, _: Punct!["-"], r: Value| Value(l.0 - r.0))
+//!             .infix(2, Associativity::Left, |l: Value, _: Punct!["*"], r: Value| Value(l.0 * r.0))
+//!             .prefix(3, |_: Punct!["-"], value: Value| Value(-value.0))
+//!             .parse(input)
+//!     }
+//! }
+//!
+//! # fn main() {
+//! let value: Value = parse_string("1 + 2 * -3 - 4".to_string()).unwrap();
+//! assert_eq!(value.0, -9);
+//! # }
+//! ```
+
+use crate::token::Spacing;
+use crate::token::Token;
+use crate::Entry;
+use crate::ParseStream;
+use crate::Result;
+use crate::Span;
+
+use std::collections::HashMap;
+
+/// The associativity of an infix operator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Associativity {
+    /// `a + b + c` is parsed as `(a + b) + c`.
+    Left,
+    /// `a = b = c` is parsed as `a = (b = c)`.
+    Right,
+}
+
+type Prefix<'a, T> =
+    Box<dyn Fn(ParseStream<'_>, &dyn Fn(ParseStream<'_>) -> Result<T>) -> Result<T> + 'a>;
+type Infix<'a, T> =
+    Box<dyn Fn(ParseStream<'_>, T, &dyn Fn(ParseStream<'_>) -> Result<T>) -> Result<T> + 'a>;
+type Postfix<'a, T> = Box<dyn Fn(ParseStream<'_>, T) -> Result<T> + 'a>;
+type Declared<'a, T> = dyn Fn(T, DeclaredOperator, T) -> T + 'a;
+
+type Find<'a> = Box<dyn Fn(ParseStream<'_>) -> Option<Found> + 'a>;
+
+struct Operator<'a, A> {
+    find: Find<'a>,
+    apply: A,
+}
+
+/// An operator found at the start of the input.
+#[derive(Clone, Copy)]
+struct Found {
+    /// The offset after the operator.
+    end: usize,
+    precedence: u8,
+    associativity: Associativity,
+}
+
+impl Found {
+    /// Returns the lowest binding power for this operator's precedence.
+    ///
+    /// A binding power of zero is reserved for the start of an expression.
+    fn base_power(&self) -> u16 {
+        self.binding_power().0.min(self.binding_power().1)
+    }
+
+    /// Returns the left and right binding powers of this operator.
+    fn binding_power(&self) -> (u16, u16) {
+        binding_power(self.precedence, self.associativity)
+    }
+}
+
+fn binding_power(precedence: u8, associativity: Associativity) -> (u16, u16) {
+    let base = u16::from(precedence) * 2 + 1;
+    match associativity {
+        Associativity::Left => (base, base + 1),
+        Associativity::Right => (base + 1, base),
+    }
+}
+
+/// Returns a function finding the operator `O`, which always has the given
+/// precedence and associativity.
+fn fixed<'a, O: Token>(precedence: u8, associativity: Associativity) -> Find<'a> {
+    Box::new(move |input| {
+        let fork = input.fork();
+        O::parse(&fork).ok()?;
+        Some(Found {
+            end: fork.cursor.offset.get(),
+            precedence,
+            associativity,
+        })
+    })
+}
+
+/// Finds the operator covering the most tokens at the start of `input`.
+fn longest<'b, 'a, A>(
+    operators: &'b [Operator<'a, A>],
+    input: ParseStream<'_>,
+) -> Option<(Found, &'b Operator<'a, A>)> {
+    let mut best: Option<(Found, &Operator<A>)> = None;
+    for operator in operators {
+        if let Some(found) = (operator.find)(input) {
+            if best.is_none_or(|(best, _)| found.end > best.end) {
+                best = Some((found, operator));
+            }
+        }
+    }
+    best
+}
+
+/// Finds the longest run of joined punctuation at the start of `input` for
+/// which `matches` returns true.
+///
+/// Returns the offset after the punctuation, its text and its span.
+fn longest_punct<F: FnMut(&str) -> bool>(
+    input: ParseStream<'_>,
+    mut matches: F,
+) -> Option<(usize, String, Span)> {
+    let cursor = &input.cursor;
+    let start = cursor.offset.get();
+    let mut offset = start;
+    let mut symbol = String::new();
+    let mut best = None;
+    while offset < cursor.last {
+        let Entry::Punct(punct) = cursor.entry(offset) else {
+            break;
+        };
+        symbol.push(char::from(punct.kind));
+        offset += 1;
+        if matches(&symbol) {
+            let span = Span::across(cursor.entry(start).span(), &punct.span);
+            best = Some((offset, symbol.clone(), span));
+        }
+        if punct.spacing == Spacing::Alone {
+            break;
+        }
+    }
+    best
+}
+
+/// Parses the longest of `symbols` at the start of `input`, returning its
+/// index and span.
+#[doc(hidden)]
+pub fn parse_symbol(input: ParseStream<'_>, symbols: &[&str]) -> Option<(usize, Span)> {
+    let (end, symbol, span) = longest_punct(input, |symbol| symbols.contains(&symbol))?;
+    input.cursor.offset.set(end);
+    let index = symbols.iter().position(|&other| other == symbol).unwrap();
+    Some((index, span))
+}
+
+/// Operators whose precedence depends on which operator was parsed, such as
+/// the enums generated by [`operators`](crate::operators).
+///
+/// See [`ExprParser::infix_operators`].
+pub trait Precedence: Token {
+    /// Returns the precedence and associativity of this operator as an infix
+    /// operator, or `None` if it can't be used as one.
+    fn precedence(&self) -> Option<(u8, Associativity)>;
+}
+
+/// A table of infix operators declared at runtime.
+///
+/// Each operator is made up of one or more adjacent punctuation characters.
+/// The table for a stream can be accessed using
+/// [`ParseBuffer::operator_table`][crate::ParseBuffer::operator_table].
+#[derive(Debug, Clone, Default)]
+pub struct OperatorTable {
+    infix: HashMap<String, (u8, Associativity)>,
+}
+
+impl OperatorTable {
+    /// Creates a new empty table.
+    pub fn new() -> OperatorTable {
+        OperatorTable::default()
+    }
+
+    /// Declares an infix operator, replacing any previous declaration of the
+    /// same symbol.
+    pub fn declare_infix(&mut self, symbol: String, precedence: u8, associativity: Associativity) {
+        self.infix.insert(symbol, (precedence, associativity));
+    }
+
+    /// Removes the declaration of the given symbol, returning true if it was
+    /// present.
+    pub fn remove(&mut self, symbol: &str) -> bool {
+        self.infix.remove(symbol).is_some()
+    }
+
+    /// Returns the precedence and associativity of the given symbol, if it
+    /// has been declared.
+    pub fn get(&self, symbol: &str) -> Option<(u8, Associativity)> {
+        self.infix.get(symbol).copied()
+    }
+}
+
+/// An operator found using an [`OperatorTable`].
+#[derive(Debug, Clone)]
+pub struct DeclaredOperator {
+    symbol: String,
+    span: Span,
+}
+
+impl DeclaredOperator {
+    /// Returns the punctuation making up this operator.
+    pub fn symbol(&self) -> &str {
+        &self.symbol
+    }
+
+    /// Returns the span covered by this operator.
+    pub fn span(&self) -> &Span {
+        &self.span
+    }
+}
+
+impl PartialEq for DeclaredOperator {
+    fn eq(&self, other: &Self) -> bool {
+        self.symbol == other.symbol
+    }
+}
+
+struct DeclaredMatch {
+    end: usize,
+    operator: DeclaredOperator,
+    precedence: u8,
+    associativity: Associativity,
+}
+
+/// Finds the longest declared operator at the start of `input`.
+fn declared_operator(input: ParseStream<'_>) -> Option<DeclaredMatch> {
+    let table = input.context.operators.borrow();
+    let (end, symbol, span) = longest_punct(input, |symbol| table.get(symbol).is_some())?;
+    let (precedence, associativity) = table.get(&symbol)?;
+    Some(DeclaredMatch {
+        end,
+        operator: DeclaredOperator { symbol, span },
+        precedence,
+        associativity,
+    })
+}
+
+enum Next<'b, 'a, T> {
+    Postfix(Found, &'b Operator<'a, Postfix<'a, T>>),
+    Infix(Found, &'b Operator<'a, Infix<'a, T>>),
+    Declared(
+        DeclaredMatch,
+        &'b (dyn Fn(T, DeclaredOperator, T) -> T + 'a),
+    ),
+}
+
+/// A parser for expressions made up of atoms and operators.
+///
+/// For more information, see the [module documentation][module].
+///
+/// [module]: crate::expr
+pub struct ExprParser<'a, T> {
+    atom: Box<dyn Fn(ParseStream<'_>) -> Result<T> + 'a>,
+    prefix: Vec<Operator<'a, Prefix<'a, T>>>,
+    infix: Vec<Operator<'a, Infix<'a, T>>>,
+    postfix: Vec<Operator<'a, Postfix<'a, T>>>,
+    declared: Option<Box<Declared<'a, T>>>,
+}
+
+impl<'a, T> ExprParser<'a, T> {
+    /// Creates a new parser with no operators, which uses `atom` to parse the
+    /// operands.
+    pub fn new<F: Fn(ParseStream<'_>) -> Result<T> + 'a>(atom: F) -> Self {
+        ExprParser {
+            atom: Box::new(atom),
+            prefix: vec![],
+            infix: vec![],
+            postfix: vec![],
+            declared: None,
+        }
+    }
+
+    /// Adds a prefix operator, such as `-` in `-x`.
+    ///
+    /// The operand of a prefix operator contains only operators with a higher
+    /// precedence.
+    pub fn prefix<O: Token, F: Fn(O, T) -> T + 'a>(mut self, precedence: u8, f: F) -> Self {
+        self.prefix.push(Operator {
+            find: fixed::<O>(precedence, Associativity::Left),
+            apply: Box::new(move |input, operand| {
+                let operator: O = input.parse()?;
+                Ok(f(operator, operand(input)?))
+            }),
+        });
+        self
+    }
+
+    /// Adds an infix operator, such as `+` in `x + y`.
+    pub fn infix<O: Token, F: Fn(T, O, T) -> T + 'a>(
+        mut self,
+        precedence: u8,
+        associativity: Associativity,
+        f: F,
+    ) -> Self {
+        self.infix.push(Operator {
+            find: fixed::<O>(precedence, associativity),
+            apply: Box::new(move |input, left, right| {
+                let operator: O = input.parse()?;
+                Ok(f(left, operator, right(input)?))
+            }),
+        });
+        self
+    }
+
+    /// Adds the infix operators of type `O`, each with the precedence and
+    /// associativity given by [`Precedence::precedence`].
+    ///
+    /// This is intended for operator sets generated by
+    /// [`operators`](crate::operators), so that a single callback builds every
+    /// binary expression.
+    pub fn infix_operators<O: Precedence, F: Fn(T, O, T) -> T + 'a>(mut self, f: F) -> Self {
+        self.infix.push(Operator {
+            find: Box::new(|input| {
+                let fork = input.fork();
+                let operator = O::parse(&fork).ok()?;
+                let (precedence, associativity) = operator.precedence()?;
+                Some(Found {
+                    end: fork.cursor.offset.get(),
+                    precedence,
+                    associativity,
+                })
+            }),
+            apply: Box::new(move |input, left, right| {
+                let operator: O = input.parse()?;
+                Ok(f(left, operator, right(input)?))
+            }),
+        });
+        self
+    }
+
+    /// Adds a postfix operator, such as `?` in `x?`.
+    pub fn postfix<O: Token, F: Fn(T, O) -> T + 'a>(mut self, precedence: u8, f: F) -> Self {
+        self.postfix.push(Operator {
+            find: fixed::<O>(precedence, Associativity::Left),
+            apply: Box::new(move |input, operand| {
+                let operator: O = input.parse()?;
+                Ok(f(operand, operator))
+            }),
+        });
+        self
+    }
+
+    /// Uses `f` to build infix operators found in the stream's
+    /// [`OperatorTable`].
+    ///
+    /// Declared operators are only recognised once this method has been
+    /// called.
+    pub fn declared_infix<F: Fn(T, DeclaredOperator, T) -> T + 'a>(mut self, f: F) -> Self {
+        self.declared = Some(Box::new(f));
+        self
+    }
+
+    /// Parses an expression from `input`.
+    ///
+    /// Parsing stops at the first token that isn't an operator, so the whole
+    /// of `input` won't necessarily be consumed.
+    pub fn parse(&self, input: ParseStream<'_>) -> Result<T> {
+        self.parse_binding_power(input, 0)
+    }
+
+    fn parse_binding_power(&self, input: ParseStream<'_>, min: u16) -> Result<T> {
+        let mut left = if let Some((found, operator)) = longest(&self.prefix, input) {
+            // Skip over both binding powers of operators with this precedence.
+            let power = found.base_power() + 2;
+            (operator.apply)(input, &|input| self.parse_binding_power(input, power))?
+        } else {
+            (self.atom)(input)?
+        };
+
+        loop {
+            let mut next = None;
+            let mut next_end = 0;
+            if let Some((found, operator)) = longest(&self.postfix, input) {
+                next = Some(Next::Postfix(found, operator));
+                next_end = found.end;
+            }
+            if let Some((found, operator)) = longest(&self.infix, input) {
+                if found.end > next_end {
+                    next = Some(Next::Infix(found, operator));
+                    next_end = found.end;
+                }
+            }
+            if let Some(f) = &self.declared {
+                if let Some(found) = declared_operator(input) {
+                    if found.end > next_end {
+                        next = Some(Next::Declared(found, &**f));
+                    }
+                }
+            }
+
+            match next {
+                Some(Next::Postfix(found, postfix)) => {
+                    if found.binding_power().0 < min {
+                        break;
+                    }
+                    left = (postfix.apply)(input, left)?;
+                }
+                Some(Next::Infix(found, infix)) => {
+                    let (left_power, right_power) = found.binding_power();
+                    if left_power < min {
+                        break;
+                    }
+                    left = (infix.apply)(input, left, &|input| {
+                        self.parse_binding_power(input, right_power)
+                    })?;
+                }
+                Some(Next::Declared(found, f)) => {
+                    let (left_power, right_power) =
+                        binding_power(found.precedence, found.associativity);
+                    if left_power < min {
+                        break;
+                    }
+                    input.cursor.offset.set(found.end);
+                    let right = self.parse_binding_power(input, right_power)?;
+                    left = f(left, found.operator, right);
+                }
+                None => break,
+            }
+        }
+
+        Ok(left)
+    }
+}
+
+/// Generate an enum for a set of punctuation operators.
+///
+/// Each variant holds the span of the operator. When parsed, the operator
+/// covering the most tokens is used, so `<` and `<<` can be declared in any
+/// order, and operators that aren't recognised by [`Punct`](crate::Punct),
+/// such as `:=`, can be used.
+///
+/// An operator can be followed by its precedence and associativity as an
+/// infix operator, which are returned by [`Precedence::precedence`] and used
+/// by [`ExprParser::infix_operators`].
+///
+/// ## Usage
+/// ```
+/// # use flexi_parse::expr::ExprParser;
+/// # use flexi_parse::parse_string;
+/// # use flexi_parse::token::LitInt;
+/// # use flexi_parse::Parse;
+/// # use flexi_parse::ParseStream;
+/// # use flexi_parse::Result;
+/// mod op {
+///     use flexi_parse::operators;
+///     operators! {
+///         pub enum BinOp {
+///             Add => "+" (1, Left),
+///             Shl => "<<" (0, Left),
+///             Pow => "**" (2, Right),
+///             Walrus => ":=",
+///         }
+///     }
+/// }
+///
+/// struct Value(u64);
+///
+/// impl Parse for Value {
+///     fn parse(input: ParseStream<'_>) -> Result<Self> {
+///         ExprParser::new(|input| Ok(Value(input.parse::<LitInt>()?.value())))
+///             .infix_operators(|l: Value, op: op::BinOp, r: Value| match op {
+///                 op::BinOp::Add(_) => Value(l.0 + r.0),
+///                 op::BinOp::Shl(_) => Value(l.0 << r.0),
+///                 op::BinOp::Pow(_) => Value(l.0.pow(r.0 as u32)),
+///                 op::BinOp::Walrus(_) => unreachable!(),
+///             })
+///             .parse(input)
+///     }
+/// }
+///
+/// # fn main() {
+/// let value: Value = parse_string("1 << 1 + 2 ** 2 ** 1".to_string()).unwrap();
+/// assert_eq!(value.0, 32);
+/// let op: op::BinOp = parse_string(":=".to_string()).unwrap();
+/// assert_eq!(op.to_string(), ":=");
+/// # }
+/// ```
+#[macro_export]
+macro_rules! operators {
+    (
+        $( #[$attr:meta] )*
+        $vis:vis enum $name:ident {
+            $(
+                $( #[$variant_attr:meta] )*
+                $variant:ident => $symbol:literal $( ( $precedence:expr, $associativity:ident ) )?
+            ),+ $(,)?
+        }
+    ) => {
+        $( #[$attr] )*
+        #[derive(Debug, Clone)]
+        $vis enum $name {
+            $(
+                $( #[$variant_attr] )*
+                #[doc = concat!("`", $symbol, "`")]
+                $variant($crate::Span),
+            )+
+        }
+
+        impl $name {
+            const SYMBOLS: &'static [&'static str] = &[$( $symbol ),+];
+
+            /// Returns the punctuation making up this operator.
+            #[allow(dead_code)]
+            pub fn symbol(&self) -> &'static str {
+                match self {
+                    $( Self::$variant(_) => $symbol, )+
+                }
+            }
+        }
+
+        impl $crate::Parse for $name {
+            fn parse(input: $crate::ParseStream<'_>) -> $crate::Result<Self> {
+                let variants: &[fn($crate::Span) -> Self] = &[$( Self::$variant ),+];
+                match $crate::expr::parse_symbol(input, Self::SYMBOLS) {
+                    ::std::option::Option::Some((index, span)) => {
+                        $crate::Result::Ok(variants[index](span))
+                    }
+                    ::std::option::Option::None => {
+                        $crate::Result::Err(input.unexpected_token_named("an operator"))
+                    }
+                }
+            }
+        }
+
+        impl $crate::private::Sealed for $name {}
+
+        impl $crate::visit::Visit for $name {
+            $crate::__visit_any!();
+        }
+
+        impl $crate::visit::Fold for $name {
+            $crate::__fold_any!();
+        }
+
+        impl $crate::token::Token for $name {
+            fn span(&self) -> &$crate::Span {
+                match self {
+                    $( Self::$variant(span) )|+ => span,
+                }
+            }
+
+            fn set_span(&mut self, span: $crate::Span) {
+                match self {
+                    $( Self::$variant(old) )|+ => *old = span,
+                }
+            }
+
+            fn display() -> String {
+                "an operator".to_string()
+            }
+
+            fn append_tokens(&self, tokens: &mut $crate::TokenStream) {
+                $crate::token::push_text(tokens, self.symbol(), $crate::token::Token::span(self));
+            }
+        }
+
+        impl $crate::Spanned for $name {
+            fn span(&self) -> $crate::Span {
+                $crate::token::Token::span(self).clone()
+            }
+        }
+
+        impl $crate::expr::Precedence for $name {
+            fn precedence(&self) -> ::std::option::Option<(u8, $crate::expr::Associativity)> {
+                match self {
+                    $(
+                        Self::$variant(_) => {
+                            $crate::operators!(@precedence $( $precedence, $associativity )?)
+                        }
+                    )+
+                }
+            }
+        }
+
+        impl ::std::fmt::Display for $name {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                f.write_str(self.symbol())
+            }
+        }
+
+        impl ::std::cmp::PartialEq for $name {
+            fn eq(&self, other: &Self) -> bool {
+                ::std::mem::discriminant(self) == ::std::mem::discriminant(other)
+            }
+        }
+
+        impl ::std::cmp::Eq for $name {}
+
+        #[doc(hidden)]
+        #[allow(dead_code, non_snake_case)]
+        $vis fn $name(marker: $crate::Marker) -> $name {
+            match marker {}
+        }
+    };
+    (@precedence $precedence:expr, $associativity:ident) => {
+        ::std::option::Option::Some(($precedence, $crate::expr::Associativity::$associativity))
+    };
+    (@precedence) => {
+        ::std::option::Option::None
+    };
+}
+
+pub use operators;
@@ -0,0 +1,343 @@
+//! Support for the [`grammar`](crate::grammar!) macro.
+//!
+//! Each rule declared with the macro becomes a tuple struct wrapping a
+//! [`Sequence`], which records which alternative of the rule was matched and
+//! the [`Element`]s it was made of.
+//!
+//! # Example
+//! ```
+//! # use flexi_parse::grammar;
+//! # use flexi_parse::grammar::Element;
+//! # use flexi_parse::parse_string;
+//! # use flexi_parse::token::Ident;
+//! # use flexi_parse::token::LitInt;
+//! #
+//! grammar! {
+//!     Expr = Term (("+" | "-") Term)*;
+//!     Term = LitInt | Ident | "(" Expr ")";
+//! }
+//!
+//! # fn main() {
+//! let expr: Expr = parse_string("x + (1 - y)".to_string()).unwrap();
+//! let Element::Repeat(tail) = &expr.0.elements()[1] else {
+//!     panic!("expected a repetition");
+//! };
+//! assert_eq!(tail.len(), 1);
+//! # }
+//! ```
+
+use crate::error::Error;
+use crate::error::ErrorKind;
+use crate::token::Ident;
+use crate::token::Spacing;
+use crate::Entry;
+use crate::ParseStream;
+use crate::Result;
+use crate::Span;
+
+use std::any::Any;
+use std::fmt;
+
+/// A single part of a rule matched by the [`grammar`](crate::grammar!) macro.
+pub enum Element {
+    /// A quoted token, such as `"+"` or `"while"`.
+    Token(Span),
+    /// A type parsed using its [`Parse`](crate::Parse) implementation,
+    /// including other rules.
+    Node(Box<dyn Any>),
+    /// A parenthesised group of elements.
+    Sequence(Sequence),
+    /// A group followed by `*` or `+`.
+    Repeat(Vec<Sequence>),
+    /// A group followed by `?`.
+    Optional(Option<Sequence>),
+}
+
+impl Element {
+    /// Returns the contained node if this element is a [`Element::Node`] of
+    /// type `T`.
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        if let Element::Node(node) = self {
+            node.downcast_ref()
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Debug for Element {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Element::Token(span) => f.debug_tuple("Token").field(span).finish(),
+            Element::Node(_) => f.write_str("Node(..)"),
+            Element::Sequence(sequence) => f.debug_tuple("Sequence").field(sequence).finish(),
+            Element::Repeat(sequences) => f.debug_tuple("Repeat").field(sequences).finish(),
+            Element::Optional(sequence) => f.debug_tuple("Optional").field(sequence).finish(),
+        }
+    }
+}
+
+/// One alternative of a rule or group, as matched by the
+/// [`grammar`](crate::grammar!) macro.
+#[derive(Debug)]
+pub struct Sequence {
+    alternative: usize,
+    elements: Vec<Element>,
+}
+
+impl Sequence {
+    /// Returns the index of the alternative that was matched, counting from
+    /// zero.
+    pub fn alternative(&self) -> usize {
+        self.alternative
+    }
+
+    /// Returns the elements making up this sequence.
+    pub fn elements(&self) -> &[Element] {
+        &self.elements
+    }
+
+    /// Consumes `self`, returning the elements making up this sequence.
+    pub fn into_elements(self) -> Vec<Element> {
+        self.elements
+    }
+}
+
+#[doc(hidden)]
+pub type Alternative<'a> = &'a dyn Fn(ParseStream<'_>) -> Result<Vec<Element>>;
+
+fn literal_impl(input: ParseStream<'_>, text: &str) -> Result<Span> {
+    if text.starts_with(|c: char| c.is_alphanumeric() || c == '_') {
+        let ident: Ident = input.parse()?;
+        return if input.is_keyword(&ident, text) {
+            Ok(ident.span)
+        } else {
+            Err(Error::empty())
+        };
+    }
+
+    let mut chars = text.chars().peekable();
+    let mut start = None;
+    let mut end = None;
+    while let Some(c) = chars.next() {
+        let Entry::Punct(punct) = input.next()? else {
+            return Err(Error::empty());
+        };
+        if char::from(punct.kind) != c
+            || (chars.peek().is_some() && punct.spacing != Spacing::Joint)
+        {
+            return Err(Error::empty());
+        }
+        start.get_or_insert_with(|| punct.span.clone());
+        end = Some(punct.span.clone());
+    }
+    match (start, end) {
+        (Some(start), Some(end)) => Ok(Span::across(&start, &end)),
+        _ => Err(Error::empty()),
+    }
+}
+
+#[doc(hidden)]
+pub fn literal(input: ParseStream<'_>, text: &str) -> Result<Element> {
+    let span = input.current_span()?;
+    let fork = input.fork();
+    match literal_impl(&fork, text) {
+        Ok(span) => {
+            input.commit(&fork);
+            Ok(Element::Token(span))
+        }
+        Err(_) => Err(Error::new(
+            input.source,
+            ErrorKind::UnexpectedToken {
+                expected: format!("'{text}'").into(),
+                span,
+            },
+        )),
+    }
+}
+
+#[doc(hidden)]
+pub fn choice(input: ParseStream<'_>, alternatives: &[Alternative<'_>]) -> Result<Sequence> {
+    let mut furthest: Option<(usize, Error)> = None;
+    for (alternative, f) in alternatives.iter().enumerate() {
+        let fork = input.fork();
+        match f(&fork) {
+            Ok(elements) => {
+                input.commit(&fork);
+                return Ok(Sequence {
+                    alternative,
+                    elements,
+                });
+            }
+            Err(err) => {
+                input.record_expected(&err);
+                let offset = fork.cursor.offset.get();
+                if furthest.as_ref().is_none_or(|(max, _)| offset > *max) {
+                    furthest = Some((offset, err));
+                }
+            }
+        }
+    }
+    Err(furthest.map_or_else(Error::empty, |(_, err)| err))
+}
+
+#[doc(hidden)]
+pub fn repeat(
+    input: ParseStream<'_>,
+    f: &dyn Fn(ParseStream<'_>) -> Result<Sequence>,
+) -> Result<Vec<Sequence>> {
+    let mut sequences = vec![];
+    while !input.is_empty() {
+        let fork = input.fork();
+        match f(&fork) {
+            // Stop if nothing was consumed, since that would repeat forever.
+            Ok(sequence) if fork.cursor.offset.get() > input.cursor.offset.get() => {
+                input.commit(&fork);
+                sequences.push(sequence);
+            }
+            Ok(_) => break,
+            Err(err) => {
+                input.record_expected(&err);
+                break;
+            }
+        }
+    }
+    Ok(sequences)
+}
+
+#[doc(hidden)]
+pub fn repeat1(
+    input: ParseStream<'_>,
+    f: &dyn Fn(ParseStream<'_>) -> Result<Sequence>,
+) -> Result<Vec<Sequence>> {
+    let mut sequences = vec![f(input)?];
+    sequences.append(&mut repeat(input, f)?);
+    Ok(sequences)
+}
+
+#[doc(hidden)]
+pub fn optional(
+    input: ParseStream<'_>,
+    f: &dyn Fn(ParseStream<'_>) -> Result<Sequence>,
+) -> Result<Option<Sequence>> {
+    let fork = input.fork();
+    match f(&fork) {
+        Ok(sequence) => {
+            input.commit(&fork);
+            Ok(Some(sequence))
+        }
+        Err(err) => {
+            input.record_expected(&err);
+            Ok(None)
+        }
+    }
+}
+
+/// Generates [`Parse`](crate::Parse) implementations from a grammar.
+///
+/// Each rule has the form `Name = ...;`, and generates a tuple struct `Name`
+/// wrapping a [`Sequence`]. The right hand side of a rule is made up of:
+/// - quoted tokens, such as `"+"`, `"::"`, or `"while"`,
+/// - the names of types implementing [`Parse`](crate::Parse), including other
+///   rules,
+/// - parenthesised groups, optionally followed by `*` (zero or more), `+` (one
+///   or more), or `?` (optional),
+/// - and alternatives separated by `|`, which are tried in order.
+///
+/// Rules and groups backtrack when an alternative fails, and repetitions stop
+/// at the first iteration that fails to parse.
+///
+/// Large grammars may need to raise the `recursion_limit` of the crate using
+/// this macro.
+///
+/// For an example, see the [module documentation][module].
+///
+/// [module]: crate::grammar
+#[macro_export]
+macro_rules! grammar {
+    (@rule [$( #[$attr:meta] )*] $name:ident [$( $body:tt )*] ; $( $rest:tt )*) => {
+        $( #[$attr] )*
+        #[derive(Debug)]
+        pub struct $name(pub $crate::grammar::Sequence);
+
+        impl $crate::Parse for $name {
+            fn parse(input: $crate::ParseStream<'_>) -> $crate::Result<Self> {
+                $crate::grammar!(@alts input [] [] $( $body )*).map($name)
+            }
+        }
+
+        $crate::grammar! { $( $rest )* }
+    };
+    (@rule [$( $attrs:tt )*] $name:ident [$( $body:tt )*] $t:tt $( $rest:tt )*) => {
+        $crate::grammar!(@rule [$( $attrs )*] $name [$( $body )* $t] $( $rest )*);
+    };
+
+    (@alts $input:ident [$( $alts:tt )*] [$( $current:tt )*] | $( $rest:tt )*) => {
+        $crate::grammar!(@alts $input [$( $alts )* [$( $current )*]] [] $( $rest )*)
+    };
+    (@alts $input:ident [$( $alts:tt )*] [$( $current:tt )*] $t:tt $( $rest:tt )*) => {
+        $crate::grammar!(@alts $input [$( $alts )*] [$( $current )* $t] $( $rest )*)
+    };
+    (@alts $input:ident [$( [$( $alt:tt )*] )*] [$( $current:tt )*]) => {
+        $crate::grammar::choice($input, &[
+            $( &|input: $crate::ParseStream<'_>| $crate::grammar!(@seq input [] $( $alt )*), )*
+            &|input: $crate::ParseStream<'_>| $crate::grammar!(@seq input [] $( $current )*),
+        ])
+    };
+
+    (@seq $input:ident [$( $element:expr ),*]) => {
+        $crate::Result::Ok(::std::vec![$( $element ),*])
+    };
+    (@seq $input:ident [$( $element:expr ),*] $lit:literal $( $rest:tt )*) => {
+        $crate::grammar!(@seq $input [
+            $( $element, )*
+            $crate::grammar::literal($input, $lit)?
+        ] $( $rest )*)
+    };
+    (@seq $input:ident [$( $element:expr ),*] ( $( $group:tt )* ) * $( $rest:tt )*) => {
+        $crate::grammar!(@seq $input [
+            $( $element, )*
+            $crate::grammar::Element::Repeat($crate::grammar::repeat(
+                $input,
+                &|input| $crate::grammar!(@alts input [] [] $( $group )*),
+            )?)
+        ] $( $rest )*)
+    };
+    (@seq $input:ident [$( $element:expr ),*] ( $( $group:tt )* ) + $( $rest:tt )*) => {
+        $crate::grammar!(@seq $input [
+            $( $element, )*
+            $crate::grammar::Element::Repeat($crate::grammar::repeat1(
+                $input,
+                &|input| $crate::grammar!(@alts input [] [] $( $group )*),
+            )?)
+        ] $( $rest )*)
+    };
+    (@seq $input:ident [$( $element:expr ),*] ( $( $group:tt )* ) ? $( $rest:tt )*) => {
+        $crate::grammar!(@seq $input [
+            $( $element, )*
+            $crate::grammar::Element::Optional($crate::grammar::optional(
+                $input,
+                &|input| $crate::grammar!(@alts input [] [] $( $group )*),
+            )?)
+        ] $( $rest )*)
+    };
+    (@seq $input:ident [$( $element:expr ),*] ( $( $group:tt )* ) $( $rest:tt )*) => {
+        $crate::grammar!(@seq $input [
+            $( $element, )*
+            $crate::grammar::Element::Sequence(
+                $crate::grammar!(@alts $input [] [] $( $group )*)?
+            )
+        ] $( $rest )*)
+    };
+    (@seq $input:ident [$( $element:expr ),*] $ty:ident $( $rest:tt )*) => {
+        $crate::grammar!(@seq $input [
+            $( $element, )*
+            $crate::grammar::Element::Node(::std::boxed::Box::new($input.parse::<$ty>()?))
+        ] $( $rest )*)
+    };
+
+    {} => {};
+    { $( #[$attr:meta] )* $name:ident = $( $rest:tt )* } => {
+        $crate::grammar!(@rule [$( #[$attr] )*] $name [] $( $rest )*);
+    };
+}
@@ -21,7 +21,10 @@
 //! # The `Delimiter` trait
 //! The `Delimiter` trait is used to provide information to [`Group`] on how to
 //! parse the delimiters. It is typically implemented for newtype structs
-//! wrapping a span.
+//! wrapping a span. The delimiters can be any token, including sequences of
+//! punctuation such as `Punct!["#", "{"]`, and keywords. The parts of a
+//! delimiter made of several punctuation tokens mustn't be separated by
+//! whitespace.
 //!
 //! ## Example
 //! Below is an example of a custom set of delimiters.
@@ -56,38 +59,52 @@
 //! # }
 //! ```
 
+use crate::error::Error;
+use crate::error::ErrorKind;
 use crate::token::DoubleQuote;
 use crate::token::LAngle;
 use crate::token::LeftBrace;
 use crate::token::LeftBracket;
 use crate::token::LeftParen;
-use crate::token::Punct;
 use crate::token::RAngle;
 use crate::token::RightBrace;
 use crate::token::RightBracket;
 use crate::token::RightParen;
 use crate::token::SingleQuote;
+use crate::token::Spacing;
 use crate::token::Token;
-use crate::Entry;
+use crate::visit::leaves;
+use crate::visit::Fold;
+use crate::visit::Folder;
+use crate::visit::Visit;
+use crate::visit::Visitor;
+use crate::Cursor;
+use crate::PackedEntry;
+use crate::PackedKind;
 use crate::Parse;
 use crate::ParseStream;
 use crate::Result;
 use crate::Span;
+use crate::Spanned;
 use crate::TokenStream;
 
+use std::cell::Cell;
 use std::marker::PhantomData;
-use std::rc::Rc;
+use std::sync::Arc;
 
 /// A trait for types that represent the delimiters of a group.
 ///
+/// The delimiters are usually punctuation, but can be any token, including
+/// keywords declared with [`keywords`][crate::token::keywords].
+///
 /// For more information, see the [module documentation][module].
 ///
 /// [module]: crate::group#the-delimiter-trait
 pub trait Delimiters: From<Span> {
-    /// The opening delimiter, e.g. `(`.
-    type Start: Punct;
-    /// The closing delimiter, e.g. `)`.
-    type End: Punct;
+    /// The opening delimiter, e.g. `(` or `begin`.
+    type Start: Token;
+    /// The closing delimiter, e.g. `)` or `end`.
+    type End: Token;
     /// Whether groups delimited by this pair can nest. Typically, this will be
     /// false if the opening and closing delimiters are the same, and true
     /// otherwise.
@@ -161,9 +178,18 @@ impl Delimiters for Braces {
 }
 
 /// The delimiters `<` `>`.
+///
+/// Punctuation is scanned one character at a time, so `>>` closes two nested
+/// groups, as in `<Vec<Vec<T>>>`.
 #[derive(Debug, Clone)]
 pub struct AngleBrackets(pub Span);
 
+impl PartialEq for AngleBrackets {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
 impl From<Span> for AngleBrackets {
     fn from(value: Span) -> Self {
         AngleBrackets(value)
@@ -234,7 +260,9 @@ impl Delimiters for DoubleQuotes {
 pub struct Group<D: Delimiters> {
     pub(crate) token_stream: TokenStream,
     pub(crate) span: Span,
-    _marker: PhantomData<D>,
+    pub(crate) open: Span,
+    pub(crate) close: Span,
+    pub(crate) _marker: PhantomData<D>,
 }
 
 impl<D: Delimiters> Group<D> {
@@ -243,7 +271,26 @@ impl<D: Delimiters> Group<D> {
         self.token_stream
     }
 
+    /// Returns the span of this group, from the start of the opening delimiter
+    /// to the end of the closing delimiter.
+    pub fn span(&self) -> &Span {
+        &self.span
+    }
+
+    /// Returns the span of the opening delimiter.
+    pub fn open_span(&self) -> &Span {
+        &self.open
+    }
+
+    /// Returns the span of the closing delimiter.
+    pub fn close_span(&self) -> &Span {
+        &self.close
+    }
+
     /// Returns a token representing the delimiters of this group.
+    ///
+    /// The token's span covers the whole group, as returned by
+    /// [`Group::span`].
     pub fn delimiters(&self) -> D {
         D::from(self.span.clone())
     }
@@ -252,57 +299,267 @@ impl<D: Delimiters> Group<D> {
     pub fn remove_whitespace(&mut self) {
         self.token_stream.remove_whitespace();
     }
+
+    /// Removes all whitespace that doesn't come at the start of a line from
+    /// the tokenstream in `self`.
+    ///
+    /// See [`TokenStream::prepare_whitespace`].
+    pub fn prepare_whitespace(&mut self) {
+        self.token_stream.prepare_whitespace();
+    }
+
+    /// Removes all non-newline whitespace from the tokenstream in `self`.
+    ///
+    /// See [`TokenStream::remove_blank_space`].
+    pub fn remove_blank_space(&mut self) {
+        self.token_stream.remove_blank_space();
+    }
+
+    /// Removes all tokens for which `function` returns false from the
+    /// tokenstream in `self`.
+    ///
+    /// See [`TokenStream::retain`].
+    pub fn retain<F: FnMut(ParseStream<'_>) -> bool>(&mut self, function: F) {
+        self.token_stream.retain(function);
+    }
 }
 
-impl<D: Delimiters> Parse for Group<D> {
-    fn parse(input: ParseStream<'_>) -> Result<Self> {
-        let group_start = input.parse::<D::Start>()?.span().start;
-        let start = input.current()?.0;
+impl<D: Delimiters> Spanned for Group<D> {
+    fn span(&self) -> Span {
+        self.span.clone()
+    }
+}
+
+impl<D: Delimiters + 'static> Visit for Group<D> {
+    crate::__visit_any!();
+}
+
+impl<D: Delimiters + 'static> Fold for Group<D> {
+    crate::__fold_any!();
+}
+
+/// Parses the delimiter `T`. If it is made of several punctuation tokens, such
+/// as `Punct!["(", "*"]`, they mustn't be separated by whitespace.
+fn parse_delimiter<T: Token>(input: ParseStream<'_>) -> Result<T> {
+    let start = input.cursor.offset.get();
+    let span = input.current()?.span().clone();
+    let value = input.parse::<T>()?;
+    let end = input.cursor.offset.get();
+    let joint = input.cursor.stream[start..end - 1].iter().all(|&index| {
+        !matches!(
+            input.cursor.arena[index as usize].kind,
+            PackedKind::Punct(_, Spacing::Alone)
+        )
+    });
+    if joint {
+        Ok(value)
+    } else {
+        input.cursor.offset.set(start);
+        Err(Error::new(
+            input.source,
+            ErrorKind::UnexpectedToken {
+                expected: T::display().into(),
+                span,
+            },
+        ))
+    }
+}
 
+fn peek<T: Token>(input: ParseStream<'_>) -> bool {
+    let offset = input.cursor.offset.get();
+    let result = parse_delimiter::<T>(input);
+    input.cursor.offset.set(offset);
+    result.is_ok()
+}
+
+/// Parses the opening delimiter of a group and moves `input` to its closing
+/// delimiter.
+///
+/// Returns the span of the opening delimiter, and the cursor offsets of the
+/// first token in the group and of the closing delimiter.
+pub(crate) fn find_contents<D: Delimiters>(input: ParseStream<'_>) -> Result<(Span, usize, usize)> {
+    let open = Token::span(&parse_delimiter::<D::Start>(input)?).clone();
+    let find_range = || -> Result<(usize, usize)> {
+        input.current()?;
+        let start = input.cursor.offset.get();
         if D::CAN_NEST {
             let mut open = 1;
             loop {
-                if D::End::peek(input) {
+                if peek::<D::End>(input) {
                     open -= 1;
                     if open == 0 {
                         break;
                     } else {
-                        input.next()?;
+                        parse_delimiter::<D::End>(input)?;
                     }
-                } else if D::Start::peek(input) {
+                } else if peek::<D::Start>(input) {
                     open += 1;
-                    input.next()?;
+                    parse_delimiter::<D::Start>(input)?;
                 } else {
                     input.next()?;
                 }
             }
         } else {
-            while !D::End::peek(input) {
+            while !peek::<D::End>(input) {
                 input.next()?;
             }
         }
+        input.current()?;
+        Ok((start, input.cursor.offset.get()))
+    };
+
+    let (start, end) = find_range().map_err(|mut err| {
+        err.eof_to_group(open.clone(), D::Start::display());
+        err
+    })?;
+    Ok((open, start, end))
+}
 
-        let end_of_last_token = input.get_relative(-1)?.1.span().end;
-        let end = input
-            .current()
-            .map_err(|mut err| {
-                err.eof_to_group(
-                    Span::new(group_start, end_of_last_token, Rc::clone(&input.source)),
-                    D::Start::display(),
-                );
-                err
-            })?
-            .0;
-        let end_token: D::End = input.parse()?;
-        let group_end = end_token.span().end;
+impl<D: Delimiters> Parse for Group<D> {
+    fn parse(input: ParseStream<'_>) -> Result<Self> {
+        let (open, start, end) = find_contents::<D>(input)?;
+        let start = input.cursor.stream[start] as usize;
+        let end = input.cursor.stream[end] as usize;
+        let close = Token::span(&input.parse::<D::End>()?).clone();
         let mut tokens = input.get_absolute_range_original(start..end)?.to_vec();
-        tokens.push(Entry::End);
-        let token_stream = TokenStream::new(tokens, Rc::clone(&input.source));
-        let span = Span::new(group_start, group_end, Rc::clone(&input.source));
+        tokens.push(PackedEntry::END);
+        let token_stream = TokenStream::from_arena(
+            tokens,
+            input.cursor.sources.to_vec(),
+            Arc::clone(input.cursor.symbols),
+            Arc::clone(input.source),
+        );
+        let span = Span::across(&open, &close);
         Ok(Group {
             token_stream,
             span,
+            open,
+            close,
             _marker: PhantomData,
         })
     }
 }
+
+/// A value of type `T` delimited by `D`.
+///
+/// The contents of the group are parsed into `T` with whitespace removed, and
+/// must be consumed entirely.
+///
+/// ## Example
+/// ```
+/// # use flexi_parse::group::Parenthesized;
+/// # use flexi_parse::token::LitInt;
+/// # use flexi_parse::parse_string;
+/// let lit: Parenthesized<LitInt> = parse_string("( 3 )".to_string()).unwrap();
+/// assert_eq!(lit.value().value(), 3);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Delimited<D: Delimiters, T> {
+    value: T,
+    span: Span,
+    open: Span,
+    close: Span,
+    _marker: PhantomData<D>,
+}
+
+/// A value of type `T` delimited by `(` `)`.
+pub type Parenthesized<T> = Delimited<Parentheses, T>;
+
+/// A value of type `T` delimited by `[` `]`.
+pub type Bracketed<T> = Delimited<Brackets, T>;
+
+/// A value of type `T` delimited by `{` `}`.
+pub type Braced<T> = Delimited<Braces, T>;
+
+impl<D: Delimiters, T> Delimited<D, T> {
+    /// Returns a reference to the contained value.
+    pub fn value(&self) -> &T {
+        &self.value
+    }
+
+    /// Returns a mutable reference to the contained value.
+    pub fn value_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+
+    /// Converts `self` into the contained value.
+    pub fn into_value(self) -> T {
+        self.value
+    }
+
+    /// Returns the span of this group, from the start of the opening delimiter
+    /// to the end of the closing delimiter.
+    pub fn span(&self) -> &Span {
+        &self.span
+    }
+
+    /// Returns the span of the opening delimiter.
+    pub fn open_span(&self) -> &Span {
+        &self.open
+    }
+
+    /// Returns the span of the closing delimiter.
+    pub fn close_span(&self) -> &Span {
+        &self.close
+    }
+
+    /// Returns a token representing the delimiters of this group.
+    ///
+    /// The token's span covers the whole group, as returned by
+    /// [`Delimited::span`].
+    pub fn delimiters(&self) -> D {
+        D::from(self.span.clone())
+    }
+}
+
+impl<D: Delimiters, T> Spanned for Delimited<D, T> {
+    fn span(&self) -> Span {
+        self.span.clone()
+    }
+}
+
+impl<D: Delimiters, T: Visit> Visit for Delimited<D, T> {
+    fn visit_children<V: Visitor>(&self, visitor: &mut V) {
+        self.value.visit_with(visitor);
+    }
+}
+
+impl<D: Delimiters, T: Fold> Fold for Delimited<D, T> {
+    fn fold_children<F: Folder>(mut self, folder: &mut F) -> Self {
+        self.value = self.value.fold_with(folder);
+        self
+    }
+}
+
+impl<D: Delimiters, T: Parse> Parse for Delimited<D, T> {
+    fn parse(input: ParseStream<'_>) -> Result<Self> {
+        let mut group: Group<D> = input.parse()?;
+        group.remove_whitespace();
+        let tokens = &group.token_stream;
+        let cursor = Cursor {
+            arena: tokens.arena.as_slice(),
+            sources: tokens.sources.as_slice(),
+            symbols: &tokens.symbols,
+            stream: tokens.tokens.as_slice(),
+            offset: Cell::new(0),
+            last: tokens.tokens.len() - 1,
+        };
+        let value = input.parse_contents::<D, T, _>(cursor, T::parse)?;
+        Ok(Delimited {
+            value,
+            span: group.span,
+            open: group.open,
+            close: group.close,
+            _marker: PhantomData,
+        })
+    }
+}
+
+leaves![
+    Parentheses,
+    Brackets,
+    Braces,
+    AngleBrackets,
+    SingleQuotes,
+    DoubleQuotes
+];
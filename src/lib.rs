@@ -14,36 +14,76 @@
 #![forbid(clippy::todo)]
 #![forbid(clippy::undocumented_unsafe_blocks)]
 
+use std::borrow::Cow;
 use std::cell::Cell;
 use std::cell::RefCell;
+use std::cell::RefMut;
 use std::collections::HashSet;
 use std::fmt;
 use std::fs;
 use std::io;
+use std::io::BufRead;
+use std::io::Read;
+use std::mem;
 use std::ops::Range;
+use std::path::Path;
 use std::path::PathBuf;
+use std::process;
 use std::ptr;
 use std::rc::Rc;
 use std::result;
+use std::sync::Arc;
 
+mod binary;
+pub mod cst;
 pub mod error;
+pub mod expr;
+pub mod grammar;
 pub mod group;
 pub mod lookahead;
 pub mod punctuated;
+pub mod recover;
 mod scanner;
+pub mod source_map;
 mod to_string;
 pub mod token;
+#[cfg(feature = "serde")]
+mod token_cache;
+pub mod visit;
 use error::Error;
+use error::ErrorCode;
 use error::ErrorKind;
+use error::Expected;
+use error::Warnings;
+use expr::OperatorTable;
+use group::Delimiters;
 use lookahead::Lookahead;
+use punctuated::Punctuated;
+use recover::Recover;
+use token::CarriageReturn;
 use token::Ident;
+use token::Interner;
+use token::NewLine;
+use token::Punct;
+use token::PunctKind;
 use token::SingleCharPunct;
+use token::Space2;
+use token::Spacing;
+use token::Symbol;
+use token::Tab;
 use token::Token;
 use token::WhiteSpace;
 
 #[cfg(feature = "proc-macro2")]
 mod proc_macro;
 
+#[cfg(feature = "derive")]
+pub use flexi_parse_derive::Parse;
+#[cfg(feature = "derive")]
+pub use flexi_parse_derive::Spanned;
+#[cfg(feature = "derive")]
+pub use flexi_parse_derive::ToTokens;
+
 /// A struct representing a file of source code.
 ///
 /// This type is the input to [`parse_source`].
@@ -52,6 +92,8 @@ pub struct SourceFile {
     name: String,
     path: Option<String>,
     contents: String,
+    line_starts: Vec<usize>,
+    origins: Vec<(Range<usize>, Span)>,
 }
 
 impl SourceFile {
@@ -69,7 +111,9 @@ impl SourceFile {
         Ok(SourceFile {
             name,
             path: Some(path.to_string_lossy().into_owned()),
+            line_starts: line_starts(&contents),
             contents,
+            origins: vec![],
         })
     }
 
@@ -78,13 +122,107 @@ impl SourceFile {
         SourceFile {
             name,
             path: None,
+            line_starts: line_starts(&contents),
+            contents,
+            origins: vec![],
+        }
+    }
+
+    /// Creates a new `SourceFile` for generated code, such as the output of a
+    /// preprocessor or macro expansion.
+    ///
+    /// Each entry in `origins` maps a byte range of `contents` to the span of
+    /// the code it was generated from. Errors raised while parsing this file
+    /// with [`parse_source`] are reported at those spans instead; see
+    /// [`Error::into_origin`].
+    ///
+    /// # Example
+    /// ```
+    /// # use flexi_parse::parse_source;
+    /// # use flexi_parse::token::Ident;
+    /// # use flexi_parse::SourceFile;
+    /// # use flexi_parse::Spanned;
+    /// # use std::sync::Arc;
+    /// let original = Arc::new(SourceFile::new("main".to_string(), "ONE".to_string()));
+    /// let macro_name: Ident = parse_source(Arc::clone(&original)).unwrap();
+    ///
+    /// // `ONE` expands to `1`
+    /// let expanded = SourceFile::new_virtual(
+    ///     "expansion".to_string(),
+    ///     "1".to_string(),
+    ///     vec![(0..1, macro_name.span())],
+    /// );
+    /// let err = parse_source::<Ident>(Arc::new(expanded)).unwrap_err();
+    /// assert_eq!(err.first_span(), Some(macro_name.span()));
+    /// ```
+    pub fn new_virtual(
+        name: String,
+        contents: String,
+        origins: Vec<(Range<usize>, Span)>,
+    ) -> SourceFile {
+        SourceFile {
+            name,
+            path: None,
+            line_starts: line_starts(&contents),
             contents,
+            origins,
         }
     }
 
+    /// Returns the name of this file.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the path this file was read from, if it was read with
+    /// [`SourceFile::read`].
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_deref()
+    }
+
+    /// Returns the contents of this file.
+    pub fn contents(&self) -> &str {
+        &self.contents
+    }
+
+    /// Returns a 64-bit FNV-1a hash of the contents of this file.
+    ///
+    /// Unlike [`Hash`][std::hash::Hash], the result is the same across runs
+    /// and platforms, so it can be used to key a cache of scanned token
+    /// streams.
+    pub fn content_hash(&self) -> u64 {
+        const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+        const PRIME: u64 = 0x0100_0000_01b3;
+        self.contents.bytes().fold(OFFSET, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+        })
+    }
+
     fn id(&self) -> &String {
         self.path.as_ref().unwrap_or(&self.name)
     }
+
+    /// Returns the zero-based index of the line containing `offset`.
+    fn line_index(&self, offset: usize) -> usize {
+        self.line_starts.partition_point(|&start| start <= offset) - 1
+    }
+
+    /// Returns the line with the given zero-based index, without its newline.
+    fn line(&self, index: usize) -> Option<&str> {
+        let start = *self.line_starts.get(index)?;
+        let end = self
+            .line_starts
+            .get(index + 1)
+            .map_or(self.contents.len(), |next| next - 1);
+        Some(&self.contents[start..end])
+    }
+}
+
+/// Returns the offset of the start of each line in `contents`.
+fn line_starts(contents: &str) -> Vec<usize> {
+    std::iter::once(0)
+        .chain(contents.match_indices('\n').map(|(i, _)| i + 1))
+        .collect()
 }
 
 impl fmt::Debug for SourceFile {
@@ -106,11 +244,11 @@ impl fmt::Debug for SourceFile {
 pub struct Span {
     start: usize,
     end: usize,
-    source: Rc<SourceFile>,
+    source: Arc<SourceFile>,
 }
 
 impl Span {
-    fn new(start: usize, end: usize, source: Rc<SourceFile>) -> Span {
+    fn new(start: usize, end: usize, source: Arc<SourceFile>) -> Span {
         Span { start, end, source }
     }
 
@@ -127,27 +265,144 @@ impl Span {
         Span {
             start: start.start,
             end: end.end,
-            source: Rc::clone(&start.source),
+            source: Arc::clone(&start.source),
         }
     }
 
+    /// Creates a new `Span` covering the source code between `start` and
+    /// `end`, not including either of them.
+    ///
+    /// If `end` begins before `start` finishes, the returned span is empty.
+    ///
+    /// ## Panics
+    /// This function will panic if `start` and `end` come from different source
+    /// files.
+    pub fn between(start: &Span, end: &Span) -> Span {
+        assert_eq!(
+            start.source, end.source,
+            "both inputs to `between` must come from the same source file"
+        );
+        Span {
+            start: start.end,
+            end: end.start.max(start.end),
+            source: Arc::clone(&start.source),
+        }
+    }
+
+    /// Returns a span covering the first character of this span.
+    ///
+    /// If this span is empty, an empty span is returned.
+    pub fn first_char(&self) -> Span {
+        let len = self.source_text().chars().next().map_or(0, char::len_utf8);
+        Span::new(self.start, self.start + len, Arc::clone(&self.source))
+    }
+
+    /// Returns a span covering the last character of this span.
+    ///
+    /// If this span is empty, an empty span is returned.
+    pub fn last_char(&self) -> Span {
+        let len = self
+            .source_text()
+            .chars()
+            .next_back()
+            .map_or(0, char::len_utf8);
+        Span::new(self.end - len, self.end, Arc::clone(&self.source))
+    }
+
+    /// Returns the part of this span covering `range`, which is given in bytes
+    /// relative to the start of this span.
+    ///
+    /// ## Panics
+    /// This function will panic if `range` extends past the end of this span.
+    pub fn subspan(&self, range: Range<usize>) -> Span {
+        assert!(
+            range.start <= range.end && self.start + range.end <= self.end,
+            "range {range:?} is out of bounds for a span of length {}",
+            self.end - self.start
+        );
+        Span::new(
+            self.start + range.start,
+            self.start + range.end,
+            Arc::clone(&self.source),
+        )
+    }
+
     #[doc(hidden)]
-    pub fn source(&self) -> &Rc<SourceFile> {
+    pub fn source(&self) -> &Arc<SourceFile> {
+        &self.source
+    }
+
+    /// Returns the file containing this span.
+    pub fn source_file(&self) -> &SourceFile {
         &self.source
     }
 
+    /// Returns the span of the code this span was generated from, if it is in
+    /// a file created with [`SourceFile::new_virtual`].
+    ///
+    /// Spans in generated code that has no origin are returned unchanged.
+    pub fn origin(&self) -> Span {
+        let Some((range, origin)) = self.source.origins.iter().find(|(range, _)| {
+            range.contains(&self.start) || (self.is_empty() && range.end == self.start)
+        }) else {
+            return self.clone();
+        };
+        let span = if self.end <= range.end && origin.end - origin.start == range.len() {
+            // The code was copied verbatim, so positions within it still match.
+            let start = origin.start + (self.start - range.start);
+            Span::new(
+                start,
+                start + self.end - self.start,
+                Arc::clone(&origin.source),
+            )
+        } else {
+            origin.clone()
+        };
+        span.origin()
+    }
+
+    /// Returns the source code covered by this span, exactly as written.
+    pub fn source_text(&self) -> &str {
+        &self.source.contents[self.start..self.end]
+    }
+
     /// Returns the start line and start column.
-    fn start_location(&self) -> (usize, usize) {
-        let mut newlines = 0;
-        let mut last_newline = 0;
-        for (i, char) in self.source.contents[..self.start].chars().enumerate() {
-            if char == '\n' {
-                newlines += 1;
-                last_newline = i;
-            }
-        }
+    ///
+    /// Both are one-based, and the column is measured in chars. See
+    /// [`Span::start_location_in`] to measure columns in other units.
+    pub fn start_location(&self) -> (usize, usize) {
+        self.location_in(self.start, ColumnUnit::default())
+    }
+
+    /// Returns the end line and end column.
+    ///
+    /// Both are one-based, and the column is measured in chars. See
+    /// [`Span::end_location_in`] to measure columns in other units.
+    pub fn end_location(&self) -> (usize, usize) {
+        self.location_in(self.end, ColumnUnit::default())
+    }
+
+    /// Returns the start line and start column, with the column measured in
+    /// `unit`.
+    pub fn start_location_in(&self, unit: ColumnUnit) -> (usize, usize) {
+        self.location_in(self.start, unit)
+    }
 
-        (newlines + 1, self.start - last_newline + 1)
+    /// Returns the end line and end column, with the column measured in
+    /// `unit`.
+    pub fn end_location_in(&self, unit: ColumnUnit) -> (usize, usize) {
+        self.location_in(self.end, unit)
+    }
+
+    /// Returns the one-based line and column of `offset`, with the column
+    /// measured in `unit`.
+    fn location_in(&self, offset: usize, unit: ColumnUnit) -> (usize, usize) {
+        let line = self.source.line_index(offset);
+        let line_start = self.source.line_starts[line];
+        (
+            line + 1,
+            unit.count(&self.source.contents[line_start..offset]) + 1,
+        )
     }
 
     /// Returns true if the span was created with `Span::new_empty()`.
@@ -162,7 +417,188 @@ impl<T: Token> From<&T> for Span {
     }
 }
 
+/// Types that cover a region of source code.
+///
+/// This is implemented for all tokens, and can be implemented for syntax tree
+/// nodes to use them with helpers such as
+/// [`Punctuated::span`][punctuated::Punctuated::span].
+///
+/// It is also implemented for `Box`, `Vec` and `Option` of spanned types, and
+/// for [`Punctuated`][punctuated::Punctuated] and [`Recover`][recover::Recover].
+/// An empty `Vec`, `Punctuated` or `Option` has no span, so calling
+/// [`Spanned::span`] on one panics; use [`Spanned::try_span`] instead.
+///
+/// With the `derive` feature enabled, this trait can be derived for structs
+/// and enums whose fields all implement it. The derived span runs from the
+/// start of the first field to the end of the last, in each variant of an
+/// enum. Fields without a span, such as an empty `Vec`, are skipped.
+///
+/// ## Example
+/// ```
+/// # #[cfg(feature = "derive")] {
+/// # use flexi_parse::parse_string;
+/// # use flexi_parse::token::Ident;
+/// # use flexi_parse::token::LitInt;
+/// # use flexi_parse::Parse;
+/// # use flexi_parse::Punct;
+/// # use flexi_parse::Spanned;
+/// #[derive(Parse, Spanned)]
+/// struct Assignment {
+///     name: Ident,
+///     equals: Punct!["="],
+///     value: LitInt,
+/// }
+///
+/// let assignment: Assignment = parse_string("x  = 1".to_string()).unwrap();
+/// assert_eq!(assignment.span().source_text(), "x  = 1");
+/// # }
+/// ```
+pub trait Spanned {
+    /// Returns the span covered by `self`.
+    fn span(&self) -> Span;
+
+    /// Returns the span covered by `self`, or `None` if it covers no tokens.
+    fn try_span(&self) -> Option<Span> {
+        Some(self.span())
+    }
+}
+
+impl<T: Spanned + ?Sized> Spanned for Box<T> {
+    fn span(&self) -> Span {
+        (**self).span()
+    }
+
+    fn try_span(&self) -> Option<Span> {
+        (**self).try_span()
+    }
+}
+
+impl<T: Spanned> Spanned for Option<T> {
+    fn span(&self) -> Span {
+        self.try_span().expect("`None` has no span")
+    }
+
+    fn try_span(&self) -> Option<Span> {
+        self.as_ref()?.try_span()
+    }
+}
+
+impl<T: Spanned> Spanned for Vec<T> {
+    fn span(&self) -> Span {
+        self.try_span().expect("an empty `Vec` has no span")
+    }
+
+    fn try_span(&self) -> Option<Span> {
+        let first = self.iter().find_map(Spanned::try_span)?;
+        let last = self.iter().rev().find_map(Spanned::try_span)?;
+        Some(Span::across(&first, &last))
+    }
+}
+
+/// Types that can be converted back into tokens.
+///
+/// This is implemented for all tokens. Tokens created without being parsed,
+/// such as with a keyword's `new` method, produce tokens with the same span as
+/// them.
+///
+/// ## Example
+/// ```
+/// # use flexi_parse::punctuated::Punctuated;
+/// # use flexi_parse::token::Ident;
+/// # use flexi_parse::Parser;
+/// # use flexi_parse::Punct;
+/// # use flexi_parse::SourceFile;
+/// # use flexi_parse::ToTokens;
+/// # use flexi_parse::TokenStream;
+/// # use std::sync::Arc;
+/// let source = Arc::new(SourceFile::new("example".to_string(), "a,  b".to_string()));
+/// let mut tokens = TokenStream::try_from(Arc::clone(&source)).unwrap();
+/// tokens.remove_whitespace();
+/// let names: Punctuated<Ident, Punct![","]> =
+///     Punctuated::parse_separated.parse(tokens).unwrap();
+///
+/// let mut output = TokenStream::empty(source);
+/// names.to_tokens(&mut output);
+/// assert_eq!(output.to_string(), "a,  b");
+/// ```
+///
+/// With the `derive` feature enabled, this trait can be derived for structs
+/// and enums whose fields all implement it. The derived implementation
+/// appends the tokens of each field in the order they are declared, so a type
+/// that also derives [`Parse`] is converted back into the tokens it was parsed
+/// from.
+pub trait ToTokens {
+    /// Appends the tokens making up `self` to `tokens`.
+    fn to_tokens(&self, tokens: &mut TokenStream);
+}
+
+impl<T: Token> ToTokens for T {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        self.append_tokens(tokens);
+    }
+}
+
 /// Parsing interface for types with a default parsing method.
+///
+/// With the `derive` feature enabled, this trait can be derived. A derived
+/// struct parses each of its fields in order. Each variant of a derived enum
+/// must be given the token it starts with, as `#[parse(peek = ...)]`, or a
+/// function that checks for it, as `#[parse(peek_func = ...)]`. The first
+/// variant that matches is parsed, and if none do, the error lists every
+/// variant's token. A `peek_func` variant is named in that error by its name,
+/// or by `#[parse(name = "...")]` if given.
+///
+/// A derived type can be given `#[parse(recover_to = ...)]`, naming a token.
+/// Then each of its fields written as [`Recover<T>`](recover::Recover) that
+/// fails to parse records the error, skips past the next instance of that
+/// token, and is replaced by [`Recover::Error`](recover::Recover::Error). Other
+/// fields are unaffected.
+///
+/// Fields can be given options of their own:
+/// - `#[parse(with = ...)]` parses the field with the named function instead
+///   of its `Parse` implementation.
+/// - `#[parse(parse_if = ...)]` parses an `Option` field only if the named
+///   function returns true, and sets it to `None` otherwise.
+/// - `#[parse(separated)]` and `#[parse(terminated)]` parse a
+///   [`Punctuated`](punctuated::Punctuated) field with
+///   [`parse_separated`](punctuated::Punctuated::parse_separated) or
+///   [`parse_terminated`](punctuated::Punctuated::parse_terminated). Given
+///   with `with`, the function parses each of its values.
+/// - `#[parse(skip)]` parses nothing, and sets the field to its default value.
+///
+/// Functions can be given as paths or as strings containing paths.
+///
+/// ## Example
+/// ```
+/// # #[cfg(feature = "derive")] {
+/// # use flexi_parse::parse_string;
+/// # use flexi_parse::token::Ident;
+/// # use flexi_parse::token::LitInt;
+/// # use flexi_parse::Parse;
+/// # use flexi_parse::Punct;
+/// #[derive(Parse)]
+/// struct Assignment {
+///     name: Ident,
+///     equals: Punct!["="],
+///     value: Value,
+/// }
+///
+/// #[derive(Parse)]
+/// enum Value {
+///     #[parse(peek = LitInt)]
+///     Int(LitInt),
+///     #[parse(peek = Ident)]
+///     Name(Ident),
+/// }
+///
+/// let assignment: Assignment = parse_string("x = 1".to_string()).unwrap();
+/// assert_eq!(assignment.name.string(), "x");
+/// assert!(matches!(assignment.value, Value::Int(_)));
+///
+/// let err = parse_string::<Assignment>("x = +".to_string()).err().unwrap();
+/// assert!(err.to_string().contains("an identifier"), "{err}");
+/// # }
+/// ```
 pub trait Parse: Sized {
     /// Parses the input into this type.
     fn parse(input: ParseStream<'_>) -> Result<Self>;
@@ -175,53 +611,772 @@ pub trait Parser: Sized {
 
     /// Parses a tokenstream into the relevant syntax tree node.
     fn parse(self, tokens: TokenStream) -> Result<Self::Output>;
+
+    /// Parses a borrowed tokenstream into the relevant syntax tree node.
+    ///
+    /// Unlike [`Parser::parse`], this leaves `tokens` intact, so the same
+    /// stream can be parsed again, for example with a different grammar.
+    fn parse_stream(self, tokens: &TokenStream) -> Result<<Self as Parser>::Output>
+    where
+        Self: FnOnce(ParseStream<'_>) -> Result<<Self as Parser>::Output>,
+    {
+        self.parse_stream_with_options(
+            tokens,
+            ParseOptions::new().whitespace(WhiteSpacePolicy::Keep),
+        )
+    }
+
+    /// Parses a tokenstream into the relevant syntax tree node, using the
+    /// given options.
+    ///
+    /// Unlike [`Parser::parse`], this applies the whitespace policy of
+    /// `options` to `tokens` before parsing.
+    ///
+    /// If parsing fails, any warnings added with
+    /// [`ParseBuffer::add_warning`] are included in the returned error.
+    fn parse_with_options(
+        self,
+        tokens: TokenStream,
+        options: ParseOptions,
+    ) -> Result<<Self as Parser>::Output>
+    where
+        Self: FnOnce(ParseStream<'_>) -> Result<<Self as Parser>::Output>,
+    {
+        self.parse_stream_with_options(&tokens, options)
+    }
+
+    /// Parses a borrowed tokenstream into the relevant syntax tree node, using
+    /// the given options.
+    ///
+    /// See [`Parser::parse_with_options`]. The whitespace policy of `options`
+    /// is applied without modifying `tokens`.
+    fn parse_stream_with_options(
+        self,
+        tokens: &TokenStream,
+        options: ParseOptions,
+    ) -> Result<<Self as Parser>::Output>
+    where
+        Self: FnOnce(ParseStream<'_>) -> Result<<Self as Parser>::Output>,
+    {
+        match self.parse_stream_with_warnings(tokens, options) {
+            (Err(mut err), Some(warnings)) => {
+                err.add(warnings);
+                Err(err)
+            }
+            (result, _) => result,
+        }
+    }
+
+    /// Parses a tokenstream into the relevant syntax tree node, using the
+    /// given options, and returns any warnings added with
+    /// [`ParseBuffer::add_warning`] alongside the result.
+    fn parse_with_warnings(
+        self,
+        tokens: TokenStream,
+        options: ParseOptions,
+    ) -> (Result<<Self as Parser>::Output>, Option<Error>)
+    where
+        Self: FnOnce(ParseStream<'_>) -> Result<<Self as Parser>::Output>,
+    {
+        self.parse_stream_with_warnings(&tokens, options)
+    }
+
+    /// Parses a borrowed tokenstream into the relevant syntax tree node,
+    /// using the given options, and returns any warnings alongside the
+    /// result.
+    ///
+    /// See [`Parser::parse_with_warnings`].
+    fn parse_stream_with_warnings(
+        self,
+        tokens: &TokenStream,
+        options: ParseOptions,
+    ) -> (Result<<Self as Parser>::Output>, Option<Error>)
+    where
+        Self: FnOnce(ParseStream<'_>) -> Result<<Self as Parser>::Output>,
+    {
+        if let Err(err) = tokens.check_limits() {
+            return (Err(err), None);
+        }
+        if options.check_delimiters {
+            if let Err(err) = tokens.check_delimiters() {
+                return (Err(options.limit_errors(err)), None);
+            }
+        }
+        let stream = match options.whitespace {
+            WhiteSpacePolicy::Keep => Cow::Borrowed(tokens.tokens.as_slice()),
+            policy => Cow::Owned(tokens.filtered(|tokens| tokens.whitespace_positions(policy))),
+        };
+        let cursor = Cursor {
+            arena: tokens.arena.as_slice(),
+            sources: tokens.sources.as_slice(),
+            symbols: &tokens.symbols,
+            stream: &stream,
+            offset: Cell::new(0),
+            last: stream.len() - 1,
+        };
+        let context = Rc::new(Context {
+            options,
+            ..Context::default()
+        });
+        let buffer = ParseBuffer::new(cursor, &tokens.source, context);
+        let result = self(&buffer).map_err(|mut err| {
+            if let Some((start, expected)) = &*buffer.context.expected.borrow() {
+                err.merge_expected(*start, expected);
+            }
+            err.suggest_similar(&buffer.context.candidates.borrow());
+            buffer.context.options.limit_errors(err)
+        });
+        let warnings = buffer.get_warnings();
+        (result, warnings)
+    }
+
+    /// Returns a parser that applies `f` to the output of `self`.
+    fn map<U, F: FnOnce(<Self as Parser>::Output) -> U>(
+        self,
+        f: F,
+    ) -> impl FnOnce(ParseStream<'_>) -> Result<U>
+    where
+        Self: FnOnce(ParseStream<'_>) -> Result<<Self as Parser>::Output>,
+    {
+        move |input| self(input).map(f)
+    }
+
+    /// Returns a parser that applies the fallible function `f` to the output
+    /// of `self`.
+    fn and_then<U, F: FnOnce(<Self as Parser>::Output) -> Result<U>>(
+        self,
+        f: F,
+    ) -> impl FnOnce(ParseStream<'_>) -> Result<U>
+    where
+        Self: FnOnce(ParseStream<'_>) -> Result<<Self as Parser>::Output>,
+    {
+        move |input| self(input).and_then(f)
+    }
+
+    /// Returns a parser that attempts to parse `self`, and parses `other` if
+    /// that fails.
+    ///
+    /// If `self` fails, any tokens it consumed are restored before `other` is
+    /// tried.
+    fn or<P: FnOnce(ParseStream<'_>) -> Result<<Self as Parser>::Output>>(
+        self,
+        other: P,
+    ) -> impl FnOnce(ParseStream<'_>) -> Result<<Self as Parser>::Output>
+    where
+        Self: FnOnce(ParseStream<'_>) -> Result<<Self as Parser>::Output>,
+    {
+        move |input| {
+            let fork = input.fork();
+            match self(&fork) {
+                Ok(value) => {
+                    input.commit(&fork);
+                    Ok(value)
+                }
+                Err(err) => {
+                    input.record_expected(&err);
+                    other(input)
+                }
+            }
+        }
+    }
+
+    /// Returns a parser that parses `self` as many times as possible.
+    ///
+    /// Note that this will stop as soon as `self` fails, without consuming
+    /// the tokens of the failed attempt. It also stops if `self` succeeds
+    /// without consuming any tokens, discarding that value.
+    fn repeated(self) -> impl FnOnce(ParseStream<'_>) -> Result<Vec<<Self as Parser>::Output>>
+    where
+        Self: Fn(ParseStream<'_>) -> Result<<Self as Parser>::Output>,
+    {
+        move |input| {
+            let mut items = vec![];
+            while !input.is_empty() {
+                let fork = input.fork();
+                match self(&fork) {
+                    // Stop if nothing was consumed, since that would repeat forever.
+                    Ok(value) if fork.cursor.offset.get() > input.cursor.offset.get() => {
+                        input.commit(&fork);
+                        items.push(value);
+                    }
+                    Ok(_) => break,
+                    Err(err) => {
+                        input.record_expected(&err);
+                        break;
+                    }
+                }
+            }
+            Ok(items)
+        }
+    }
+
+    /// Returns a parser that attempts to parse `self`, returning `None`
+    /// without consuming any tokens if that fails.
+    fn optional(self) -> impl FnOnce(ParseStream<'_>) -> Result<Option<<Self as Parser>::Output>>
+    where
+        Self: FnOnce(ParseStream<'_>) -> Result<<Self as Parser>::Output>,
+    {
+        move |input| {
+            let fork = input.fork();
+            match self(&fork) {
+                Ok(value) => {
+                    input.commit(&fork);
+                    Ok(Some(value))
+                }
+                Err(err) => {
+                    input.record_expected(&err);
+                    Ok(None)
+                }
+            }
+        }
+    }
+
+    /// Returns a parser that parses instances of `self` separated by
+    /// instances of `P`, with no trailing punctuation.
+    ///
+    /// See [`Punctuated::parse_separated_with`].
+    fn separated_by<P: Punct>(
+        self,
+    ) -> impl FnOnce(ParseStream<'_>) -> Result<Punctuated<<Self as Parser>::Output, P>>
+    where
+        Self: FnMut(ParseStream<'_>) -> Result<<Self as Parser>::Output>,
+    {
+        move |input| Punctuated::parse_separated_with(input, self)
+    }
 }
 
 impl<F: FnOnce(ParseStream<'_>) -> Result<T>, T> Parser for F {
     type Output = T;
 
     fn parse(self, tokens: TokenStream) -> Result<Self::Output> {
-        let cursor = Cursor {
-            original_stream: tokens.original_tokens.as_slice(),
-            stream: tokens.tokens.as_slice(),
-            offset: Cell::new(0),
-            last: tokens.tokens.len() - 1,
-        };
-        self(&ParseBuffer::new(cursor, Rc::clone(&tokens.source)))
+        self.parse_with_options(
+            tokens,
+            ParseOptions::new().whitespace(WhiteSpacePolicy::Keep),
+        )
+    }
+}
+
+/// The unit that columns are measured in when reporting positions.
+///
+/// Spans are stored as byte offsets, so on lines containing non-ASCII text the
+/// byte column does not match the position a reader sees.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ColumnUnit {
+    /// Bytes of UTF-8.
+    Byte,
+    /// Unicode scalar values.
+    #[default]
+    Char,
+    /// UTF-16 code units, as used by the Language Server Protocol.
+    Utf16,
+    /// User-perceived characters, the extended grapheme clusters of Unicode
+    /// Standard Annex #29.
+    #[cfg(feature = "unicode-segmentation")]
+    Grapheme,
+}
+
+impl ColumnUnit {
+    fn count(self, text: &str) -> usize {
+        match self {
+            ColumnUnit::Byte => text.len(),
+            ColumnUnit::Char => text.chars().count(),
+            ColumnUnit::Utf16 => text.encode_utf16().count(),
+            #[cfg(feature = "unicode-segmentation")]
+            ColumnUnit::Grapheme => {
+                unicode_segmentation::UnicodeSegmentation::graphemes(text, true).count()
+            }
+        }
+    }
+}
+
+/// How whitespace tokens are treated before parsing.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WhiteSpacePolicy {
+    /// Keep all whitespace tokens.
+    Keep,
+    /// Keep only whitespace at the start of a line, as with
+    /// [`TokenStream::prepare_whitespace`].
+    Prepare,
+    /// Keep only newlines, as with [`TokenStream::remove_blank_space`].
+    RemoveBlankSpace,
+    /// Remove all whitespace tokens, as with
+    /// [`TokenStream::remove_whitespace`].
+    #[default]
+    Remove,
+}
+
+/// Options controlling how source code is split into tokens.
+///
+/// By default, identifiers consist of alphanumeric characters and underscores.
+///
+/// # Example
+/// ```
+/// # use flexi_parse::parse_string_with;
+/// # use flexi_parse::token::Ident;
+/// # use flexi_parse::ParseOptions;
+/// # use flexi_parse::ScanOptions;
+/// let options = ParseOptions::new().scanner(ScanOptions::new().ident_chars("-"));
+/// let ident: Ident = parse_string_with("kebab-case".to_string(), options).unwrap();
+/// assert_eq!(ident.string(), "kebab-case");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ScanOptions {
+    ident_chars: Vec<char>,
+}
+
+impl ScanOptions {
+    /// Creates the default set of options.
+    pub fn new() -> ScanOptions {
+        ScanOptions::default()
+    }
+
+    /// Allows each character of `chars` to appear in identifiers, as well as
+    /// alphanumeric characters and underscores.
+    pub fn ident_chars(mut self, chars: &str) -> Self {
+        self.ident_chars.extend(chars.chars());
+        self
+    }
+}
+
+/// Options controlling how a stream of tokens is parsed.
+///
+/// The default options are those used by the `parse*` functions: all
+/// whitespace is removed, keywords are case sensitive, there is no limit on
+/// recursion, and at most [`ParseOptions::DEFAULT_ERROR_LIMIT`] errors are
+/// reported.
+///
+/// # Example
+/// ```
+/// # use flexi_parse::parse_string_with;
+/// # use flexi_parse::token::Ident;
+/// # use flexi_parse::ParseOptions;
+/// # use flexi_parse::WhiteSpacePolicy;
+/// let options = ParseOptions::new()
+///     .whitespace(WhiteSpacePolicy::RemoveBlankSpace)
+///     .recursion_limit(64);
+/// let ident: Ident = parse_string_with("name".to_string(), options).unwrap();
+/// assert_eq!(ident.string(), "name");
+/// ```
+#[derive(Debug, Clone)]
+pub struct ParseOptions {
+    whitespace: WhiteSpacePolicy,
+    recursion_limit: Option<usize>,
+    error_limit: Option<usize>,
+    case_sensitive_keywords: bool,
+    check_delimiters: bool,
+    error_codes: Vec<ErrorCode>,
+    scanner: ScanOptions,
+}
+
+impl ParseOptions {
+    /// The default maximum number of errors returned from a failed parse.
+    pub const DEFAULT_ERROR_LIMIT: usize = 20;
+
+    /// Creates the default set of options.
+    pub fn new() -> ParseOptions {
+        ParseOptions::default()
+    }
+
+    /// Sets how whitespace is treated before parsing.
+    pub fn whitespace(mut self, policy: WhiteSpacePolicy) -> Self {
+        self.whitespace = policy;
+        self
+    }
+
+    /// Sets the maximum depth of nested calls to [`ParseBuffer::parse`].
+    ///
+    /// Exceeding this depth causes parsing to fail with an error, rather than
+    /// overflowing the stack.
+    pub fn recursion_limit(mut self, limit: usize) -> Self {
+        self.recursion_limit = Some(limit);
+        self
+    }
+
+    /// Sets the maximum number of errors returned from a failed parse.
+    ///
+    /// Any further errors are dropped, and a note saying how many were not
+    /// shown is added to the last error reported.
+    pub fn error_limit(mut self, limit: usize) -> Self {
+        self.error_limit = Some(limit);
+        self
+    }
+
+    /// Removes the limit on the number of errors returned from a failed parse.
+    pub fn no_error_limit(mut self) -> Self {
+        self.error_limit = None;
+        self
+    }
+
+    /// Sets whether keywords declared with [`keywords`] or
+    /// [`keywords_prefixed`] must match case exactly.
+    ///
+    /// [`keywords`]: token::keywords
+    /// [`keywords_prefixed`]: token::keywords_prefixed
+    pub fn case_sensitive_keywords(mut self, case_sensitive: bool) -> Self {
+        self.case_sensitive_keywords = case_sensitive;
+        self
+    }
+
+    /// Sets whether to check that all delimiters are matched before parsing,
+    /// so that every unmatched delimiter is reported at once.
+    ///
+    /// When source code is scanned by this crate, such as by
+    /// [`parse_string_with`], the delimiters are checked as it is scanned, and
+    /// any scanning errors are reported alongside unmatched delimiters.
+    ///
+    /// See [`TokenStream::check_delimiters`].
+    pub fn check_delimiters(mut self, check: bool) -> Self {
+        self.check_delimiters = check;
+        self
+    }
+
+    /// Registers a named error code.
+    ///
+    /// Errors created with [`ParseBuffer::new_error`] using the number of a
+    /// registered code, or with [`ParseBuffer::new_coded_error`] using its
+    /// name, carry the code's details, and link to its documentation when
+    /// reported.
+    pub fn error_code(mut self, error_code: ErrorCode) -> Self {
+        self.error_codes.push(error_code);
+        self
+    }
+
+    /// Sets the options used to scan source code, for the functions that scan
+    /// it before parsing, such as [`parse_string_with`].
+    pub fn scanner(mut self, options: ScanOptions) -> Self {
+        self.scanner = options;
+        self
+    }
+
+    fn limit_errors(&self, mut error: Error) -> Error {
+        if let Some(limit) = self.error_limit {
+            error.truncate(limit);
+        }
+        error
+    }
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            whitespace: WhiteSpacePolicy::default(),
+            recursion_limit: None,
+            error_limit: Some(ParseOptions::DEFAULT_ERROR_LIMIT),
+            case_sensitive_keywords: true,
+            check_delimiters: false,
+            error_codes: vec![],
+            scanner: ScanOptions::default(),
+        }
     }
 }
 
 /// Parses the given tokens into the syntax tree node `T`.
 ///
 /// This function ignores all whitespace.
-pub fn parse<T: Parse>(mut tokens: TokenStream) -> Result<T> {
-    tokens.remove_whitespace();
-    Parser::parse(T::parse, tokens)
+pub fn parse<T: Parse>(tokens: TokenStream) -> Result<T> {
+    Parser::parse_with_options(T::parse, tokens, ParseOptions::new())
+}
+
+/// Parses the given borrowed tokens into the syntax tree node `T`.
+///
+/// Unlike [`parse`], this leaves `tokens` intact, so the same stream can be
+/// parsed again. This function ignores all whitespace.
+///
+/// ## Example
+/// ```
+/// # use flexi_parse::parse_ref;
+/// # use flexi_parse::token::Ident;
+/// # use flexi_parse::token::LitInt;
+/// # use flexi_parse::SourceFile;
+/// # use flexi_parse::TokenStream;
+/// # use std::sync::Arc;
+/// let source = SourceFile::new("example".to_string(), "x".to_string());
+/// let tokens = TokenStream::try_from(Arc::new(source)).unwrap();
+/// assert!(parse_ref::<LitInt>(&tokens).is_err());
+/// let x: Ident = parse_ref(&tokens).unwrap();
+/// assert_eq!(x.string(), "x");
+/// ```
+pub fn parse_ref<T: Parse>(tokens: &TokenStream) -> Result<T> {
+    Parser::parse_stream_with_options(T::parse, tokens, ParseOptions::new())
 }
 
 /// Scans and parses the given source file into the syntax tree node `T`.
 ///
 /// This function ignores all whitespace.
-pub fn parse_source<T: Parse>(source: Rc<SourceFile>) -> Result<T> {
-    let (tokens, error) = scanner::scan(source);
-    parse(tokens).map_err(|mut err| {
+pub fn parse_source<T: Parse>(source: Arc<SourceFile>) -> Result<T> {
+    parse_source_with(source, ParseOptions::new())
+}
+
+/// Scans and parses the given source file into the syntax tree node `T`,
+/// using the given options.
+pub fn parse_source_with<T: Parse>(source: Arc<SourceFile>, options: ParseOptions) -> Result<T> {
+    let (tokens, error, parse_options) = scanner::scan_checked(source, &options)
+        .map_err(|err| options.limit_errors(err.into_origin()))?;
+    Parser::parse_with_options(T::parse, tokens, parse_options).map_err(|mut err| {
         if let Some(error) = error {
             err.add(error);
         }
-        err
+        options.limit_errors(err.into_origin())
     })
 }
 
+/// Reads, scans and parses the file at the given path into the syntax tree
+/// node `T`.
+///
+/// This function ignores all whitespace.
+///
+/// ## Errors
+/// If the file cannot be read, this returns an error of kind
+/// [`DiagnosticKind::Io`][error::DiagnosticKind::Io], reported against the
+/// path.
+pub fn parse_file<T: Parse>(path: PathBuf) -> Result<T> {
+    parse_file_with(path, ParseOptions::new())
+}
+
+/// Reads, scans and parses the file at the given path into the syntax tree
+/// node `T`, using the given options.
+///
+/// ## Errors
+/// See [`parse_file`].
+pub fn parse_file_with<T: Parse>(path: PathBuf, options: ParseOptions) -> Result<T> {
+    let source = SourceFile::read(path.clone()).map_err(|err| Error::io(&path, &err))?;
+    parse_source_with(Arc::new(source), options)
+}
+
+/// Reads standard input until EOF, and scans and parses it into the syntax tree
+/// node `T`.
+///
+/// Errors are reported as coming from a source called `<stdin>`. This function
+/// ignores all whitespace.
+///
+/// ## Errors
+/// If standard input cannot be read, this returns an error of kind
+/// [`DiagnosticKind::Io`][error::DiagnosticKind::Io].
+pub fn parse_stdin<T: Parse>() -> Result<T> {
+    parse_stdin_with(ParseOptions::new())
+}
+
+/// Reads standard input until EOF, and scans and parses it into the syntax tree
+/// node `T`, using the given options.
+///
+/// ## Errors
+/// See [`parse_stdin`].
+pub fn parse_stdin_with<T: Parse>(options: ParseOptions) -> Result<T> {
+    const NAME: &str = "<stdin>";
+    let mut contents = String::new();
+    io::stdin()
+        .read_to_string(&mut contents)
+        .map_err(|err| Error::io(Path::new(NAME), &err))?;
+    parse_source_with(
+        Arc::new(SourceFile::new(NAME.to_string(), contents)),
+        options,
+    )
+}
+
+/// Reads `reader` one line at a time, parsing each line into the syntax tree
+/// node `T` and passing it to `function`.
+///
+/// Unlike [`parse_lines`], only one line is held in memory at a time, and no
+/// more errors are kept than the error limit in the options allows, so this
+/// can be used for inputs of any size with one record per line. Each line is
+/// scanned as its own source file, named `<input>:` followed by its line
+/// number. Blank lines are skipped, and all whitespace is ignored.
+///
+/// ## Errors
+/// When a line fails to parse, its error is recorded and reading continues
+/// from the next line. Returns the recorded errors, if there were any, once
+/// `reader` is exhausted, or as soon as `function` returns an error. If
+/// `reader` cannot be read, this returns an error of kind
+/// [`DiagnosticKind::Io`][error::DiagnosticKind::Io].
+///
+/// ## Example
+/// ```
+/// # use flexi_parse::parse_reader_lines;
+/// # use flexi_parse::token::LitInt;
+/// let input = "1\n2\n\n3\n";
+/// let mut total = 0;
+/// parse_reader_lines(input.as_bytes(), |value: LitInt| {
+///     total += value.value();
+///     Ok(())
+/// })
+/// .unwrap();
+/// assert_eq!(total, 6);
+/// ```
+pub fn parse_reader_lines<T: Parse, R: BufRead, F: FnMut(T) -> Result<()>>(
+    reader: R,
+    function: F,
+) -> Result<()> {
+    parse_reader_lines_with(reader, ParseOptions::new(), function)
+}
+
+/// Reads `reader` one line at a time, parsing each line into the syntax tree
+/// node `T` using the given options, and passing it to `function`.
+///
+/// ## Errors
+/// See [`parse_reader_lines`].
+pub fn parse_reader_lines_with<T: Parse, R: BufRead, F: FnMut(T) -> Result<()>>(
+    mut reader: R,
+    options: ParseOptions,
+    mut function: F,
+) -> Result<()> {
+    const NAME: &str = "<input>";
+    let mut errors = Error::empty();
+    let mut hidden = 0;
+    let mut line = String::new();
+    let mut number = 0;
+    loop {
+        line.clear();
+        number += 1;
+        let read = reader
+            .read_line(&mut line)
+            .map_err(|err| Error::io(Path::new(NAME), &err));
+        match read {
+            Ok(0) => break,
+            Ok(_) => {}
+            Err(err) => {
+                errors.add(err);
+                break;
+            }
+        }
+        let record = line.trim_end_matches(['\n', '\r']);
+        if record.trim().is_empty() {
+            continue;
+        }
+        let source = SourceFile::new(format!("{NAME}:{number}"), record.to_string());
+        match parse_source_with(Arc::new(source), options.clone()) {
+            Ok(item) => {
+                if let Err(err) = function(item) {
+                    errors.add(err);
+                    break;
+                }
+            }
+            Err(err) => {
+                errors.add(err);
+                if let Some(limit) = options.error_limit {
+                    hidden += errors.drop_after(limit);
+                }
+            }
+        }
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        if let Some(limit) = options.error_limit {
+            hidden += errors.drop_after(limit);
+        }
+        errors.note_hidden(hidden);
+        Err(errors)
+    }
+}
+
 /// Scans and parses the given string into the syntax tree node `T`.
 ///
 /// This function ignores all whitespace.
 pub fn parse_string<T: Parse>(source: String) -> Result<T> {
-    let source = Rc::new(SourceFile {
-        name: "str".to_string(),
-        path: None,
-        contents: source,
+    parse_string_with(source, ParseOptions::new())
+}
+
+/// Scans and parses the given string into the syntax tree node `T`, using the
+/// given options.
+pub fn parse_string_with<T: Parse>(source: String, options: ParseOptions) -> Result<T> {
+    let source = Arc::new(SourceFile::new("str".to_string(), source));
+    parse_source_with(source, options)
+}
+
+/// Scans and parses the given string into the syntax tree node `T`, reporting
+/// any errors as coming from a source called `name`.
+///
+/// This function ignores all whitespace.
+///
+/// # Example
+/// ```
+/// # use flexi_parse::parse_string_named;
+/// # use flexi_parse::token::Ident;
+/// let err = parse_string_named::<Ident>("1".to_string(), "query #3".to_string()).unwrap_err();
+/// assert!(err.to_string().contains("query #3"));
+/// ```
+pub fn parse_string_named<T: Parse>(source: String, name: String) -> Result<T> {
+    parse_string_named_with(source, name, ParseOptions::new())
+}
+
+/// Scans and parses the given string into the syntax tree node `T`, using the
+/// given options, and reporting any errors as coming from a source called
+/// `name`.
+pub fn parse_string_named_with<T: Parse>(
+    source: String,
+    name: String,
+    options: ParseOptions,
+) -> Result<T> {
+    parse_source_with(Arc::new(SourceFile::new(name, source)), options)
+}
+
+/// Scans and parses the given source file into the syntax tree node `T`,
+/// using the given options, and returns any warnings added with
+/// [`ParseBuffer::add_warning`] alongside the result.
+///
+/// Unlike [`parse_source_with`], warnings are kept separate from the error if
+/// parsing fails.
+pub fn parse_source_with_warnings<T: Parse>(
+    source: Arc<SourceFile>,
+    options: ParseOptions,
+) -> (Result<T>, Warnings) {
+    let (tokens, error, parse_options) = match scanner::scan_checked(source, &options) {
+        Ok(scanned) => scanned,
+        Err(err) => {
+            let err = options.limit_errors(err.into_origin());
+            return (Err(err), Warnings::new(None));
+        }
+    };
+    let (result, warnings) = Parser::parse_with_warnings(T::parse, tokens, parse_options);
+    let result = result.map_err(|mut err| {
+        if let Some(error) = error {
+            err.add(error);
+        }
+        options.limit_errors(err.into_origin())
     });
-    parse_source(source)
+    (result, Warnings::new(warnings.map(Error::into_origin)))
+}
+
+/// Scans and parses the given string into the syntax tree node `T`, using the
+/// given options, and returns any warnings added with
+/// [`ParseBuffer::add_warning`] alongside the result.
+///
+/// # Example
+/// ```
+/// # use flexi_parse::parse_string_with_warnings;
+/// # use flexi_parse::token::Ident;
+/// # use flexi_parse::Parse;
+/// # use flexi_parse::ParseOptions;
+/// # use flexi_parse::ParseStream;
+/// # use flexi_parse::Result;
+/// struct Name(Ident);
+///
+/// impl Parse for Name {
+///     fn parse(input: ParseStream) -> Result<Self> {
+///         let ident: Ident = input.parse()?;
+///         if ident.string().starts_with(char::is_uppercase) {
+///             input.add_warning(input.new_error(
+///                 "Capitalised name".to_string(),
+///                 &ident,
+///                 0,
+///             ));
+///         }
+///         Ok(Name(ident))
+///     }
+/// }
+///
+/// let (result, warnings) =
+///     parse_string_with_warnings::<Name>("Name".to_string(), ParseOptions::new());
+/// assert!(result.is_ok());
+/// assert_eq!(warnings.len(), 1);
+/// ```
+pub fn parse_string_with_warnings<T: Parse>(
+    source: String,
+    options: ParseOptions,
+) -> (Result<T>, Warnings) {
+    let source = Arc::new(SourceFile::new("str".to_string(), source));
+    parse_source_with_warnings(source, options)
 }
 
 /// Attempts to repeatedly parse `input` into the given syntax tree node,
@@ -237,11 +1392,140 @@ pub fn parse_repeated<T: Parse>(input: ParseStream<'_>) -> Result<Vec<T>> {
     Ok(items)
 }
 
+/// Attempts to repeatedly parse `input` into the given syntax tree node,
+/// using `T`'s default parsing implementation, and continuing until `input` is
+/// exhausted.
+///
+/// ## Errors
+/// Returns an error if `input` is empty.
+pub fn parse_repeated_nonempty<T: Parse>(input: ParseStream<'_>) -> Result<Vec<T>> {
+    parse_repeated_n(input, 1, usize::MAX)
+}
+
+/// Attempts to repeatedly parse `input` into the given syntax tree node,
+/// using `T`'s default parsing implementation, and continuing until `input` is
+/// exhausted.
+///
+/// ## Errors
+/// Returns an error at the first node past `max`, or at the end of `input` if
+/// there are fewer than `min` nodes.
+pub fn parse_repeated_n<T: Parse>(
+    input: ParseStream<'_>,
+    min: usize,
+    max: usize,
+) -> Result<Vec<T>> {
+    let mut items = vec![];
+
+    while !input.is_empty() {
+        let (item, span) = input.spanned(T::parse)?;
+        if items.len() == max {
+            return Err(Error::new(
+                input.source,
+                ErrorKind::TooManyElements { max, span },
+            ));
+        }
+        items.push(item);
+    }
+
+    if items.len() < min {
+        return Err(Error::new(
+            input.source,
+            ErrorKind::TooFewElements {
+                min,
+                span: input.next_span(),
+            },
+        ));
+    }
+
+    Ok(items)
+}
+
+/// Attempts to repeatedly parse `input` into the given syntax tree node,
+/// using `T`'s default parsing implementation, and continuing until `function`
+/// returns true or `input` is exhausted.
+///
+/// The token `function` stops at is not consumed.
+pub fn parse_repeated_until<T: Parse, F: FnMut(ParseStream<'_>) -> bool>(
+    input: ParseStream<'_>,
+    mut function: F,
+) -> Result<Vec<T>> {
+    let mut items = vec![];
+
+    while !input.is_empty() && !function(input) {
+        items.push(input.parse()?);
+    }
+
+    Ok(items)
+}
+
+/// Attempts to parse one instance of the given syntax tree node from each line
+/// of `input`, using `T`'s default parsing implementation, and continuing until
+/// `input` is exhausted.
+///
+/// Blank lines are skipped. When a line fails to parse, its error is recorded
+/// and parsing continues from the next line. Returns the nodes that were parsed
+/// successfully, along with all of the recorded errors, if there were any.
+///
+/// Newlines must be kept in `input`, for example by parsing with
+/// [`WhiteSpacePolicy::RemoveBlankSpace`].
+pub fn parse_lines<T: Parse>(input: ParseStream<'_>) -> (Vec<T>, Option<Error>) {
+    parse_lines_with(input, T::parse)
+}
+
+/// Attempts to parse one value from each line of `input` using `function`,
+/// continuing until `input` is exhausted.
+///
+/// See [`parse_lines`].
+pub fn parse_lines_with<T, F: FnMut(ParseStream<'_>) -> Result<T>>(
+    input: ParseStream<'_>,
+    mut function: F,
+) -> (Vec<T>, Option<Error>) {
+    let mut items = vec![];
+    let mut errors = Error::empty();
+
+    loop {
+        input.skip_whitespace();
+        while input.peek(token::NewLine) {
+            let _ = input.next();
+            input.skip_whitespace();
+        }
+        if input.is_empty() {
+            break;
+        }
+
+        let offset = input.cursor.offset.get();
+        match function(input) {
+            Ok(item) => {
+                input.skip_whitespace();
+                if input.is_empty() || input.peek(token::NewLine) {
+                    items.push(item);
+                } else {
+                    errors.add(input.unexpected(token::NewLine::display()));
+                }
+            }
+            Err(err) => {
+                errors.add(err);
+                input.cursor.offset.set(offset);
+            }
+        }
+
+        while !input.is_empty() && !input.peek(token::NewLine) {
+            let _ = input.next();
+        }
+    }
+
+    let errors = if errors.is_empty() {
+        None
+    } else {
+        Some(errors)
+    };
+    (items, errors)
+}
+
 /// Gets the `Ok` value, panicking with a formatted error message if the value
 /// is `Err`.
 /// ## Panics
 /// Panics if the contained value is `Err`.
-#[cfg(feature = "ariadne")]
 pub fn pretty_unwrap<T>(result: Result<T>) -> T {
     result.unwrap_or_else(|err| {
         err.eprint().unwrap();
@@ -249,73 +1533,252 @@ pub fn pretty_unwrap<T>(result: Result<T>) -> T {
     })
 }
 
+/// Gets the `Ok` value, printing a formatted error message and exiting the
+/// process with the given status code if the value is `Err`.
+///
+/// Unlike [`pretty_unwrap`], this doesn't panic, so no backtrace is printed.
+pub fn pretty_expect_or_exit<T>(result: Result<T>, code: i32) -> T {
+    result.unwrap_or_else(|err| {
+        let _ = err.eprint();
+        process::exit(code);
+    })
+}
+
 /// A sequence of tokens.
 ///
 /// This is the return type of
 /// [`Group::token_stream`][group::Group::into_token_stream], and can be created
 /// from a [`proc_macro::TokenStream`][proc-macro] or
-/// [`proc_macro2::TokenStream`][proc-macro2].
+/// [`proc_macro2::TokenStream`][proc-macro2]. It can also be converted back
+/// into either with [`TryFrom`], if its text is valid Rust.
+///
+/// ## Limits
+/// A stream holds at most `u32::MAX` tokens, including the end marker, from at
+/// most 65,536 source files, each smaller than 4 GiB. Scanning a source file,
+/// or expanding includes, past these limits returns an error of kind
+/// [`DiagnosticKind::LimitExceeded`][error::DiagnosticKind::LimitExceeded].
+/// Tokens appended past them with [`ToTokens`] are dropped, and parsing the
+/// stream then returns that error.
 ///
 /// [proc-macro]: https://doc.rust-lang.org/proc_macro/struct.TokenStream.html
 /// [proc-macro2]: https://docs.rs/proc-macro2/latest/proc_macro2/struct.TokenStream.html
 #[derive(Debug, Clone, PartialEq)]
 pub struct TokenStream {
-    original_tokens: Vec<Entry>,
-    tokens: Vec<(usize, Entry)>,
-    source: Rc<SourceFile>,
+    /// Every token in the stream, including any that have been removed.
+    arena: Vec<PackedEntry>,
+    /// The indices in `arena` of the tokens in the stream.
+    tokens: Vec<u32>,
+    /// The source files of the tokens in `arena`.
+    sources: Vec<Arc<SourceFile>>,
+    /// The symbols of the identifiers in `arena`.
+    symbols: Arc<Interner>,
+    source: Arc<SourceFile>,
+    /// The first limit exceeded while adding tokens, and the span of the
+    /// token that was dropped.
+    exceeded: Option<(Limit, Span)>,
 }
 
 impl TokenStream {
-    fn new(tokens: Vec<Entry>, source: Rc<SourceFile>) -> TokenStream {
+    fn new(tokens: Vec<Entry>, source: Arc<SourceFile>) -> TokenStream {
+        TokenStream::with_symbols(tokens, Interner::default(), source)
+    }
+
+    /// Creates a stream of `tokens`, whose identifiers share their text with
+    /// the symbols in `symbols`.
+    fn with_symbols(
+        tokens: Vec<Entry>,
+        mut symbols: Interner,
+        source: Arc<SourceFile>,
+    ) -> TokenStream {
+        let mut sources = vec![];
+        let mut arena = Vec::with_capacity(tokens.len());
+        let mut exceeded = None;
+        for entry in &tokens {
+            match PackedEntry::pack(entry, &mut sources, &mut symbols) {
+                Ok(packed) => arena.push(packed),
+                Err(limit) => {
+                    exceeded = Some((limit, entry.span().clone()));
+                    arena.push(PackedEntry::END);
+                    break;
+                }
+            }
+        }
+        let mut stream = TokenStream::from_arena(arena, sources, Arc::new(symbols), source);
+        if exceeded.is_some() {
+            stream.exceeded = exceeded;
+        }
+        stream
+    }
+
+    fn from_arena(
+        mut arena: Vec<PackedEntry>,
+        sources: Vec<Arc<SourceFile>>,
+        symbols: Arc<Interner>,
+        source: Arc<SourceFile>,
+    ) -> TokenStream {
+        let mut exceeded = None;
+        let last = index(arena.len() - 1).unwrap_or_else(|| {
+            exceeded = Some((Limit::Tokens, Span::new(0, 0, Arc::clone(&source))));
+            arena.truncate(u32::MAX as usize - 1);
+            arena.push(PackedEntry::END);
+            u32::MAX - 1
+        });
         TokenStream {
-            original_tokens: tokens.clone(),
-            tokens: tokens.into_iter().enumerate().collect(),
+            tokens: (0..=last).collect(),
+            arena,
+            sources,
+            symbols,
             source,
+            exceeded,
+        }
+    }
+
+    /// Creates an empty stream of tokens from the given source file.
+    pub fn empty(source: Arc<SourceFile>) -> TokenStream {
+        TokenStream::new(vec![Entry::End], source)
+    }
+
+    /// Appends `entry` to the end of this stream, before the end marker.
+    ///
+    /// If this would exceed one of the stream's limits, `entry` and every
+    /// token appended after it are dropped.
+    fn push(&mut self, entry: Entry) {
+        if self.exceeded.is_some() {
+            return;
+        }
+        let Some(end) = index(self.arena.len()) else {
+            self.exceeded = Some((Limit::Tokens, entry.span().clone()));
+            return;
+        };
+        let packed = PackedEntry::pack(&entry, &mut self.sources, Arc::make_mut(&mut self.symbols));
+        match packed {
+            Ok(packed) => {
+                let last = self.arena.len() - 1;
+                self.arena.insert(last, packed);
+                self.tokens.pop();
+                self.tokens.push(end - 1);
+                self.tokens.push(end);
+            }
+            Err(limit) => self.exceeded = Some((limit, entry.span().clone())),
+        }
+    }
+
+    /// Returns an error if a limit was exceeded while adding tokens to this
+    /// stream.
+    fn check_limits(&self) -> Result<()> {
+        match &self.exceeded {
+            Some((limit, span)) => Err(limit.error(span.clone())),
+            None => Ok(()),
         }
     }
 
-    fn filter<F: FnMut(&TokenStream) -> Vec<usize>>(&mut self, mut function: F) {
-        let mut indices = function(self);
-        indices.sort_unstable();
-        indices.reverse();
-        for index in indices {
-            self.tokens.remove(index);
+    /// Returns an iterator over the tokens in this stream, including the end
+    /// marker.
+    #[cfg(feature = "proc-macro2")]
+    fn entries(&self) -> impl Iterator<Item = Entry> + '_ {
+        self.packed()
+            .map(|entry| entry.unpack(&self.sources, &self.symbols))
+    }
+
+    /// Returns an iterator over the packed tokens in this stream, including
+    /// the end marker.
+    fn packed(&self) -> impl DoubleEndedIterator<Item = &PackedEntry> {
+        self.tokens.iter().map(|&index| &self.arena[index as usize])
+    }
+
+    fn filter<F: FnMut(&TokenStream) -> Vec<usize>>(&mut self, function: F) {
+        self.tokens = self.filtered(function);
+    }
+
+    /// Returns the indices in the arena of the tokens in this stream, leaving
+    /// out those at the positions returned by `function`.
+    fn filtered<F: FnMut(&TokenStream) -> Vec<usize>>(&self, mut function: F) -> Vec<u32> {
+        let mut removed = function(self);
+        removed.sort_unstable();
+        self.tokens
+            .iter()
+            .enumerate()
+            .filter(|(position, _)| removed.binary_search(position).is_err())
+            .map(|(_, &index)| index)
+            .collect()
+    }
+
+    /// Returns the positions of the whitespace tokens removed by `policy`.
+    fn whitespace_positions(&self, policy: WhiteSpacePolicy) -> Vec<usize> {
+        let mut indices = vec![];
+        let mut post_newline = true;
+        for (index, token) in self.packed().enumerate() {
+            if !token.kind.is_whitespace() {
+                post_newline = false;
+                continue;
+            }
+            let newline = token.kind == PackedKind::NewLine;
+            let removed = match policy {
+                WhiteSpacePolicy::Keep => false,
+                WhiteSpacePolicy::Prepare => !newline && !post_newline,
+                WhiteSpacePolicy::RemoveBlankSpace => !newline,
+                WhiteSpacePolicy::Remove => true,
+            };
+            if removed {
+                indices.push(index);
+            }
+            post_newline |= newline;
         }
+        indices
     }
 
     /// Removes all whitespace that doesn't come at the start of a line.
     ///
     /// Note that the `parse*` functions remove all whitespace.
     pub fn prepare_whitespace(&mut self) {
-        self.filter(|tokens| {
-            let mut indices = vec![];
-            let mut post_newline = true;
-            for (index, (_, token)) in tokens.tokens.iter().enumerate() {
-                if let Entry::WhiteSpace(whitespace) = token {
-                    if matches!(whitespace, WhiteSpace::NewLine(_)) {
-                        post_newline = true;
-                    } else if !post_newline {
-                        indices.push(index);
-                    }
-                } else {
-                    post_newline = false;
-                }
-            }
-            indices
-        });
+        self.filter(|tokens| tokens.whitespace_positions(WhiteSpacePolicy::Prepare));
     }
 
     /// Removes all non-newline whitespace from `self`.
     ///
     /// Note that the `parse*` functions will remove all whitespace.
     pub fn remove_blank_space(&mut self) {
+        self.filter(|tokens| tokens.whitespace_positions(WhiteSpacePolicy::RemoveBlankSpace));
+    }
+
+    /// Removes all tokens for which `function` returns false.
+    ///
+    /// `function` is called with a stream positioned at each token in turn,
+    /// so tokens can be tested with [`ParseBuffer::peek`].
+    ///
+    /// ## Example
+    /// ```
+    /// # use flexi_parse::parse;
+    /// # use flexi_parse::token::Ident;
+    /// # use flexi_parse::Punct;
+    /// # use flexi_parse::SourceFile;
+    /// # use flexi_parse::TokenStream;
+    /// # use std::sync::Arc;
+    /// let source = SourceFile::new("example".to_string(), "; x ;".to_string());
+    /// let mut tokens = TokenStream::try_from(Arc::new(source)).unwrap();
+    /// tokens.retain(|input| !input.peek(Punct![";"]));
+    /// let x: Ident = parse(tokens).unwrap();
+    /// assert_eq!(x.string(), "x");
+    /// ```
+    pub fn retain<F: FnMut(ParseStream<'_>) -> bool>(&mut self, mut function: F) {
         self.filter(|tokens| {
+            let buffer = ParseBuffer::new(
+                Cursor {
+                    arena: tokens.arena.as_slice(),
+                    sources: tokens.sources.as_slice(),
+                    symbols: &tokens.symbols,
+                    stream: tokens.tokens.as_slice(),
+                    offset: Cell::new(0),
+                    last: tokens.tokens.len() - 1,
+                },
+                &tokens.source,
+                Rc::default(),
+            );
             let mut indices = vec![];
-            for (index, (_, token)) in tokens.tokens.iter().enumerate() {
-                if let Entry::WhiteSpace(whitespace) = token {
-                    if !matches!(whitespace, WhiteSpace::NewLine(_)) {
-                        indices.push(index);
-                    }
+            for index in 0..buffer.cursor.last {
+                buffer.cursor.offset.set(index);
+                if !function(&buffer) {
+                    indices.push(index);
                 }
             }
             indices
@@ -326,23 +1789,274 @@ impl TokenStream {
     ///
     /// This method is automatically called by the `parse*` functions.
     pub fn remove_whitespace(&mut self) {
-        self.filter(|tokens| {
-            let mut indices = vec![];
-            for (index, (_, token)) in tokens.tokens.iter().enumerate() {
-                if let Entry::WhiteSpace(_) = token {
-                    indices.push(index);
+        self.filter(|tokens| tokens.whitespace_positions(WhiteSpacePolicy::Remove));
+    }
+}
+
+impl TokenStream {
+    /// Checks that every `(`, `[`, and `{` in this stream is matched by a
+    /// closing delimiter of the same kind.
+    ///
+    /// Unlike parsing a [`Group`][group::Group], this reports every unmatched
+    /// delimiter at once. Delimiters inside string literals, and character
+    /// literals such as `'('`, are ignored.
+    ///
+    /// ## Errors
+    /// Returns an error for each unmatched delimiter, if there are any.
+    pub fn check_delimiters(&self) -> Result<()> {
+        fn closing(kind: PunctKind) -> Option<char> {
+            match kind {
+                PunctKind::LeftParen => Some(')'),
+                PunctKind::LeftBracket => Some(']'),
+                PunctKind::LeftBrace => Some('}'),
+                _ => None,
+            }
+        }
+
+        let unterminated = |(kind, open): (PunctKind, Span), span: Span| {
+            Error::new(
+                &self.source,
+                ErrorKind::UnterminatedGroup {
+                    start: char::from(kind).to_string(),
+                    span,
+                    open,
+                },
+            )
+        };
+
+        let entries: Vec<_> = self.packed().collect();
+        let punct = |index: usize| match entries.get(index)?.kind {
+            PackedKind::Punct(kind, _) => Some(kind),
+            _ => None,
+        };
+
+        let mut error = Error::empty();
+        let mut open: Vec<(PunctKind, Span)> = vec![];
+        let mut index = 0;
+        while let Some(entry) = entries.get(index) {
+            index += 1;
+            let PackedKind::Punct(kind, _) = entry.kind else {
+                continue;
+            };
+            if kind == PunctKind::DoubleQuote {
+                while index < entries.len() {
+                    index += 1;
+                    match punct(index - 1) {
+                        Some(PunctKind::Backslash) => index += 1,
+                        Some(PunctKind::DoubleQuote) => break,
+                        _ => {}
+                    }
                 }
+                continue;
             }
-            indices
-        });
+            if kind == PunctKind::SingleQuote {
+                // A lone quote, such as in a lifetime, isn't a literal.
+                let len = if punct(index) == Some(PunctKind::Backslash) {
+                    3
+                } else {
+                    2
+                };
+                if punct(index + len - 1) == Some(PunctKind::SingleQuote) {
+                    index += len;
+                }
+                continue;
+            }
+            if closing(kind).is_some() {
+                open.push((kind, entry.span(&self.sources)));
+                continue;
+            }
+            if !matches!(
+                kind,
+                PunctKind::RightParen | PunctKind::RightBracket | PunctKind::RightBrace
+            ) {
+                continue;
+            }
+            let close = char::from(kind);
+            if let Some(index) = open
+                .iter()
+                .rposition(|(kind, _)| closing(*kind) == Some(close))
+            {
+                for unclosed in open.drain(index + 1..) {
+                    error.add(unterminated(unclosed, entry.span(&self.sources)));
+                }
+                open.pop();
+            } else {
+                error.add(Error::new(
+                    &self.source,
+                    ErrorKind::UnmatchedDelimiter {
+                        delimiter: close.to_string(),
+                        span: entry.span(&self.sources),
+                        open: open
+                            .last()
+                            .map(|(kind, span)| (char::from(*kind).to_string(), span.clone())),
+                    },
+                ));
+            }
+        }
+        let end = self
+            .packed()
+            .rev()
+            .find(|entry| entry.kind != PackedKind::End && !entry.kind.is_whitespace())
+            .map_or(self.source.contents.len(), |entry| entry.end as usize);
+        for unclosed in open {
+            error.add(unterminated(
+                unclosed,
+                Span::new(end, end, Arc::clone(&self.source)),
+            ));
+        }
+
+        if error.is_empty() {
+            Ok(())
+        } else {
+            Err(error)
+        }
+    }
+
+    /// Replaces include directives in this stream with the tokens of the files
+    /// they include.
+    ///
+    /// `function` is called with a stream positioned at each token in turn. If
+    /// it parses an include directive, it should return the file to include,
+    /// and the tokens it consumed are replaced by the tokens of that file. The
+    /// token the stream was positioned at is always replaced. Otherwise, it
+    /// should return `Ok(None)`, and the stream is left as it was.
+    ///
+    /// As with the `parse*` functions, whitespace is removed from the stream
+    /// `function` sees, so it is never called at a whitespace token and
+    /// directives may be spread over several lines. Whitespace that isn't part
+    /// of a directive is kept.
+    ///
+    /// Included files are expanded in the same way, so they may include other
+    /// files. Tokens from an included file keep their spans in that file, so
+    /// diagnostics about them are reported against it.
+    ///
+    /// ## Errors
+    /// Returns an error if `function` returns one, if an included file cannot
+    /// be scanned, or if a file includes itself, directly or indirectly.
+    ///
+    /// ## Example
+    /// ```
+    /// # use flexi_parse::parse_repeated;
+    /// # use flexi_parse::token::Ident;
+    /// # use flexi_parse::Parser;
+    /// # use flexi_parse::Punct;
+    /// # use flexi_parse::SourceFile;
+    /// # use flexi_parse::Spanned;
+    /// # use flexi_parse::TokenStream;
+    /// # use std::sync::Arc;
+    /// let lib = Arc::new(SourceFile::new("lib".to_string(), "b".to_string()));
+    /// let main = SourceFile::new("main".to_string(), "a # lib c".to_string());
+    /// let mut tokens = TokenStream::try_from(Arc::new(main)).unwrap();
+    /// tokens
+    ///     .expand_includes(|input| {
+    ///         if !input.peek(Punct!["#"]) {
+    ///             return Ok(None);
+    ///         }
+    ///         let _: Punct!["#"] = input.parse()?;
+    ///         let name: Ident = input.parse()?;
+    ///         assert_eq!(name.string(), "lib");
+    ///         Ok(Some(Arc::clone(&lib)))
+    ///     })
+    ///     .unwrap();
+    ///
+    /// let idents = parse_repeated::<Ident>.parse(tokens).unwrap();
+    /// assert_eq!(idents.len(), 3);
+    /// assert_eq!(idents[1].span().source_file().name(), "lib");
+    /// ```
+    pub fn expand_includes<F: FnMut(ParseStream<'_>) -> Result<Option<Arc<SourceFile>>>>(
+        &mut self,
+        mut function: F,
+    ) -> Result<()> {
+        self.check_limits()?;
+        let mut entries = self.expanded(&mut function, &mut vec![self.source.id().clone()])?;
+        entries.push(Entry::End);
+        let tokens = TokenStream::new(entries, Arc::clone(&self.source));
+        tokens.check_limits()?;
+        *self = tokens;
+        Ok(())
+    }
+
+    /// Returns the tokens of this stream with include directives expanded,
+    /// without the end marker.
+    ///
+    /// `including` holds the ids of the files currently being expanded.
+    fn expanded<F: FnMut(ParseStream<'_>) -> Result<Option<Arc<SourceFile>>>>(
+        &self,
+        function: &mut F,
+        including: &mut Vec<String>,
+    ) -> Result<Vec<Entry>> {
+        let mut removed = self.whitespace_positions(WhiteSpacePolicy::Remove);
+        removed.sort_unstable();
+        // The position in `self.tokens` of each token `function` sees.
+        let positions: Vec<usize> = (0..self.tokens.len())
+            .filter(|position| removed.binary_search(position).is_err())
+            .collect();
+        let stream: Vec<u32> = positions
+            .iter()
+            .map(|&position| self.tokens[position])
+            .collect();
+        let buffer = ParseBuffer::new(
+            Cursor {
+                arena: self.arena.as_slice(),
+                sources: self.sources.as_slice(),
+                symbols: &self.symbols,
+                stream: stream.as_slice(),
+                offset: Cell::new(0),
+                last: stream.len() - 1,
+            },
+            &self.source,
+            Rc::default(),
+        );
+        let entry = |position: usize| {
+            self.arena[self.tokens[position] as usize].unpack(&self.sources, &self.symbols)
+        };
+        let mut entries = vec![];
+        let mut position = 0;
+        let mut index = 0;
+        while position < self.tokens.len() - 1 {
+            if positions[index] != position {
+                entries.push(entry(position));
+                position += 1;
+                continue;
+            }
+            buffer.cursor.offset.set(index);
+            let Some(file) = function(&buffer)? else {
+                entries.push(entry(position));
+                position += 1;
+                index += 1;
+                continue;
+            };
+            let directive = buffer.cursor.entry(index).span().clone();
+            if including.contains(file.id()) {
+                return Err(buffer.new_error(
+                    format!("'{}' includes itself", file.id()),
+                    directive,
+                    0,
+                ));
+            }
+            including.push(file.id().clone());
+            let included = TokenStream::try_from(file)?;
+            entries.extend(included.expanded(function, including)?);
+            including.pop();
+            index = buffer.cursor.offset.get().max(index + 1);
+            position = positions[index - 1] + 1;
+        }
+        Ok(entries)
     }
 }
 
-impl TryFrom<Rc<SourceFile>> for TokenStream {
+impl TryFrom<Arc<SourceFile>> for TokenStream {
     type Error = Error;
 
-    fn try_from(value: Rc<SourceFile>) -> Result<Self> {
-        let (tokens, error) = scanner::scan(value);
+    fn try_from(value: Arc<SourceFile>) -> Result<Self> {
+        TokenStream::scan_with(value, &ScanOptions::default())
+    }
+}
+
+impl TokenStream {
+    /// Scans `source` into a stream of tokens, using the given options.
+    pub fn scan_with(source: Arc<SourceFile>, options: &ScanOptions) -> Result<TokenStream> {
+        let (tokens, error) = scanner::scan(source, options);
         if let Some(error) = error {
             Err(error)
         } else {
@@ -351,26 +2065,76 @@ impl TryFrom<Rc<SourceFile>> for TokenStream {
     }
 }
 
+/// State shared between a `ParseBuffer` and all of its forks.
+#[derive(Default)]
+struct Context {
+    options: ParseOptions,
+    operators: RefCell<OperatorTable>,
+    depth: Cell<usize>,
+    /// The furthest position at which a speculative parse failed, and the
+    /// tokens it expected there.
+    expected: RefCell<Option<(usize, Expected)>>,
+    /// Extra names to consider for "did you mean" suggestions.
+    candidates: RefCell<HashSet<String>>,
+    /// The boundary to skip to when parsing a [`Recover`] fails.
+    recovery: RefCell<Option<Rc<RecoveryBoundary>>>,
+    /// Whether [`ParseBuffer::start_node`] and [`ParseBuffer::finish_node`]
+    /// are recorded, which is only the case inside [`cst::parse`].
+    record_events: Cell<bool>,
+}
+
+type RecoveryBoundary = dyn Fn(ParseStream<'_>) -> bool;
+
 /// A cursor position within a token stream.
 pub struct ParseBuffer<'a> {
     cursor: Cursor<'a>,
-    source: Rc<SourceFile>,
+    source: &'a Arc<SourceFile>,
     error: RefCell<Error>,
+    /// The warnings added to this buffer, which are kept if it is committed.
+    warnings: RefCell<Error>,
+    context: Rc<Context>,
+    /// The nodes started and finished in this buffer, for [`cst::parse`].
+    events: RefCell<Vec<cst::Event>>,
 }
 
 impl<'a> ParseBuffer<'a> {
-    fn new(cursor: Cursor<'a>, source: Rc<SourceFile>) -> ParseBuffer<'a> {
+    fn new(
+        cursor: Cursor<'a>,
+        source: &'a Arc<SourceFile>,
+        context: Rc<Context>,
+    ) -> ParseBuffer<'a> {
         ParseBuffer {
             cursor,
             source,
             error: RefCell::new(Error::empty()),
+            warnings: RefCell::new(Error::empty()),
+            context,
+            events: RefCell::default(),
         }
     }
 
     /// Attempts to parse `self` into the given syntax tree node, using `T`'s
     /// default parsing implementation.
+    ///
+    /// ## Errors
+    /// As well as any error from `T`'s implementation, this function returns
+    /// an error if it would exceed the
+    /// [recursion limit](ParseOptions::recursion_limit).
     pub fn parse<T: Parse>(&self) -> Result<T> {
-        T::parse(self)
+        let depth = self.context.depth.get();
+        if self
+            .context
+            .options
+            .recursion_limit
+            .is_some_and(|limit| depth >= limit)
+        {
+            let span = self.current_span()?;
+            return Err(Error::new(self.source, ErrorKind::RecursionLimit(span)));
+        }
+        self.context.depth.set(depth + 1);
+        let result = T::parse(self);
+        self.context.depth.set(depth);
+        result
     }
 
     /// Attempts to parse `self` into the given syntax tree node, using
@@ -379,6 +2143,104 @@ impl<'a> ParseBuffer<'a> {
         function(self)
     }
 
+    /// Parses `self` using `function`, also returning the span from the start
+    /// of the first token consumed to the end of the last.
+    ///
+    /// If `function` doesn't consume any tokens, the returned span is empty
+    /// and positioned at the start of the next token.
+    pub fn spanned<T, F: FnOnce(ParseStream<'_>) -> Result<T>>(
+        &self,
+        function: F,
+    ) -> Result<(T, Span)> {
+        let start = self.cursor.offset.get();
+        let value = function(self)?;
+        let end = self.cursor.offset.get();
+        let span = if end > start {
+            Span::across(
+                self.cursor.entry(start).span(),
+                self.cursor.entry(end - 1).span(),
+            )
+        } else {
+            let position = self.next_span().start;
+            Span::new(position, position, Arc::clone(self.source))
+        };
+        Ok((value, span))
+    }
+
+    /// Returns the span of the next token, or an empty span at the end of the
+    /// source if there are no more tokens.
+    fn next_span(&self) -> Span {
+        match self.cursor.current() {
+            Entry::End => {
+                let end = self.source.contents.len();
+                Span::new(end, end, Arc::clone(self.source))
+            }
+            entry => entry.span().clone(),
+        }
+    }
+
+    /// Parses a group delimited by `D`, parsing its contents into `T` in place.
+    ///
+    /// Unlike parsing a [`Group`][group::Group], this doesn't copy the tokens
+    /// inside the group, and errors added to the contents with
+    /// [`ParseBuffer::add_error`] are added to `self`.
+    ///
+    /// ## Errors
+    /// As well as errors from parsing the group and `T`, this function returns
+    /// an error if `T` doesn't consume the whole group.
+    pub fn parse_group<D: Delimiters, T: Parse>(&self) -> Result<T> {
+        self.parse_group_with::<D, T, _>(T::parse)
+    }
+
+    /// Parses a group delimited by `D`, parsing its contents in place using
+    /// `function`.
+    ///
+    /// See [`ParseBuffer::parse_group`].
+    pub fn parse_group_with<D: Delimiters, T, F: FnOnce(ParseStream<'_>) -> Result<T>>(
+        &self,
+        function: F,
+    ) -> Result<T> {
+        let (_, start, end) = group::find_contents::<D>(self)?;
+        let cursor = Cursor {
+            arena: self.cursor.arena,
+            sources: self.cursor.sources,
+            symbols: self.cursor.symbols,
+            stream: &self.cursor.stream[start..=end],
+            offset: Cell::new(0),
+            last: end - start,
+        };
+        let value = self.parse_contents::<D, T, F>(cursor, function)?;
+        self.parse::<D::End>()?;
+        Ok(value)
+    }
+
+    /// Parses the contents of a group delimited by `D` with `function`,
+    /// forwarding any errors added to the contents to `self`.
+    ///
+    /// Returns an error if `function` doesn't consume all of the contents.
+    fn parse_contents<D: Delimiters, T, F: FnOnce(ParseStream<'_>) -> Result<T>>(
+        &self,
+        cursor: Cursor<'_>,
+        function: F,
+    ) -> Result<T> {
+        let contents = ParseBuffer::new(cursor, self.source, Rc::clone(&self.context));
+        let value = function(&contents);
+        if let Some(error) = contents.get_error() {
+            self.add_error(error);
+        }
+        self.warnings
+            .borrow_mut()
+            .add(contents.warnings.replace(Error::empty()));
+        self.events
+            .borrow_mut()
+            .append(&mut contents.events.borrow_mut());
+        let value = value?;
+        if !contents.is_empty() {
+            return Err(contents.unexpected(D::End::display()));
+        }
+        Ok(value)
+    }
+
     /// Returns true if this stream has been exhausted.
     pub fn is_empty(&self) -> bool {
         self.cursor.eof()
@@ -386,15 +2248,58 @@ impl<'a> ParseBuffer<'a> {
 
     /// Creates a new error at the given location with the given message and
     /// code.
+    ///
+    /// If `code` has been registered with [`ParseOptions::error_code`], the
+    /// error carries the registered details.
     pub fn new_error<T: Into<Span>>(&self, message: String, location: T, code: u16) -> Error {
-        Error::new(
-            Rc::clone(&self.source),
+        let error = Error::new(
+            self.source,
             ErrorKind::Custom {
                 message,
                 span: location.into(),
                 code,
             },
-        )
+        );
+        match self
+            .context
+            .options
+            .error_codes
+            .iter()
+            .find(|error_code| error_code.code() == code)
+        {
+            Some(error_code) => error.with_error_code(error_code.clone()),
+            None => error,
+        }
+    }
+
+    /// Creates a new error at the given location, using the code registered
+    /// with [`ParseOptions::error_code`] under the given name and its default
+    /// message.
+    ///
+    /// If no error code named `name` has been registered, the error has no
+    /// code, and `name` is used as its message.
+    pub fn new_coded_error<T: Into<Span>>(&self, name: &str, location: T) -> Error {
+        match self
+            .context
+            .options
+            .error_codes
+            .iter()
+            .find(|error_code| error_code.name() == name)
+        {
+            Some(error_code) => self.new_error(
+                error_code.message().to_string(),
+                location,
+                error_code.code(),
+            ),
+            None => Error::new(
+                self.source,
+                ErrorKind::Custom {
+                    message: name.to_string(),
+                    span: location.into(),
+                    code: 0,
+                },
+            ),
+        }
     }
 
     /// Adds a new error to this buffer's storage.
@@ -412,6 +2317,115 @@ impl<'a> ParseBuffer<'a> {
         }
     }
 
+    /// Adds a warning to this buffer's storage.
+    ///
+    /// Unlike errors, warnings don't cause parsing to fail. Warnings added to a
+    /// fork are only kept if it is committed with [`ParseBuffer::commit`]. The severity of
+    /// every diagnostic in `warning` is set to [`Severity::Warning`], unless
+    /// it has already been set to [`Severity::Advice`].
+    ///
+    /// Warnings can be retrieved with [`ParseBuffer::get_warnings`] or
+    /// [`Parser::parse_with_warnings`].
+    ///
+    /// [`Severity::Warning`]: error::Severity::Warning
+    /// [`Severity::Advice`]: error::Severity::Advice
+    pub fn add_warning(&self, warning: Error) {
+        self.warnings.borrow_mut().add(warning.into_warning());
+    }
+
+    /// Returns all warnings from [`ParseBuffer::add_warning`], if it has been
+    /// called.
+    pub fn get_warnings(&self) -> Option<Error> {
+        if self.warnings.borrow().is_empty() {
+            None
+        } else {
+            Some(self.warnings.borrow().to_owned())
+        }
+    }
+
+    /// Adds names to consider when suggesting corrections for misspelled
+    /// identifiers.
+    ///
+    /// When parsing fails because of an unexpected identifier, the expected
+    /// tokens and these names are searched for a similar spelling, which is
+    /// suggested in the error, e.g. "did you mean `count`?".
+    pub fn add_candidates<I: IntoIterator<Item = String>>(&self, names: I) {
+        self.context.candidates.borrow_mut().extend(names);
+    }
+
+    /// Returns the names added with [`ParseBuffer::add_candidates`].
+    pub fn candidates(&self) -> HashSet<String> {
+        self.context.candidates.borrow().clone()
+    }
+
+    /// Returns the table of operators declared while parsing this stream.
+    ///
+    /// The table is shared with any forks of this stream, and is consulted by
+    /// [`ExprParser`][expr::ExprParser] for operators added with
+    /// [`ExprParser::declared_infix`][expr::ExprParser::declared_infix].
+    ///
+    /// ## Panics
+    /// This function will panic if the table is already borrowed.
+    pub fn operator_table(&self) -> RefMut<'_, OperatorTable> {
+        self.context.operators.borrow_mut()
+    }
+
+    #[doc(hidden)]
+    pub fn is_keyword(&self, ident: &Ident, keyword: &str) -> bool {
+        let string = ident.string();
+        string == keyword
+            || !self.context.options.case_sensitive_keywords
+                && string
+                    .chars()
+                    .flat_map(char::to_lowercase)
+                    .eq(keyword.chars().flat_map(char::to_lowercase))
+    }
+
+    #[doc(hidden)]
+    pub fn is_keyword_symbol(&self, ident: &Ident, keyword: &Symbol) -> bool {
+        ident.symbol() == keyword
+            || !self.context.options.case_sensitive_keywords
+                && self.is_keyword(ident, keyword.string())
+    }
+
+    #[doc(hidden)]
+    pub fn is_any_keyword(&self, ident: &Ident, keywords: &HashSet<Symbol>) -> bool {
+        if self.context.options.case_sensitive_keywords {
+            keywords.contains(ident.symbol())
+        } else {
+            keywords
+                .iter()
+                .any(|keyword| self.is_keyword(ident, keyword.string()))
+        }
+    }
+
+    /// Returns true if the next token is the keyword `keyword`, without
+    /// cloning it.
+    #[doc(hidden)]
+    pub fn peek_keyword(&self, keyword: &Symbol) -> bool {
+        Ident::peek_token(self)
+            && matches!(
+                self.cursor.current(),
+                Entry::Ident(ident) if self.is_keyword_symbol(&ident, keyword)
+            )
+    }
+
+    #[doc(hidden)]
+    pub fn peek_any_keyword(&self, keywords: &HashSet<Symbol>) -> bool {
+        Ident::peek_token(self)
+            && matches!(
+                self.cursor.current(),
+                Entry::Ident(ident) if self.is_any_keyword(&ident, keywords)
+            )
+    }
+
+    #[doc(hidden)]
+    pub fn peek2_any_keyword(&self, keywords: &HashSet<Symbol>) -> bool {
+        let buffer = self.fork();
+        let _ = buffer.next();
+        buffer.peek_any_keyword(keywords)
+    }
+
     /// Repeatedly skips tokens until `function` returns true or `self` is
     /// empty.
     pub fn synchronise<F: FnMut(ParseStream<'_>) -> bool>(&self, mut function: F) {
@@ -421,18 +2435,69 @@ impl<'a> ParseBuffer<'a> {
         let _ = self.next();
     }
 
+    /// Parses `T`, or, if that fails, adds the error to this buffer's storage
+    /// and skips tokens from where `T` started as in
+    /// [`ParseBuffer::synchronise`].
+    ///
+    /// The returned [`Recover::Error`] contains the span of the skipped tokens.
+    pub fn recover<T: Parse, F: FnMut(ParseStream<'_>) -> bool>(&self, boundary: F) -> Recover<T> {
+        let offset = self.cursor.offset.get();
+        let (value, span) = self
+            .spanned(|input| {
+                Ok(input.parse().map_err(|err| {
+                    input.add_error(err);
+                    input.cursor.offset.set(offset);
+                    input.synchronise(boundary);
+                }))
+            })
+            .unwrap();
+        match value {
+            Ok(value) => Recover::Ok(value),
+            Err(()) => Recover::Error(span),
+        }
+    }
+
+    /// Sets the boundary skipped to when parsing a [`Recover`] fails, for this
+    /// buffer and all buffers created from it.
+    ///
+    /// See [`ParseBuffer::synchronise`] for how `boundary` is used.
+    pub fn set_recovery<F: Fn(ParseStream<'_>) -> bool + 'static>(&self, boundary: F) {
+        *self.context.recovery.borrow_mut() = Some(Rc::new(boundary));
+    }
+
+    fn recovery(&self) -> Option<Rc<RecoveryBoundary>> {
+        self.context.recovery.borrow().clone()
+    }
+
     fn try_parse<T: Parse>(&self) -> Result<T> {
         let offset = self.cursor.offset.get();
-        T::parse(self).map_err(move |err| {
+        T::parse(self).inspect_err(move |err| {
+            self.record_expected(err);
             self.cursor.offset.set(offset);
-            err
         })
     }
 
+    /// Records the tokens expected by a failed speculative parse, so that
+    /// they can be included in the final error if parsing fails at the same
+    /// position.
+    fn record_expected(&self, error: &Error) {
+        let Some((start, tokens)) = error.furthest_expected() else {
+            return;
+        };
+        let mut expected = self.context.expected.borrow_mut();
+        match &mut *expected {
+            Some((current, existing)) if *current == start => {
+                existing.extend(tokens.clone());
+            }
+            Some((current, _)) if *current > start => {}
+            _ => *expected = Some((start, tokens.clone())),
+        }
+    }
+
     /// Returns true if the next token is an instance of `T`.
     pub fn peek<T: Peek>(&self, token: T) -> bool {
         let _ = token;
-        self.parse_undo::<T::Token>().is_ok()
+        T::Token::peek_token(self)
     }
 
     /// Returns true if the next token is an instance of `T`.
@@ -446,6 +2511,15 @@ impl<'a> ParseBuffer<'a> {
         buffer.peek::<T>(token)
     }
 
+    /// Returns true if the next tokens are a group delimited by `D`, followed
+    /// by an instance of `T`.
+    ///
+    /// The group is skipped over without parsing its contents.
+    pub fn peek_after_group<D: Delimiters, T: Peek>(&self, token: T) -> bool {
+        let fork = self.fork();
+        group::find_contents::<D>(&fork).is_ok() && D::End::parse(&fork).is_ok() && fork.peek(token)
+    }
+
     fn parse_undo<T: Parse>(&self) -> Result<T> {
         let offset = self.cursor.offset.get();
         let val = T::parse(self);
@@ -455,24 +2529,21 @@ impl<'a> ParseBuffer<'a> {
 
     fn report_error_tokens(&self) -> Result<()> {
         let mut error = false;
-        while let (Entry::Error(_), offset) = self.cursor.next() {
-            self.cursor.offset.set(offset);
+        while self.cursor.packed(self.cursor.offset.get()).kind == PackedKind::Error {
+            self.cursor.offset.set(self.cursor.bump());
             error = true;
         }
         if error {
-            Err(Error::new(Rc::clone(&self.source), ErrorKind::Silent))
+            Err(Error::new(self.source, ErrorKind::Silent))
         } else {
             Ok(())
         }
     }
 
-    fn next(&self) -> Result<&'a Entry> {
+    fn next(&self) -> Result<Entry> {
         self.report_error_tokens()?;
         if self.cursor.eof() {
-            Err(Error::new(
-                Rc::clone(&self.source),
-                ErrorKind::EndOfFile(self.source.contents.len()),
-            ))
+            Err(self.end_of_file())
         } else {
             let (token, offset) = self.cursor.next();
             self.cursor.offset.set(offset);
@@ -480,13 +2551,30 @@ impl<'a> ParseBuffer<'a> {
         }
     }
 
-    fn current(&self) -> Result<&'a (usize, Entry)> {
+    /// Creates an error for reaching the end of the input, positioned just
+    /// after the last token that isn't whitespace.
+    fn end_of_file(&self) -> Error {
+        let end = self
+            .cursor
+            .stream
+            .iter()
+            .map(|&index| &self.cursor.arena[index as usize])
+            .rev()
+            .find(|entry| entry.kind != PackedKind::End && !entry.kind.is_whitespace())
+            .map_or(self.source.contents.len(), |entry| entry.end as usize);
+        Error::new(
+            self.source,
+            ErrorKind::EndOfFile {
+                end,
+                expected: Expected::None,
+            },
+        )
+    }
+
+    fn current(&self) -> Result<Entry> {
         self.report_error_tokens()?;
         if self.cursor.eof() {
-            Err(Error::new(
-                Rc::clone(&self.source),
-                ErrorKind::EndOfFile(self.source.contents.len()),
-            ))
+            Err(self.end_of_file())
         } else {
             Ok(self.cursor.current())
         }
@@ -494,30 +2582,26 @@ impl<'a> ParseBuffer<'a> {
 
     /// Gets the span of the current token, unless `self` is empty.
     pub fn current_span(&self) -> Result<Span> {
-        Ok(self.current()?.1.span().to_owned())
+        Ok(self.current()?.span().to_owned())
     }
 
-    fn get_relative(&self, offset: isize) -> Result<&'a (usize, Entry)> {
-        self.cursor.get_relative(offset).ok_or(Error::new(
-            Rc::clone(&self.source),
-            ErrorKind::EndOfFile(self.source.contents.len()),
-        ))
+    fn get_relative(&self, offset: isize) -> Result<Entry> {
+        self.cursor
+            .get_relative(offset)
+            .ok_or_else(|| self.end_of_file())
     }
 
-    fn get_absolute_range_original(&self, range: Range<usize>) -> Result<&'a [Entry]> {
+    fn get_absolute_range_original(&self, range: Range<usize>) -> Result<&'a [PackedEntry]> {
         self.cursor
             .get_absolute_range_original(range)
-            .ok_or(Error::new(
-                Rc::clone(&self.source),
-                ErrorKind::EndOfFile(self.source.contents.len()),
-            ))
+            .ok_or_else(|| self.end_of_file())
     }
 
     /// Creates a new `ParseBuffer` at the same position as `self`.
     ///
     /// Changes to `self` will not affect the fork, and vice versa.
     pub fn fork(&self) -> ParseBuffer<'a> {
-        ParseBuffer::new(self.cursor.clone(), Rc::clone(&self.source))
+        ParseBuffer::new(self.cursor.clone(), self.source, Rc::clone(&self.context))
     }
 
     /// Commits a forked buffer into `self`, updating `self` to reflect `fork`.
@@ -532,6 +2616,53 @@ impl<'a> ParseBuffer<'a> {
             panic!("cannot commit original ParseBuffer into fork");
         }
         self.cursor.offset.set(fork.cursor.offset.get());
+        self.warnings
+            .borrow_mut()
+            .add(fork.warnings.replace(Error::empty()));
+        self.events
+            .borrow_mut()
+            .append(&mut fork.events.borrow_mut());
+    }
+
+    /// Starts a node of kind `kind` in the concrete syntax tree built by
+    /// [`cst::parse`], beginning at the next token.
+    ///
+    /// Each call should be matched by a call to [`ParseBuffer::finish_node`].
+    /// Nodes started on a fork are only kept if the fork is committed. Outside
+    /// of [`cst::parse`], this has no effect.
+    pub fn start_node(&self, kind: &'static str) {
+        if !self.context.record_events.get() {
+            return;
+        }
+        let position = if self.cursor.eof() {
+            self.previous_end()
+        } else {
+            cst::Position::Before(self.cursor.stream[self.cursor.offset.get()] as usize)
+        };
+        self.events
+            .borrow_mut()
+            .push(cst::Event::Start(kind, position));
+    }
+
+    /// Finishes the node most recently started with
+    /// [`ParseBuffer::start_node`], ending at the last token parsed.
+    ///
+    /// Outside of [`cst::parse`], this has no effect.
+    pub fn finish_node(&self) {
+        if !self.context.record_events.get() {
+            return;
+        }
+        let position = self.previous_end();
+        self.events.borrow_mut().push(cst::Event::Finish(position));
+    }
+
+    /// Returns the end of the last token parsed, or the start of the stream if
+    /// none have been.
+    fn previous_end(&self) -> cst::Position {
+        match self.cursor.offset.get() {
+            0 => cst::Position::Origin,
+            offset => cst::Position::After(self.cursor.stream[offset - 1] as usize),
+        }
     }
 
     /// Creates an error with the message `Unexpected token` and the given
@@ -540,15 +2671,30 @@ impl<'a> ParseBuffer<'a> {
     /// Use of this function is generally discouraged in favour of
     /// [`Lookahead::error`].
     pub fn unexpected_token(&self, expected: HashSet<String>) -> Error {
+        self.unexpected(expected)
+    }
+
+    /// Creates an error for an unexpected token, built from a name fixed at
+    /// compile time.
+    ///
+    /// This is used by the [`keywords`] macros, and avoids allocating until
+    /// the error is reported.
+    #[doc(hidden)]
+    pub fn unexpected_token_named(&self, expected: &'static str) -> Error {
+        self.unexpected(expected)
+    }
+
+    fn unexpected<T: Into<Expected>>(&self, expected: T) -> Error {
+        let expected = expected.into();
         let current = match self.current() {
             Ok(current) => current,
-            Err(err) => return err,
+            Err(err) => return err.expected_at_end(expected),
         };
         Error::new(
-            Rc::clone(&self.source),
+            self.source,
             ErrorKind::UnexpectedToken {
                 expected,
-                span: current.1.span().clone(),
+                span: current.span().clone(),
             },
         )
     }
@@ -576,7 +2722,7 @@ impl<'a> ParseBuffer<'a> {
         Span {
             start: 0,
             end: 0,
-            source: Rc::clone(&self.source),
+            source: Arc::clone(self.source),
         }
     }
 }
@@ -627,8 +2773,14 @@ pub type ParseStream<'a> = &'a ParseBuffer<'a>;
 
 #[derive(Debug, Clone)]
 struct Cursor<'a> {
-    original_stream: &'a [Entry],
-    stream: &'a [(usize, Entry)],
+    /// The tokens of the stream, including any that have been removed.
+    arena: &'a [PackedEntry],
+    /// The source files of the tokens in `arena`.
+    sources: &'a [Arc<SourceFile>],
+    /// The symbols of the identifiers in `arena`.
+    symbols: &'a Arc<Interner>,
+    /// The indices in `arena` of the tokens in the stream.
+    stream: &'a [u32],
     offset: Cell<usize>,
     last: usize,
 }
@@ -643,27 +2795,43 @@ impl<'a> Cursor<'a> {
         }
     }
 
-    fn current(&self) -> &'a (usize, Entry) {
-        &self.stream[self.offset.get()]
+    fn current(&self) -> Entry {
+        self.entry(self.offset.get())
+    }
+
+    fn entry(&self, offset: usize) -> Entry {
+        self.packed(offset).unpack(self.sources, self.symbols)
+    }
+
+    /// Returns the packed token at `offset`, which can be inspected without
+    /// cloning its source file.
+    fn packed(&self, offset: usize) -> &'a PackedEntry {
+        &self.arena[self.stream[offset] as usize]
     }
 
     pub fn eof(&self) -> bool {
         self.offset.get() == self.last
     }
 
-    fn next(&self) -> (&'a Entry, usize) {
-        let (_, token_tree) = self.current();
+    fn next(&self) -> (Entry, usize) {
+        let token_tree = self.current();
         let offset = self.bump();
         (token_tree, offset)
     }
 
-    fn get_relative(&self, offset: isize) -> Option<&'a (usize, Entry)> {
+    fn get_relative(&self, offset: isize) -> Option<Entry> {
+        self.get_relative_packed(offset)
+            .map(|entry| entry.unpack(self.sources, self.symbols))
+    }
+
+    fn get_relative_packed(&self, offset: isize) -> Option<&'a PackedEntry> {
         self.stream
             .get((self.offset.get() as isize + offset) as usize)
+            .map(|&index| &self.arena[index as usize])
     }
 
-    fn get_absolute_range_original(&self, range: Range<usize>) -> Option<&'a [Entry]> {
-        self.original_stream.get(range)
+    fn get_absolute_range_original(&self, range: Range<usize>) -> Option<&'a [PackedEntry]> {
+        self.arena.get(range)
     }
 }
 
@@ -699,6 +2867,167 @@ impl Entry {
     }
 }
 
+/// Converts an index into a token stream's arena to the type stored in the
+/// stream, or returns `None` if a stream can't have that many tokens.
+fn index(index: usize) -> Option<u32> {
+    u32::try_from(index).ok().filter(|&index| index < u32::MAX)
+}
+
+/// A limit on the size of a [`TokenStream`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Limit {
+    SourceLength,
+    Sources,
+    Tokens,
+    Identifiers,
+}
+
+impl Limit {
+    fn error(self, span: Span) -> Error {
+        let message = match self {
+            Limit::SourceLength => "Source file is too large; the limit is 4 GiB",
+            Limit::Sources => "Too many source files in token stream; the limit is 65,536",
+            Limit::Tokens => "Too many tokens in token stream",
+            Limit::Identifiers => "Too many identifiers in token stream",
+        };
+        Error::new(
+            &Arc::clone(&span.source),
+            ErrorKind::LimitExceeded {
+                message: message.to_string(),
+                span,
+            },
+        )
+    }
+}
+
+/// The kind of a [`PackedEntry`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PackedKind {
+    Error,
+    Ident,
+    Punct(PunctKind, Spacing),
+    Space2,
+    Tab,
+    NewLine,
+    CarriageReturn,
+    End,
+}
+
+impl PackedKind {
+    fn is_whitespace(self) -> bool {
+        matches!(
+            self,
+            PackedKind::Space2 | PackedKind::Tab | PackedKind::NewLine | PackedKind::CarriageReturn
+        )
+    }
+}
+
+/// An [`Entry`] as stored in a token stream.
+///
+/// Source files are stored once per stream and referred to by index, and
+/// identifiers by the index of their symbol in the stream's symbols, so that
+/// each token takes 16 bytes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct PackedEntry {
+    start: u32,
+    end: u32,
+    /// The index of the symbol of an identifier.
+    symbol: u32,
+    /// The index of the token's source file in the stream's sources.
+    source: u16,
+    kind: PackedKind,
+}
+
+const _: () = assert!(mem::size_of::<PackedEntry>() == 16);
+
+impl PackedEntry {
+    const END: PackedEntry = PackedEntry {
+        start: 0,
+        end: 0,
+        symbol: 0,
+        source: 0,
+        kind: PackedKind::End,
+    };
+
+    /// Packs `entry`, adding its source file to `sources` and its symbol to
+    /// `symbols` if they aren't already present.
+    ///
+    /// Returns the limit that would be exceeded if `entry` can't be packed.
+    fn pack(
+        entry: &Entry,
+        sources: &mut Vec<Arc<SourceFile>>,
+        symbols: &mut Interner,
+    ) -> result::Result<PackedEntry, Limit> {
+        let (kind, symbol) = match entry {
+            Entry::Error(_) => (PackedKind::Error, 0),
+            Entry::Ident(ident) => {
+                let symbol = symbols.insert(ident.symbol()).ok_or(Limit::Identifiers)?;
+                (PackedKind::Ident, symbol)
+            }
+            Entry::Punct(punct) => (PackedKind::Punct(punct.kind, punct.spacing), 0),
+            Entry::WhiteSpace(WhiteSpace::Space2(_)) => (PackedKind::Space2, 0),
+            Entry::WhiteSpace(WhiteSpace::Tab(_)) => (PackedKind::Tab, 0),
+            Entry::WhiteSpace(WhiteSpace::NewLine(_)) => (PackedKind::NewLine, 0),
+            Entry::WhiteSpace(WhiteSpace::CarriageReturn(_)) => (PackedKind::CarriageReturn, 0),
+            Entry::End => return Ok(PackedEntry::END),
+        };
+        let span = entry.span();
+        let start = u32::try_from(span.start).map_err(|_| Limit::SourceLength)?;
+        let end = u32::try_from(span.end).map_err(|_| Limit::SourceLength)?;
+        let source = match sources
+            .iter()
+            .position(|source| Arc::ptr_eq(source, &span.source))
+        {
+            Some(source) => source,
+            None if sources.len() <= usize::from(u16::MAX) => {
+                sources.push(Arc::clone(&span.source));
+                sources.len() - 1
+            }
+            None => return Err(Limit::Sources),
+        };
+        Ok(PackedEntry {
+            start,
+            end,
+            symbol,
+            source: source as u16,
+            kind,
+        })
+    }
+
+    fn span(&self, sources: &[Arc<SourceFile>]) -> Span {
+        Span::new(
+            self.start as usize,
+            self.end as usize,
+            Arc::clone(&sources[usize::from(self.source)]),
+        )
+    }
+
+    fn unpack(&self, sources: &[Arc<SourceFile>], symbols: &Interner) -> Entry {
+        if self.kind == PackedKind::End {
+            return Entry::End;
+        }
+        let span = self.span(sources);
+        match self.kind {
+            PackedKind::Error => Entry::Error(span),
+            PackedKind::Ident => {
+                Entry::Ident(Ident::from_symbol(symbols.get(self.symbol).clone(), span))
+            }
+            PackedKind::Punct(kind, spacing) => Entry::Punct(SingleCharPunct {
+                kind,
+                spacing,
+                span,
+            }),
+            PackedKind::Space2 => Entry::WhiteSpace(WhiteSpace::Space2(Space2 { span })),
+            PackedKind::Tab => Entry::WhiteSpace(WhiteSpace::Tab(Tab { span })),
+            PackedKind::NewLine => Entry::WhiteSpace(WhiteSpace::NewLine(NewLine { span })),
+            PackedKind::CarriageReturn => {
+                Entry::WhiteSpace(WhiteSpace::CarriageReturn(CarriageReturn { span }))
+            }
+            PackedKind::End => unreachable!(),
+        }
+    }
+}
+
 impl From<Ident> for Entry {
     fn from(value: Ident) -> Self {
         Self::Ident(value)
@@ -809,5 +3138,8 @@ pub mod private {
 }
 use private::Sealed;
 
+#[cfg(all(test, feature = "derive"))]
+extern crate self as flexi_parse;
+
 #[cfg(test)]
 mod tests;
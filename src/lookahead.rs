@@ -3,6 +3,7 @@
 use crate::error::Error;
 use crate::token::Token;
 use crate::ParseBuffer;
+use crate::ParseStream;
 use crate::Peek;
 
 use std::cell::RefCell;
@@ -33,6 +34,20 @@ impl<'a> Lookahead<'a> {
         }
     }
 
+    /// Returns true if `function` returns true for the input at the next
+    /// token.
+    ///
+    /// If it doesn't, `name` is listed in the error generated by
+    /// [`Lookahead::error`].
+    pub fn peek_func<F: FnOnce(ParseStream<'_>) -> bool>(&self, function: F, name: &str) -> bool {
+        if function(&self.stream) {
+            true
+        } else {
+            self.comparisons.borrow_mut().insert(name.to_string());
+            false
+        }
+    }
+
     /// Generates an error based on the peek attempts.
     pub fn error(self) -> Error {
         self.stream.unexpected_token(self.comparisons.into_inner())
@@ -1,13 +1,18 @@
+use crate::error::Error;
+use crate::error::ErrorKind;
 use crate::scanner;
 use crate::token::Ident;
 use crate::token::SingleCharPunct;
 use crate::token::Spacing;
 use crate::Entry;
+use crate::Result;
+use crate::ScanOptions;
 use crate::SourceFile;
 use crate::Span;
 use crate::TokenStream;
 
-use std::rc::Rc;
+use std::fmt::Write;
+use std::sync::Arc;
 
 use proc_macro2::Delimiter;
 use proc_macro2::Spacing as Spacing2;
@@ -26,18 +31,37 @@ impl From<Spacing2> for Spacing {
     }
 }
 
-fn tree_to_trees(token: TokenTree2) -> Vec<Entry> {
-    let mut tokens = vec![];
-    let span = Span {
-        start: 0,
-        end: 0,
-        source: Rc::new(SourceFile {
-            name: String::new(),
-            path: None,
-            contents: String::new(),
-        }),
-    };
-    match token {
+/// Appends a punctuation token to `tokens`, and its text to `contents`.
+fn push_punct(
+    c: char,
+    spacing: Spacing,
+    contents: &mut String,
+    tokens: &mut Vec<Entry>,
+    source: &Arc<SourceFile>,
+) {
+    let start = contents.len();
+    contents.push(c);
+    tokens.push(Entry::Punct(SingleCharPunct {
+        kind: c.try_into().unwrap(),
+        spacing,
+        span: Span::new(start, contents.len(), Arc::clone(source)),
+    }));
+    if spacing == Spacing::Alone {
+        contents.push(' ');
+    }
+}
+
+/// Appends the tokens of `tree` to `tokens`, and their text to `contents`.
+///
+/// Each token's span covers its text in `contents`, but refers to `source`
+/// until the source file is complete.
+fn tree_to_entries(
+    tree: TokenTree2,
+    contents: &mut String,
+    tokens: &mut Vec<Entry>,
+    source: &Arc<SourceFile>,
+) {
+    match tree {
         TokenTree2::Group(group) => {
             let delimiters = match group.delimiter() {
                 Delimiter::Parenthesis => Some(('(', ')')),
@@ -45,76 +69,121 @@ fn tree_to_trees(token: TokenTree2) -> Vec<Entry> {
                 Delimiter::Brace => Some(('{', '}')),
                 Delimiter::None => None,
             };
-            if let Some((start, _)) = delimiters {
-                tokens.push(Entry::Punct(SingleCharPunct {
-                    kind: start.try_into().unwrap(),
-                    spacing: Spacing::Alone,
-                    span: span.clone(),
-                }));
+            if let Some((open, _)) = delimiters {
+                push_punct(open, Spacing::Alone, contents, tokens, source);
             }
-            for token in group.stream() {
-                tokens.append(&mut tree_to_trees(token));
+            for tree in group.stream() {
+                tree_to_entries(tree, contents, tokens, source);
             }
-            if let Some((_, end)) = delimiters {
-                tokens.push(Entry::Punct(SingleCharPunct {
-                    kind: end.try_into().unwrap(),
-                    spacing: Spacing::Alone,
-                    span: span.clone(),
-                }));
+            if let Some((_, close)) = delimiters {
+                push_punct(close, Spacing::Alone, contents, tokens, source);
             }
         }
         TokenTree2::Ident(ident) => {
+            let start = contents.len();
             let string = ident.to_string();
-            tokens.push(Entry::Ident(Ident {
-                string,
-                span: span.clone(),
-            }));
+            contents.push_str(&string);
+            let span = Span::new(start, contents.len(), Arc::clone(source));
+            tokens.push(Entry::Ident(Ident::new(&string, span)));
+            contents.push(' ');
         }
         TokenTree2::Literal(literal) => {
-            tokens.extend(
-                scanner::scan(Rc::new(SourceFile {
-                    name: String::new(),
-                    path: None,
-                    contents: literal.to_string(),
-                }))
-                .0
-                .tokens
-                .into_iter()
-                .map(|(_, mut token)| {
-                    token.set_span(span.clone());
-                    token
-                }),
+            let start = contents.len();
+            let text = literal.to_string();
+            let (literal_tokens, _) = scanner::scan(
+                Arc::new(SourceFile::new(String::new(), text.clone())),
+                &ScanOptions::default(),
             );
+            for mut token in literal_tokens.entries() {
+                if matches!(token, Entry::End) {
+                    break;
+                }
+                let span = token.span();
+                let span = Span::new(start + span.start, start + span.end, Arc::clone(source));
+                token.set_span(span);
+                tokens.push(token);
+            }
+            contents.push_str(&text);
+            contents.push(' ');
         }
         TokenTree2::Punct(punct) => {
-            let kind = punct.as_char().try_into().unwrap();
-            let spacing = punct.spacing().into();
-            tokens.push(Entry::Punct(SingleCharPunct {
-                kind,
-                spacing,
-                span: span.clone(),
-            }));
+            push_punct(
+                punct.as_char(),
+                punct.spacing().into(),
+                contents,
+                tokens,
+                source,
+            );
         }
     }
-    tokens
 }
 
+/// Converts a `proc_macro2::TokenStream` to a [`TokenStream`].
+///
+/// The source file of the new stream contains the text of each token, and each
+/// token's span covers its own text.
 impl From<TokenStream2> for TokenStream {
     fn from(value: TokenStream2) -> Self {
+        let placeholder = Arc::new(SourceFile::new(String::new(), String::new()));
+        let mut contents = String::new();
         let mut tokens = vec![];
-        let contents = value.to_string();
-        let source = Rc::new(SourceFile::new("<TokenStream>".to_string(), contents));
-        for token in value {
-            tokens.append(&mut tree_to_trees(token));
+        for tree in value {
+            tree_to_entries(tree, &mut contents, &mut tokens, &placeholder);
         }
-        let span = Span {
-            start: 0,
-            end: source.contents.len(),
-            source: Rc::clone(&source),
-        };
+        let contents = contents.trim_end().to_string();
+        let source = Arc::new(SourceFile::new("<TokenStream>".to_string(), contents));
         for token in &mut tokens {
-            token.set_span(span.clone());
+            let span = token.span();
+            let span = Span::new(span.start, span.end, Arc::clone(&source));
+            token.set_span(span);
         }
+        tokens.push(Entry::End);
         TokenStream::new(tokens, source)
     }
 }
+
+/// Converts a [`TokenStream`] to a `proc_macro2::TokenStream`.
+///
+/// The tokens are re-lexed as Rust from the text of the stream, so this fails
+/// if that text is not valid Rust tokens, or if the stream contains an error
+/// token. Every resulting token has the span [`Span::call_site`].
+///
+/// [`Span::call_site`]: proc_macro2::Span::call_site
+impl TryFrom<TokenStream> for TokenStream2 {
+    type Error = Error;
+
+    fn try_from(value: TokenStream) -> Result<Self> {
+        let mut text = String::new();
+        let span = {
+            let mut entries = value
+                .entries()
+                .take_while(|entry| !matches!(entry, Entry::End));
+            let first = entries.next();
+            let last = entries.last().or_else(|| first.clone());
+            match (first, last) {
+                (Some(first), Some(last)) if first.span().source == last.span().source => {
+                    Span::across(first.span(), last.span())
+                }
+                (Some(first), _) => first.span().clone(),
+                _ => Span::new(0, 0, Arc::clone(&value.source)),
+            }
+        };
+        let message = if write!(text, "{value}").is_err() {
+            "token stream contains an error token".to_string()
+        } else {
+            match text.parse() {
+                Ok(tokens) => return Ok(tokens),
+                Err(err) => format!("token stream is not valid Rust: {err}"),
+            }
+        };
+        let source = Arc::clone(span.source());
+        Err(Error::new(
+            &source,
+            ErrorKind::Custom {
+                message,
+                span,
+                code: 0,
+            },
+        ))
+    }
+}
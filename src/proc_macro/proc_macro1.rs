@@ -1,3 +1,4 @@
+use crate::error::Error;
 use crate::TokenStream;
 
 extern crate proc_macro;
@@ -9,3 +10,11 @@ impl From<proc_macro::TokenStream> for TokenStream {
         TokenStream2::from(value).into()
     }
 }
+
+impl TryFrom<TokenStream> for proc_macro::TokenStream {
+    type Error = Error;
+
+    fn try_from(value: TokenStream) -> Result<Self, Self::Error> {
+        TokenStream2::try_from(value).map(Into::into)
+    }
+}
@@ -1,16 +1,38 @@
 //! Utilities for parsing types separated by punctuation.
 
+use crate::error::Error;
+use crate::error::ErrorKind;
+use crate::group::Braces;
+use crate::group::Brackets;
+use crate::group::Delimiters;
+use crate::group::Group;
+use crate::group::Parentheses;
+use crate::recover::Recover;
+use crate::token::Ident;
 use crate::token::Punct;
+use crate::token::Token;
+use crate::visit::Fold;
+use crate::visit::Folder;
+use crate::visit::Visit;
+use crate::visit::Visitor;
 use crate::Parse;
 use crate::ParseStream;
+use crate::Peek;
 use crate::Result;
+use crate::Span;
+use crate::Spanned;
+use crate::ToTokens;
+use crate::TokenStream;
 
+use std::collections::HashSet;
+use std::fmt;
 use std::slice;
 use std::vec;
 
 /// A series of pairs of `T` and `P`, optionally followed by another `T`.
 #[derive(Debug, Clone)]
 pub struct Punctuated<T, P: Punct> {
+    leading: Option<P>,
     pairs: Vec<(T, P)>,
     end: Option<T>,
 }
@@ -18,6 +40,7 @@ pub struct Punctuated<T, P: Punct> {
 impl<T, P: Punct> Punctuated<T, P> {
     fn new() -> Punctuated<T, P> {
         Punctuated {
+            leading: None,
             pairs: vec![],
             end: None,
         }
@@ -64,6 +87,169 @@ impl<T, P: Punct> Punctuated<T, P> {
         Ok(punctuated)
     }
 
+    /// Parses instances of `T` separated by instances of `P`, with optional
+    /// leading punctuation and no trailing punctuation.
+    ///
+    /// The leading punctuation, if any, is available from
+    /// [`Punctuated::leading_punct`].
+    ///
+    /// Note that this will stop as soon as it encounters a token that doesn't
+    /// fit this pattern.
+    pub fn parse_separated_allow_leading(input: ParseStream<'_>) -> Result<Self>
+    where
+        T: Parse,
+    {
+        Self::parse_separated_allow_leading_with(input, T::parse)
+    }
+
+    /// Parses instances of `T` using `f`, separated by instances of `P`, with
+    /// optional leading punctuation and no trailing punctuation.
+    ///
+    /// See [`Punctuated::parse_separated_allow_leading`].
+    pub fn parse_separated_allow_leading_with<F: FnMut(ParseStream<'_>) -> Result<T>>(
+        input: ParseStream<'_>,
+        f: F,
+    ) -> Result<Self> {
+        let leading = if P::peek(input) {
+            Some(input.parse()?)
+        } else {
+            None
+        };
+        let mut punctuated = Self::parse_separated_with(input, f)?;
+        punctuated.leading = leading;
+        Ok(punctuated)
+    }
+
+    /// Parses between `min` and `max` instances of `T` separated by instances
+    /// of `P`, with no trailing punctuation.
+    ///
+    /// Note that this will stop as soon as it encounters a token that doesn't
+    /// fit this pattern.
+    ///
+    /// ## Errors
+    /// Returns an error at the first value past `max`, or at the next token if
+    /// there are fewer than `min` values.
+    pub fn parse_separated_bounded(input: ParseStream<'_>, min: usize, max: usize) -> Result<Self>
+    where
+        T: Parse,
+    {
+        Self::parse_separated_bounded_with(input, min, max, T::parse)
+    }
+
+    /// Parses between `min` and `max` instances of `T` using `f`, separated by
+    /// instances of `P`, with no trailing punctuation.
+    ///
+    /// See [`Punctuated::parse_separated_bounded`].
+    pub fn parse_separated_bounded_with<F: FnMut(ParseStream<'_>) -> Result<T>>(
+        input: ParseStream<'_>,
+        min: usize,
+        max: usize,
+        mut f: F,
+    ) -> Result<Self> {
+        let mut punctuated = Punctuated::new();
+        let mut count = 0;
+
+        if min > 0 || !input.is_empty() {
+            loop {
+                let (value, span) = input.spanned(&mut f)?;
+                count += 1;
+                if count > max {
+                    return Err(Error::new(
+                        input.source,
+                        ErrorKind::TooManyElements { max, span },
+                    ));
+                }
+                punctuated.push_value(value);
+                if !P::peek(input) {
+                    break;
+                }
+                punctuated.push_punct(input.parse()?);
+            }
+        }
+
+        if count < min {
+            return Err(Error::new(
+                input.source,
+                ErrorKind::TooFewElements {
+                    min,
+                    span: input.next_span(),
+                },
+            ));
+        }
+
+        Ok(punctuated)
+    }
+
+    /// Parses instances of `T` separated by instances of `P`, with optional
+    /// trailing punctuation, until the next token is an instance of `end`.
+    ///
+    /// The terminating token is not consumed.
+    ///
+    /// ## Example
+    /// ```
+    /// # use flexi_parse::punctuated::Punctuated;
+    /// # use flexi_parse::token::Ident;
+    /// # use flexi_parse::parse_string;
+    /// # use flexi_parse::Parse;
+    /// # use flexi_parse::ParseStream;
+    /// # use flexi_parse::Punct;
+    /// # use flexi_parse::Result;
+    /// struct Signature {
+    ///     params: Punctuated<Ident, Punct![","]>,
+    ///     ret: Ident,
+    /// }
+    ///
+    /// impl Parse for Signature {
+    ///     fn parse(input: ParseStream<'_>) -> Result<Self> {
+    ///         let params = Punctuated::parse_separated_until(input, Punct!["->"])?;
+    ///         let _: Punct!["->"] = input.parse()?;
+    ///         Ok(Signature { params, ret: input.parse()? })
+    ///     }
+    /// }
+    ///
+    /// # fn main() {
+    /// let sig: Signature = parse_string("a, b, c -> d".to_string()).unwrap();
+    /// assert_eq!(sig.params.iter().len(), 3);
+    /// assert_eq!(sig.ret.string(), "d");
+    /// # }
+    /// ```
+    pub fn parse_separated_until<E: Peek>(input: ParseStream<'_>, end: E) -> Result<Self>
+    where
+        T: Parse,
+    {
+        Self::parse_separated_until_with(input, end, T::parse)
+    }
+
+    /// Parses instances of `T` using `f`, separated by instances of `P`, with
+    /// optional trailing punctuation, until the next token is an instance of
+    /// `end`.
+    ///
+    /// See [`Punctuated::parse_separated_until`].
+    pub fn parse_separated_until_with<E: Peek, F: FnMut(ParseStream<'_>) -> Result<T>>(
+        input: ParseStream<'_>,
+        end: E,
+        mut f: F,
+    ) -> Result<Self> {
+        let _ = end;
+        let at_end = || input.parse_undo::<E::Token>().is_ok();
+        let mut punctuated = Punctuated::new();
+
+        while !at_end() {
+            punctuated.push_value(f(input)?);
+            if at_end() {
+                break;
+            }
+            if !P::peek(input) {
+                return Err(
+                    input.unexpected_token(HashSet::from_iter([P::display(), E::Token::display()]))
+                );
+            }
+            punctuated.push_punct(input.parse()?);
+        }
+
+        Ok(punctuated)
+    }
+
     /// Parses instances of `T` separated by instances of `P`, with optional
     /// trailing punctuation.
     ///
@@ -112,7 +298,7 @@ impl<T, P: Punct> Punctuated<T, P> {
         Self::parse_terminated_with(input, T::parse)
     }
 
-    // Parses instances of `T` using `f`, separated by instances of `P`, with
+    /// Parses instances of `T` using `f`, separated by instances of `P`, with
     /// trailing punctuation.
     ///
     /// Note that this attempts to consume the entire stream.
@@ -130,8 +316,38 @@ impl<T, P: Punct> Punctuated<T, P> {
         Ok(punctuated)
     }
 
+    /// Returns the span from the start of the leading punctuation or first
+    /// value to the end of the last value or punctuation, or `None` if `self`
+    /// is empty.
+    pub fn span(&self) -> Option<Span>
+    where
+        T: Spanned,
+        P: Token,
+    {
+        let first = self
+            .pairs
+            .first()
+            .map(|(value, _)| value)
+            .or(self.end.as_ref())?;
+        let first = match &self.leading {
+            Some(punct) => Token::span(punct).clone(),
+            None => first.span(),
+        };
+        let last = match (&self.end, self.pairs.last()) {
+            (Some(value), _) => value.span(),
+            (None, Some((_, punct))) => Token::span(punct).clone(),
+            (None, None) => unreachable!(),
+        };
+        Some(Span::across(&first, &last))
+    }
+
+    /// Returns the leading punctuation, if there was any.
+    pub fn leading_punct(&self) -> Option<&P> {
+        self.leading.as_ref()
+    }
+
     /// Returns an iterator over the values in this struct.
-    pub fn iter(&self) -> Iter<T, P> {
+    pub fn iter(&self) -> Iter<'_, T, P> {
         Iter {
             main: self.pairs.iter(),
             end: self.end.as_ref(),
@@ -139,7 +355,7 @@ impl<T, P: Punct> Punctuated<T, P> {
     }
 
     /// Returns an iterator that allows modifying each value.
-    pub fn iter_mut(&mut self) -> IterMut<T, P> {
+    pub fn iter_mut(&mut self) -> IterMut<'_, T, P> {
         IterMut {
             main: self.pairs.iter_mut(),
             end: self.end.as_mut(),
@@ -148,7 +364,7 @@ impl<T, P: Punct> Punctuated<T, P> {
 
     /// Returns an iterator over the pairs of values and punctuation in this
     /// struct.
-    pub fn pairs(&self) -> Pairs<T, P> {
+    pub fn pairs(&self) -> Pairs<'_, T, P> {
         Pairs {
             main: self.pairs.iter(),
             end: self.end.as_ref(),
@@ -156,7 +372,7 @@ impl<T, P: Punct> Punctuated<T, P> {
     }
 
     /// Returns an iterator that allows modifying each pair.
-    pub fn pairs_mut(&mut self) -> PairsMut<T, P> {
+    pub fn pairs_mut(&mut self) -> PairsMut<'_, T, P> {
         PairsMut {
             main: self.pairs.iter_mut(),
             end: self.end.as_mut(),
@@ -172,6 +388,171 @@ impl<T, P: Punct> Punctuated<T, P> {
     }
 }
 
+impl<T, P: Punct> Punctuated<Recover<T>, P> {
+    /// Parses instances of `T` separated by instances of `P`, with no trailing
+    /// punctuation, recovering from values that fail to parse.
+    ///
+    /// When a value fails to parse, its error is recorded and tokens are
+    /// skipped up to the next instance of `P` outside of any group, after
+    /// which parsing continues. The value is replaced by a
+    /// [`Recover::Error`] containing the span of the skipped tokens. Returns
+    /// the values along with all of the recorded errors, if there were any.
+    ///
+    /// Note that this will stop as soon as it encounters a value that isn't
+    /// followed by an instance of `P`.
+    pub fn parse_separated_recovering(input: ParseStream<'_>) -> (Self, Option<Error>)
+    where
+        T: Parse,
+    {
+        Self::parse_separated_recovering_with(input, T::parse)
+    }
+
+    /// Parses instances of `T` using `f`, separated by instances of `P`, with
+    /// no trailing punctuation, recovering from values that fail to parse.
+    ///
+    /// See [`Punctuated::parse_separated_recovering`].
+    pub fn parse_separated_recovering_with<F: FnMut(ParseStream<'_>) -> Result<T>>(
+        input: ParseStream<'_>,
+        mut f: F,
+    ) -> (Self, Option<Error>) {
+        let mut punctuated = Punctuated::new();
+        let mut errors = Error::empty();
+
+        loop {
+            let offset = input.cursor.offset.get();
+            let (value, span) = input
+                .spanned(|input| {
+                    Ok(f(input).map_err(|err| {
+                        errors.add(err);
+                        input.cursor.offset.set(offset);
+                        while !input.is_empty() && !P::peek(input) {
+                            skip_token_or_group(input);
+                        }
+                    }))
+                })
+                .unwrap();
+            punctuated.push_value(match value {
+                Ok(value) => Recover::Ok(value),
+                Err(()) => Recover::Error(span),
+            });
+            if !P::peek(input) {
+                break;
+            }
+            match input.parse() {
+                Ok(punct) => punctuated.push_punct(punct),
+                Err(err) => {
+                    errors.add(err);
+                    break;
+                }
+            }
+        }
+
+        let errors = if errors.is_empty() {
+            None
+        } else {
+            Some(errors)
+        };
+        (punctuated, errors)
+    }
+}
+
+/// Skips the next token, or the whole group if it starts one.
+fn skip_token_or_group(input: ParseStream<'_>) {
+    fn skip_group<D: Delimiters>(input: ParseStream<'_>) -> bool {
+        let fork = input.fork();
+        if fork.parse::<Group<D>>().is_ok() {
+            input.commit(&fork);
+            true
+        } else {
+            false
+        }
+    }
+
+    if !(skip_group::<Parentheses>(input)
+        || skip_group::<Brackets>(input)
+        || skip_group::<Braces>(input))
+    {
+        let _ = input.next();
+    }
+}
+
+impl<T: fmt::Display, P: Punct + fmt::Display> fmt::Display for Punctuated<T, P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(leading) = &self.leading {
+            write!(f, "{leading} ")?;
+        }
+        for (i, (value, punct)) in self.pairs.iter().enumerate() {
+            if i + 1 < self.pairs.len() || self.end.is_some() {
+                write!(f, "{value}{punct} ")?;
+            } else {
+                write!(f, "{value}{punct}")?;
+            }
+        }
+        if let Some(end) = &self.end {
+            write!(f, "{end}")?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: Spanned, P: Punct> Spanned for Punctuated<T, P> {
+    fn span(&self) -> Span {
+        Punctuated::span(self).expect("an empty `Punctuated` has no span")
+    }
+
+    fn try_span(&self) -> Option<Span> {
+        Punctuated::span(self)
+    }
+}
+
+impl<T: ToTokens, P: Punct> ToTokens for Punctuated<T, P> {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        if let Some(leading) = &self.leading {
+            leading.to_tokens(tokens);
+        }
+        for (value, punct) in &self.pairs {
+            value.to_tokens(tokens);
+            punct.to_tokens(tokens);
+        }
+        if let Some(end) = &self.end {
+            end.to_tokens(tokens);
+        }
+    }
+}
+
+impl<T: Visit, P: Punct + Visit> Visit for Punctuated<T, P> {
+    fn visit_children<V: Visitor>(&self, visitor: &mut V) {
+        self.visit_with(visitor);
+    }
+
+    fn visit_with<V: Visitor>(&self, visitor: &mut V) {
+        self.leading.visit_with(visitor);
+        for (value, punct) in &self.pairs {
+            value.visit_with(visitor);
+            punct.visit_with(visitor);
+        }
+        self.end.visit_with(visitor);
+    }
+}
+
+impl<T: Fold, P: Punct + Fold> Fold for Punctuated<T, P> {
+    fn fold_children<F: Folder>(self, folder: &mut F) -> Self {
+        self.fold_with(folder)
+    }
+
+    fn fold_with<F: Folder>(self, folder: &mut F) -> Self {
+        Punctuated {
+            leading: self.leading.fold_with(folder),
+            pairs: self
+                .pairs
+                .into_iter()
+                .map(|(value, punct)| (value.fold_with(folder), punct.fold_with(folder)))
+                .collect(),
+            end: self.end.fold_with(folder),
+        }
+    }
+}
+
 impl<T, P: Punct> IntoIterator for Punctuated<T, P> {
     type Item = T;
     type IntoIter = IntoIter<T, P>;
@@ -184,6 +565,172 @@ impl<T, P: Punct> IntoIterator for Punctuated<T, P> {
     }
 }
 
+/// A path made up of identifiers separated by `Sep`, such as `foo.bar.baz`
+/// or `a::b::c`.
+///
+/// A path always contains at least one segment, and has no leading or
+/// trailing separator.
+///
+/// ## Example
+/// ```
+/// # use flexi_parse::punctuated::Path;
+/// # use flexi_parse::parse_string;
+/// # use flexi_parse::Punct;
+/// let path: Path<Punct!["::"]> = parse_string("std::rc::Rc".to_string()).unwrap();
+/// assert_eq!(path.segments().len(), 3);
+/// assert_eq!(path.to_string(), "std::rc::Rc");
+/// ```
+#[derive(Debug, Clone)]
+pub struct Path<Sep: Punct = crate::Punct!["."]> {
+    segments: Punctuated<Ident, Sep>,
+}
+
+impl<Sep: Punct> Path<Sep> {
+    /// Returns an iterator over the segments of this path.
+    pub fn segments(&self) -> Iter<'_, Ident, Sep> {
+        self.segments.iter()
+    }
+
+    /// Returns the span from the start of the first segment to the end of the
+    /// last segment.
+    pub fn span(&self) -> Span {
+        self.segments.span().unwrap()
+    }
+
+    /// Converts `self` into the segments and separators of this path.
+    pub fn into_punctuated(self) -> Punctuated<Ident, Sep> {
+        self.segments
+    }
+}
+
+impl<Sep: Punct> Parse for Path<Sep> {
+    fn parse(input: ParseStream<'_>) -> Result<Self> {
+        Ok(Path {
+            segments: Punctuated::parse_separated(input)?,
+        })
+    }
+}
+
+impl<Sep: Punct> Spanned for Path<Sep> {
+    fn span(&self) -> Span {
+        Path::span(self)
+    }
+}
+
+impl<Sep: Punct> ToTokens for Path<Sep> {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        self.segments.to_tokens(tokens);
+    }
+}
+
+impl<Sep: Punct + Visit + 'static> Visit for Path<Sep> {
+    fn visit_children<V: Visitor>(&self, visitor: &mut V) {
+        self.segments.visit_with(visitor);
+    }
+
+    crate::__visit_any!();
+}
+
+impl<Sep: Punct + Fold + 'static> Fold for Path<Sep> {
+    fn fold_children<F: Folder>(self, folder: &mut F) -> Self {
+        Path {
+            segments: self.segments.fold_with(folder),
+        }
+    }
+
+    crate::__fold_any!();
+}
+
+impl<Sep: Punct + fmt::Display> fmt::Display for Path<Sep> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for pair in self.segments.pairs() {
+            write!(f, "{}", pair.value())?;
+            if let Some(sep) = pair.punct() {
+                write!(f, "{sep}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A `T` followed by a terminating token `P`, such as a statement followed by
+/// `;`.
+///
+/// ## Example
+/// ```
+/// # use flexi_parse::punctuated::Terminated;
+/// # use flexi_parse::token::Ident;
+/// # use flexi_parse::parse_string;
+/// # use flexi_parse::Punct;
+/// let stmt: Terminated<Ident, Punct![";"]> = parse_string("x;".to_string()).unwrap();
+/// assert_eq!(stmt.value().string(), "x");
+/// ```
+#[derive(Debug, Clone)]
+pub struct Terminated<T, P: Token> {
+    value: T,
+    terminator: P,
+}
+
+impl<T, P: Token> Terminated<T, P> {
+    /// Returns a reference to the value.
+    pub fn value(&self) -> &T {
+        &self.value
+    }
+
+    /// Returns a reference to the terminating token.
+    pub fn terminator(&self) -> &P {
+        &self.terminator
+    }
+
+    /// Converts `self` into the value, discarding the terminating token.
+    pub fn into_value(self) -> T {
+        self.value
+    }
+
+    /// Converts `self` into the value and the terminating token.
+    pub fn into_parts(self) -> (T, P) {
+        (self.value, self.terminator)
+    }
+}
+
+impl<T: Parse, P: Token> Parse for Terminated<T, P> {
+    fn parse(input: ParseStream<'_>) -> Result<Self> {
+        Ok(Terminated {
+            value: input.parse()?,
+            terminator: input.parse()?,
+        })
+    }
+}
+
+impl<T: Spanned, P: Token> Spanned for Terminated<T, P> {
+    fn span(&self) -> Span {
+        Span::across(&self.value.span(), Token::span(&self.terminator))
+    }
+}
+
+impl<T: ToTokens, P: Token> ToTokens for Terminated<T, P> {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        self.value.to_tokens(tokens);
+        self.terminator.to_tokens(tokens);
+    }
+}
+
+impl<T: Visit, P: Token + Visit> Visit for Terminated<T, P> {
+    fn visit_children<V: Visitor>(&self, visitor: &mut V) {
+        self.value.visit_with(visitor);
+        self.terminator.visit_with(visitor);
+    }
+}
+
+impl<T: Fold, P: Token + Fold> Fold for Terminated<T, P> {
+    fn fold_children<F: Folder>(self, folder: &mut F) -> Self {
+        Terminated {
+            value: self.value.fold_with(folder),
+            terminator: self.terminator.fold_with(folder),
+        }
+    }
+}
+
 /// An iterator over `&T`.
 pub struct Iter<'a, T, P> {
     main: slice::Iter<'a, (T, P)>,
@@ -302,6 +849,39 @@ pub enum Pair<T, P> {
 }
 
 impl<T, P> Pair<T, P> {
+    /// Returns a reference to the value in this pair.
+    pub fn value(&self) -> &T {
+        match self {
+            Pair::Punctuated(value, _) => value,
+            Pair::End(value) => value,
+        }
+    }
+
+    /// Returns a mutable reference to the value in this pair.
+    pub fn value_mut(&mut self) -> &mut T {
+        match self {
+            Pair::Punctuated(value, _) => value,
+            Pair::End(value) => value,
+        }
+    }
+
+    /// Returns a reference to the punctuation in this pair, if there is any.
+    pub fn punct(&self) -> Option<&P> {
+        match self {
+            Pair::Punctuated(_, punct) => Some(punct),
+            Pair::End(_) => None,
+        }
+    }
+
+    /// Returns a mutable reference to the punctuation in this pair, if there
+    /// is any.
+    pub fn punct_mut(&mut self) -> Option<&mut P> {
+        match self {
+            Pair::Punctuated(_, punct) => Some(punct),
+            Pair::End(_) => None,
+        }
+    }
+
     /// Converts the pair into the inner value.
     pub fn into_value(self) -> T {
         match self {
@@ -0,0 +1,113 @@
+//! Error recovery, allowing a whole syntax tree to be produced for erroneous
+//! input.
+//!
+//! The [`Recover`] type parses a syntax tree node, or, if that fails, records
+//! the error with [`ParseBuffer::add_error`], skips ahead to a recovery
+//! boundary, and stands in for the node in the tree. The errors can be
+//! retrieved afterwards with [`ParseBuffer::get_error`].
+//!
+//! [`ParseBuffer::add_error`]: crate::ParseBuffer::add_error
+//! [`ParseBuffer::get_error`]: crate::ParseBuffer::get_error
+
+use crate::Parse;
+use crate::ParseStream;
+use crate::Result;
+use crate::Span;
+use crate::Spanned;
+
+/// A syntax tree node `T`, or the span of the tokens skipped when it failed
+/// to parse.
+///
+/// When parsed with [`Parse`], this skips to the boundary set with
+/// [`ParseBuffer::set_recovery`], or past a single token if none has been
+/// set. [`ParseBuffer::recover`] can be used to give the boundary directly.
+///
+/// # Example
+/// ```
+/// # use flexi_parse::parse_repeated;
+/// # use flexi_parse::parse_string;
+/// # use flexi_parse::recover::Recover;
+/// # use flexi_parse::token::LitInt;
+/// # use flexi_parse::Parse;
+/// # use flexi_parse::ParseStream;
+/// # use flexi_parse::Punct;
+/// # use flexi_parse::Result;
+/// # #[derive(Debug)]
+/// struct Stmt(LitInt);
+///
+/// impl Parse for Stmt {
+///     fn parse(input: ParseStream) -> Result<Self> {
+///         let value = input.parse()?;
+///         let _: Punct![";"] = input.parse()?;
+///         Ok(Stmt(value))
+///     }
+/// }
+///
+/// # #[derive(Debug)]
+/// struct Program(Vec<Recover<Stmt>>);
+///
+/// impl Parse for Program {
+///     fn parse(input: ParseStream) -> Result<Self> {
+///         input.set_recovery(|input| input.peek(Punct![";"]));
+///         let stmts = parse_repeated(input)?;
+///         match input.get_error() {
+///             Some(err) => Err(err),
+///             None => Ok(Program(stmts)),
+///         }
+///     }
+/// }
+///
+/// let err = parse_string::<Program>("1; x; 2; y;".to_string()).unwrap_err();
+/// assert_eq!(err.diagnostics().count(), 2);
+/// ```
+///
+/// [`ParseBuffer::set_recovery`]: crate::ParseBuffer::set_recovery
+/// [`ParseBuffer::recover`]: crate::ParseBuffer::recover
+#[derive(Debug, Clone)]
+pub enum Recover<T> {
+    /// The node was parsed successfully.
+    Ok(T),
+    /// The node failed to parse, and the tokens in the span were skipped.
+    Error(Span),
+}
+
+impl<T> Recover<T> {
+    /// Returns true if the node failed to parse.
+    pub fn is_error(&self) -> bool {
+        matches!(self, Recover::Error(_))
+    }
+
+    /// Returns the parsed node, if there is one.
+    pub fn ok(self) -> Option<T> {
+        match self {
+            Recover::Ok(value) => Some(value),
+            Recover::Error(_) => None,
+        }
+    }
+}
+
+impl<T: Parse> Parse for Recover<T> {
+    fn parse(input: ParseStream<'_>) -> Result<Self> {
+        let boundary = input.recovery();
+        Ok(input.recover(|input| match &boundary {
+            Some(boundary) => boundary(input),
+            None => true,
+        }))
+    }
+}
+
+impl<T: Spanned> Spanned for Recover<T> {
+    fn span(&self) -> Span {
+        match self {
+            Recover::Ok(value) => value.span(),
+            Recover::Error(span) => span.clone(),
+        }
+    }
+
+    fn try_span(&self) -> Option<Span> {
+        match self {
+            Recover::Ok(value) => value.try_span(),
+            Recover::Error(span) => Some(span.clone()),
+        }
+    }
+}
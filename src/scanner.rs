@@ -2,6 +2,7 @@ use crate::error::Error;
 use crate::error::ErrorKind;
 use crate::token::CarriageReturn;
 use crate::token::Ident;
+use crate::token::Interner;
 use crate::token::NewLine;
 use crate::token::PunctKind;
 use crate::token::SingleCharPunct;
@@ -10,152 +11,231 @@ use crate::token::Spacing;
 use crate::token::Tab;
 use crate::token::WhiteSpace;
 use crate::Entry;
+use crate::Limit;
+use crate::ParseOptions;
 use crate::Result;
+use crate::ScanOptions;
 use crate::SourceFile;
 use crate::Span;
 use crate::TokenStream;
 
-use std::rc::Rc;
+use std::borrow::Cow;
+use std::sync::Arc;
+use std::sync::OnceLock;
 
-fn valid_ident_char(c: Option<char>) -> bool {
-    if let Some(c) = c {
-        c.is_alphanumeric() || c == '_'
-    } else {
-        false
+/// The kind of token a character starts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Class {
+    Punct(PunctKind),
+    Ident,
+    Space,
+    Tab,
+    NewLine,
+    CarriageReturn,
+    Other,
+}
+
+impl Class {
+    fn of(c: char) -> Class {
+        match c {
+            ' ' => Class::Space,
+            '\t' => Class::Tab,
+            '\n' => Class::NewLine,
+            '\u{000D}' => Class::CarriageReturn,
+            c if c.is_alphanumeric() || c == '_' => Class::Ident,
+            c => PunctKind::try_from(c).map_or(Class::Other, Class::Punct),
+        }
+    }
+}
+
+/// Returns the class of every ASCII character, indexed by byte.
+fn ascii_classes() -> &'static [Class; 128] {
+    static CLASSES: OnceLock<[Class; 128]> = OnceLock::new();
+    CLASSES.get_or_init(|| {
+        let mut classes = [Class::Other; 128];
+        for (byte, class) in (0..).zip(&mut classes) {
+            *class = Class::of(char::from(byte));
+        }
+        classes
+    })
+}
+
+/// Returns the class of every ASCII character, indexed by byte, with the
+/// identifier characters given in `options`.
+fn classes(options: &ScanOptions) -> Cow<'static, [Class; 128]> {
+    if options.ident_chars.is_empty() {
+        return Cow::Borrowed(ascii_classes());
+    }
+    let mut classes = *ascii_classes();
+    for &c in &options.ident_chars {
+        if c.is_ascii() {
+            classes[c as usize] = Class::Ident;
+        }
     }
+    Cow::Owned(classes)
 }
 
 #[derive(Debug)]
-struct Scanner {
+struct Scanner<'a> {
     current: usize,
     end: usize,
     errors: Error,
-    source: Rc<SourceFile>,
+    source: Arc<SourceFile>,
+    classes: Cow<'static, [Class; 128]>,
+    /// Extra characters allowed in identifiers.
+    ident_chars: &'a [char],
+    symbols: Interner,
 }
 
-impl Scanner {
-    fn scan(mut self) -> (TokenStream, Option<Error>) {
+impl Scanner<'_> {
+    fn scan(mut self) -> (Vec<Entry>, Interner, Option<Error>) {
         let mut tokens = vec![];
 
-        while !self.is_at_end() {
-            match self.scan_token() {
-                Ok(token) => tokens.push(token),
-                Err(err) => {
-                    self.errors.add(err);
-                    tokens.push(Entry::Error(Span::new(0, 0, Rc::clone(&self.source))));
+        while self.current < self.end {
+            let start = self.current;
+            let (class, len) = self.class_at(start);
+            self.current += len;
+            let span = || Span::new(start, start + len, Arc::clone(&self.source));
+            let token = match class {
+                Class::Punct(kind) => {
+                    // A punct can't be joined to anything after the scanned
+                    // text.
+                    let spacing = if self.current < self.end
+                        && matches!(self.class_at(self.current).0, Class::Punct(_))
+                    {
+                        Spacing::Joint
+                    } else {
+                        Spacing::Alone
+                    };
+                    Entry::Punct(SingleCharPunct {
+                        kind,
+                        spacing,
+                        span: span(),
+                    })
+                }
+                Class::Ident => {
+                    while self.current < self.end {
+                        let (class, len) = self.class_at(self.current);
+                        if class != Class::Ident {
+                            break;
+                        }
+                        self.current += len;
+                    }
+                    let span = Span::new(start, self.current, Arc::clone(&self.source));
+                    let symbol = self
+                        .symbols
+                        .intern(&self.source.contents[start..self.current]);
+                    let Some(symbol) = symbol else {
+                        self.errors.add(Limit::Identifiers.error(span));
+                        tokens.push(Entry::Error(Span::new(0, 0, Arc::clone(&self.source))));
+                        break;
+                    };
+                    Entry::Ident(Ident::from_symbol(self.symbols.get(symbol).clone(), span))
+                }
+                Class::Space if self.class_at(self.current).0 == Class::Space => {
+                    self.current += 1;
+                    Entry::WhiteSpace(WhiteSpace::Space2(Space2 {
+                        span: Span::new(start, self.current, Arc::clone(&self.source)),
+                    }))
+                }
+                Class::Space => continue,
+                Class::Tab => Entry::WhiteSpace(WhiteSpace::Tab(Tab { span: span() })),
+                Class::NewLine => Entry::WhiteSpace(WhiteSpace::NewLine(NewLine { span: span() })),
+                Class::CarriageReturn => {
+                    Entry::WhiteSpace(WhiteSpace::CarriageReturn(CarriageReturn { span: span() }))
+                }
+                Class::Other => {
+                    self.errors.add(Error::new(
+                        &self.source,
+                        ErrorKind::UnknownCharacter(span()),
+                    ));
+                    tokens.push(Entry::Error(Span::new(0, 0, Arc::clone(&self.source))));
                     break;
                 }
-            }
+            };
+            tokens.push(token);
         }
 
-        tokens.push(Entry::End);
-
         let errors = if self.errors.is_empty() {
             None
         } else {
             Some(self.errors)
         };
 
-        (TokenStream::new(tokens, self.source), errors)
+        (tokens, self.symbols, errors)
     }
 
-    fn scan_token(&mut self) -> Result<Entry> {
-        let token = match self.peek(0)? {
-            c if PunctKind::try_from(c).is_ok() => {
-                let kind = c.try_into().unwrap();
-                let span = Span::new(self.current, self.current + 1, Rc::clone(&self.source));
-                self.current += 1;
-                let spacing = if self.peek(0).is_ok_and(|c| PunctKind::try_from(c).is_ok()) {
-                    Spacing::Joint
+    /// Returns the class and length in bytes of the character at `index`.
+    ///
+    /// ASCII characters are classified with a lookup table, and the end of
+    /// the source is classified as [`Class::Other`].
+    fn class_at(&self, index: usize) -> (Class, usize) {
+        match self.source.contents.as_bytes().get(index) {
+            Some(&byte) if byte.is_ascii() => (self.classes[usize::from(byte)], 1),
+            Some(_) => {
+                let c = self.source.contents[index..].chars().next().unwrap();
+                let class = if self.ident_chars.contains(&c) {
+                    Class::Ident
                 } else {
-                    Spacing::Alone
+                    Class::of(c)
                 };
-
-                Entry::Punct(SingleCharPunct {
-                    kind,
-                    spacing,
-                    span,
-                })
-            }
-            c if c.is_alphanumeric() || c == '_' => {
-                let start = self.current;
-                while valid_ident_char(self.peek(0).ok()) {
-                    self.current += 1;
-                }
-                let string = self.source.contents[start..self.current].to_string();
-                let span = Span::new(start, self.current, Rc::clone(&self.source));
-
-                Entry::Ident(Ident { string, span })
-            }
-            ' ' if self.peek(1).is_ok_and(|c| c == ' ') => {
-                self.current += 2;
-                Entry::WhiteSpace(WhiteSpace::Space2(Space2 {
-                    span: Span::new(self.current - 2, self.current, Rc::clone(&self.source)),
-                }))
-            }
-            ' ' => {
-                self.current += 1;
-                self.scan_token()?
-            }
-            '\t' => {
-                let span = Span::new(self.current, self.current + 1, Rc::clone(&self.source));
-                self.current += 1;
-                Entry::WhiteSpace(WhiteSpace::Tab(Tab { span }))
-            }
-            '\n' => {
-                let span = Span::new(self.current, self.current + 1, Rc::clone(&self.source));
-                self.current += 1;
-                Entry::WhiteSpace(WhiteSpace::NewLine(NewLine { span }))
+                (class, c.len_utf8())
             }
-            '\u{000D}' => {
-                let span = Span::new(self.current, self.current + 1, Rc::clone(&self.source));
-                self.current += 1;
-                Entry::WhiteSpace(WhiteSpace::CarriageReturn(CarriageReturn { span }))
-            }
-            _ => {
-                self.current += 1;
-                return Err(Error::new(
-                    Rc::clone(&self.source),
-                    ErrorKind::UnknownCharacter(Span::new(
-                        self.current,
-                        self.current + 1,
-                        Rc::clone(&self.source),
-                    )),
-                ));
-            }
-        };
-
-        Ok(token)
-    }
-
-    fn peek(&mut self, offset: usize) -> Result<char> {
-        if self.current + offset >= self.source.contents.len() {
-            Err(Error::new(
-                Rc::clone(&self.source),
-                ErrorKind::EndOfFile(self.source.contents.len()),
-            ))
-        } else {
-            Ok(
-                self.source.contents[self.current + offset..self.current + offset + 1]
-                    .chars()
-                    .next()
-                    .unwrap(),
-            )
+            None => (Class::Other, 0),
         }
     }
-
-    fn is_at_end(&mut self) -> bool {
-        self.current >= self.end
-    }
 }
 
-pub(crate) fn scan(source: Rc<SourceFile>) -> (TokenStream, Option<Error>) {
-    let (tokens, errors) = Scanner {
+pub(crate) fn scan(source: Arc<SourceFile>, options: &ScanOptions) -> (TokenStream, Option<Error>) {
+    if u32::try_from(source.contents.len()).is_err() {
+        let error = Limit::SourceLength.error(Span::new(0, 0, Arc::clone(&source)));
+        return (TokenStream::empty(source), Some(error));
+    }
+    let (mut tokens, symbols, errors) = Scanner {
         current: 0,
         end: source.contents.len(),
         errors: Error::empty(),
-        source,
+        source: Arc::clone(&source),
+        classes: classes(options),
+        ident_chars: &options.ident_chars,
+        symbols: Interner::default(),
     }
     .scan();
+    tokens.push(Entry::End);
+    let tokens = TokenStream::with_symbols(tokens, symbols, source);
+    let errors = match tokens.check_limits() {
+        Ok(()) => errors,
+        Err(err) => Some(match errors {
+            Some(errors) => errors.with(err),
+            None => err,
+        }),
+    };
     (tokens, errors)
 }
+
+/// Scans `source` to be parsed with `options`, checking its delimiters if
+/// [`ParseOptions::check_delimiters`] is set.
+///
+/// Returns the tokens, any scanning errors, and the options to parse the
+/// tokens with, which don't check the delimiters again. If any delimiters are
+/// unmatched, returns them alongside any scanning errors instead.
+pub(crate) fn scan_checked(
+    source: Arc<SourceFile>,
+    options: &ParseOptions,
+) -> Result<(TokenStream, Option<Error>, ParseOptions)> {
+    let (tokens, error) = scan(source, &options.scanner);
+    if !options.check_delimiters {
+        return Ok((tokens, error, options.clone()));
+    }
+    if let Err(mut err) = tokens.check_delimiters() {
+        if let Some(error) = error {
+            err.add(error);
+        }
+        return Err(err);
+    }
+    let options = ParseOptions {
+        check_delimiters: false,
+        ..options.clone()
+    };
+    Ok((tokens, error, options))
+}
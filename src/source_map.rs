@@ -0,0 +1,255 @@
+//! Management of many source files, for projects spanning multiple files.
+//!
+//! A [`SourceMap`] gives each file added to it a stable [`FileId`], and places
+//! the files one after another in a single global offset space, so that any
+//! position in the project can be described by one number.
+
+use crate::error::Error;
+use crate::parse_source_with;
+use crate::Parse;
+use crate::ParseOptions;
+use crate::Result;
+use crate::SourceFile;
+use crate::Span;
+
+use std::num::NonZeroUsize;
+use std::panic;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::thread;
+
+/// The identifier of a file in a [`SourceMap`].
+///
+/// Ids are assigned in the order files are added, starting from zero.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct FileId(usize);
+
+impl FileId {
+    /// Returns the index of this file in its [`SourceMap`].
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+/// A collection of source files.
+///
+/// # Example
+/// ```
+/// # use flexi_parse::source_map::SourceMap;
+/// # use flexi_parse::token::Ident;
+/// # use flexi_parse::SourceFile;
+/// # use flexi_parse::Spanned;
+/// let mut map = SourceMap::new();
+/// let main = map.add(SourceFile::new("main".to_string(), "import lib".to_string()));
+/// let lib = map.add(SourceFile::new("lib".to_string(), "1".to_string()));
+///
+/// let keyword: Ident = map.parse(main).unwrap().unwrap();
+/// assert_eq!(map.file_id(&keyword.span()), Some(main));
+/// assert!(map.parse::<Ident>(lib).unwrap().is_err());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    files: Vec<(usize, Arc<SourceFile>)>,
+    end: usize,
+}
+
+impl SourceMap {
+    /// Creates an empty source map.
+    pub fn new() -> SourceMap {
+        SourceMap::default()
+    }
+
+    /// Adds `file` to this map, returning its id.
+    pub fn add(&mut self, file: SourceFile) -> FileId {
+        self.add_shared(Arc::new(file))
+    }
+
+    /// Adds a file that is already shared to this map, returning its id.
+    ///
+    /// If `file` has already been added, its existing id is returned.
+    pub fn add_shared(&mut self, file: Arc<SourceFile>) -> FileId {
+        if let Some(id) = self.find(&file) {
+            return id;
+        }
+        let start = self.end;
+        // Leave a gap after each file so that its end has its own offset.
+        self.end += file.contents.len() + 1;
+        self.files.push((start, file));
+        FileId(self.files.len() - 1)
+    }
+
+    /// Returns the file with the given id, if it is in this map.
+    pub fn get(&self, id: FileId) -> Option<&Arc<SourceFile>> {
+        self.files.get(id.0).map(|(_, file)| file)
+    }
+
+    /// Returns an iterator over the files in this map, with their ids.
+    pub fn files(&self) -> impl Iterator<Item = (FileId, &Arc<SourceFile>)> {
+        self.files
+            .iter()
+            .enumerate()
+            .map(|(i, (_, file))| (FileId(i), file))
+    }
+
+    /// Returns the number of files in this map.
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Returns true if this map contains no files.
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Returns the id of the file containing `span`, if it is in this map.
+    pub fn file_id(&self, span: &Span) -> Option<FileId> {
+        self.find(&span.source)
+    }
+
+    /// Returns the global offset of the start of `span`, if its file is in
+    /// this map.
+    pub fn global_offset(&self, span: &Span) -> Option<usize> {
+        let id = self.file_id(span)?;
+        Some(self.files[id.0].0 + span.start)
+    }
+
+    /// Returns an empty span at the given global offset, if it is within a
+    /// file in this map.
+    pub fn lookup(&self, offset: usize) -> Option<Span> {
+        let index = self
+            .files
+            .partition_point(|(start, _)| *start <= offset)
+            .checked_sub(1)?;
+        let (start, file) = &self.files[index];
+        let offset = offset - start;
+        if offset > file.contents.len() {
+            return None;
+        }
+        Some(Span::new(offset, offset, Arc::clone(file)))
+    }
+
+    /// Scans and parses the file with the given id into the syntax tree node
+    /// `T`.
+    ///
+    /// This function ignores all whitespace. Returns `None` if `id` is not in
+    /// this map.
+    pub fn parse<T: Parse>(&self, id: FileId) -> Option<Result<T>> {
+        self.parse_with(id, ParseOptions::new())
+    }
+
+    /// Scans and parses the file with the given id into the syntax tree node
+    /// `T`, using the given options.
+    ///
+    /// Returns `None` if `id` is not in this map.
+    pub fn parse_with<T: Parse>(&self, id: FileId, options: ParseOptions) -> Option<Result<T>> {
+        let file = self.get(id)?;
+        Some(parse_source_with(Arc::clone(file), options))
+    }
+
+    /// Reads the files at the given paths into this map, and scans and parses
+    /// them into the syntax tree node `T` in parallel.
+    ///
+    /// Returns the result for each path, in the order given, alongside every
+    /// error from all the files combined and ordered as by
+    /// [`SourceMap::sort`]. This function ignores all whitespace.
+    ///
+    /// ## Errors
+    /// If a file cannot be read, its result is an error of kind
+    /// [`DiagnosticKind::Io`][crate::error::DiagnosticKind::Io], reported
+    /// against the path.
+    pub fn parse_all<T: Parse + Send>(
+        &mut self,
+        paths: impl IntoIterator<Item = PathBuf>,
+    ) -> (Vec<Result<T>>, Option<Error>) {
+        self.parse_all_with(paths, ParseOptions::new())
+    }
+
+    /// Reads the files at the given paths into this map, and scans and parses
+    /// them into the syntax tree node `T` in parallel, using the given
+    /// options.
+    ///
+    /// ## Errors
+    /// See [`SourceMap::parse_all`].
+    pub fn parse_all_with<T: Parse + Send>(
+        &mut self,
+        paths: impl IntoIterator<Item = PathBuf>,
+        options: ParseOptions,
+    ) -> (Vec<Result<T>>, Option<Error>) {
+        let mut results = vec![];
+        let mut files = vec![];
+        for path in paths {
+            match SourceFile::read(path.clone()) {
+                Ok(file) => {
+                    let file = Arc::new(file);
+                    self.add_shared(Arc::clone(&file));
+                    files.push((results.len(), file));
+                    results.push(None);
+                }
+                Err(err) => results.push(Some(Err(Error::io(&path, &err)))),
+            }
+        }
+
+        let next = AtomicUsize::new(0);
+        let workers = thread::available_parallelism()
+            .map_or(1, NonZeroUsize::get)
+            .min(files.len());
+        thread::scope(|scope| {
+            let workers: Vec<_> = (0..workers)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut parsed = vec![];
+                        while let Some((index, file)) =
+                            files.get(next.fetch_add(1, Ordering::Relaxed))
+                        {
+                            let result = parse_source_with(Arc::clone(file), options.clone());
+                            parsed.push((*index, result));
+                        }
+                        parsed
+                    })
+                })
+                .collect();
+            for worker in workers {
+                let parsed = worker
+                    .join()
+                    .unwrap_or_else(|payload| panic::resume_unwind(payload));
+                for (index, result) in parsed {
+                    results[index] = Some(result);
+                }
+            }
+        });
+
+        let results: Vec<Result<T>> = results.into_iter().map(Option::unwrap).collect();
+        let mut error = Error::empty();
+        for err in results.iter().filter_map(|result| result.as_ref().err()) {
+            error.add(err.clone());
+        }
+        let error = if error.is_empty() {
+            None
+        } else {
+            Some(self.sort(error))
+        };
+        (results, error)
+    }
+
+    /// Consumes `error`, returning it with its diagnostics ordered by the
+    /// order their files were added to this map, and then by position in the
+    /// file.
+    ///
+    /// Diagnostics from files not in this map are placed last.
+    pub fn sort(&self, mut error: Error) -> Error {
+        error.sort_by_source(|a, b| {
+            let key = |file| self.find(file).map_or(usize::MAX, FileId::index);
+            key(a).cmp(&key(b))
+        });
+        error
+    }
+
+    fn find(&self, file: &Arc<SourceFile>) -> Option<FileId> {
+        self.files
+            .iter()
+            .position(|(_, other)| Arc::ptr_eq(file, other))
+            .map(FileId)
+    }
+}
@@ -0,0 +1,108 @@
+use crate::cst;
+use crate::cst::SyntaxElement;
+use crate::cst::SyntaxNode;
+use crate::cst::TokenKind;
+use crate::group::Parentheses;
+use crate::token::Ident;
+use crate::token::LitInt;
+use crate::ParseStream;
+use crate::Parser;
+use crate::Result;
+
+use super::scan;
+
+/// Parses a call such as `f(1, 2)`, marking the call and each argument.
+fn call(input: ParseStream<'_>) -> Result<()> {
+    input.start_node("call");
+    let _: Ident = input.parse()?;
+    input.parse_group_with::<Parentheses, _, _>(|input| {
+        while !input.is_empty() {
+            input.start_node("argument");
+            let _: LitInt = input.parse()?;
+            input.finish_node();
+            if !input.is_empty() {
+                let _: Punct![","] = input.parse()?;
+            }
+        }
+        Ok(())
+    })?;
+    input.finish_node();
+    Ok(())
+}
+
+fn kinds(node: &SyntaxNode) -> Vec<&'static str> {
+    node.nodes()
+        .flat_map(|node| [node.kind()].into_iter().chain(kinds(node)))
+        .collect()
+}
+
+#[test]
+fn lossless() {
+    let source = "\n f (1,\t2 )\n";
+    let tokens = scan(source);
+    let (result, tree) = cst::parse(call, &tokens, "root");
+    result.unwrap();
+    assert_eq!(tree.kind(), "root");
+    assert_eq!(tree.to_string(), source);
+    assert_eq!(kinds(&tree), ["call", "argument", "argument"]);
+
+    let call = tree.nodes().next().unwrap();
+    assert_eq!(call.text(), "f (1,\t2 )");
+    let arguments: Vec<_> = call.nodes().map(SyntaxNode::text).collect();
+    assert_eq!(arguments, ["1", "2"]);
+
+    let SyntaxElement::Token(newline) = &tree.children()[0] else {
+        panic!("expected a token");
+    };
+    assert_eq!(newline.kind(), TokenKind::WhiteSpace);
+    let text: String = call.children().iter().map(SyntaxElement::text).collect();
+    assert_eq!(text, call.text());
+}
+
+#[test]
+fn forks() {
+    let tokens = scan("a b");
+    let (result, tree) = cst::parse(
+        |input| {
+            let fork = input.fork();
+            fork.start_node("discarded");
+            let _: Ident = fork.parse()?;
+            fork.finish_node();
+
+            let fork = input.fork();
+            fork.start_node("committed");
+            let _: Ident = fork.parse()?;
+            fork.finish_node();
+            input.commit(&fork);
+            Ok(())
+        },
+        &tokens,
+        "root",
+    );
+    result.unwrap();
+    assert_eq!(kinds(&tree), ["committed"]);
+    assert_eq!(tree.to_string(), "a b");
+}
+
+#[test]
+fn unfinished_nodes() {
+    let tokens = scan("f(1, x)");
+    let (result, tree) = cst::parse(call, &tokens, "root");
+    assert!(result.is_err());
+    assert_eq!(tree.to_string(), "f(1, x)");
+    assert_eq!(kinds(&tree), ["call", "argument", "argument"]);
+    let call = tree.nodes().next().unwrap();
+    assert_eq!(call.text(), "f(1, ");
+    assert_eq!(call.nodes().nth(1).unwrap().text(), "");
+}
+
+#[test]
+fn events_outside_cst() {
+    (|input: ParseStream<'_>| {
+        call(input)?;
+        assert!(input.events.borrow().is_empty());
+        Ok(())
+    })
+    .parse(scan("f(1)"))
+    .unwrap();
+}
@@ -0,0 +1,267 @@
+use crate::parse_repeated;
+use crate::parse_string;
+use crate::punctuated::Punctuated;
+use crate::punctuated::Terminated;
+use crate::recover::Recover;
+use crate::token::Ident;
+use crate::token::LitInt;
+use crate::visit::cast;
+use crate::visit::map_as;
+use crate::visit::Fold;
+use crate::visit::Folder;
+use crate::visit::Visit;
+use crate::visit::Visitor;
+use crate::Parse;
+use crate::ParseOptions;
+use crate::ParseStream;
+use crate::Parser;
+use crate::Result;
+use crate::Spanned;
+use crate::ToTokens;
+use crate::TokenStream;
+
+use super::scan;
+
+#[derive(Debug, Parse, Spanned, ToTokens, Visit, Fold)]
+struct Pair {
+    key: Ident,
+    _colon: Punct![":"],
+    value: LitInt,
+}
+
+#[derive(Parse, Spanned, Visit, Fold)]
+struct Wrapper<T>(T);
+
+#[derive(Debug, Parse)]
+#[parse(recover_to = Punct![";"])]
+struct Binding {
+    name: Ident,
+    _equals: Punct!["="],
+    value: Recover<Terminated<LitInt, Punct![";"]>>,
+}
+
+fn starts_with_minus(input: ParseStream<'_>) -> bool {
+    input.peek(Punct!["-"])
+}
+
+fn parse_doubled(input: ParseStream<'_>) -> Result<u64> {
+    Ok(input.parse::<LitInt>()?.value() * 2)
+}
+
+#[derive(Debug, Parse)]
+struct Call {
+    name: Ident,
+    #[parse(parse_if = "starts_with_minus")]
+    minus: Option<Punct!["-"]>,
+    #[parse(separated, with = parse_doubled)]
+    args: Punctuated<u64, Punct![","]>,
+    #[parse(terminated)]
+    options: Punctuated<Ident, Punct![";"]>,
+    #[parse(skip)]
+    checked: bool,
+}
+
+#[derive(Debug, Parse, Spanned, ToTokens, Visit, Fold)]
+enum Item {
+    #[parse(peek = LitInt)]
+    Int(LitInt),
+    #[parse(peek = Ident)]
+    Pair(Pair),
+    #[parse(peek_func = starts_with_minus, name = "a negative number")]
+    Negative { _minus: Punct!["-"], value: LitInt },
+}
+
+#[derive(Spanned)]
+enum Negation {
+    Int(LitInt),
+    Neg(Punct!["-"], Box<Negation>),
+}
+
+fn negation(input: ParseStream<'_>) -> Result<Negation> {
+    if input.peek(Punct!["-"]) {
+        Ok(Negation::Neg(input.parse()?, Box::new(negation(input)?)))
+    } else {
+        Ok(Negation::Int(input.parse()?))
+    }
+}
+
+#[derive(Spanned)]
+struct Statement {
+    labels: Vec<Ident>,
+    value: Option<Negation>,
+    semicolon: Option<Punct![";"]>,
+}
+
+struct Text<'a>(&'a str);
+
+impl Visit for Text<'_> {}
+
+impl Fold for Text<'_> {}
+
+#[derive(Visit, Fold)]
+struct Borrowed<'a> {
+    value: LitInt,
+    text: Text<'a>,
+}
+
+#[test]
+fn derive_struct() {
+    let pair: Pair = parse_string("x: 1".to_string()).unwrap();
+    assert_eq!(pair.key.string(), "x");
+    assert_eq!(pair.value.value(), 1);
+
+    let Wrapper(ident): Wrapper<Ident> = parse_string("y".to_string()).unwrap();
+    assert_eq!(ident.string(), "y");
+}
+
+#[test]
+fn derive_enum() {
+    let item: Item = parse_string("2".to_string()).unwrap();
+    assert!(matches!(item, Item::Int(int) if int.value() == 2));
+    let item: Item = parse_string("a: 3".to_string()).unwrap();
+    assert!(matches!(item, Item::Pair(pair) if pair.value.value() == 3));
+    let item: Item = parse_string("-4".to_string()).unwrap();
+    assert!(matches!(item, Item::Negative { value, .. } if value.value() == 4));
+
+    let err = parse_string::<Item>("+".to_string()).unwrap_err();
+    let expected = err.diagnostics().next().unwrap().expected().unwrap();
+    assert_eq!(expected.len(), 3);
+    assert!(expected.contains("a negative number"), "{expected:?}");
+}
+
+#[test]
+fn derive_recover_to() {
+    let mut tokens = scan("x = 1; y = + 2; z = 3;");
+    tokens.remove_whitespace();
+    let (bindings, error) =
+        (|input: ParseStream<'_>| Ok((parse_repeated::<Binding>(input)?, input.get_error())))
+            .parse(tokens)
+            .unwrap();
+    assert_eq!(bindings.len(), 3);
+    assert!(matches!(&bindings[0].value, Recover::Ok(value) if value.value().value() == 1));
+    assert!(bindings[1].value.is_error());
+    assert_eq!(bindings[2].name.string(), "z");
+    assert!(matches!(&bindings[2].value, Recover::Ok(value) if value.value().value() == 3));
+    assert_eq!(error.unwrap().diagnostics().count(), 1);
+}
+
+#[test]
+fn derive_field_options() {
+    let call: Call = parse_string("f - 1, 2 a; b;".to_string()).unwrap();
+    assert_eq!(call.name.string(), "f");
+    assert!(call.minus.is_some());
+    assert_eq!(call.args.into_iter().collect::<Vec<_>>(), [2, 4]);
+    assert_eq!(call.options.iter().count(), 2);
+    assert!(!call.checked);
+
+    let call: Call = parse_string("g 3 c;".to_string()).unwrap();
+    assert!(call.minus.is_none());
+}
+
+#[test]
+fn derive_spanned() {
+    let pair: Pair = parse_string("x :  1".to_string()).unwrap();
+    assert_eq!(pair.span().source_text(), "x :  1");
+
+    let wrapper: Wrapper<Ident> = parse_string(" y ".to_string()).unwrap();
+    assert_eq!(wrapper.span().source_text(), "y");
+
+    let item: Item = parse_string("- 4".to_string()).unwrap();
+    assert_eq!(item.span().source_text(), "- 4");
+    let item: Item = parse_string("4".to_string()).unwrap();
+    assert_eq!(item.span().source_text(), "4");
+
+    let value = negation.parse(scan("- - 5")).unwrap();
+    assert_eq!(value.span().source_text(), "- - 5");
+
+    let mut statement = Statement {
+        labels: vec![],
+        value: Some(value),
+        semicolon: None,
+    };
+    assert_eq!(statement.span().source_text(), "- - 5");
+    statement.value = None;
+    assert!(statement.try_span().is_none());
+}
+
+#[test]
+fn derive_to_tokens() {
+    let tokens = scan("a:1 -  2 3");
+    let items = parse_repeated::<Item>
+        .parse_stream_with_options(&tokens, ParseOptions::new())
+        .unwrap();
+    let mut output = TokenStream::empty(tokens.source);
+    for item in &items {
+        item.to_tokens(&mut output);
+    }
+    assert_eq!(output.to_string(), "a:1 -  2 3");
+}
+
+#[test]
+fn derive_visit() {
+    struct Ints(Vec<u64>);
+
+    impl Visitor for Ints {
+        fn visit<T: Visit>(&mut self, node: &T) {
+            if let Some(int) = node.downcast_ref::<LitInt>() {
+                self.0.push(int.value());
+            }
+            node.visit_children(self);
+        }
+    }
+
+    let items = parse_repeated::<Item>.parse(scan("1 a: 2 - 3")).unwrap();
+    let mut ints = Ints(vec![]);
+    items.visit_with(&mut ints);
+    assert_eq!(ints.0, [1, 2, 3]);
+
+    let text = "text".to_string();
+    let borrowed = Borrowed {
+        value: parse_string("4".to_string()).unwrap(),
+        text: Text(&text),
+    };
+    borrowed.visit_with(&mut ints);
+    assert_eq!(ints.0, [1, 2, 3, 4]);
+
+    let wrapper: Wrapper<LitInt> = parse_string("5".to_string()).unwrap();
+    assert_eq!(
+        wrapper.downcast_ref::<Wrapper<LitInt>>().unwrap().0.value(),
+        5
+    );
+    assert!(wrapper.downcast_ref::<Wrapper<Ident>>().is_none());
+}
+
+#[test]
+fn derive_fold() {
+    struct RemoveNegatives;
+
+    impl Folder for RemoveNegatives {
+        fn fold<T: Fold>(&mut self, node: T) -> T {
+            map_as(node, |item| match item {
+                Item::Negative { value, .. } => Item::Int(value),
+                item => item.fold_children(self),
+            })
+            .unwrap_or_else(|node| node.fold_children(self))
+        }
+    }
+
+    let items = parse_repeated::<Item>.parse(scan("-1 a: 2 - 3")).unwrap();
+    let items = items.fold_with(&mut RemoveNegatives);
+    assert!(matches!(items[0], Item::Int(ref int) if int.value() == 1));
+    assert!(matches!(items[1], Item::Pair(_)));
+    assert!(matches!(items[2], Item::Int(ref int) if int.value() == 3));
+
+    let text = "text".to_string();
+    let borrowed = Borrowed {
+        value: parse_string("5".to_string()).unwrap(),
+        text: Text(&text),
+    }
+    .fold_with(&mut RemoveNegatives);
+    assert_eq!(borrowed.value.value(), 5);
+    assert_eq!(borrowed.text.0, "text");
+
+    let wrapper: Wrapper<Item> = parse_string("-6".to_string()).unwrap();
+    let wrapper = wrapper.fold_with(&mut RemoveNegatives);
+    assert!(matches!(wrapper.0, Item::Int(ref int) if int.value() == 6));
+    assert!(cast::<_, Wrapper<Item>>(wrapper).is_ok());
+}
@@ -0,0 +1,130 @@
+use crate::expr::Associativity;
+use crate::expr::ExprParser;
+use crate::parse;
+use crate::token::Ident;
+use crate::token::LitInt;
+use crate::ParseStream;
+use crate::Parser;
+use crate::Result;
+use crate::Spanned;
+
+use super::scan;
+
+mod kw {
+    use crate::keywords;
+
+    keywords![infixl, infixr];
+}
+
+mod op {
+    use crate::operators;
+
+    operators! {
+        pub enum BinOp {
+            Less => "<" (1, Left),
+            Shl => "<<" (2, Left),
+            Assign => ":=" (0, Right),
+            Arrow => "->",
+        }
+    }
+}
+
+fn expr(input: ParseStream<'_>) -> Result<String> {
+    ExprParser::new(|input| Ok(input.parse::<Ident>()?.string().to_owned()))
+        .infix(1, Associativity::Right, |l, _: Punct!["="], r| {
+            format!("({l} = {r})")
+        })
+        .infix(2, Associativity::Left, |l, _: Punct!["=="], r| {
+            format!("({l} == {r})")
+        })
+        .infix(3, Associativity::Left, |l, _: Punct!["+"], r| {
+            format!("({l} + {r})")
+        })
+        .infix(4, Associativity::Left, |l, _: Punct!["*"], r| {
+            format!("({l} * {r})")
+        })
+        .prefix(5, |_: Punct!["-"], expr| format!("(-{expr})"))
+        .postfix(6, |expr, _: Punct!["?"]| format!("({expr}?)"))
+        .parse(input)
+}
+
+fn parse_expr(source: &str) -> String {
+    let mut tokens = scan(source);
+    tokens.remove_whitespace();
+    expr.parse(tokens).unwrap()
+}
+
+#[test]
+fn precedence() {
+    assert_eq!(parse_expr("a + b * c + d"), "((a + (b * c)) + d)");
+    assert_eq!(parse_expr("-a * b?"), "((-a) * (b?))");
+}
+
+#[test]
+fn associativity() {
+    assert_eq!(parse_expr("a = b = c + d"), "(a = (b = (c + d)))");
+}
+
+#[test]
+fn longest_operator() {
+    assert_eq!(parse_expr("a == b = c"), "((a == b) = c)");
+}
+
+#[test]
+fn declared_operators() {
+    fn declarations(input: ParseStream<'_>) -> Result<Vec<String>> {
+        let mut exprs = vec![];
+        while !input.is_empty() {
+            if input.peek(kw::infixl) || input.peek(kw::infixr) {
+                let associativity = if input.parse::<Option<kw::infixl>>()?.is_some() {
+                    Associativity::Left
+                } else {
+                    let _: kw::infixr = input.parse()?;
+                    Associativity::Right
+                };
+                let precedence = input.parse::<LitInt>()?.value() as u8;
+                let mut symbol = String::new();
+                while !input.peek(Punct![";"]) {
+                    symbol.push_str(&input.parse::<Punct!["+"]>()?.to_string());
+                }
+                input
+                    .operator_table()
+                    .declare_infix(symbol, precedence, associativity);
+            } else {
+                exprs.push(
+                    ExprParser::new(|input| Ok(input.parse::<Ident>()?.string().to_owned()))
+                        .declared_infix(|l, op, r| format!("({l} {} {r})", op.symbol()))
+                        .parse(input)?,
+                );
+            }
+            let _: Punct![";"] = input.parse()?;
+        }
+        Ok(exprs)
+    }
+
+    let mut tokens = scan("infixl 1 ++; infixr 2 +++; a ++ b +++ c +++ d ++ e;");
+    tokens.remove_whitespace();
+    let exprs = declarations.parse(tokens).unwrap();
+    assert_eq!(exprs, ["((a ++ (b +++ (c +++ d))) ++ e)"]);
+}
+
+#[test]
+fn operator_set() {
+    let op: op::BinOp = parse(scan("<<")).unwrap();
+    assert_eq!(op, op::BinOp::Shl(op.span().clone()));
+    assert_eq!(op.to_string(), "<<");
+    parse::<op::BinOp>(scan(": =")).unwrap_err();
+
+    let ops = |input: ParseStream<'_>| {
+        ExprParser::new(|input| Ok(input.parse::<Ident>()?.string().to_owned()))
+            .infix_operators(|l, op: op::BinOp, r| format!("({l} {op} {r})"))
+            .parse(input)
+    };
+    let mut tokens = scan("a := b < c << d := e -> f");
+    tokens.remove_whitespace();
+    let (expr, peek) = (|input: ParseStream<'_>| Ok((ops(input)?, input.peek(op::BinOp))))
+        .parse(tokens)
+        .unwrap();
+    assert_eq!(expr, "(a := ((b < (c << d)) := e))");
+    assert!(peek);
+}
@@ -0,0 +1,54 @@
+use crate::grammar::Element;
+use crate::parse;
+use crate::token::Ident;
+use crate::token::LitInt;
+
+use super::scan;
+
+crate::grammar! {
+    /// A list of statements.
+    Block = "{" (Statement)* "}";
+    Statement = "let" Ident ("=" LitInt)? ";" | Ident "::" Ident ";";
+}
+
+#[test]
+fn sequence() {
+    let block: Block = parse(scan("{ let x = 3; let y; a::b; }")).unwrap();
+    let Element::Repeat(statements) = &block.0.elements()[1] else {
+        panic!("expected a repetition");
+    };
+    let alternatives: Vec<_> = statements
+        .iter()
+        .map(|sequence| {
+            sequence.elements()[0]
+                .downcast_ref::<Statement>()
+                .unwrap()
+                .0
+                .alternative()
+        })
+        .collect();
+    assert_eq!(alternatives, [0, 0, 1]);
+}
+
+#[test]
+fn optional() {
+    let statement: Statement = parse(scan("let x = 3;")).unwrap();
+    let Element::Optional(Some(value)) = &statement.0.elements()[2] else {
+        panic!("expected an initialiser");
+    };
+    assert_eq!(
+        value.elements()[1]
+            .downcast_ref::<LitInt>()
+            .unwrap()
+            .value(),
+        3
+    );
+    let statement: Statement = parse(scan("let x;")).unwrap();
+    assert!(matches!(statement.0.elements()[2], Element::Optional(None)));
+}
+
+#[test]
+fn failure() {
+    parse::<Block>(scan("{ let x = 3 }")).unwrap_err();
+    parse::<Statement>(scan("a : b;")).unwrap_err();
+}
@@ -0,0 +1,229 @@
+use crate::group::AngleBrackets;
+use crate::group::Braces;
+use crate::group::Bracketed;
+use crate::group::Delimiters;
+use crate::group::Group;
+use crate::group::Parentheses;
+use crate::parse;
+use crate::token::Ident;
+use crate::token::NewLine;
+use crate::ParseStream;
+use crate::Parser;
+use crate::Span;
+
+use super::scan;
+
+#[derive(Debug)]
+struct HashBraces(#[allow(dead_code)] Span);
+
+impl From<Span> for HashBraces {
+    fn from(value: Span) -> Self {
+        HashBraces(value)
+    }
+}
+
+impl Delimiters for HashBraces {
+    type Start = Punct!["#", "{"];
+    type End = Punct!["}"];
+    const CAN_NEST: bool = true;
+}
+
+#[test]
+fn custom_delimiters() {
+    let group: Group<HashBraces> = parse(scan("#{ a #{ b } }")).unwrap();
+    let mut tokens = group.into_token_stream();
+    tokens.remove_whitespace();
+    let (a, inner) = (|input: ParseStream<'_>| {
+        Ok((input.parse::<Ident>()?, input.parse::<Group<HashBraces>>()?))
+    })
+    .parse(tokens)
+    .unwrap();
+    assert_eq!(a.string(), "a");
+    let b: Ident = parse(inner.into_token_stream()).unwrap();
+    assert_eq!(b.string(), "b");
+}
+
+#[test]
+fn custom_delimiters_unterminated() {
+    let err = parse::<Group<HashBraces>>(scan("#{ a #{ b }")).unwrap_err();
+    assert!(err.to_string().contains("Unmatched '#{'"), "{err}");
+    let err = parse::<Group<HashBraces>>(scan("#{")).unwrap_err();
+    assert!(err.to_string().contains("Unmatched '#{'"), "{err}");
+}
+
+#[derive(Debug)]
+struct Comment(Span);
+
+impl From<Span> for Comment {
+    fn from(value: Span) -> Self {
+        Comment(value)
+    }
+}
+
+impl Delimiters for Comment {
+    type Start = Punct!["(", "*"];
+    type End = Punct!["*", ")"];
+    const CAN_NEST: bool = true;
+}
+
+#[test]
+fn multi_character_delimiters() {
+    let group: Group<Comment> = parse(scan("(* a (* b *) * ) *)")).unwrap();
+    let Comment(span) = group.delimiters();
+    assert_eq!((span.start, span.end), (0, 19));
+    parse::<Group<Comment>>(scan("( * a *)")).unwrap_err();
+}
+
+mod kw {
+    use crate::keywords;
+
+    keywords![begin, end];
+}
+
+#[derive(Debug)]
+struct Block(#[allow(dead_code)] Span);
+
+impl From<Span> for Block {
+    fn from(value: Span) -> Self {
+        Block(value)
+    }
+}
+
+impl Delimiters for Block {
+    type Start = kw::begin;
+    type End = kw::end;
+    const CAN_NEST: bool = true;
+}
+
+#[test]
+fn keyword_delimiters() {
+    let group: Group<Block> = parse(scan("begin a begin b end end")).unwrap();
+    let mut tokens = group.into_token_stream();
+    tokens.remove_whitespace();
+    let (a, _) =
+        (|input: ParseStream<'_>| Ok((input.parse::<Ident>()?, input.parse::<Group<Block>>()?)))
+            .parse(tokens)
+            .unwrap();
+    assert_eq!(a.string(), "a");
+
+    let err = parse::<Group<Block>>(scan("begin a begin b end")).unwrap_err();
+    assert!(err.to_string().contains("Unmatched 'begin'"), "{err}");
+}
+
+#[test]
+fn nested_angle_brackets() {
+    let group: Group<AngleBrackets> = parse(scan("<Vec<Vec<T>>>")).unwrap();
+    let mut tokens = group.into_token_stream();
+    tokens.remove_whitespace();
+    let (vec, inner) = (|input: ParseStream<'_>| {
+        Ok((
+            input.parse::<Ident>()?,
+            input.parse::<Group<AngleBrackets>>()?,
+        ))
+    })
+    .parse(tokens)
+    .unwrap();
+    assert_eq!(vec.string(), "Vec");
+    let AngleBrackets(span) = inner.delimiters();
+    assert_eq!((span.start, span.end), (4, 12));
+}
+
+#[test]
+fn parse_group() {
+    let mut tokens = scan("(a (b)) c");
+    tokens.remove_whitespace();
+    let (a, c) = (|input: ParseStream<'_>| {
+        let a = input.parse_group_with::<Parentheses, _, _>(|input: ParseStream<'_>| {
+            let a: Ident = input.parse()?;
+            input.parse_group::<Parentheses, Ident>()?;
+            Ok(a)
+        })?;
+        Ok((a, input.parse::<Ident>()?))
+    })
+    .parse(tokens)
+    .unwrap();
+    assert_eq!((a.string().as_str(), c.string().as_str()), ("a", "c"));
+}
+
+#[test]
+fn group_spans() {
+    let group: Group<Comment> = parse(scan("(* a *)")).unwrap();
+    assert_eq!((group.span().start, group.span().end), (0, 7));
+    assert_eq!((group.open_span().start, group.open_span().end), (0, 2));
+    assert_eq!((group.close_span().start, group.close_span().end), (5, 7));
+}
+
+#[test]
+fn unmatched_delimiters() {
+    scan("(a [b] {c})").check_delimiters().unwrap();
+    scan(r#"f("(\"(", ']', '\'', 'a [b])"#)
+        .check_delimiters()
+        .unwrap();
+    let message = scan("(a [b { c) ]")
+        .check_delimiters()
+        .unwrap_err()
+        .to_string();
+    assert_eq!(message.matches("Unmatched").count(), 3, "{message}");
+    assert!(message.contains("Unmatched '{'"), "{message}");
+    assert!(message.contains("Unmatched '['"), "{message}");
+    assert!(message.contains("Unmatched ']'"), "{message}");
+}
+
+#[test]
+fn unterminated_group_opener() {
+    let err = (|input: ParseStream<'_>| {
+        input.parse::<Ident>()?;
+        input.parse::<Group<Parentheses>>()
+    })
+    .parse(scan("a (b c"))
+    .unwrap_err();
+    let diagnostic = err.diagnostics().next().unwrap();
+    assert_eq!(diagnostic.span().start_location(), (1, 7));
+    assert!(diagnostic.span().is_empty());
+    let message = err.to_string();
+    assert!(
+        message.contains("'(' opened at [1:3] is not closed"),
+        "{message}"
+    );
+}
+
+#[test]
+fn selective_whitespace() {
+    let mut group: Group<Parentheses> = parse(scan("(a  \n  b)")).unwrap();
+    group.remove_blank_space();
+    let (a, _, b) = (|input: ParseStream<'_>| {
+        Ok((
+            input.parse::<Ident>()?,
+            input.parse::<NewLine>()?,
+            input.parse::<Ident>()?,
+        ))
+    })
+    .parse(group.into_token_stream())
+    .unwrap();
+    assert_eq!((a.string().as_str(), b.string().as_str()), ("a", "b"));
+}
+
+#[test]
+fn peek_after_group() {
+    let mut tokens = scan("(a, (b)) { c }");
+    tokens.remove_whitespace();
+    (|input: ParseStream<'_>| {
+        assert!(input.peek_after_group::<Parentheses, _>(Punct!["{"]));
+        assert!(!input.peek_after_group::<Parentheses, _>(Punct![";"]));
+        assert!(!input.peek_after_group::<Braces, _>(Punct!["{"]));
+        input.parse::<Group<Parentheses>>()?;
+        input.parse::<Group<Braces>>()
+    })
+    .parse(tokens)
+    .unwrap();
+}
+
+#[test]
+fn delimited() {
+    let bracketed: Bracketed<Ident> = parse(scan("[ a  ]")).unwrap();
+    assert_eq!(bracketed.value().string(), "a");
+    assert_eq!(bracketed.open_span().start, 0);
+    assert_eq!(bracketed.close_span().start, 5);
+    let err = parse::<Bracketed<Ident>>(scan("[a b]")).unwrap_err();
+    assert!(err.to_string().contains(']'), "{err}");
+}
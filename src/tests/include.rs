@@ -0,0 +1,106 @@
+use crate::token::Ident;
+use crate::token::NewLine;
+use crate::ParseStream;
+use crate::Parser;
+use crate::Result;
+use crate::SourceFile;
+use crate::TokenStream;
+
+use std::sync::Arc;
+
+fn expand(main: &str, files: &[(&str, &str)]) -> Result<TokenStream> {
+    let files: Vec<Arc<SourceFile>> = files
+        .iter()
+        .map(|(name, contents)| Arc::new(SourceFile::new(name.to_string(), contents.to_string())))
+        .collect();
+    let main = Arc::new(SourceFile::new("main".to_string(), main.to_string()));
+    let mut tokens = TokenStream::try_from(main)?;
+    tokens.expand_includes(|input| {
+        if !input.peek(Punct!["#"]) {
+            return Ok(None);
+        }
+        let _: Punct!["#"] = input.parse()?;
+        let name: Ident = input.parse()?;
+        let file = files.iter().find(|file| file.name() == name.string());
+        Ok(Some(Arc::clone(file.ok_or_else(|| {
+            input.new_error("Unknown file".to_string(), &name, 0)
+        })?)))
+    })?;
+    Ok(tokens)
+}
+
+#[test]
+fn nested_includes() {
+    let tokens = expand("a # b d", &[("b", "# c"), ("c", "x")]).unwrap();
+    let (first, second, third) = (|input: ParseStream<'_>| {
+        Ok((
+            input.parse::<Ident>()?,
+            input.parse::<Ident>()?,
+            input.parse::<Ident>()?,
+        ))
+    })
+    .parse(tokens)
+    .unwrap();
+    assert_eq!(first.string(), "a");
+    assert_eq!(second.string(), "x");
+    assert_eq!(second.span.source().name(), "c");
+    assert_eq!(third.string(), "d");
+}
+
+#[test]
+fn errors_in_included_file() {
+    let tokens = expand("a # b", &[("b", ";")]).unwrap();
+    let err = (|input: ParseStream<'_>| Ok((input.parse::<Ident>()?, input.parse::<Ident>()?)))
+        .parse(tokens)
+        .unwrap_err();
+    assert_eq!(
+        err.diagnostics().next().unwrap().span().source().name(),
+        "b"
+    );
+    assert!(err.render_plain().starts_with("b:1:1: error"));
+
+    let err = expand("# a", &[("a", "# b"), ("b", "# a")]).unwrap_err();
+    assert!(err.to_string().contains("'a' includes itself"), "{err}");
+    expand("# z", &[]).unwrap_err();
+}
+
+#[test]
+fn whitespace_in_directives() {
+    let tokens = expand("a #  b\n#\tc", &[("b", "x"), ("c", "y")]).unwrap();
+    let (a, x, y) = (|input: ParseStream<'_>| {
+        let a: Ident = input.parse()?;
+        let x: Ident = input.parse()?;
+        let _: NewLine = input.parse()?;
+        let y: Ident = input.parse()?;
+        Ok((a, x, y))
+    })
+    .parse(tokens)
+    .unwrap();
+    assert_eq!(a.string(), "a");
+    assert_eq!(x.string(), "x");
+    assert_eq!(y.string(), "y");
+}
+
+#[test]
+fn syntax_tree() {
+    use crate::cst;
+
+    let tokens = expand("a # b c", &[("b", "included_ident y")]).unwrap();
+    let (result, tree) = cst::parse(
+        |input| {
+            while !input.is_empty() {
+                input.start_node("ident");
+                let _: Ident = input.parse()?;
+                input.finish_node();
+            }
+            Ok(())
+        },
+        &tokens,
+        "root",
+    );
+    result.unwrap();
+    assert_eq!(tree.to_string(), "a # b c");
+    let texts: Vec<_> = tree.nodes().map(|node| node.text()).collect();
+    assert_eq!(texts, ["a", "", "", "c"]);
+    assert_eq!(tree.nodes().nth(1).unwrap().span().start, 1);
+}
@@ -1,5 +1,12 @@
 use crate::parse;
+use crate::token::Ident;
+use crate::token::LitStrDoubleQuote;
+use crate::ParseOptions;
+use crate::ParseStream;
 use crate::Parser;
+use crate::ToTokens;
+
+use std::collections::HashSet;
 
 use super::scan;
 
@@ -9,6 +16,28 @@ mod kw {
     keywords_prefixed!["let", "if", "for"];
 }
 
+mod categories {
+    use crate::keywords;
+
+    keywords![
+        print,
+        declaration: [var, fun as Fun],
+        operator: [and, or],
+    ];
+}
+
+mod tok {
+    use crate::custom_token;
+
+    custom_token! {
+        pub struct Select = "select";
+    }
+
+    custom_token! {
+        pub struct Hex = |c| c.is_ascii_hexdigit(), "a hexadecimal number";
+    }
+}
+
 #[test]
 fn keyword() {
     parse::<kw::keyword_let>(scan("let")).unwrap();
@@ -23,3 +52,102 @@ fn ident() {
 fn ident_fail() {
     Parser::parse(kw::ident, scan("let")).unwrap_err();
 }
+
+#[test]
+fn peek() {
+    let peeks =
+        |input: ParseStream<'_>| Ok((input.peek(kw::keyword_let), input.peek(kw::keyword_if)));
+    assert_eq!(peeks.parse(scan("let")).unwrap(), (true, false));
+    assert_eq!(peeks.parse(scan("LET")).unwrap(), (false, false));
+    let options = ParseOptions::new().case_sensitive_keywords(false);
+    assert_eq!(
+        peeks
+            .parse_with_options(scan("LET"), options.clone())
+            .unwrap(),
+        (true, false)
+    );
+    Parser::parse_with_options(kw::ident, scan("For"), options).unwrap_err();
+}
+
+#[test]
+fn categories() {
+    parse::<categories::Fun>(scan("fun")).unwrap();
+    parse::<categories::and>(scan("and")).unwrap();
+    Parser::parse(categories::ident, scan("or")).unwrap_err();
+    Parser::parse(categories::ident, scan("print")).unwrap_err();
+
+    let peeks = |input: ParseStream<'_>| {
+        Ok((
+            categories::peek_any_declaration_keyword(input),
+            categories::peek_any_operator_keyword(input),
+        ))
+    };
+    assert_eq!(peeks.parse(scan("fun")).unwrap(), (true, false));
+    assert_eq!(peeks.parse(scan("or")).unwrap(), (false, true));
+    assert_eq!(peeks.parse(scan("print")).unwrap(), (false, false));
+    assert_eq!(peeks.parse(scan("x")).unwrap(), (false, false));
+    let peek2 = |input: ParseStream<'_>| Ok(categories::peek2_any_declaration_keyword(input));
+    assert!(peek2.parse(scan("print var")).unwrap());
+}
+
+#[test]
+fn did_you_mean() {
+    let err = parse::<kw::keyword_let>(scan("lett")).unwrap_err();
+    let message = err.to_string();
+    assert!(message.contains("did you mean `let`?"), "{message}");
+    assert_eq!(err.suggestions().next().unwrap().replacement(), "let");
+
+    let err = parse::<kw::keyword_let>(scan("loop")).unwrap_err();
+    assert!(err.suggestions().next().is_none());
+
+    let err = (|input: ParseStream<'_>| {
+        input.add_candidates(["counter".to_string()]);
+        Err::<(), _>(input.unexpected_token(HashSet::from_iter(["a number".to_string()])))
+    })
+    .parse(scan("countr"))
+    .unwrap_err();
+    let message = err.to_string();
+    assert!(message.contains("did you mean `counter`?"), "{message}");
+}
+
+#[test]
+fn custom_token() {
+    let select: tok::Select = parse(scan("select")).unwrap();
+    assert_eq!(select.to_string(), "select");
+    parse::<tok::Select>(scan("SELECT")).unwrap_err();
+
+    let hex: tok::Hex = parse(scan("0ff")).unwrap();
+    assert_eq!(hex.to_string(), "0ff");
+    parse::<tok::Hex>(scan("fg")).unwrap_err();
+
+    let peeks = |input: ParseStream<'_>| Ok((input.peek(tok::Select), input.peek(tok::Hex)));
+    assert_eq!(peeks.parse(scan("select")).unwrap(), (true, false));
+    assert_eq!(peeks.parse(scan("12")).unwrap(), (false, true));
+
+    let message = (|input: ParseStream<'_>| {
+        let lookahead = input.lookahead();
+        assert!(!lookahead.peek(tok::Select));
+        assert!(!lookahead.peek(tok::Hex));
+        Err::<(), _>(lookahead.error())
+    })
+    .parse(scan("xyz"))
+    .unwrap_err()
+    .to_string();
+    assert!(message.contains("select"), "{message}");
+    assert!(message.contains("a hexadecimal number"), "{message}");
+}
+
+#[test]
+fn to_tokens() {
+    let mut output = scan("");
+    (|input: ParseStream<'_>| {
+        kw::keyword_let::new(input).to_tokens(&mut output);
+        input.parse::<Ident>()?.to_tokens(&mut output);
+        input.parse::<Punct!["+="]>()?.to_tokens(&mut output);
+        input.parse::<LitStrDoubleQuote>()?.to_tokens(&mut output);
+        Ok(())
+    })
+    .parse(scan("x += \"a (b\""))
+    .unwrap();
+    assert_eq!(output.to_string(), "letx += \"a (b\"");
+}
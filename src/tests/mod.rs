@@ -1,19 +1,33 @@
 use crate::scanner;
+use crate::ScanOptions;
 use crate::SourceFile;
 use crate::TokenStream;
 
-use std::rc::Rc;
+use std::sync::Arc;
 
+mod cst;
+#[cfg(feature = "derive")]
+mod derive;
+mod expr;
+mod grammar;
+mod group;
+mod include;
 mod keywords;
+mod options;
+mod parser;
+#[cfg(feature = "proc-macro2")]
+mod proc_macro;
 mod punct;
+mod recover;
 mod simple;
+mod source_map;
 mod whitespace;
 
 fn scan(source: &str) -> TokenStream {
-    let (tokens, error) = scanner::scan(Rc::new(SourceFile::new(
-        "test data".to_string(),
-        source.to_string(),
-    )));
+    let (tokens, error) = scanner::scan(
+        Arc::new(SourceFile::new("test data".to_string(), source.to_string())),
+        &ScanOptions::default(),
+    );
     if let Some(error) = error {
         panic!("{:?}", error);
     }
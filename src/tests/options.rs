@@ -0,0 +1,164 @@
+use crate::error::ErrorCode;
+use crate::parse_string_with;
+use crate::token::Ident;
+use crate::token::NewLine;
+use crate::Parse;
+use crate::ParseOptions;
+use crate::ParseStream;
+use crate::Parser;
+use crate::Result;
+use crate::ScanOptions;
+use crate::WhiteSpacePolicy;
+
+use super::scan;
+
+mod kw {
+    use crate::keywords;
+
+    keywords![select];
+}
+
+#[derive(Debug)]
+struct Nested(usize);
+
+impl Parse for Nested {
+    fn parse(input: ParseStream<'_>) -> Result<Self> {
+        let _: Ident = input.parse()?;
+        if input.is_empty() {
+            Ok(Nested(1))
+        } else {
+            Ok(Nested(input.parse::<Nested>()?.0 + 1))
+        }
+    }
+}
+
+#[test]
+fn whitespace() {
+    let options = ParseOptions::new().whitespace(WhiteSpacePolicy::RemoveBlankSpace);
+    Parser::parse_with_options(
+        |input: ParseStream<'_>| Ok((input.parse::<Ident>()?, input.parse::<NewLine>()?)),
+        scan("a  \n"),
+        options,
+    )
+    .unwrap();
+}
+
+#[test]
+fn recursion_limit() {
+    let source = "a b c d e".to_string();
+    let nested: Nested =
+        parse_string_with(source.clone(), ParseOptions::new().recursion_limit(5)).unwrap();
+    assert_eq!(nested.0, 5);
+    parse_string_with::<Nested>(source, ParseOptions::new().recursion_limit(4)).unwrap_err();
+}
+
+#[test]
+fn case_insensitive_keywords() {
+    parse_string_with::<kw::select>("SELECT".to_string(), ParseOptions::new()).unwrap_err();
+    let options = ParseOptions::new().case_sensitive_keywords(false);
+    parse_string_with::<kw::select>("SELECT".to_string(), options.clone()).unwrap();
+    Parser::parse_with_options(kw::ident, scan("Select"), options).unwrap_err();
+}
+
+#[test]
+fn check_delimiters() {
+    parse_string_with::<Ident>("a (".to_string(), ParseOptions::new()).unwrap();
+    let options = ParseOptions::new().check_delimiters(true);
+    let message = parse_string_with::<Ident>("a ( \u{1}".to_string(), options)
+        .unwrap_err()
+        .to_string();
+    assert!(message.contains("Unmatched '('"), "{message}");
+    assert!(message.contains("Unrecognised character"), "{message}");
+}
+
+#[test]
+fn ident_chars() {
+    let ident: Ident = parse_string_with("kebab-case".to_string(), ParseOptions::new()).unwrap();
+    assert_eq!(ident.string(), "kebab");
+    let options = ParseOptions::new().scanner(ScanOptions::new().ident_chars("-λ"));
+    let ident: Ident = parse_string_with("kebab-case".to_string(), options.clone()).unwrap();
+    assert_eq!(ident.string(), "kebab-case");
+    let ident: Ident = parse_string_with("λx".to_string(), options).unwrap();
+    assert_eq!(ident.string(), "λx");
+}
+
+#[test]
+fn error_codes() {
+    let options = ParseOptions::new().error_code(
+        ErrorCode::new(123, "reserved".to_string(), "Reserved name".to_string())
+            .with_url("https://example.com/E0123".to_string()),
+    );
+    let reserved = |input: ParseStream<'_>| {
+        let ident: Ident = input.parse()?;
+        Err::<(), _>(input.new_coded_error("reserved", &ident))
+    };
+    let err = reserved
+        .parse_with_options(scan("x"), options.clone())
+        .unwrap_err();
+    let message = err.to_string();
+    assert!(
+        message.contains("[E0123] Error: Reserved name"),
+        "{message}"
+    );
+    assert!(
+        message.contains("See: https://example.com/E0123"),
+        "{message}"
+    );
+    assert_eq!(err.error_codes().next().unwrap().name(), "reserved");
+
+    let err = (|input: ParseStream<'_>| {
+        let ident: Ident = input.parse()?;
+        Err::<(), _>(input.new_error("Other".to_string(), &ident, 7))
+    })
+    .parse_with_options(scan("x"), options.clone())
+    .unwrap_err();
+    assert!(err.error_codes().next().is_none());
+
+    let err = (|input: ParseStream<'_>| {
+        let ident: Ident = input.parse()?;
+        Err::<(), _>(input.new_coded_error("unregistered", &ident))
+    })
+    .parse_with_options(scan("x"), options)
+    .unwrap_err();
+    assert!(err.error_codes().next().is_none());
+    let message = err.to_string();
+    assert!(message.contains("Error: unregistered"), "{message}");
+}
+
+#[test]
+fn error_limit() {
+    let many = |input: ParseStream<'_>| {
+        let ident: Ident = input.parse()?;
+        let mut err = input.new_error("First".to_string(), &ident, 0);
+        for i in 0..24 {
+            err.add(input.new_error(format!("Error {i}"), &ident, 0));
+        }
+        Err::<(), _>(err)
+    };
+
+    let message = many.parse(scan("x")).unwrap_err().to_string();
+    assert_eq!(message.matches("Error:").count(), 20);
+    assert!(
+        message.ends_with("Note: 5 more errors not shown"),
+        "{message}"
+    );
+
+    let options = ParseOptions::new().error_limit(24);
+    let message = many
+        .parse_with_options(scan("x"), options)
+        .unwrap_err()
+        .to_string();
+    assert_eq!(message.matches("Error:").count(), 24);
+    assert!(
+        message.ends_with("Note: 1 more error not shown"),
+        "{message}"
+    );
+
+    let options = ParseOptions::new().no_error_limit();
+    let message = many
+        .parse_with_options(scan("x"), options)
+        .unwrap_err()
+        .to_string();
+    assert_eq!(message.matches("Error:").count(), 25);
+    assert!(!message.contains("not shown"), "{message}");
+}
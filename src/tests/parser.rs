@@ -0,0 +1,819 @@
+use crate::error::Applicability;
+use crate::parse_repeated_n;
+use crate::parse_repeated_nonempty;
+use crate::parse_repeated_until;
+use crate::token::Ident;
+use crate::token::LitInt;
+use crate::ParseOptions;
+use crate::ParseStream;
+use crate::Parser;
+use crate::Result;
+
+use super::scan;
+
+fn int(input: ParseStream<'_>) -> Result<u64> {
+    Ok(input.parse::<LitInt>()?.value())
+}
+
+fn name(input: ParseStream<'_>) -> Result<u64> {
+    Ok(input.parse::<Ident>()?.string().len() as u64)
+}
+
+#[test]
+fn map() {
+    let value = int.map(|value| value * 2).parse(scan("21")).unwrap();
+    assert_eq!(value, 42);
+}
+
+#[test]
+fn parse_stream() {
+    let tokens = scan("hello  world");
+    assert!(int.parse_stream(&tokens).is_err());
+    assert_eq!(name.parse_stream(&tokens).unwrap(), 5);
+    let options = ParseOptions::new();
+    let both = |input: ParseStream<'_>| Ok(name(input)? + name(input)?);
+    assert_eq!(
+        both.parse_stream_with_options(&tokens, options).unwrap(),
+        10
+    );
+    assert_eq!(tokens.to_string(), "hello  world");
+}
+
+#[test]
+fn or() {
+    assert_eq!(int.or(name).parse(scan("hello")).unwrap(), 5);
+    assert_eq!(int.or(name).parse(scan("3")).unwrap(), 3);
+}
+
+#[test]
+fn repeated() {
+    let mut tokens = scan("1 2 3 four");
+    tokens.remove_whitespace();
+    let (values, rest) =
+        (|input: ParseStream<'_>| Ok((int.repeated()(input)?, name.optional()(input)?)))
+            .parse(tokens)
+            .unwrap();
+    assert_eq!(values, [1, 2, 3]);
+    assert_eq!(rest, Some(4));
+}
+
+#[test]
+fn repeated_without_progress() {
+    let nothing = |_: ParseStream<'_>| -> Result<u64> { Ok(0) };
+    let values = nothing.repeated().parse(scan("1 2")).unwrap();
+    assert!(values.is_empty());
+}
+
+#[test]
+fn separated_by() {
+    let mut tokens = scan("1, 2, 3");
+    tokens.remove_whitespace();
+    let values = int.separated_by::<Punct![","]>().parse(tokens).unwrap();
+    assert_eq!(values.into_iter().collect::<Vec<_>>(), [1, 2, 3]);
+}
+
+#[test]
+fn expected_alternatives() {
+    let err = (|input: ParseStream<'_>| {
+        let _: Option<Punct!["+"]> = input.parse()?;
+        input.parse::<Ident>()
+    })
+    .parse(scan("1"))
+    .unwrap_err();
+    let message = err.to_string();
+    assert!(message.contains("'+'"), "{message}");
+    assert!(message.contains("an identifier"), "{message}");
+}
+
+#[test]
+fn spanned() {
+    let mut tokens = scan("x 12 y");
+    tokens.remove_whitespace();
+    let span = (|input: ParseStream<'_>| {
+        let _: Ident = input.parse()?;
+        Ok(input.spanned(|input| Ok((int(input)?, name(input)?)))?.1)
+    })
+    .parse(tokens)
+    .unwrap();
+    assert_eq!((span.start, span.end), (2, 6));
+}
+
+#[test]
+fn repeated_bounded() {
+    let mut tokens = scan("a b c");
+    tokens.remove_whitespace();
+    let idents = parse_repeated_nonempty::<Ident>
+        .parse(tokens.clone())
+        .unwrap();
+    assert_eq!(idents.len(), 3);
+    parse_repeated_nonempty::<Ident>
+        .parse(scan(""))
+        .unwrap_err();
+
+    let bounded =
+        |min, max| move |input: ParseStream<'_>| parse_repeated_n::<Ident>(input, min, max);
+    bounded(3, 3).parse(tokens.clone()).unwrap();
+    let err = bounded(0, 2).parse(tokens.clone()).unwrap_err();
+    assert!(err.to_string().contains("at most 2"), "{err}");
+    let err = bounded(4, 5).parse(tokens).unwrap_err();
+    assert!(err.to_string().contains("at least 4"), "{err}");
+}
+
+#[test]
+fn repeated_until() {
+    let mut tokens = scan("a b ; c");
+    tokens.remove_whitespace();
+    let (idents, rest) = (|input: ParseStream<'_>| {
+        let idents = parse_repeated_until::<Ident, _>(input, |input| input.peek(Punct![";"]))?;
+        let _: Punct![";"] = input.parse()?;
+        Ok((idents, input.parse::<Ident>()?))
+    })
+    .parse(tokens)
+    .unwrap();
+    assert_eq!(idents.len(), 2);
+    assert_eq!(rest.string(), "c");
+}
+
+#[test]
+fn warnings() {
+    let mut tokens = scan("x y");
+    tokens.remove_whitespace();
+    let unused = |input: ParseStream<'_>| {
+        let ident: Ident = input.parse()?;
+        input.add_warning(input.new_error("Unused name".to_string(), &ident, 0));
+        Ok(ident)
+    };
+    let (result, warnings) = unused.parse_with_warnings(tokens.clone(), ParseOptions::new());
+    assert_eq!(result.unwrap().string(), "x");
+    let message = warnings.unwrap().to_string();
+    assert!(message.contains("Warning: Unused name"), "{message}");
+
+    let err = (|input: ParseStream<'_>| {
+        unused(input)?;
+        input.parse::<LitInt>()
+    })
+    .parse(tokens)
+    .unwrap_err();
+    let message = err.to_string();
+    assert!(message.contains("Warning: Unused name"), "{message}");
+}
+
+#[test]
+fn parse_string_with_warnings() {
+    use crate::parse_string_with_warnings;
+    use crate::Parse;
+
+    struct Unused(Ident);
+
+    impl Parse for Unused {
+        fn parse(input: ParseStream<'_>) -> Result<Self> {
+            let ident: Ident = input.parse()?;
+            input.add_warning(input.new_error("Unused name".to_string(), &ident, 0));
+            Ok(Unused(ident))
+        }
+    }
+
+    let (result, warnings) =
+        parse_string_with_warnings::<Unused>("x".to_string(), ParseOptions::new());
+    assert_eq!(result.unwrap().0.string(), "x");
+    assert_eq!(warnings.len(), 1);
+    let diagnostic = warnings.diagnostics().next().unwrap();
+    assert_eq!(diagnostic.message(), "Unused name");
+    assert!(warnings.into_error().is_some());
+
+    let (result, warnings) =
+        parse_string_with_warnings::<Ident>("x".to_string(), ParseOptions::new());
+    assert!(result.is_ok());
+    assert!(warnings.is_empty());
+    assert!(warnings.into_error().is_none());
+}
+
+#[test]
+fn warnings_in_forks() {
+    let mut tokens = scan("x y");
+    tokens.remove_whitespace();
+    let (result, warnings) = (|input: ParseStream<'_>| {
+        let discarded = input.fork();
+        let ident: Ident = discarded.parse()?;
+        discarded.add_warning(discarded.new_error("Discarded".to_string(), &ident, 0));
+
+        let committed = input.fork();
+        let ident: Ident = committed.parse()?;
+        committed.add_warning(committed.new_error("Committed".to_string(), &ident, 0));
+        input.commit(&committed);
+        input.parse::<Ident>()
+    })
+    .parse_with_warnings(tokens, ParseOptions::new());
+    assert_eq!(result.unwrap().string(), "y");
+    let message = warnings.unwrap().to_string();
+    assert!(message.contains("Warning: Committed"), "{message}");
+    assert!(!message.contains("Discarded"), "{message}");
+}
+
+#[test]
+fn help_and_note() {
+    let err = (|input: ParseStream<'_>| {
+        input.parse::<LitInt>().map_err(|err| {
+            err.with_help("use a number".to_string())
+                .with_note("names are not allowed here".to_string())
+        })
+    })
+    .parse(scan("x"))
+    .unwrap_err();
+    let message = err.to_string();
+    assert!(message.contains("\nHelp: use a number"), "{message}");
+    assert!(
+        message.contains("\nNote: names are not allowed here"),
+        "{message}"
+    );
+}
+
+#[test]
+fn suggestions() {
+    let err = (|input: ParseStream<'_>| {
+        let ident: Ident = input.parse()?;
+        Err::<(), _>(
+            input
+                .new_error("Unknown keyword".to_string(), &ident, 0)
+                .with_suggestion(
+                    "did you mean".to_string(),
+                    &ident,
+                    "while".to_string(),
+                    Applicability::MaybeIncorrect,
+                ),
+        )
+    })
+    .parse(scan("whiel"))
+    .unwrap_err();
+    let suggestion = err.suggestions().next().unwrap();
+    assert_eq!(suggestion.replacement(), "while");
+    assert_eq!(suggestion.applicability(), Applicability::MaybeIncorrect);
+    assert_eq!((suggestion.span().start, suggestion.span().end), (0, 5));
+    let message = err.to_string();
+    assert!(
+        message.contains("Suggestion: did you mean: 'while'"),
+        "{message}"
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn json() {
+    let err = name.parse(scan("+")).unwrap_err();
+    let json = err.to_json();
+    assert!(
+        json.starts_with(r#"[{"code":"E06","severity":"error","#),
+        "{json}"
+    );
+    assert!(json.contains(r#""range":{"start":0,"end":1}"#), "{json}");
+    assert!(json.contains(r#""start":{"line":1,"column":1}"#), "{json}");
+    assert!(json.contains(r#""primary":true"#), "{json}");
+
+    let json = name.parse(scan("")).unwrap_err().to_json();
+    assert!(json.contains(r#""range":{"start":0,"end":0}"#), "{json}");
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde() {
+    use crate::SourceFile;
+    use crate::Spanned;
+
+    let ident: Ident = crate::parse(scan("abc")).unwrap();
+    let json = serde_json::to_string(&ident.span()).unwrap();
+    assert!(
+        json.starts_with(r#"{"file":"test data","range":{"start":0,"end":3}"#),
+        "{json}"
+    );
+
+    let source = SourceFile::new("file".to_string(), "a\nb".to_string());
+    let json = serde_json::to_string(&source).unwrap();
+    let source: SourceFile = serde_json::from_str(&json).unwrap();
+    assert_eq!(source.name(), "file");
+    assert_eq!(source.contents(), "a\nb");
+
+    let err = name.parse(scan("+")).unwrap_err();
+    let diagnostic = serde_json::to_string(&err.diagnostics().next().unwrap()).unwrap();
+    assert_eq!(format!("[{diagnostic}]"), err.to_json());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn sarif() {
+    let err = name.parse(scan("+")).unwrap_err();
+    let sarif = err.to_sarif("test");
+    assert!(sarif.contains(r#""version":"2.1.0""#), "{sarif}");
+    assert!(sarif.contains(r#""ruleId":"E06""#), "{sarif}");
+    assert!(sarif.contains(r#""level":"error""#), "{sarif}");
+    assert!(sarif.contains(r#""uri":"test data""#), "{sarif}");
+}
+
+#[cfg(feature = "lsp")]
+#[test]
+fn lsp() {
+    use crate::error::Error;
+    use crate::error::ErrorKind;
+    use crate::error::Severity;
+    use crate::SourceFile;
+    use crate::Span;
+    use lsp_types::DiagnosticSeverity;
+    use lsp_types::Position;
+    use lsp_types::Url;
+    use std::sync::Arc;
+
+    let source = Arc::new(SourceFile::new(
+        "test data".to_string(),
+        "1\n\"é😀\" +".to_string(),
+    ));
+    let span = Span::new(9, 10, Arc::clone(&source));
+    let range = span.to_lsp_range();
+    assert_eq!(range.start, Position::new(1, 4));
+    assert_eq!(range.end, Position::new(1, 5));
+    let err = Error::new(
+        &source,
+        ErrorKind::Custom {
+            message: "message".to_string(),
+            span,
+            code: 0,
+        },
+    )
+    .with_severity(Severity::Warning);
+    let uri = Url::parse("file:///test").unwrap();
+    let diagnostics = err.to_lsp_diagnostics(&uri);
+    assert_eq!(diagnostics.len(), 1);
+    let diagnostic = &diagnostics[0];
+    assert_eq!(diagnostic.range.start, Position::new(1, 4));
+    assert_eq!(diagnostic.range.end, Position::new(1, 5));
+    assert_eq!(diagnostic.severity, Some(DiagnosticSeverity::WARNING));
+    assert_eq!(diagnostic.message, "message");
+}
+
+#[cfg(feature = "lsp")]
+#[test]
+fn lsp_related_information() {
+    use crate::error::Error;
+    use crate::error::ErrorKind;
+    use crate::SourceFile;
+    use crate::Span;
+    use lsp_types::Position;
+    use lsp_types::Url;
+    use std::sync::Arc;
+
+    let main = Arc::new(SourceFile::new("main".to_string(), "use lib".to_string()));
+    let lib = Arc::new(SourceFile::new("lib".to_string(), "\nfn lib".to_string()));
+    let err = Error::new(
+        &main,
+        ErrorKind::Custom {
+            message: "message".to_string(),
+            span: Span::new(4, 7, Arc::clone(&main)),
+            code: 0,
+        },
+    )
+    .with_label(Span::new(0, 3, Arc::clone(&main)), "here".to_string())
+    .with_label(
+        Span::new(4, 7, Arc::clone(&lib)),
+        "defined here".to_string(),
+    )
+    .with_help("help text".to_string());
+
+    let uri = Url::parse("file:///main").unwrap();
+    let diagnostics = err.to_lsp_diagnostics(&uri);
+    assert_eq!(diagnostics[0].message, "message\nhelp: help text");
+    let related = diagnostics[0].related_information.as_ref().unwrap();
+    assert_eq!(related.len(), 1);
+    assert_eq!(related[0].location.uri, uri);
+    assert_eq!(related[0].message, "here");
+
+    let diagnostics = err
+        .to_lsp_diagnostics_with(|source| Url::parse(&format!("file:///{}", source.name())).ok());
+    let related = diagnostics[0].related_information.as_ref().unwrap();
+    assert_eq!(related.len(), 2);
+    assert_eq!(related[0].location.uri, uri);
+    assert_eq!(related[1].location.uri.as_str(), "file:///lib");
+    assert_eq!(related[1].location.range.start, Position::new(1, 3));
+    assert_eq!(related[1].message, "defined here");
+}
+
+#[test]
+fn render_plain() {
+    let err = (|input: ParseStream<'_>| {
+        int(input)?;
+        name(input)
+    })
+    .parse(scan("1+"))
+    .unwrap_err();
+    assert_eq!(
+        err.render_plain(),
+        "test data:1:2: error: Unexpected token: Expected an identifier\n1+\n ^\n"
+    );
+}
+
+#[test]
+fn render_plain_style() {
+    use crate::error::Error;
+    use crate::error::ErrorKind;
+    use crate::error::PlainStyle;
+    use crate::SourceFile;
+    use crate::Span;
+    use std::sync::Arc;
+
+    let source = Arc::new(SourceFile::new(
+        "test data".to_string(),
+        "0\n1\n+\n3\n4".to_string(),
+    ));
+    let span = Span::new(4, 5, Arc::clone(&source));
+    let err = Error::new(
+        &source,
+        ErrorKind::Custom {
+            message: "message".to_string(),
+            span,
+            code: 0,
+        },
+    );
+    assert_eq!(
+        err.render_plain_with_style(&PlainStyle::new().context_lines(1)),
+        "test data:3:1: error: message\n1\n+\n^\n3\n"
+    );
+    assert_eq!(
+        err.render_plain_with_style(&PlainStyle::new().compact(true)),
+        "test data:3:1: error: message\n"
+    );
+}
+
+#[test]
+fn column_units() {
+    use crate::error::Error;
+    use crate::error::ErrorKind;
+    use crate::error::PlainStyle;
+    use crate::ColumnUnit;
+    use crate::SourceFile;
+    use crate::Span;
+    use std::sync::Arc;
+
+    let source = Arc::new(SourceFile::new(
+        "test data".to_string(),
+        "1\n\"é😀\" +".to_string(),
+    ));
+    let span = Span::new(9, 10, Arc::clone(&source));
+    assert_eq!(span.start_location(), (2, 4));
+    let err = Error::new(
+        &source,
+        ErrorKind::Custom {
+            message: "message".to_string(),
+            span,
+            code: 0,
+        },
+    );
+    let header =
+        |columns| err.render_plain_with_style(&PlainStyle::new().columns(columns).compact(true));
+    assert_eq!(header(ColumnUnit::Byte), "test data:2:8: error: message\n");
+    assert_eq!(header(ColumnUnit::Char), "test data:2:4: error: message\n");
+    assert_eq!(header(ColumnUnit::Utf16), "test data:2:5: error: message\n");
+    assert!(err.render_plain().ends_with("\"é😀\" +\n   ^\n"));
+}
+
+#[cfg(feature = "miette")]
+#[test]
+fn miette() {
+    use miette::Diagnostic;
+
+    let err = name.parse(scan("+")).unwrap_err();
+    assert_eq!(err.code().unwrap().to_string(), "E06");
+    assert_eq!(err.severity(), Some(miette::Severity::Error));
+    let labels: Vec<_> = err.labels().unwrap().collect();
+    assert_eq!(labels.len(), 1);
+    assert!(labels[0].primary());
+    assert_eq!(labels[0].offset(), 0);
+    assert_eq!(labels[0].len(), 1);
+
+    let contents = err
+        .source_code()
+        .unwrap()
+        .read_span(labels[0].inner(), 0, 0)
+        .unwrap();
+    assert_eq!(contents.name(), Some("test data"));
+    assert_eq!(contents.data(), b"+");
+}
+
+#[cfg(feature = "annotate-snippets")]
+#[test]
+fn annotate_snippets() {
+    use annotate_snippets::Renderer;
+
+    let err = name.parse(scan("+")).unwrap_err();
+    let rendered = err.render_snippets(&Renderer::plain());
+    assert!(
+        rendered.starts_with("error[E06]: Unexpected token"),
+        "{rendered}"
+    );
+    assert!(rendered.contains("--> test data:1:1"), "{rendered}");
+    assert!(rendered.contains("^ Expected an identifier"), "{rendered}");
+
+    let rendered = name
+        .parse(scan(""))
+        .unwrap_err()
+        .render_snippets(&Renderer::plain());
+    assert!(rendered.starts_with("error[E07]"), "{rendered}");
+}
+
+#[cfg(feature = "ariadne")]
+#[test]
+fn report_string() {
+    let err = name.parse(scan("+")).unwrap_err();
+    let report = err.to_report_string();
+    assert!(!report.contains('\x1b'), "{report}");
+    assert!(report.contains("[E06] Error: Unexpected token"), "{report}");
+    assert!(report.contains("Expected an identifier"), "{report}");
+}
+
+#[cfg(feature = "ariadne")]
+#[test]
+fn report_style() {
+    use crate::error::ReportStyle;
+
+    let err = name.parse(scan("+")).unwrap_err();
+    let reports = err.reports(&ReportStyle::new().color(false));
+    let report = reports[0].to_string_styled();
+    assert!(!report.contains('\x1b'), "{report}");
+    let reports = err.reports(&ReportStyle::new().color(true));
+    let report = reports[0].to_string_styled();
+    assert!(report.contains('\x1b'), "{report}");
+}
+
+#[cfg(feature = "ariadne")]
+#[test]
+fn report_html() {
+    use crate::error::ReportStyle;
+
+    let err = name.parse(scan("+")).unwrap_err();
+    let html = err.reports(&ReportStyle::new().color(true))[0].to_html();
+    assert!(
+        html.starts_with("<pre class=\"flexi-parse-report\">"),
+        "{html}"
+    );
+    assert!(html.ends_with("</pre>"), "{html}");
+    assert!(!html.contains('\x1b'), "{html}");
+    assert!(html.contains("<span style=\"color:"), "{html}");
+    assert!(html.contains("Expected an identifier"), "{html}");
+    assert_eq!(
+        html.matches("<span").count(),
+        html.matches("</span>").count()
+    );
+    let html = err.reports(&ReportStyle::new().color(false))[0].to_html();
+    assert!(!html.contains("<span"), "{html}");
+}
+
+#[cfg(feature = "ariadne")]
+#[test]
+fn report_config() {
+    use crate::error::CharSet;
+    use crate::error::Config;
+    use crate::error::ReportStyle;
+
+    let err = name.parse(scan("+")).unwrap_err();
+    let style = ReportStyle::new().color(false);
+    let report = err.reports(&style)[0].to_string_plain();
+    assert!(!report.is_ascii(), "{report}");
+    let style = style.config(Config::default().with_char_set(CharSet::Ascii));
+    let report = err.reports(&style)[0].to_string_plain();
+    assert!(report.is_ascii(), "{report}");
+}
+
+#[cfg(feature = "ariadne")]
+#[test]
+fn severity_override() {
+    use crate::error::DiagnosticKind;
+    use crate::error::ReportStyle;
+    use crate::error::Severity;
+
+    let err = name.parse(scan("+")).unwrap_err();
+    let style = ReportStyle::new()
+        .color(false)
+        .severity(DiagnosticKind::UnexpectedToken, Severity::Warning);
+    let report = err.reports(&style)[0].to_string_plain();
+    assert!(
+        report.contains("[E06] Warning: Unexpected token"),
+        "{report}"
+    );
+    let report = err.reports(&ReportStyle::new())[0].to_string_plain();
+    assert!(report.contains("[E06] Error: Unexpected token"), "{report}");
+}
+
+#[test]
+fn deduplicate() {
+    let repeated = |input: ParseStream<'_>| {
+        let ident: Ident = input.parse()?;
+        let mut err = input.new_error("Repeated".to_string(), &ident, 0);
+        err.add(input.new_error("Repeated".to_string(), &ident, 0));
+        err.add(input.new_error("Different".to_string(), &ident, 0));
+        Err::<(), _>(err.with(input.new_error("Repeated".to_string(), &ident, 0)))
+    };
+    let message = repeated.parse(scan("x")).unwrap_err().to_string();
+    assert_eq!(message.matches("Error:").count(), 2, "{message}");
+}
+
+#[test]
+fn sorted() {
+    let backwards = |input: ParseStream<'_>| {
+        let first: Ident = input.parse()?;
+        let second: Ident = input.parse()?;
+        let err = input
+            .new_error("Second".to_string(), &second, 0)
+            .with(input.new_error("First".to_string(), &first, 0));
+        Err::<(), _>(err.sorted())
+    };
+    let message = backwards.parse(scan("a b")).unwrap_err().to_string();
+    assert!(
+        message.find("First").unwrap() < message.find("Second").unwrap(),
+        "{message}"
+    );
+}
+
+#[test]
+fn diagnostics() {
+    use crate::error::DiagnosticKind;
+    use crate::error::Severity;
+
+    let err = name.parse(scan("+")).unwrap_err();
+    let diagnostics: Vec<_> = err.diagnostics().collect();
+    assert_eq!(diagnostics.len(), 1);
+    let diagnostic = diagnostics[0];
+    assert_eq!(diagnostic.kind(), DiagnosticKind::UnexpectedToken);
+    assert_eq!(diagnostic.severity(), Severity::Error);
+    assert_eq!(diagnostic.message(), "Unexpected token");
+    assert_eq!(diagnostic.code(), "E06");
+    assert_eq!(diagnostic.span().start_location(), (1, 1));
+    assert!(diagnostic.expected().unwrap().contains("an identifier"));
+
+    let err = name.parse(scan("")).unwrap_err();
+    let diagnostic = err.diagnostics().next().unwrap();
+    assert_eq!(diagnostic.kind(), DiagnosticKind::EndOfFile);
+    assert!(diagnostic.span().is_empty());
+    assert!(diagnostic.expected().unwrap().contains("an identifier"));
+}
+
+#[test]
+fn end_of_file_expected() {
+    let parser = |input: ParseStream<'_>| {
+        int(input)?;
+        let _: Punct![";"] = input.parse()?;
+        Ok(())
+    };
+    let err = parser.parse(scan("1")).unwrap_err();
+    let diagnostic = err.diagnostics().next().unwrap();
+    assert_eq!(diagnostic.kind(), crate::error::DiagnosticKind::EndOfFile);
+    assert!(diagnostic.expected().unwrap().contains("';'"));
+    assert_eq!(diagnostic.span().start_location(), (1, 2));
+    assert!(err.to_string().contains("';'"), "{err}");
+}
+
+#[test]
+fn error_is_send_sync() {
+    fn boxed(err: crate::error::Error) -> Box<dyn std::error::Error + Send + Sync> {
+        Box::new(err)
+    }
+
+    let err = name.parse(scan("+")).unwrap_err();
+    let message = err.to_string();
+    let boxed = std::thread::spawn(move || boxed(err)).join().unwrap();
+    assert_eq!(boxed.to_string(), message);
+}
+
+#[test]
+fn tokens_are_send_sync() {
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    assert_send_sync::<crate::TokenStream>();
+    assert_send_sync::<crate::SourceFile>();
+    assert_send_sync::<crate::Span>();
+    assert_send_sync::<crate::group::Group<crate::group::Parentheses>>();
+    assert_send_sync::<crate::punctuated::Punctuated<Ident, Punct![","]>>();
+    assert_send_sync::<Ident>();
+    assert_send_sync::<LitInt>();
+}
+
+#[test]
+fn payloads() {
+    struct Symbol(&'static str);
+
+    let payload = |input: ParseStream<'_>| {
+        let ident: Ident = input.parse()?;
+        let mut err = input.new_error("First".to_string(), &ident, 0);
+        err.add(input.new_error("Second".to_string(), &ident, 0));
+        err.insert(Symbol("second"));
+        err.insert(7u32);
+        Err::<(), _>(err)
+    };
+    let err = payload.parse(scan("x")).unwrap_err();
+    assert_eq!(err.get::<Symbol>().unwrap().0, "second");
+    assert_eq!(err.get::<u32>(), Some(&7));
+    assert!(err.get::<u64>().is_none());
+    let diagnostics: Vec<_> = err.diagnostics().collect();
+    assert!(diagnostics[0].get::<Symbol>().is_none());
+    assert_eq!(diagnostics[1].get::<Symbol>().unwrap().0, "second");
+}
+
+#[test]
+fn first_span() {
+    let err = (|input: ParseStream<'_>| {
+        int(input)?;
+        name(input)
+    })
+    .parse(scan("1+"))
+    .unwrap_err();
+    let span = err.first_span().unwrap();
+    assert_eq!(span.start_location(), (1, 2));
+    assert!(!span.is_empty());
+
+    let span = name.parse(scan("")).unwrap_err().first_span().unwrap();
+    assert!(span.is_empty());
+}
+
+#[test]
+fn combine() {
+    use crate::error::Error;
+
+    let errors = |input: ParseStream<'_>| {
+        let ident: Ident = input.parse()?;
+        let errors = vec![
+            input.new_error("First".to_string(), &ident, 0),
+            input.new_error("Second".to_string(), &ident, 0),
+        ];
+        let mut error: Error = errors.into_iter().collect();
+        error.extend([input.new_error("Third".to_string(), &ident, 0)]);
+        assert!(Error::combine(vec![]).is_none());
+        Err::<(), _>(Error::combine([error]).unwrap())
+    };
+    let message = errors.parse(scan("x")).unwrap_err().to_string();
+    assert_eq!(message.matches("Error:").count(), 3, "{message}");
+}
+
+#[test]
+fn eprint_and_count() {
+    let err = name.parse(scan("+")).unwrap_err();
+    assert_eq!(err.eprint_and_count().unwrap(), 1);
+}
+
+#[test]
+fn parse_file() {
+    use crate::error::DiagnosticKind;
+    use crate::parse_file;
+    use std::fs;
+
+    let path = std::env::temp_dir().join("flexi-parse-parse-file-test");
+    fs::write(&path, "hello").unwrap();
+    let ident: Ident = parse_file(path.clone()).unwrap();
+    assert_eq!(ident.string(), "hello");
+    fs::remove_file(&path).unwrap();
+
+    let err = parse_file::<Ident>(path.clone()).unwrap_err();
+    let diagnostic = err.diagnostics().next().unwrap();
+    assert_eq!(diagnostic.kind(), DiagnosticKind::Io);
+    assert_eq!(diagnostic.span().source_file().path(), path.to_str());
+    assert!(err.to_string().contains("Failed to read file"), "{err}");
+    #[cfg(feature = "ariadne")]
+    assert!(err.to_report_string().contains("Failed to read file"));
+}
+
+#[test]
+fn parse_reader_lines() {
+    use crate::parse_reader_lines;
+
+    let mut names = vec![];
+    parse_reader_lines("a\r\n\n  b  \nc".as_bytes(), |ident: Ident| {
+        names.push(ident.string().clone());
+        Ok(())
+    })
+    .unwrap();
+    assert_eq!(names, ["a", "b", "c"]);
+
+    let mut count = 0;
+    let err = parse_reader_lines("1\nx\n2\ny\n3\n".as_bytes(), |_: LitInt| {
+        count += 1;
+        Ok(())
+    })
+    .unwrap_err();
+    assert_eq!(count, 3);
+    let spans: Vec<_> = err
+        .diagnostics()
+        .map(|diagnostic| diagnostic.span())
+        .collect();
+    assert_eq!(spans.len(), 2);
+    assert_eq!(spans[0].source_file().name(), "<input>:2");
+    assert_eq!(spans[1].source_file().name(), "<input>:4");
+}
+
+#[test]
+fn parse_reader_lines_error_limit() {
+    use crate::parse_reader_lines_with;
+
+    let input = "x\n".repeat(5);
+    let options = ParseOptions::new().error_limit(3);
+    let err = parse_reader_lines_with(input.as_bytes(), options, |_: LitInt| Ok(())).unwrap_err();
+    assert_eq!(err.diagnostics().count(), 3);
+    let message = err.to_string();
+    assert!(
+        message.ends_with("Note: 2 more errors not shown"),
+        "{message}"
+    );
+}
@@ -0,0 +1,43 @@
+use crate::token::Ident;
+use crate::token::LitInt;
+use crate::ParseStream;
+use crate::Parser;
+use crate::Result;
+use crate::Spanned;
+use crate::TokenStream;
+
+use proc_macro2::TokenStream as TokenStream2;
+
+use super::scan;
+
+#[test]
+fn from_proc_macro2() {
+    let rust: TokenStream2 = "total += f(1)".parse().unwrap();
+    let tokens = TokenStream::from(rust);
+    let (name, plus_equals, int) = (|input: ParseStream<'_>| -> Result<_> {
+        let name: Ident = input.parse()?;
+        let plus_equals: Punct!["+="] = input.parse()?;
+        let _: Ident = input.parse()?;
+        let _: Punct!["("] = input.parse()?;
+        let int: LitInt = input.parse()?;
+        Ok((name, plus_equals, int))
+    })
+    .parse(tokens)
+    .unwrap();
+    assert_eq!(name.span().source_text(), "total");
+    assert_eq!(plus_equals.span().source_text(), "+=");
+    assert_eq!(int.span().source_text(), "1");
+}
+
+#[test]
+fn to_proc_macro2() {
+    let rust: TokenStream2 = "let x = [a, 'b', \"c\"];".parse().unwrap();
+    let round_trip = TokenStream2::try_from(TokenStream::from(rust.clone())).unwrap();
+    assert_eq!(round_trip.to_string(), rust.to_string());
+
+    let tokens = scan("x = 1");
+    assert_eq!(TokenStream2::try_from(tokens).unwrap().to_string(), "x = 1");
+
+    let tokens = scan("\"unterminated");
+    assert!(TokenStream2::try_from(tokens).is_err());
+}
@@ -1,5 +1,14 @@
 use crate::parse;
-use crate::Punct;
+use crate::parse_repeated;
+use crate::punctuated::Pair;
+use crate::punctuated::Path;
+use crate::punctuated::Punctuated;
+use crate::punctuated::Terminated;
+use crate::recover::Recover;
+use crate::token::Ident;
+use crate::ParseStream;
+use crate::Parser;
+use crate::Spanned;
 
 use super::scan;
 
@@ -8,6 +17,11 @@ fn multi_part() {
     parse::<Punct!["++", "-", "$"]>(scan("++-$")).unwrap();
 }
 
+#[test]
+fn multi_part_alone_delimiter() {
+    parse::<Punct!["(", "*"]>(scan("( *")).unwrap();
+}
+
 #[test]
 fn joint() {
     parse::<Punct!["<="]>(scan("<=")).unwrap();
@@ -17,3 +31,209 @@ fn joint() {
 fn joint_fail() {
     parse::<Punct!["+="]>(scan("+ =")).unwrap_err();
 }
+
+#[test]
+fn terminated_with() {
+    let mut tokens = scan("a; bc; def;");
+    tokens.remove_whitespace();
+    let lengths = (|input: ParseStream<'_>| {
+        Punctuated::<usize, Punct![";"]>::parse_terminated_with(input, |input| {
+            Ok(input.parse::<Ident>()?.string().len())
+        })
+    })
+    .parse(tokens)
+    .unwrap();
+    assert_eq!(lengths.into_iter().collect::<Vec<_>>(), [1, 2, 3]);
+}
+
+#[test]
+fn pairs() {
+    let mut tokens = scan("a, b, c");
+    tokens.remove_whitespace();
+    let mut idents = Punctuated::<Ident, Punct![","]>::parse_separated
+        .parse(tokens)
+        .unwrap();
+    for mut pair in idents.pairs_mut() {
+        let span = pair.value().span.clone();
+        if let Some(comma) = pair.punct_mut() {
+            comma.span = span;
+        }
+    }
+    let pairs: Vec<_> = idents.into_pairs().collect();
+    assert_eq!(pairs.len(), 3);
+    assert_eq!(pairs[0].punct().unwrap().span.start, 0);
+    assert!(pairs[2].punct().is_none());
+    assert_eq!(pairs[2].value().string(), "c");
+}
+
+#[test]
+fn span() {
+    let mut tokens = scan("a, b,");
+    tokens.remove_whitespace();
+    let idents = Punctuated::<Ident, Punct![","]>::parse_terminated
+        .parse(tokens)
+        .unwrap();
+    let span = idents.span().unwrap();
+    assert_eq!((span.start, span.end), (0, 5));
+}
+
+#[test]
+fn bounded() {
+    let bounded = |min, max| {
+        move |input: ParseStream<'_>| {
+            Punctuated::<Ident, Punct![","]>::parse_separated_bounded(input, min, max)
+        }
+    };
+    let mut tokens = scan("a, b, c");
+    tokens.remove_whitespace();
+    assert_eq!(bounded(1, 3).parse(tokens.clone()).unwrap().iter().len(), 3);
+    let err = bounded(0, 2).parse(tokens.clone()).unwrap_err();
+    assert!(err.to_string().contains("at most 2"), "{err}");
+    let err = bounded(4, 5).parse(tokens).unwrap_err();
+    assert!(err.to_string().contains("at least 4"), "{err}");
+    bounded(0, 2).parse(scan("")).unwrap();
+}
+
+#[test]
+fn leading() {
+    let mut tokens = scan("| a | b");
+    tokens.remove_whitespace();
+    let arms = Punctuated::<Ident, Punct!["|"]>::parse_separated_allow_leading
+        .parse(tokens)
+        .unwrap();
+    assert!(arms.leading_punct().is_some());
+    assert_eq!(arms.iter().len(), 2);
+    let span = arms.span().unwrap();
+    assert_eq!((span.start, span.end), (0, 7));
+}
+
+#[test]
+fn display() {
+    let mut tokens = scan("a ,b,  c");
+    tokens.remove_whitespace();
+    let idents = Punctuated::<Ident, Punct![","]>::parse_separated
+        .parse(tokens)
+        .unwrap();
+    assert_eq!(idents.to_string(), "a, b, c");
+}
+
+#[test]
+fn recovering() {
+    let mut tokens = scan("a, +, b, - -, c");
+    tokens.remove_whitespace();
+    let (idents, errors) = (|input: ParseStream<'_>| {
+        Ok(Punctuated::<Recover<Ident>, Punct![","]>::parse_separated_recovering(input))
+    })
+    .parse(tokens)
+    .unwrap();
+    let separators = idents
+        .pairs()
+        .filter(|pair| matches!(pair, Pair::Punctuated(..)))
+        .count();
+    assert_eq!(separators, 4);
+    let names: Vec<_> = idents
+        .into_iter()
+        .filter_map(Recover::ok)
+        .map(|ident| ident.string().clone())
+        .collect();
+    assert_eq!(names, ["a", "b", "c"]);
+    assert_eq!(errors.unwrap().to_string().matches("Error:").count(), 2);
+}
+
+#[test]
+fn recovering_groups() {
+    let mut tokens = scan("a, 1(b, c), d");
+    tokens.remove_whitespace();
+    let (idents, errors) = (|input: ParseStream<'_>| {
+        Ok(Punctuated::<Recover<Ident>, Punct![","]>::parse_separated_recovering(input))
+    })
+    .parse(tokens)
+    .unwrap();
+    let values: Vec<_> = idents.iter().collect();
+    assert!(matches!(values[0], Recover::Ok(_)));
+    let Recover::Error(span) = values[1] else {
+        panic!("{values:?}")
+    };
+    assert_eq!(span.source_text(), "1(b, c)");
+    assert!(matches!(values[2], Recover::Ok(_)));
+    assert_eq!(values.len(), 3);
+    assert_eq!(errors.unwrap().to_string().matches("Error:").count(), 1);
+}
+
+#[test]
+fn until() {
+    let mut tokens = scan("a, b, -> c");
+    tokens.remove_whitespace();
+    let (idents, rest) = (|input: ParseStream<'_>| {
+        let idents = Punctuated::<Ident, Punct![","]>::parse_separated_until(input, Punct!["->"])?;
+        let _: Punct!["->"] = input.parse()?;
+        Ok((idents, input.parse::<Ident>()?))
+    })
+    .parse(tokens)
+    .unwrap();
+    assert_eq!(idents.iter().len(), 2);
+    assert_eq!(rest.string(), "c");
+
+    let mut tokens = scan("a b -> c");
+    tokens.remove_whitespace();
+    let err = (|input: ParseStream<'_>| {
+        Punctuated::<Ident, Punct![","]>::parse_separated_until(input, Punct!["->"])
+    })
+    .parse(tokens)
+    .unwrap_err();
+    assert!(err.to_string().contains("->"), "{err}");
+}
+
+#[test]
+fn terminated() {
+    let mut tokens = scan("a ; b");
+    tokens.remove_whitespace();
+    parse_repeated::<Terminated<Ident, Punct![";"]>>
+        .parse(tokens)
+        .unwrap_err();
+
+    let mut tokens = scan("a ; b;");
+    tokens.remove_whitespace();
+    let stmts = parse_repeated::<Terminated<Ident, Punct![";"]>>
+        .parse(tokens)
+        .unwrap();
+    assert_eq!(stmts.len(), 2);
+    let span = stmts[1].span();
+    assert_eq!((span.start, span.end), (4, 6));
+}
+
+#[test]
+fn path() {
+    let path: Path = parse(scan("a.bc.d")).unwrap();
+    let names: Vec<_> = path.segments().map(Ident::string).collect();
+    assert_eq!(names, ["a", "bc", "d"]);
+    assert_eq!(path.to_string(), "a.bc.d");
+    assert_eq!((path.span().start, path.span().end), (0, 6));
+    parse::<Path>(scan("a.")).unwrap_err();
+}
+
+#[test]
+fn peek() {
+    let peeks = |input: ParseStream<'_>| {
+        Ok((
+            input.peek(Punct!["<="]),
+            input.peek(Punct!["<"]),
+            input.peek(Punct!["<<="]),
+            input.peek(Ident),
+        ))
+    };
+    assert_eq!(peeks.parse(scan("<=")).unwrap(), (true, true, false, false));
+    assert_eq!(
+        peeks.parse(scan("< =")).unwrap(),
+        (false, true, false, false)
+    );
+    assert_eq!(
+        peeks.parse(scan("<<=")).unwrap(),
+        (false, true, true, false)
+    );
+    assert_eq!(peeks.parse(scan("x")).unwrap(), (false, false, false, true));
+    assert_eq!(
+        peeks.parse(scan("1")).unwrap(),
+        (false, false, false, false)
+    );
+}
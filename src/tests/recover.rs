@@ -0,0 +1,45 @@
+use crate::recover::Recover;
+use crate::token::LitInt;
+use crate::ParseStream;
+use crate::Parser;
+
+use super::scan;
+
+#[test]
+fn recover() {
+    let (values, err) = (|input: ParseStream<'_>| {
+        let mut values = vec![];
+        while !input.is_empty() {
+            let value = input.recover::<LitInt, _>(|input| input.peek(Punct![","]));
+            if !value.is_error() && !input.is_empty() {
+                let _: Punct![","] = input.parse()?;
+            }
+            values.push(value);
+        }
+        Ok((values, input.get_error()))
+    })
+    .parse(scan("1, x y, 2"))
+    .unwrap();
+    assert_eq!(values.len(), 3);
+    assert!(values[1].is_error());
+    assert_eq!(err.unwrap().diagnostics().count(), 1);
+}
+
+#[test]
+fn default_boundary() {
+    let (values, err) = (|input: ParseStream<'_>| {
+        let mut values: Vec<Recover<LitInt>> = vec![];
+        while !input.is_empty() {
+            values.push(input.parse()?);
+        }
+        Ok((values, input.get_error()))
+    })
+    .parse(scan("1 x 2"))
+    .unwrap();
+    let values: Vec<_> = values
+        .into_iter()
+        .map(|value| value.ok().map(|value| value.value()))
+        .collect();
+    assert_eq!(values, [Some(1), None, Some(2)]);
+    assert_eq!(err.unwrap().diagnostics().count(), 1);
+}
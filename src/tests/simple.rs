@@ -5,12 +5,20 @@ use crate::token::LitFloat;
 use crate::token::LitInt;
 use crate::token::LitStrDoubleQuote;
 use crate::token::LitStrSingleQuote;
+use crate::token::Symbol;
+use crate::ColumnUnit;
 use crate::ParseStream;
 use crate::Parser;
 use crate::Result;
+use crate::SourceFile;
+use crate::Spanned;
+use crate::TokenStream;
 
 use super::scan;
 
+use std::ptr;
+use std::sync::Arc;
+
 #[test]
 fn sequential_idents() {
     fn sequential_idents_inner(input: ParseStream<'_>) -> Result<(Ident, Ident)> {
@@ -40,3 +48,224 @@ fn literals() {
     let s2: LitStrSingleQuote = parse(scan("'Hello, world!'")).unwrap();
     assert_eq!(s2.string(), "Hello, world!");
 }
+
+#[test]
+fn span_location() {
+    let mut tokens = scan("hello\n  world");
+    tokens.remove_whitespace();
+    let (_, ident) = (|input: ParseStream<'_>| {
+        let first: Ident = input.parse()?;
+        let second: Ident = input.parse()?;
+        Ok((first, second))
+    })
+    .parse(tokens)
+    .unwrap();
+    let span = Spanned::span(&ident);
+    assert_eq!(span.start_location(), (2, 3));
+    assert_eq!(span.end_location(), (2, 8));
+    assert_eq!(span.start_location_in(ColumnUnit::Byte), (2, 3));
+}
+
+#[cfg(feature = "unicode-segmentation")]
+#[test]
+fn grapheme_columns() {
+    let text = "e\u{301}\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}\u{1F1EC}\u{1F1E7}\u{1F1EB}\u{1F1F7}\u{270B}\u{1F3FD}x";
+    let source = Arc::new(SourceFile::new("test data".to_string(), text.to_string()));
+    let span = crate::Span::new(text.len() - 1, text.len(), source);
+    assert_eq!(span.start_location_in(ColumnUnit::Char), (1, 14));
+    assert_eq!(span.start_location_in(ColumnUnit::Grapheme), (1, 6));
+}
+
+#[test]
+fn source_text() {
+    let s: LitStrDoubleQuote = parse(scan("\"a  b\"")).unwrap();
+    let span = Spanned::span(&s);
+    assert_eq!(span.source_text(), "\"a  b\"");
+    assert_eq!(span.source_file().name(), "test data");
+    assert_eq!(span.source_file().contents(), "\"a  b\"");
+}
+
+#[test]
+fn line_index() {
+    use crate::SourceFile;
+    use crate::Span;
+    use std::sync::Arc;
+
+    let source = Arc::new(SourceFile::new(
+        "test data".to_string(),
+        "ab\n\ncd\n".to_string(),
+    ));
+    let location = |offset| Span::new(offset, offset, Arc::clone(&source)).start_location();
+    assert_eq!(location(0), (1, 1));
+    assert_eq!(location(2), (1, 3));
+    assert_eq!(location(3), (2, 1));
+    assert_eq!(location(5), (3, 2));
+    assert_eq!(location(7), (4, 1));
+}
+
+#[test]
+fn subspans() {
+    let mut tokens = scan("'hi' + x");
+    tokens.remove_whitespace();
+    let (string, ident) = (|input: ParseStream<'_>| {
+        let string: LitStrSingleQuote = input.parse()?;
+        let _: Punct!["+"] = input.parse()?;
+        let ident: Ident = input.parse()?;
+        Ok((string, ident))
+    })
+    .parse(tokens)
+    .unwrap();
+    let string = Spanned::span(&string);
+    let ident = Spanned::span(&ident);
+    assert_eq!(string.first_char().source_text(), "'");
+    assert_eq!(string.last_char().source_text(), "'");
+    assert_eq!(string.subspan(1..3).source_text(), "hi");
+    assert_eq!(crate::Span::between(&string, &ident).source_text(), " + ");
+    assert!(crate::Span::between(&ident, &string).is_empty());
+}
+
+#[test]
+fn interned_idents() {
+    let (a, b, c) = (|input: ParseStream<'_>| {
+        Ok((
+            input.parse::<Ident>()?,
+            input.parse::<Ident>()?,
+            input.parse::<Ident>()?,
+        ))
+    })
+    .parse(scan("x y x"))
+    .unwrap();
+    assert_eq!(a, c);
+    assert_eq!(a.symbol(), c.symbol());
+    assert_ne!(a.symbol(), b.symbol());
+    assert!(ptr::eq(a.string(), c.string()));
+    assert_eq!(*a.symbol(), Symbol::new("x"));
+
+    let other: Ident = parse(scan("x")).unwrap();
+    assert_eq!(a, other);
+    assert!(!ptr::eq(a.string(), other.string()));
+}
+
+#[test]
+fn scan_unicode() {
+    let (a, pound, b) = (|input: ParseStream<'_>| {
+        Ok((
+            input.parse::<Ident>()?,
+            input.parse::<Punct!["£"]>()?,
+            input.parse::<Ident>()?,
+        ))
+    })
+    .parse(scan("café £ naïve "))
+    .unwrap();
+    assert_eq!(a.string(), "café");
+    assert_eq!(pound.span().source_text(), "£");
+    assert_eq!(b.string(), "naïve");
+
+    let source = SourceFile::new("test data".to_string(), "x ✓".to_string());
+    let err = TokenStream::try_from(Arc::new(source)).unwrap_err();
+    assert_eq!(err.diagnostics().next().unwrap().span().source_text(), "✓");
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serialize_token_stream() {
+    use crate::parse;
+
+    let tokens = scan("let x  =\n\t'a';");
+    let json = serde_json::to_string(&tokens).unwrap();
+    let cached: TokenStream = serde_json::from_str(&json).unwrap();
+    assert_eq!(cached.to_string(), tokens.to_string());
+    assert_eq!(cached.source.content_hash(), tokens.source.content_hash());
+    let ident: Ident = parse(cached).unwrap();
+    assert_eq!(ident.string(), "let");
+    assert_eq!(ident.span().source_file().name(), "test data");
+
+    let changed = SourceFile::new("test data".to_string(), "let x =\n\t'a';".to_string());
+    assert_ne!(changed.content_hash(), tokens.source.content_hash());
+    let invalid = json.replace("\"end\":3", "\"end\":100");
+    assert!(serde_json::from_str::<TokenStream>(&invalid).is_err());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serialize_tokens() {
+    use crate::group::Group;
+    use crate::group::Parentheses;
+
+    let ident: Ident = parse(scan("abc")).unwrap();
+    let json = serde_json::to_string(&ident).unwrap();
+    assert!(
+        json.starts_with(r#"{"value":"abc","text":"abc","span":{"file":"test data","#),
+        "{json}"
+    );
+    let ident: Ident = serde_json::from_str(&json).unwrap();
+    assert_eq!(ident.string(), "abc");
+    assert_eq!(ident.span().source_text(), "abc");
+    assert_eq!(ident.span().source_file().name(), "test data");
+
+    let int: LitInt = parse(scan("007")).unwrap();
+    let json = serde_json::to_string(&int).unwrap();
+    assert!(json.starts_with(r#"{"value":7,"text":"007""#), "{json}");
+    let int: LitInt = serde_json::from_str(&json).unwrap();
+    assert_eq!(int.value(), 7);
+
+    let string: LitStrDoubleQuote = parse(scan("\"a b\"")).unwrap();
+    let json = serde_json::to_string(&string).unwrap();
+    let string: LitStrDoubleQuote = serde_json::from_str(&json).unwrap();
+    assert_eq!(string.string(), "a b");
+
+    let mut tokens = scan("(x + 1)");
+    tokens.remove_whitespace();
+    let group: Group<Parentheses> = parse(tokens).unwrap();
+    let json = serde_json::to_string(&group).unwrap();
+    let group: Group<Parentheses> = serde_json::from_str(&json).unwrap();
+    assert_eq!(group.span().source_text(), "(x + 1)");
+    assert_eq!(group.into_token_stream().to_string(), "x + 1");
+
+    let invalid = json.replace(r#""file":"test data""#, r#""file":"other""#);
+    assert!(serde_json::from_str::<Group<Parentheses>>(&invalid).is_err());
+}
+
+#[test]
+fn binary_token_stream() {
+    use crate::parse;
+    use std::io;
+
+    let mut tokens = scan("fn f(x) {\r\n\tx + 1 }");
+    tokens.remove_blank_space();
+    let bytes = tokens.to_bytes();
+    let decoded = TokenStream::from_bytes(&bytes).unwrap();
+    assert_eq!(decoded.to_string(), tokens.to_string());
+    assert_eq!(decoded.to_bytes(), bytes);
+    let ident: Ident = parse(decoded).unwrap();
+    assert_eq!(ident.string(), "fn");
+
+    let mut other_version = bytes.clone();
+    other_version[4] += 1;
+    let err = TokenStream::from_bytes(&other_version).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert!(err.to_string().contains("version"), "{err}");
+    assert!(TokenStream::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    assert!(TokenStream::from_bytes(b"FPTS").is_err());
+}
+
+#[test]
+fn source_file_limit() {
+    use crate::error::DiagnosticKind;
+    use crate::ToTokens;
+
+    let mut tokens = scan("a");
+    let file = |name: String| Arc::new(SourceFile::new(name, "b".to_string()));
+    tokens
+        .sources
+        .extend((1..=usize::from(u16::MAX)).map(|i| file(i.to_string())));
+    let ident: Ident = parse(TokenStream::try_from(file("last".to_string())).unwrap()).unwrap();
+    ident.to_tokens(&mut tokens);
+    ident.to_tokens(&mut tokens);
+    assert_eq!(tokens.to_string(), "a");
+
+    let err = parse::<Ident>(tokens).unwrap_err();
+    let diagnostic = err.diagnostics().next().unwrap();
+    assert_eq!(diagnostic.kind(), DiagnosticKind::LimitExceeded);
+    assert_eq!(diagnostic.span().source_file().name(), "last");
+}
@@ -0,0 +1,113 @@
+use crate::error::Error;
+use crate::source_map::SourceMap;
+use crate::token::Ident;
+use crate::SourceFile;
+use crate::Spanned;
+
+#[test]
+fn global_offsets() {
+    let mut map = SourceMap::new();
+    let a = map.add(SourceFile::new("a".to_string(), "x".to_string()));
+    let b = map.add(SourceFile::new("b".to_string(), "  y".to_string()));
+    assert_eq!(map.len(), 2);
+
+    let y: Ident = map.parse(b).unwrap().unwrap();
+    let offset = map.global_offset(&y.span()).unwrap();
+    assert_eq!(offset, 4);
+    let span = map.lookup(offset).unwrap();
+    assert_eq!(map.file_id(&span), Some(b));
+    assert_eq!(span.start_location(), (1, 3));
+
+    assert_eq!(map.file_id(&map.lookup(1).unwrap()), Some(a));
+    assert!(map.lookup(6).is_none());
+    let shared = map.get(a).unwrap().clone();
+    assert_eq!(map.add_shared(shared), a);
+}
+
+#[test]
+fn unknown_file() {
+    let mut map = SourceMap::new();
+    map.add(SourceFile::new("a".to_string(), "x".to_string()));
+    let mut other = SourceMap::new();
+    other.add(SourceFile::new("a".to_string(), "x".to_string()));
+    let id = other.add(SourceFile::new("b".to_string(), "y".to_string()));
+    assert!(map.parse::<Ident>(id).is_none());
+}
+
+#[test]
+fn sort() {
+    let mut map = SourceMap::new();
+    let z = map.add(SourceFile::new("z".to_string(), "1".to_string()));
+    let a = map.add(SourceFile::new("a".to_string(), "2".to_string()));
+    let err = map.parse::<Ident>(a).unwrap().unwrap_err();
+    let err = err.with(map.parse::<Ident>(z).unwrap().unwrap_err());
+
+    let sorted = map.sort(err.clone());
+    let first = sorted.diagnostics().next().unwrap().span();
+    assert_eq!(map.file_id(&first), Some(z));
+    let first = Error::sorted(err).diagnostics().next().unwrap().span();
+    assert_eq!(map.file_id(&first), Some(a));
+}
+
+#[test]
+fn virtual_source() {
+    use crate::ParseStream;
+    use crate::Parser;
+    use crate::Span;
+    use crate::TokenStream;
+    use std::sync::Arc;
+
+    let original = Arc::new(SourceFile::new("main".to_string(), "1 + y".to_string()));
+    let generated = Arc::new(SourceFile::new_virtual(
+        "generated".to_string(),
+        "(1 + y) 1".to_string(),
+        vec![(1..6, Span::new(0, 5, Arc::clone(&original)))],
+    ));
+
+    let mut tokens = TokenStream::try_from(Arc::clone(&generated)).unwrap();
+    tokens.remove_whitespace();
+    let err = (|input: ParseStream<'_>| {
+        let _: Punct!["("] = input.parse()?;
+        input.parse::<Ident>()
+    })
+    .parse(tokens)
+    .unwrap_err()
+    .into_origin();
+    let span = err.first_span().unwrap();
+    assert_eq!(span.source_file().name(), "main");
+    assert_eq!(span.source_text(), "1");
+
+    let span = Span::new(8, 9, Arc::clone(&generated));
+    assert_eq!(span.origin(), span);
+}
+
+#[test]
+fn parse_all() {
+    use std::env;
+    use std::fs;
+
+    let dir = env::temp_dir().join("flexi-parse-parse-all-test");
+    fs::create_dir_all(&dir).unwrap();
+    let paths: Vec<_> = ["a", "b", "c", "missing"]
+        .iter()
+        .map(|name| dir.join(name))
+        .collect();
+    fs::write(&paths[0], "x").unwrap();
+    fs::write(&paths[1], "\n\n1").unwrap();
+    fs::write(&paths[2], "1").unwrap();
+
+    let mut map = SourceMap::new();
+    let (results, err) = map.parse_all::<Ident>(paths.clone());
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(map.len(), 3);
+    assert_eq!(results.len(), 4);
+    assert_eq!(results[0].as_ref().unwrap().string(), "x");
+    assert!(results[1].is_err() && results[2].is_err() && results[3].is_err());
+
+    let err = err.unwrap();
+    let files: Vec<_> = err
+        .diagnostics()
+        .map(|diagnostic| map.file_id(&diagnostic.span()).map(|id| id.index()))
+        .collect();
+    assert_eq!(files, [Some(1), Some(2), None]);
+}
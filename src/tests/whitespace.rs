@@ -1,3 +1,4 @@
+use crate::parse_lines;
 use crate::token::Ident;
 use crate::token::NewLine;
 use crate::token::Space2;
@@ -17,7 +18,11 @@ fn space2() {
         let _: NewLine = input.parse().unwrap();
         let _: Space2 = input.parse().unwrap();
         let still_testing: Ident = input.parse().unwrap();
-        Ok((test.string, testing.string, still_testing.string))
+        Ok((
+            test.string().clone(),
+            testing.string().clone(),
+            still_testing.string().clone(),
+        ))
     }
 
     // Three spaces after 'test'.
@@ -38,7 +43,11 @@ fn space4() {
         let _: NewLine = input.parse().unwrap();
         let _: Space4 = input.parse().unwrap();
         let still_testing: Ident = input.parse().unwrap();
-        Ok((test.string, testing.string, still_testing.string))
+        Ok((
+            test.string().clone(),
+            testing.string().clone(),
+            still_testing.string().clone(),
+        ))
     }
 
     // Five spaces after 'test'.
@@ -62,7 +71,7 @@ fn skip() {
         let _: Space2 = input.parse().unwrap();
         input.skip_whitespace();
         let testing: Ident = input.parse().unwrap();
-        Ok((test.string, testing.string))
+        Ok((test.string().clone(), testing.string().clone()))
     }
 
     let tokens = scan("      test    \n      testing");
@@ -70,3 +79,31 @@ fn skip() {
     assert_eq!(test, "test");
     assert_eq!(testing, "testing");
 }
+
+#[test]
+fn lines() {
+    let mut tokens = scan("a\n\n  b\n+\nc d\r\ne\n");
+    tokens.remove_blank_space();
+    let (idents, errors) = (|input: ParseStream<'_>| Ok(parse_lines::<Ident>(input)))
+        .parse(tokens)
+        .unwrap();
+    let names: Vec<_> = idents.iter().map(Ident::string).collect();
+    assert_eq!(names, ["a", "b", "e"]);
+    assert_eq!(errors.unwrap().to_string().matches("Error:").count(), 2);
+}
+
+#[test]
+fn display_round_trip() {
+    let source = "a  (b,\n\tc) + d";
+    let mut tokens = scan(source);
+    assert_eq!(tokens.to_string(), source);
+    tokens.remove_whitespace();
+    assert_eq!(tokens.to_string(), source);
+}
+
+#[test]
+fn display_after_retain() {
+    let mut tokens = scan("a ; b;c  d");
+    tokens.retain(|input| !input.peek(Punct![";"]));
+    assert_eq!(tokens.to_string(), "a b c  d");
+}
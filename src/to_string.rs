@@ -1,36 +1,57 @@
+use crate::token::WhiteSpace;
 use crate::Entry;
+use crate::Span;
 use crate::TokenStream;
 
 use std::fmt;
+use std::sync::Arc;
 
 impl fmt::Display for TokenStream {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let mut last_token_end = usize::MAX;
-        let mut this_token_span;
-        for (_, token) in &self.tokens {
-            let string = match token {
-                Entry::Error(_) => return Err(fmt::Error),
-                Entry::Ident(ident) => {
-                    this_token_span = ident.span.clone();
-                    ident.string().to_owned()
-                }
-                Entry::Punct(punct) => {
-                    this_token_span = punct.span.clone();
-                    char::from(punct.kind).to_string()
-                }
-                Entry::WhiteSpace(whitespace) => {
-                    this_token_span = whitespace.span().clone();
-                    whitespace.display()
+        let mut last: Option<(usize, Span)> = None;
+        for &index in &self.tokens {
+            let index = index as usize;
+            let token = self.arena[index].unpack(&self.sources, &self.symbols);
+            if matches!(token, Entry::End) {
+                break;
+            }
+            let span = token.span().clone();
+            // Copy the text between tokens from the source, so that spacing
+            // and removed whitespace are reproduced exactly, unless other
+            // tokens were removed from between them.
+            if let Some((last_index, last_span)) = &last {
+                if !Arc::ptr_eq(&last_span.source, &span.source)
+                    || !self.only_whitespace(*last_index, index)
+                {
+                    f.write_str(" ")?;
+                } else if last_span.end <= span.start {
+                    f.write_str(&span.source.contents[last_span.end..span.start])?;
                 }
-                Entry::End => break,
-            };
-            for _ in last_token_end..this_token_span.start {
-                write!(f, " ")?;
             }
-            write!(f, "{string}")?;
-            last_token_end = this_token_span.end;
+            match &token {
+                Entry::Error(_) => return Err(fmt::Error),
+                Entry::Ident(ident) => f.write_str(ident.string())?,
+                Entry::Punct(punct) => write!(f, "{}", char::from(punct.kind))?,
+                Entry::WhiteSpace(WhiteSpace::Space2(_)) => f.write_str("  ")?,
+                Entry::WhiteSpace(WhiteSpace::Tab(_)) => f.write_str("\t")?,
+                Entry::WhiteSpace(WhiteSpace::NewLine(_)) => f.write_str("\n")?,
+                Entry::WhiteSpace(WhiteSpace::CarriageReturn(_)) => f.write_str("\r")?,
+                Entry::End => unreachable!(),
+            }
+            last = Some((index, span));
         }
 
         Ok(())
     }
 }
+
+impl TokenStream {
+    /// Returns true if every entry scanned between the arena entries `start`
+    /// and `end` is whitespace.
+    fn only_whitespace(&self, start: usize, end: usize) -> bool {
+        start < end
+            && self.arena[start + 1..end]
+                .iter()
+                .all(|entry| entry.kind.is_whitespace())
+    }
+}
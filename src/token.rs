@@ -12,18 +12,24 @@ use crate::group::DoubleQuotes;
 use crate::group::Group;
 use crate::group::SingleQuotes;
 use crate::private::Sealed;
+use crate::visit::leaves;
 use crate::Entry;
 use crate::Marker;
+use crate::PackedEntry;
+use crate::PackedKind;
 use crate::Parse;
 use crate::ParseStream;
 use crate::Result;
 use crate::Span;
+use crate::TokenStream;
 
+use std::borrow::Borrow;
 use std::cmp::Ordering;
-use std::collections::HashSet;
+use std::collections::HashMap;
 use std::fmt;
-use std::rc::Rc;
+use std::hash;
 use std::result;
+use std::sync::Arc;
 
 #[doc(hidden)]
 pub use concat_idents::concat_idents;
@@ -41,6 +47,97 @@ pub trait Token: Parse + Sealed {
 
     #[doc(hidden)]
     fn display() -> String;
+
+    /// Appends the tokens making up this token to `tokens`.
+    #[doc(hidden)]
+    fn append_tokens(&self, tokens: &mut TokenStream);
+
+    #[doc(hidden)]
+    fn peek_token(input: ParseStream<'_>) -> bool {
+        input.parse_undo::<Self>().is_ok()
+    }
+}
+
+/// Returns true if the next tokens are the punctuation `kinds`, with the first
+/// joined to the next, as checked when parsing multi-character punctuation.
+///
+/// Unlike parsing, this doesn't clone tokens or create errors.
+fn peek_punct(input: ParseStream<'_>, kinds: &[PunctKind]) -> bool {
+    kinds.iter().enumerate().all(|(i, kind)| {
+        matches!(
+            input.cursor.get_relative_packed(i as isize),
+            Some(PackedEntry { kind: PackedKind::Punct(punct, spacing), .. })
+                if punct == kind && (i > 0 || kinds.len() == 1 || *spacing == Spacing::Joint)
+        )
+    })
+}
+
+/// Returns the part of `span` from `start` to `end` bytes into it if `span` is
+/// `len` bytes long, or all of `span` otherwise, such as for a token created
+/// without being parsed.
+fn sub_span(span: &Span, len: usize, start: usize, end: usize) -> Span {
+    if span.end.checked_sub(span.start) == Some(len) {
+        Span::new(
+            span.start + start,
+            span.start + end,
+            Arc::clone(&span.source),
+        )
+    } else {
+        span.clone()
+    }
+}
+
+/// Appends the identifiers and punctuation making up `text` to `tokens`, with
+/// spans inside `span`.
+#[doc(hidden)]
+pub fn push_text(tokens: &mut TokenStream, text: &str, span: &Span) {
+    let mut chars = text.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        if let Ok(kind) = PunctKind::try_from(c) {
+            let spacing = if chars
+                .peek()
+                .is_some_and(|&(_, next)| PunctKind::try_from(next).is_ok())
+            {
+                Spacing::Joint
+            } else {
+                Spacing::Alone
+            };
+            tokens.push(Entry::Punct(SingleCharPunct {
+                kind,
+                spacing,
+                span: sub_span(span, text.len(), start, start + c.len_utf8()),
+            }));
+        } else if !c.is_whitespace() {
+            let mut end = start + c.len_utf8();
+            while let Some(&(index, c)) = chars.peek() {
+                if PunctKind::try_from(c).is_ok() || c.is_whitespace() {
+                    break;
+                }
+                end = index + c.len_utf8();
+                chars.next();
+            }
+            let span = sub_span(span, text.len(), start, end);
+            tokens.push(Entry::Ident(Ident::new(&text[start..end], span)));
+        }
+    }
+}
+
+/// Appends a literal made up of `contents` between two `quote`s to `tokens`.
+fn push_quoted(tokens: &mut TokenStream, quote: PunctKind, contents: &str, span: &Span) {
+    let len = contents.len() + 2;
+    let quote = |start| {
+        Entry::Punct(SingleCharPunct {
+            kind: quote,
+            spacing: Spacing::Alone,
+            span: sub_span(span, len, start, start + 1),
+        })
+    };
+    tokens.push(quote(0));
+    if !contents.is_empty() {
+        let span = sub_span(span, len, 1, len - 1);
+        tokens.push(Entry::Ident(Ident::new(contents, span)));
+    }
+    tokens.push(quote(len - 1));
 }
 
 impl<T: Token> Parse for Option<T> {
@@ -66,8 +163,8 @@ pub trait Punct: Token {
 /// See also [`DoubleQuotes`].
 #[derive(Debug, Clone)]
 pub struct LitStrDoubleQuote {
-    string: String,
-    span: Span,
+    pub(crate) string: String,
+    pub(crate) span: Span,
 }
 
 impl LitStrDoubleQuote {
@@ -123,6 +220,16 @@ impl Token for LitStrDoubleQuote {
     fn display() -> String {
         "a string literal".to_string()
     }
+
+    fn append_tokens(&self, tokens: &mut TokenStream) {
+        push_quoted(tokens, PunctKind::DoubleQuote, &self.string, &self.span);
+    }
+}
+
+impl crate::Spanned for LitStrDoubleQuote {
+    fn span(&self) -> Span {
+        Token::span(self).clone()
+    }
 }
 
 #[doc(hidden)]
@@ -136,8 +243,8 @@ pub fn LitStrDoubleQuote(marker: Marker) -> LitStrDoubleQuote {
 /// See also [`SingleQuotes`].
 #[derive(Debug, Clone)]
 pub struct LitStrSingleQuote {
-    string: String,
-    span: Span,
+    pub(crate) string: String,
+    pub(crate) span: Span,
 }
 
 impl LitStrSingleQuote {
@@ -193,6 +300,16 @@ impl Token for LitStrSingleQuote {
     fn display() -> String {
         "a string literal".to_string()
     }
+
+    fn append_tokens(&self, tokens: &mut TokenStream) {
+        push_quoted(tokens, PunctKind::SingleQuote, &self.string, &self.span);
+    }
+}
+
+impl crate::Spanned for LitStrSingleQuote {
+    fn span(&self) -> Span {
+        Token::span(self).clone()
+    }
 }
 
 #[doc(hidden)]
@@ -206,8 +323,8 @@ pub fn LitStrSingleQuote(marker: Marker) -> LitStrSingleQuote {
 /// See also [`SingleQuotes`].
 #[derive(Debug, Clone)]
 pub struct LitChar {
-    ch: char,
-    span: Span,
+    pub(crate) ch: char,
+    pub(crate) span: Span,
 }
 
 impl LitChar {
@@ -246,10 +363,7 @@ impl Parse for LitChar {
         let span = group.span;
         let string = group.token_stream.to_string();
         if string.len() != 1 {
-            return Err(Error::new(
-                Rc::clone(&input.source),
-                ErrorKind::LongChar(span),
-            ));
+            return Err(Error::new(input.source, ErrorKind::LongChar(span)));
         }
         let ch = string.chars().next().unwrap();
         Ok(LitChar { ch, span })
@@ -270,6 +384,21 @@ impl Token for LitChar {
     fn display() -> String {
         "a character literal".to_string()
     }
+
+    fn append_tokens(&self, tokens: &mut TokenStream) {
+        push_quoted(
+            tokens,
+            PunctKind::SingleQuote,
+            &self.ch.to_string(),
+            &self.span,
+        );
+    }
+}
+
+impl crate::Spanned for LitChar {
+    fn span(&self) -> Span {
+        Token::span(self).clone()
+    }
 }
 
 #[doc(hidden)]
@@ -278,29 +407,173 @@ pub fn LitChar(marker: Marker) -> LitChar {
     match marker {}
 }
 
+/// The text of an identifier.
+///
+/// Symbols are compared and hashed by their text, so they are suitable as keys
+/// in a symbol table. Each token stream stores the text of every distinct
+/// identifier in it once, and its identifiers share that text, so symbols from
+/// the same stream can usually be compared without reading it. The text is
+/// freed once nothing refers to it any more.
+///
+/// # Example
+/// ```
+/// # use flexi_parse::parse_string;
+/// # use flexi_parse::token::Ident;
+/// # use flexi_parse::token::Symbol;
+/// let ident: Ident = parse_string("name".to_string()).unwrap();
+/// assert_eq!(*ident.symbol(), Symbol::new("name"));
+/// assert_eq!(ident.symbol().string(), "name");
+/// ```
+#[derive(Clone)]
+pub struct Symbol(Arc<String>);
+
+impl Symbol {
+    /// Creates a symbol with the text `string`.
+    pub fn new(string: &str) -> Symbol {
+        Symbol(Arc::new(string.to_string()))
+    }
+
+    /// Returns the text of this symbol.
+    pub fn string(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq for Symbol {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0) || self.0 == other.0
+    }
+}
+
+impl Eq for Symbol {}
+
+impl PartialOrd for Symbol {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Symbol {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+
+impl hash::Hash for Symbol {
+    fn hash<H: hash::Hasher>(&self, state: &mut H) {
+        self.string().hash(state);
+    }
+}
+
+impl Borrow<str> for Symbol {
+    fn borrow(&self) -> &str {
+        self.string()
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.string(), f)
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.string())
+    }
+}
+
+/// The symbols of a token stream, each stored once and referred to by index.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Interner {
+    symbols: Vec<Symbol>,
+    indices: HashMap<Symbol, u32>,
+}
+
+impl Interner {
+    /// Returns the index of the symbol with the text `string`, adding it if
+    /// necessary.
+    ///
+    /// Returns `None` if the index of a new symbol wouldn't fit in a `u32`.
+    pub(crate) fn intern(&mut self, string: &str) -> Option<u32> {
+        match self.indices.get(string) {
+            Some(&index) => Some(index),
+            None => self.add(Symbol::new(string)),
+        }
+    }
+
+    /// Returns the index of the symbol with the same text as `symbol`, adding
+    /// it if necessary.
+    ///
+    /// Returns `None` if the index of a new symbol wouldn't fit in a `u32`.
+    pub(crate) fn insert(&mut self, symbol: &Symbol) -> Option<u32> {
+        match self.indices.get(symbol) {
+            Some(&index) => Some(index),
+            None => self.add(symbol.clone()),
+        }
+    }
+
+    fn add(&mut self, symbol: Symbol) -> Option<u32> {
+        let index = u32::try_from(self.symbols.len()).ok()?;
+        self.symbols.push(symbol.clone());
+        self.indices.insert(symbol, index);
+        Some(index)
+    }
+
+    /// Returns the symbol at `index`.
+    pub(crate) fn get(&self, index: u32) -> &Symbol {
+        &self.symbols[index as usize]
+    }
+}
+
+impl PartialEq for Interner {
+    fn eq(&self, other: &Self) -> bool {
+        self.symbols == other.symbols
+    }
+}
+
 /// An identifier consisting of alphanumeric characters and underscores, and
 /// starting with an alphabetic character or an underscore.
 #[derive(Debug, Clone)]
 pub struct Ident {
-    pub(crate) string: String,
+    symbol: Symbol,
     pub(crate) span: Span,
 }
 
 impl Ident {
+    pub(crate) fn new(string: &str, span: Span) -> Ident {
+        Ident::from_symbol(Symbol::new(string), span)
+    }
+
+    pub(crate) fn from_symbol(symbol: Symbol, span: Span) -> Ident {
+        Ident { symbol, span }
+    }
+
     /// Returns the text that makes up the identifier.
     pub fn string(&self) -> &String {
-        &self.string
+        &self.symbol.0
+    }
+
+    /// Returns the symbol for this identifier.
+    ///
+    /// Identifiers with the same text have equal symbols.
+    pub fn symbol(&self) -> &Symbol {
+        &self.symbol
     }
 
-    fn parse_simple(input: ParseStream<'_>) -> Result<Self> {
-        let token = input.next()?;
+    /// Parses any identifier token, including ones starting with a digit.
+    #[doc(hidden)]
+    pub fn parse_simple(input: ParseStream<'_>) -> Result<Self> {
+        let token = input
+            .next()
+            .map_err(|err| err.expected_at_end("an identifier"))?;
         if let Ok(ident) = Self::try_from(token.to_owned()) {
             Ok(ident)
         } else {
             Err(Error::new(
-                Rc::clone(&input.source),
+                input.source,
                 ErrorKind::UnexpectedToken {
-                    expected: HashSet::from_iter(["an identifier".to_string()]),
+                    expected: "an identifier".into(),
                     span: token.span().clone(),
                 },
             ))
@@ -308,9 +581,15 @@ impl Ident {
     }
 }
 
+impl fmt::Display for Ident {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.string())
+    }
+}
+
 impl PartialEq for Ident {
     fn eq(&self, other: &Self) -> bool {
-        self.string == other.string
+        self.symbol == other.symbol
     }
 }
 
@@ -331,11 +610,11 @@ impl TryFrom<Entry> for Ident {
 impl Parse for Ident {
     fn parse(input: ParseStream) -> Result<Self> {
         let ident = Self::parse_simple(input)?;
-        if ident.string.chars().next().unwrap().is_ascii_digit() {
+        if ident.string().chars().next().unwrap().is_ascii_digit() {
             Err(Error::new(
-                Rc::clone(&input.source),
+                input.source,
                 ErrorKind::UnexpectedToken {
-                    expected: HashSet::from_iter(["an identifier".to_string()]),
+                    expected: "an identifier".into(),
                     span: ident.span().clone(),
                 },
             ))
@@ -359,6 +638,24 @@ impl Token for Ident {
     fn display() -> String {
         "an identifier".to_string()
     }
+
+    fn peek_token(input: ParseStream<'_>) -> bool {
+        matches!(
+            input.cursor.get_relative_packed(0),
+            Some(PackedEntry { kind: PackedKind::Ident, symbol, .. })
+                if !input.cursor.symbols.get(*symbol).string().starts_with(|c: char| c.is_ascii_digit())
+        )
+    }
+
+    fn append_tokens(&self, tokens: &mut TokenStream) {
+        tokens.push(Entry::Ident(self.clone()));
+    }
+}
+
+impl crate::Spanned for Ident {
+    fn span(&self) -> Span {
+        Token::span(self).clone()
+    }
 }
 
 #[doc(hidden)]
@@ -419,8 +716,8 @@ impl From<Result<char>> for Spacing {
 /// respectively.
 #[derive(Debug, Clone)]
 pub struct LitInt {
-    value: u64,
-    span: Span,
+    pub(crate) value: u64,
+    pub(crate) span: Span,
 }
 
 impl LitInt {
@@ -432,16 +729,14 @@ impl LitInt {
     fn parse_decimal_impl(input: ParseStream<'_>) -> Result<Self> {
         let ident = Ident::parse_simple(input)?;
         Ok(LitInt {
-            value: ident.string.parse().map_err(|_| Error::empty())?,
+            value: ident.string().parse().map_err(|_| Error::empty())?,
             span: ident.span,
         })
     }
 
     /// Accepts a string of ascii digits.
     pub fn parse_decimal(input: ParseStream<'_>) -> Result<Self> {
-        Self::parse_decimal_impl(input).map_err(|_| {
-            input.unexpected_token(HashSet::from_iter(["an integer literal".to_string()]))
-        })
+        Self::parse_decimal_impl(input).map_err(|_| input.unexpected("an integer literal"))
     }
 
     fn parse_impl(input: ParseStream<'_>) -> Result<Self> {
@@ -452,12 +747,12 @@ impl LitInt {
             .unwrap()
             .try_into()
             .map_err(|_| Error::empty())?;
-        if start == 0 && ident.string.len() >= 3 {
+        if start == 0 && ident.string().len() >= 3 {
             let ch = chars.next().unwrap();
             let result = match ch {
-                'b' | 'B' => Some(u64::from_str_radix(&ident.string[2..], 2)),
-                'o' | 'O' => Some(u64::from_str_radix(&ident.string[2..], 8)),
-                'x' | 'X' => Some(u64::from_str_radix(&ident.string[2..], 16)),
+                'b' | 'B' => Some(u64::from_str_radix(&ident.string()[2..], 2)),
+                'o' | 'O' => Some(u64::from_str_radix(&ident.string()[2..], 8)),
+                'x' | 'X' => Some(u64::from_str_radix(&ident.string()[2..], 16)),
                 _ => None,
             };
             if let Some(result) = result {
@@ -469,7 +764,7 @@ impl LitInt {
         }
 
         Ok(LitInt {
-            value: ident.string.parse().map_err(|_| Error::empty())?,
+            value: ident.string().parse().map_err(|_| Error::empty())?,
             span: ident.span,
         })
     }
@@ -500,7 +795,7 @@ impl Parse for LitInt {
         if let Ok(lit) = Self::parse_impl(input) {
             Ok(lit)
         } else {
-            Err(input.unexpected_token(HashSet::from_iter(["an integer literal".to_string()])))
+            Err(input.unexpected("an integer literal"))
         }
     }
 }
@@ -519,6 +814,16 @@ impl Token for LitInt {
     fn display() -> String {
         "an integer".to_string()
     }
+
+    fn append_tokens(&self, tokens: &mut TokenStream) {
+        push_text(tokens, &self.value.to_string(), &self.span);
+    }
+}
+
+impl crate::Spanned for LitInt {
+    fn span(&self) -> Span {
+        Token::span(self).clone()
+    }
 }
 
 fn int_to_decimal(int: u64) -> f64 {
@@ -539,8 +844,8 @@ pub fn LitInt(marker: Marker) -> LitInt {
 /// ascii digits.
 #[derive(Debug, Clone)]
 pub struct LitFloat {
-    value: f64,
-    span: Span,
+    pub(crate) value: f64,
+    pub(crate) span: Span,
 }
 
 impl LitFloat {
@@ -555,7 +860,7 @@ impl LitFloat {
         let end = LitInt::parse_decimal(input)?;
         Ok(LitFloat {
             value: start.value as f64 + int_to_decimal(end.value),
-            span: Span::new(start.span.start, end.span.end, Rc::clone(&input.source)),
+            span: Span::new(start.span.start, end.span.end, Arc::clone(input.source)),
         })
     }
 }
@@ -577,7 +882,7 @@ impl Parse for LitFloat {
         if let Ok(value) = Self::parse_impl(input) {
             Ok(value)
         } else {
-            Err(input.unexpected_token(HashSet::from_iter(["a float literal".to_string()])))
+            Err(input.unexpected("a float literal"))
         }
     }
 }
@@ -596,6 +901,16 @@ impl Token for LitFloat {
     fn display() -> String {
         "a floating point literal".to_string()
     }
+
+    fn append_tokens(&self, tokens: &mut TokenStream) {
+        push_text(tokens, &format!("{:?}", self.value), &self.span);
+    }
+}
+
+impl crate::Spanned for LitFloat {
+    fn span(&self) -> Span {
+        Token::span(self).clone()
+    }
 }
 
 #[doc(hidden)]
@@ -626,12 +941,15 @@ macro_rules! tokens {
 
             impl Parse for $t1 {
                 fn parse(input: ParseStream<'_>) -> Result<Self> {
-                    let token = input.next()?.to_owned();
+                    let token = input
+                        .next()
+                        .map_err(|err| err.expected_at_end(concat!("'", $name1, "'")))?
+                        .to_owned();
                     if let Entry::Punct(SingleCharPunct { kind: PunctKind::$t1, span, .. }) = token {
                         Ok(Self { span })
                     } else {
-                        Err(Error::new(Rc::clone(&input.source), ErrorKind::UnexpectedToken {
-                            expected: HashSet::from_iter(vec![format!("'{}'", $name1)]),
+                        Err(Error::new(input.source, ErrorKind::UnexpectedToken {
+                            expected: concat!("'", $name1, "'").into(),
                             span: token.span().clone(),
                         }))
                     }
@@ -640,6 +958,8 @@ macro_rules! tokens {
 
             impl Sealed for $t1 {}
 
+            leaves![$t1];
+
             impl Token for $t1 {
                 fn span(&self) -> &Span {
                     &self.span
@@ -652,6 +972,20 @@ macro_rules! tokens {
                 fn display() -> String {
                     $name1.to_string()
                 }
+
+                fn peek_token(input: ParseStream<'_>) -> bool {
+                    peek_punct(input, &[PunctKind::$t1])
+                }
+
+                fn append_tokens(&self, tokens: &mut TokenStream) {
+                    push_text(tokens, &self.to_string(), &self.span);
+                }
+            }
+
+            impl crate::Spanned for $t1 {
+                fn span(&self) -> Span {
+                    Token::span(self).clone()
+                }
             }
 
             impl fmt::Display for $t1 {
@@ -713,7 +1047,7 @@ macro_rules! tokens {
 
             impl $t2 {
                 fn from_tokens_impl(input: ParseStream<'_>) -> Result<Self> {
-                    if let Entry::Punct(SingleCharPunct { spacing: Spacing::Joint, .. }) = input.current()?.1 {
+                    if let Entry::Punct(SingleCharPunct { spacing: Spacing::Joint, .. }) = input.current()? {
 
                     } else {
                         return Err(Error::empty());
@@ -729,10 +1063,14 @@ macro_rules! tokens {
 
             impl Parse for $t2 {
                 fn parse(input: ParseStream<'_>) -> Result<Self> {
-                    let span = input.current()?.1.span();
+                    let span = input
+                        .current()
+                        .map_err(|err| err.expected_at_end(concat!("'", $name2, "'")))?
+                        .span()
+                        .clone();
                     Self::from_tokens_impl(input).map_err(|_| {
-                        Error::new(Rc::clone(&input.source), ErrorKind::UnexpectedToken {
-                            expected: HashSet::from_iter(vec![format!("'{}'", $name2)]),
+                        Error::new(input.source, ErrorKind::UnexpectedToken {
+                            expected: concat!("'", $name2, "'").into(),
                             span: span.clone(),
                         })
                     })
@@ -741,6 +1079,8 @@ macro_rules! tokens {
 
             impl Sealed for $t2 {}
 
+            leaves![$t2];
+
             impl Token for $t2 {
                 fn span(&self) -> &Span {
                     &self.span
@@ -753,6 +1093,20 @@ macro_rules! tokens {
                 fn display() -> String {
                     $name2.to_string()
                 }
+
+                fn peek_token(input: ParseStream<'_>) -> bool {
+                    peek_punct(input, &[PunctKind::$t21, PunctKind::$t22])
+                }
+
+                fn append_tokens(&self, tokens: &mut TokenStream) {
+                    push_text(tokens, &self.to_string(), &self.span);
+                }
+            }
+
+            impl crate::Spanned for $t2 {
+                fn span(&self) -> Span {
+                    Token::span(self).clone()
+                }
             }
 
             impl fmt::Display for $t2 {
@@ -790,12 +1144,12 @@ macro_rules! tokens {
 
             impl $t3 {
                 fn from_tokens_impl(input: ParseStream<'_>) -> Result<Self> {
-                    if let Entry::Punct(SingleCharPunct { spacing: Spacing::Joint, .. }) = input.current()?.1 {
+                    if let Entry::Punct(SingleCharPunct { spacing: Spacing::Joint, .. }) = input.current()? {
 
                     } else {
                         return Err(Error::empty());
                     }
-                    if let Entry::Punct(SingleCharPunct { spacing: Spacing::Joint, .. }) = input.current()?.1 {
+                    if let Entry::Punct(SingleCharPunct { spacing: Spacing::Joint, .. }) = input.current()? {
 
                     } else {
                         return Err(Error::empty());
@@ -811,10 +1165,14 @@ macro_rules! tokens {
 
             impl Parse for $t3 {
                 fn parse(input: ParseStream<'_>) -> Result<Self> {
-                    let span = input.current()?.1.span();
+                    let span = input
+                        .current()
+                        .map_err(|err| err.expected_at_end(concat!("'", $name3, "'")))?
+                        .span()
+                        .clone();
                     Self::from_tokens_impl(input).map_err(|_| {
-                        Error::new(Rc::clone(&input.source), ErrorKind::UnexpectedToken {
-                            expected: HashSet::from_iter(vec![format!("'{}'", $name3)]),
+                        Error::new(input.source, ErrorKind::UnexpectedToken {
+                            expected: concat!("'", $name3, "'").into(),
                             span: span.clone(),
                         })
                     })
@@ -823,6 +1181,8 @@ macro_rules! tokens {
 
             impl Sealed for $t3 {}
 
+            leaves![$t3];
+
             impl Token for $t3 {
                 fn span(&self) -> &Span {
                     &self.span
@@ -835,6 +1195,20 @@ macro_rules! tokens {
                 fn display() -> String {
                     $name3.to_string()
                 }
+
+                fn peek_token(input: ParseStream<'_>) -> bool {
+                    peek_punct(input, &[PunctKind::$t31, PunctKind::$t32, PunctKind::$t33])
+                }
+
+                fn append_tokens(&self, tokens: &mut TokenStream) {
+                    push_text(tokens, &self.to_string(), &self.span);
+                }
+            }
+
+            impl crate::Spanned for $t3 {
+                fn span(&self) -> Span {
+                    Token::span(self).clone()
+                }
             }
 
             impl fmt::Display for $t3 {
@@ -936,6 +1310,8 @@ tokens! {
 trait JoinedPunct: Sized {
     fn display() -> String;
 
+    fn append_tokens(&self, tokens: &mut TokenStream);
+
     fn parse(input: ParseStream<'_>) -> Result<Self>;
 }
 
@@ -944,6 +1320,11 @@ impl<T1: JoinedPunct, T2: JoinedPunct> JoinedPunct for (T1, T2) {
         T1::display() + &T2::display()
     }
 
+    fn append_tokens(&self, tokens: &mut TokenStream) {
+        self.0.append_tokens(tokens);
+        self.1.append_tokens(tokens);
+    }
+
     fn parse(input: ParseStream<'_>) -> Result<Self> {
         Ok((T1::parse(input)?, T2::parse(input)?))
     }
@@ -954,6 +1335,10 @@ impl<T: Punct> JoinedPunct for (T,) {
         T::display()
     }
 
+    fn append_tokens(&self, tokens: &mut TokenStream) {
+        self.0.append_tokens(tokens);
+    }
+
     fn parse(input: ParseStream<'_>) -> Result<Self> {
         T::parse(input).map(|value| (value,))
     }
@@ -961,20 +1346,20 @@ impl<T: Punct> JoinedPunct for (T,) {
 
 impl<T: JoinedPunct> Parse for (T, Span) {
     fn parse(input: ParseStream<'_>) -> Result<Self> {
-        let span = input.current()?.1.span();
+        let span = input.current()?.span().clone();
         let value = T::parse(input).map_err(|_| {
             Error::new(
-                Rc::clone(&input.source),
+                input.source,
                 ErrorKind::UnexpectedToken {
-                    expected: HashSet::from_iter(vec![T::display()]),
+                    expected: T::display().into(),
                     span: span.clone(),
                 },
             )
         })?;
         let span = Span::new(
             span.start,
-            input.get_relative(-1)?.1.span().end,
-            Rc::clone(&input.source),
+            input.get_relative(-1)?.span().end,
+            Arc::clone(input.source),
         );
         Ok((value, span))
     }
@@ -995,6 +1380,16 @@ impl<T: JoinedPunct> Token for (T, Span) {
     fn display() -> String {
         T::display()
     }
+
+    fn append_tokens(&self, tokens: &mut TokenStream) {
+        self.0.append_tokens(tokens);
+    }
+}
+
+impl<T: JoinedPunct> crate::Spanned for (T, Span) {
+    fn span(&self) -> Span {
+        Token::span(self).clone()
+    }
 }
 
 impl<T: JoinedPunct> Punct for (T, Span) {
@@ -1038,15 +1433,6 @@ impl WhiteSpace {
             }) => *original_span = span,
         }
     }
-
-    pub(crate) fn display(&self) -> String {
-        match self {
-            WhiteSpace::Space2(_) => Space2::display(),
-            WhiteSpace::Tab(_) => Tab::display(),
-            WhiteSpace::NewLine(_) => NewLine::display(),
-            WhiteSpace::CarriageReturn(_) => CarriageReturn::display(),
-        }
-    }
 }
 
 impl PartialEq for WhiteSpace {
@@ -1070,11 +1456,13 @@ pub struct Space2 {
 
 impl Parse for Space2 {
     fn parse(input: ParseStream) -> Result<Self> {
-        let token = input.next()?;
+        let token = input
+            .next()
+            .map_err(|err| err.expected_at_end("a two-space tab"))?;
         if let Entry::WhiteSpace(WhiteSpace::Space2(value)) = token {
             Ok(value.clone())
         } else {
-            Err(input.unexpected_token(HashSet::from_iter(["a two-space tab".to_string()])))
+            Err(input.unexpected("a two-space tab"))
         }
     }
 }
@@ -1093,6 +1481,16 @@ impl Token for Space2 {
     fn display() -> String {
         "  ".to_string()
     }
+
+    fn append_tokens(&self, tokens: &mut TokenStream) {
+        tokens.push(Entry::WhiteSpace(WhiteSpace::Space2(self.clone())));
+    }
+}
+
+impl crate::Spanned for Space2 {
+    fn span(&self) -> Span {
+        Token::span(self).clone()
+    }
 }
 
 #[doc(hidden)]
@@ -1123,9 +1521,7 @@ impl Space4 {
 
 impl Parse for Space4 {
     fn parse(input: ParseStream<'_>) -> Result<Self> {
-        Self::parse_impl(input).map_err(|_| {
-            input.unexpected_token(HashSet::from_iter(["a four-space tab".to_string()]))
-        })
+        Self::parse_impl(input).map_err(|_| input.unexpected("a four-space tab"))
     }
 }
 
@@ -1143,6 +1539,20 @@ impl Token for Space4 {
     fn display() -> String {
         "    ".to_string()
     }
+
+    fn append_tokens(&self, tokens: &mut TokenStream) {
+        for start in [0, 2] {
+            tokens.push(Entry::WhiteSpace(WhiteSpace::Space2(Space2 {
+                span: sub_span(&self.span, 4, start, start + 2),
+            })));
+        }
+    }
+}
+
+impl crate::Spanned for Space4 {
+    fn span(&self) -> Span {
+        Token::span(self).clone()
+    }
 }
 
 #[doc(hidden)]
@@ -1160,10 +1570,11 @@ pub struct Tab {
 
 impl Parse for Tab {
     fn parse(input: ParseStream) -> Result<Self> {
-        if let Entry::WhiteSpace(WhiteSpace::Tab(value)) = input.next()? {
+        let token = input.next().map_err(|err| err.expected_at_end("a tab"))?;
+        if let Entry::WhiteSpace(WhiteSpace::Tab(value)) = token {
             Ok(value.clone())
         } else {
-            Err(input.unexpected_token(HashSet::from_iter(["a tab".to_string()])))
+            Err(input.unexpected("a tab"))
         }
     }
 }
@@ -1182,6 +1593,16 @@ impl Token for Tab {
     fn display() -> String {
         "\t".to_string()
     }
+
+    fn append_tokens(&self, tokens: &mut TokenStream) {
+        tokens.push(Entry::WhiteSpace(WhiteSpace::Tab(self.clone())));
+    }
+}
+
+impl crate::Spanned for Tab {
+    fn span(&self) -> Span {
+        Token::span(self).clone()
+    }
 }
 
 #[doc(hidden)]
@@ -1199,10 +1620,11 @@ pub struct NewLine {
 
 impl Parse for NewLine {
     fn parse(input: ParseStream) -> Result<Self> {
-        if let Entry::WhiteSpace(WhiteSpace::NewLine(value)) = input.next()? {
+        let token = input.next().map_err(|err| err.expected_at_end("\\n"))?;
+        if let Entry::WhiteSpace(WhiteSpace::NewLine(value)) = token {
             Ok(value.clone())
         } else {
-            Err(input.unexpected_token(HashSet::from_iter(["\\n".to_string()])))
+            Err(input.unexpected("\\n"))
         }
     }
 }
@@ -1221,6 +1643,16 @@ impl Token for NewLine {
     fn display() -> String {
         "\\n".to_string()
     }
+
+    fn append_tokens(&self, tokens: &mut TokenStream) {
+        tokens.push(Entry::WhiteSpace(WhiteSpace::NewLine(self.clone())));
+    }
+}
+
+impl crate::Spanned for NewLine {
+    fn span(&self) -> Span {
+        Token::span(self).clone()
+    }
 }
 
 #[doc(hidden)]
@@ -1238,10 +1670,13 @@ pub struct CarriageReturn {
 
 impl Parse for CarriageReturn {
     fn parse(input: ParseStream<'_>) -> Result<Self> {
-        if let Entry::WhiteSpace(WhiteSpace::CarriageReturn(value)) = input.next()? {
+        let token = input
+            .next()
+            .map_err(|err| err.expected_at_end("a carriage return"))?;
+        if let Entry::WhiteSpace(WhiteSpace::CarriageReturn(value)) = token {
             Ok(value.clone())
         } else {
-            Err(input.unexpected_token(HashSet::from_iter(["a carriage return".to_string()])))
+            Err(input.unexpected("a carriage return"))
         }
     }
 }
@@ -1260,6 +1695,16 @@ impl Token for CarriageReturn {
     fn display() -> String {
         "a carriage return".to_string()
     }
+
+    fn append_tokens(&self, tokens: &mut TokenStream) {
+        tokens.push(Entry::WhiteSpace(WhiteSpace::CarriageReturn(self.clone())));
+    }
+}
+
+impl crate::Spanned for CarriageReturn {
+    fn span(&self) -> Span {
+        Token::span(self).clone()
+    }
 }
 
 #[doc(hidden)]
@@ -1268,59 +1713,235 @@ pub fn CarriageReturn(marker: Marker) -> CarriageReturn {
     match marker {}
 }
 
+leaves![
+    LitStrDoubleQuote,
+    LitStrSingleQuote,
+    LitChar,
+    Ident,
+    LitInt,
+    LitFloat,
+    Space2,
+    Space4,
+    Tab,
+    NewLine,
+    CarriageReturn,
+];
+
 /// Generate types for keywords.
 ///
+/// A keyword can be given a different type name with `as`, and keywords can
+/// be grouped into categories with `category: [...]`. For each category, a
+/// function `peek_any_<category>_keyword` is generated, which returns true if
+/// the next token is one of the keywords in that category, along with
+/// `peek2_any_<category>_keyword`, which checks the token after it.
+///
+/// A function `ident` is also generated, which parses identifiers that aren't
+/// any of the keywords.
+///
 /// ## Usage
 /// ```
 /// use flexi_parse::parse_string;
 /// mod kw {
 ///     use flexi_parse::keywords;
-///     keywords![var, then];
+///     keywords![
+///         var,
+///         then as Then,
+///         declaration: [fun, class as Class],
+///     ];
 /// }
 ///
 /// # fn main() {
 /// let kw1: kw::var = parse_string("var".to_string()).unwrap();
-/// let kw2: kw::then = parse_string("then".to_string()).unwrap();
+/// let kw2: kw::Then = parse_string("then".to_string()).unwrap();
+/// let kw3: kw::Class = parse_string("class".to_string()).unwrap();
 /// # }
 /// ```
 #[macro_export]
 macro_rules! keywords {
-    [ $( $kw:ident ),+ $(,)? ] => {
+    [ $( $input:tt )+ ] => {
+        #[allow(unused_imports)]
         use $crate::token::Token as _;
-        $(
+        $crate::__keywords!(@munch plain [] $( $input )+);
+    };
+}
+
+pub use keywords;
+
+/// Generate types for keywords, with the type names prefixed with `keyword_`.
+///
+/// This accepts the same options as [`keywords`], with each keyword given as
+/// a string. Keywords given a type name with `as` aren't prefixed.
+///
+/// ## Usage
+/// ```
+/// use flexi_parse::parse_string;
+/// mod kw {
+///     use flexi_parse::keywords_prefixed;
+///     keywords_prefixed!["let", "if", statement: ["for", "while" as While]];
+/// }
+///
+/// # fn main() {
+/// let kw1: kw::keyword_let = parse_string("let".to_string()).unwrap();
+/// let kw2: kw::keyword_if = parse_string("if".to_string()).unwrap();
+/// let kw3: kw::While = parse_string("while".to_string()).unwrap();
+/// # }
+/// ```
+#[macro_export]
+macro_rules! keywords_prefixed {
+    [ $( $input:tt )+ ] => {
+        #[allow(unused_imports)]
+        use $crate::token::Token as _;
+        $crate::__keywords!(@munch prefixed [] $( $input )+);
+    };
+}
+
+pub use keywords_prefixed;
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __keywords {
+    (@munch $mode:ident [ $( $all:expr ),* ]
+        $category:ident : [ $( $kw:tt $( as $name:ident )? ),+ $(,)? ]
+        $( , $( $rest:tt )* )?
+    ) => {
+        $( $crate::__keywords!(@one $mode $kw $( $name )?); )+
+
+        $crate::token::concat_idents!(function_name = peek_any_, $category, _keyword {
+            #[doc = concat!(
+                "Returns true if the next token is one of the `",
+                stringify!($category),
+                "` keywords.",
+            )]
+            #[allow(dead_code)]
+            pub fn function_name(input: $crate::ParseStream<'_>) -> bool {
+                input.peek_any_keyword($crate::__keywords!(@set $mode $( $kw ),+))
+            }
+        });
+
+        $crate::token::concat_idents!(function_name = peek2_any_, $category, _keyword {
+            #[doc = concat!(
+                "Returns true if the token after the next one is one of the `",
+                stringify!($category),
+                "` keywords.",
+            )]
+            #[allow(dead_code)]
+            pub fn function_name(input: $crate::ParseStream<'_>) -> bool {
+                input.peek2_any_keyword($crate::__keywords!(@set $mode $( $kw ),+))
+            }
+        });
+
+        $crate::__keywords!(
+            @munch $mode
+            [ $( $all, )* $( $crate::__keywords!(@string $mode $kw) ),+ ]
+            $( $( $rest )* )?
+        );
+    };
+    (@munch $mode:ident [ $( $all:expr ),* ]
+        $kw:tt $( as $name:ident )?
+        $( , $( $rest:tt )* )?
+    ) => {
+        $crate::__keywords!(@one $mode $kw $( $name )?);
+
+        $crate::__keywords!(
+            @munch $mode
+            [ $( $all, )* $crate::__keywords!(@string $mode $kw) ]
+            $( $( $rest )* )?
+        );
+    };
+    (@munch $mode:ident [ $( $all:expr ),* ]) => {
+        /// Parses non-keyword identifiers.
+        #[allow(dead_code)]
+        pub fn ident(input: $crate::ParseStream<'_>) -> $crate::Result<$crate::token::Ident> {
+            let fork = input.fork();
+            let ident: $crate::token::Ident = fork.parse()?;
+            static KEYWORDS: ::std::sync::OnceLock<
+                ::std::collections::HashSet<$crate::token::Symbol>,
+            > = ::std::sync::OnceLock::new();
+            let keywords = KEYWORDS.get_or_init(|| {
+                ::std::collections::HashSet::from_iter([$( $crate::token::Symbol::new($all) ),*])
+            });
+            if input.is_any_keyword(&ident, keywords) {
+                $crate::Result::Err(input.unexpected_token_named("an identifier"))
+            } else {
+                input.commit(&fork);
+                $crate::Result::Ok(ident)
+            }
+        }
+    };
+    (@set $mode:ident $( $kw:tt ),+) => {{
+        static KEYWORDS: ::std::sync::OnceLock<
+            ::std::collections::HashSet<$crate::token::Symbol>,
+        > = ::std::sync::OnceLock::new();
+        KEYWORDS.get_or_init(|| {
+            ::std::collections::HashSet::from_iter([$(
+                $crate::token::Symbol::new($crate::__keywords!(@string $mode $kw))
+            ),+])
+        })
+    }};
+    (@string plain $kw:ident) => {
+        stringify!($kw)
+    };
+    (@string prefixed $kw:literal) => {
+        $kw
+    };
+    (@one plain $kw:ident $name:ident) => {
+        $crate::__keywords!(@keyword $name, stringify!($kw));
+    };
+    (@one plain $kw:ident) => {
+        $crate::__keywords!(@keyword $kw, stringify!($kw));
+    };
+    (@one prefixed $kw:literal $name:ident) => {
+        $crate::__keywords!(@keyword $name, $kw);
+    };
+    (@one prefixed $kw:literal) => {
+        $crate::token::concat_idents!(struct_name = keyword_, $kw {
             #[derive(Debug, Clone)]
             #[allow(non_camel_case_types)]
-            pub struct $kw {
+            pub struct struct_name {
                 span: $crate::Span
             }
 
-            impl $kw {
+            impl struct_name {
                 #[allow(dead_code)]
                 pub fn new(input: $crate::ParseStream<'_>) -> Self {
                     Self {
                         span: input.empty_span()
                     }
                 }
+
+                fn symbol() -> &'static $crate::token::Symbol {
+                    static SYMBOL: ::std::sync::OnceLock<$crate::token::Symbol> =
+                        ::std::sync::OnceLock::new();
+                    SYMBOL.get_or_init(|| $crate::token::Symbol::new($kw))
+                }
             }
 
-            impl $crate::Parse for $kw {
+            impl $crate::Parse for struct_name {
                 fn parse(input: $crate::ParseStream<'_>) -> $crate::Result<Self> {
-                    let ident: $crate::token::Ident = input.parse()?;
-                    if ident.string() == stringify!($kw) {
+                    let fork = input.fork();
+                    let ident: $crate::token::Ident = fork.parse()?;
+                    if input.is_keyword_symbol(&ident, Self::symbol()) {
+                        input.commit(&fork);
                         $crate::Result::Ok(Self {
-                            span: ident.span().to_owned()
+                            span: $crate::token::Token::span(&ident).to_owned()
                         })
                     } else {
-                        $crate::Result::Err(input.unexpected_token(
-                            ::std::collections::HashSet::from_iter([stringify!($kw).to_string()]),
-                        ))
+                        $crate::Result::Err(input.unexpected_token_named($kw))
                     }
                 }
             }
 
-            impl $crate::private::Sealed for $kw {}
+            impl $crate::private::Sealed for struct_name {}
+
+            impl $crate::visit::Visit for struct_name {
+                $crate::__visit_any!();
+            }
+
+            impl $crate::visit::Fold for struct_name {
+                $crate::__fold_any!();
+            }
 
-            impl $crate::token::Token for $kw {
+            impl $crate::token::Token for struct_name {
                 fn span(&self) -> &$crate::Span {
                     &self.span
                 }
@@ -1330,136 +1951,336 @@ macro_rules! keywords {
                 }
 
                 fn display() -> String {
-                    stringify!($kw).to_string()
+                    $kw.to_string()
+                }
+
+                fn append_tokens(&self, tokens: &mut $crate::TokenStream) {
+                    $crate::token::push_text(tokens, $kw, &self.span);
+                }
+
+                fn peek_token(input: $crate::ParseStream<'_>) -> bool {
+                    input.peek_keyword(Self::symbol())
+                }
+            }
+
+            impl $crate::Spanned for struct_name {
+                fn span(&self) -> $crate::Span {
+                    $crate::token::Token::span(self).clone()
                 }
             }
 
-            impl ::std::cmp::PartialEq for $kw {
+            impl ::std::cmp::PartialEq for struct_name {
                 fn eq(&self, _other: &Self) -> bool {
                     true
                 }
             }
 
-            impl ::std::cmp::Eq for $kw {}
+            impl ::std::cmp::Eq for struct_name {}
 
             #[doc(hidden)]
-            #[allow(dead_code)]
-            pub fn $kw(marker: $crate::Marker) -> $kw {
+            #[allow(dead_code, non_snake_case)]
+            pub fn struct_name(marker: $crate::Marker) -> struct_name {
                 match marker {}
             }
-        )+
+        });
+    };
+    (@keyword $name:ident, $string:expr) => {
+        #[derive(Debug, Clone)]
+        #[allow(non_camel_case_types)]
+        pub struct $name {
+            span: $crate::Span
+        }
 
-        /// Parses non-keyword identifiers.
-        #[allow(dead_code)]
-        pub fn ident(input: $crate::ParseStream<'_>) -> $crate::Result<$crate::token::Ident> {
-            let ident: $crate::token::Ident = input.parse()?;
-            if [$( stringify!($kw) ),+].contains(&ident.string().as_str()) {
-                $crate::Result::Err(input.unexpected_token(
-                    ::std::collections::HashSet::from_iter(["an identifier".to_string()]),
-                ))
-            } else {
-                $crate::Result::Ok(ident)
+        impl $name {
+            #[allow(dead_code)]
+            pub fn new(input: $crate::ParseStream<'_>) -> Self {
+                Self {
+                    span: input.empty_span()
+                }
+            }
+
+            fn symbol() -> &'static $crate::token::Symbol {
+                static SYMBOL: ::std::sync::OnceLock<$crate::token::Symbol> =
+                    ::std::sync::OnceLock::new();
+                SYMBOL.get_or_init(|| $crate::token::Symbol::new($string))
+            }
+        }
+
+        impl $crate::Parse for $name {
+            fn parse(input: $crate::ParseStream<'_>) -> $crate::Result<Self> {
+                let fork = input.fork();
+                let ident: $crate::token::Ident = fork.parse()?;
+                if input.is_keyword_symbol(&ident, Self::symbol()) {
+                    input.commit(&fork);
+                    $crate::Result::Ok(Self {
+                        span: $crate::token::Token::span(&ident).to_owned()
+                    })
+                } else {
+                    $crate::Result::Err(input.unexpected_token_named($string))
+                }
+            }
+        }
+
+        impl $crate::private::Sealed for $name {}
+
+        impl $crate::visit::Visit for $name {
+            $crate::__visit_any!();
+        }
+
+        impl $crate::visit::Fold for $name {
+            $crate::__fold_any!();
+        }
+
+        impl $crate::token::Token for $name {
+            fn span(&self) -> &$crate::Span {
+                &self.span
+            }
+
+            fn set_span(&mut self, span: $crate::Span) {
+                self.span = span;
+            }
+
+            fn display() -> String {
+                $string.to_string()
+            }
+
+            fn append_tokens(&self, tokens: &mut $crate::TokenStream) {
+                $crate::token::push_text(tokens, $string, &self.span);
+            }
+
+            fn peek_token(input: $crate::ParseStream<'_>) -> bool {
+                input.peek_keyword(Self::symbol())
             }
         }
+
+        impl $crate::Spanned for $name {
+            fn span(&self) -> $crate::Span {
+                $crate::token::Token::span(self).clone()
+            }
+        }
+
+        impl ::std::cmp::PartialEq for $name {
+            fn eq(&self, _other: &Self) -> bool {
+                true
+            }
+        }
+
+        impl ::std::cmp::Eq for $name {}
+
+        #[doc(hidden)]
+        #[allow(dead_code, non_snake_case)]
+        pub fn $name(marker: $crate::Marker) -> $name {
+            match marker {}
+        }
     };
 }
 
-pub use keywords;
-
-/// Generate types for keywords, with the type names prefixed with `keyword_`.
+/// Generate a token type matching a fixed word or a class of characters.
+///
+/// A fixed word matches an identifier with exactly that text. A character
+/// class matches an identifier token, including one starting with a digit,
+/// whose characters all satisfy the given predicate, and is described by the
+/// given name in error messages.
+///
+/// The generated types implement [`Token`], so they can be parsed, peeked and
+/// used with [`Lookahead`](crate::lookahead::Lookahead), and they implement
+/// [`Display`](fmt::Display).
 ///
 /// ## Usage
 /// ```
 /// use flexi_parse::parse_string;
-/// mod kw {
-///     use flexi_parse::keywords_prefixed;
-///     keywords_prefixed!["let", "if"];
+/// mod tok {
+///     use flexi_parse::custom_token;
+///     custom_token! {
+///         /// The `select` command.
+///         pub struct Select = "select";
+///     }
+///     custom_token! {
+///         pub struct Hex = |c| c.is_ascii_hexdigit(), "a hexadecimal number";
+///     }
 /// }
 ///
 /// # fn main() {
-/// let kw1: kw::keyword_let = parse_string("let".to_string()).unwrap();
-/// let kw2: kw::keyword_if = parse_string("if".to_string()).unwrap();
+/// let select: tok::Select = parse_string("select".to_string()).unwrap();
+/// let hex: tok::Hex = parse_string("7f0a".to_string()).unwrap();
+/// assert_eq!(hex.to_string(), "7f0a");
 /// # }
 /// ```
 #[macro_export]
-macro_rules! keywords_prefixed {
-    [ $( $kw:tt ),+ $(,)? ] => {
-        use $crate::token::Token as _;
-        $(
-            $crate::token::concat_idents!(struct_name = keyword_, $kw {
-                #[derive(Debug, Clone)]
-                #[allow(non_camel_case_types)]
-                pub struct struct_name {
-                    span: $crate::Span
-                }
+macro_rules! custom_token {
+    ( $( #[$attr:meta] )* $vis:vis struct $name:ident = $word:literal; ) => {
+        $( #[$attr] )*
+        #[derive(Debug, Clone)]
+        $vis struct $name {
+            span: $crate::Span
+        }
 
-                impl struct_name {
-                    #[allow(dead_code)]
-                    pub fn new(input: $crate::ParseStream<'_>) -> Self {
-                        Self {
-                            span: input.empty_span()
-                        }
-                    }
-                }
+        impl $name {
+            fn symbol() -> &'static $crate::token::Symbol {
+                static SYMBOL: ::std::sync::OnceLock<$crate::token::Symbol> =
+                    ::std::sync::OnceLock::new();
+                SYMBOL.get_or_init(|| $crate::token::Symbol::new($word))
+            }
+        }
 
-                impl $crate::Parse for struct_name {
-                    fn parse(input: $crate::ParseStream<'_>) -> $crate::Result<Self> {
-                        let ident: $crate::token::Ident = input.parse()?;
-                        if ident.string() == $kw {
-                            $crate::Result::Ok(Self {
-                                span: ident.span().to_owned()
-                            })
-                        } else {
-                            $crate::Result::Err(input.unexpected_token(
-                                ::std::collections::HashSet::from_iter([$kw.to_string()]),
-                            ))
-                        }
+        impl $crate::Parse for $name {
+            fn parse(input: $crate::ParseStream<'_>) -> $crate::Result<Self> {
+                let fork = input.fork();
+                match $crate::token::Ident::parse_simple(&fork) {
+                    ::std::result::Result::Ok(ident) if ident.symbol() == Self::symbol() => {
+                        input.commit(&fork);
+                        $crate::Result::Ok(Self {
+                            span: $crate::token::Token::span(&ident).to_owned()
+                        })
                     }
+                    _ => $crate::Result::Err(input.unexpected_token_named($word)),
                 }
+            }
+        }
 
-                impl $crate::private::Sealed for struct_name {}
+        impl $crate::private::Sealed for $name {}
 
-                impl $crate::token::Token for struct_name {
-                    fn span(&self) -> &$crate::Span {
-                        &self.span
-                    }
+        impl $crate::visit::Visit for $name {
+            $crate::__visit_any!();
+        }
 
-                    fn set_span(&mut self, span: $crate::Span) {
-                        self.span = span;
-                    }
+        impl $crate::visit::Fold for $name {
+            $crate::__fold_any!();
+        }
 
-                    fn display() -> String {
-                        $kw.to_string()
-                    }
-                }
+        impl $crate::token::Token for $name {
+            fn span(&self) -> &$crate::Span {
+                &self.span
+            }
+
+            fn set_span(&mut self, span: $crate::Span) {
+                self.span = span;
+            }
+
+            fn display() -> String {
+                $word.to_string()
+            }
+
+            fn append_tokens(&self, tokens: &mut $crate::TokenStream) {
+                $crate::token::push_text(tokens, $word, &self.span);
+            }
+        }
+
+        impl $crate::Spanned for $name {
+            fn span(&self) -> $crate::Span {
+                $crate::token::Token::span(self).clone()
+            }
+        }
 
-                impl ::std::cmp::PartialEq for struct_name {
-                    fn eq(&self, _other: &Self) -> bool {
-                        true
+        impl ::std::fmt::Display for $name {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                f.write_str($word)
+            }
+        }
+
+        impl ::std::cmp::PartialEq for $name {
+            fn eq(&self, _other: &Self) -> bool {
+                true
+            }
+        }
+
+        impl ::std::cmp::Eq for $name {}
+
+        #[doc(hidden)]
+        #[allow(dead_code, non_snake_case)]
+        $vis fn $name(marker: $crate::Marker) -> $name {
+            match marker {}
+        }
+    };
+    (
+        $( #[$attr:meta] )*
+        $vis:vis struct $name:ident = |$c:ident| $predicate:expr, $display:literal;
+    ) => {
+        $( #[$attr] )*
+        #[derive(Debug, Clone)]
+        $vis struct $name {
+            ident: $crate::token::Ident
+        }
+
+        impl $name {
+            /// Returns the text that makes up the token.
+            #[allow(dead_code)]
+            pub fn string(&self) -> &String {
+                self.ident.string()
+            }
+
+            fn matches(string: &str) -> bool {
+                string.chars().all(|$c: char| $predicate)
+            }
+        }
+
+        impl $crate::Parse for $name {
+            fn parse(input: $crate::ParseStream<'_>) -> $crate::Result<Self> {
+                let fork = input.fork();
+                match $crate::token::Ident::parse_simple(&fork) {
+                    ::std::result::Result::Ok(ident) if Self::matches(ident.string()) => {
+                        input.commit(&fork);
+                        $crate::Result::Ok(Self { ident })
                     }
+                    _ => $crate::Result::Err(input.unexpected_token_named($display)),
                 }
+            }
+        }
 
-                #[doc(hidden)]
-                #[allow(dead_code)]
-                pub fn struct_name(marker: $crate::Marker) -> struct_name {
-                    match marker {}
-                }
-            });
-        )+
+        impl $crate::private::Sealed for $name {}
 
-        /// Parses non-keyword identifiers.
-        #[allow(dead_code)]
-        pub fn ident(input: $crate::ParseStream<'_>) -> $crate::Result<$crate::token::Ident> {
-            let ident: $crate::token::Ident = input.parse()?;
-            if [$( $kw ),+].contains(&ident.string().as_str()) {
-                $crate::Result::Err(input.unexpected_token(
-                    ::std::collections::HashSet::from_iter(["an identifier".to_string()]),
-                ))
-            } else {
-                $crate::Result::Ok(ident)
+        impl $crate::visit::Visit for $name {
+            $crate::__visit_any!();
+        }
+
+        impl $crate::visit::Fold for $name {
+            $crate::__fold_any!();
+        }
+
+        impl $crate::token::Token for $name {
+            fn span(&self) -> &$crate::Span {
+                $crate::token::Token::span(&self.ident)
+            }
+
+            fn set_span(&mut self, span: $crate::Span) {
+                $crate::token::Token::set_span(&mut self.ident, span);
+            }
+
+            fn display() -> String {
+                $display.to_string()
+            }
+
+            fn append_tokens(&self, tokens: &mut $crate::TokenStream) {
+                $crate::token::Token::append_tokens(&self.ident, tokens);
             }
         }
+
+        impl $crate::Spanned for $name {
+            fn span(&self) -> $crate::Span {
+                $crate::token::Token::span(self).clone()
+            }
+        }
+
+        impl ::std::fmt::Display for $name {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                f.write_str(self.string())
+            }
+        }
+
+        impl ::std::cmp::PartialEq for $name {
+            fn eq(&self, other: &Self) -> bool {
+                self.ident == other.ident
+            }
+        }
+
+        impl ::std::cmp::Eq for $name {}
+
+        #[doc(hidden)]
+        #[allow(dead_code, non_snake_case)]
+        $vis fn $name(marker: $crate::Marker) -> $name {
+            match marker {}
+        }
     };
 }
 
-pub use keywords_prefixed;
+pub use custom_token;
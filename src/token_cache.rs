@@ -0,0 +1,337 @@
+//! Serialization of token streams, so that build tools can cache the result
+//! of scanning a file between runs, and of individual tokens and groups, so
+//! that other tools can inspect parsed structure.
+
+use crate::group::Delimiters;
+use crate::group::Group;
+use crate::token::Ident;
+use crate::token::Interner;
+use crate::token::LitChar;
+use crate::token::LitFloat;
+use crate::token::LitInt;
+use crate::token::LitStrDoubleQuote;
+use crate::token::LitStrSingleQuote;
+use crate::token::PunctKind;
+use crate::token::Spacing;
+use crate::PackedEntry;
+use crate::PackedKind;
+use crate::SourceFile;
+use crate::Span;
+use crate::TokenStream;
+
+use std::iter;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use serde::de;
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
+use serde::Serializer;
+
+#[derive(Serialize, Deserialize)]
+enum KindData {
+    Error,
+    Ident(String),
+    Punct(char, bool),
+    Space2,
+    Tab,
+    NewLine,
+    CarriageReturn,
+}
+
+#[derive(Serialize, Deserialize)]
+struct TokenData {
+    kind: KindData,
+    source: u16,
+    start: u32,
+    end: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct TokenStreamData<S> {
+    sources: Vec<S>,
+    source: u16,
+    tokens: Vec<TokenData>,
+}
+
+/// Token streams are serialized with the source files their tokens come
+/// from, and the kind and byte range of each token that hasn't been removed.
+///
+/// Use [`SourceFile::content_hash`] to check whether a cached stream is still
+/// up to date.
+impl Serialize for TokenStream {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut sources: Vec<&SourceFile> = self.sources.iter().map(|source| &**source).collect();
+        let source = self
+            .sources
+            .iter()
+            .position(|source| Arc::ptr_eq(source, &self.source))
+            .unwrap_or_else(|| {
+                sources.push(&self.source);
+                sources.len() - 1
+            });
+        let tokens = self
+            .packed()
+            .filter(|entry| entry.kind != PackedKind::End)
+            .map(|entry| TokenData {
+                kind: match entry.kind {
+                    PackedKind::Error => KindData::Error,
+                    PackedKind::Ident => {
+                        KindData::Ident(self.symbols.get(entry.symbol).string().to_string())
+                    }
+                    PackedKind::Punct(kind, spacing) => {
+                        KindData::Punct(char::from(kind), spacing == Spacing::Joint)
+                    }
+                    PackedKind::Space2 => KindData::Space2,
+                    PackedKind::Tab => KindData::Tab,
+                    PackedKind::NewLine => KindData::NewLine,
+                    PackedKind::CarriageReturn => KindData::CarriageReturn,
+                    PackedKind::End => unreachable!(),
+                },
+                source: entry.source,
+                start: entry.start,
+                end: entry.end,
+            })
+            .collect();
+        TokenStreamData {
+            sources,
+            source: u16::try_from(source).map_err(serde::ser::Error::custom)?,
+            tokens,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for TokenStream {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let data = TokenStreamData::<SourceFile>::deserialize(deserializer)?;
+        let sources: Vec<_> = data.sources.into_iter().map(Arc::new).collect();
+        let file = |index: u16| {
+            sources
+                .get(usize::from(index))
+                .ok_or_else(|| de::Error::custom(format!("no source file with index {index}")))
+        };
+        let source = Arc::clone(file(data.source)?);
+        let mut arena = Vec::with_capacity(data.tokens.len() + 1);
+        let mut symbols = Interner::default();
+        for token in data.tokens {
+            let contents = &file(token.source)?.contents;
+            if token.start > token.end
+                || !contents.is_char_boundary(token.start as usize)
+                || !contents.is_char_boundary(token.end as usize)
+            {
+                return Err(de::Error::custom(format!(
+                    "invalid token range {}..{}",
+                    token.start, token.end
+                )));
+            }
+            let (kind, symbol) = match token.kind {
+                KindData::Error => (PackedKind::Error, 0),
+                KindData::Ident(string) => {
+                    let symbol = symbols
+                        .intern(&string)
+                        .ok_or_else(|| de::Error::custom("too many identifiers"))?;
+                    (PackedKind::Ident, symbol)
+                }
+                KindData::Punct(c, joint) => {
+                    let kind = PunctKind::try_from(c)
+                        .map_err(|c| de::Error::custom(format!("unknown punctuation '{c}'")))?;
+                    let spacing = if joint {
+                        Spacing::Joint
+                    } else {
+                        Spacing::Alone
+                    };
+                    (PackedKind::Punct(kind, spacing), 0)
+                }
+                KindData::Space2 => (PackedKind::Space2, 0),
+                KindData::Tab => (PackedKind::Tab, 0),
+                KindData::NewLine => (PackedKind::NewLine, 0),
+                KindData::CarriageReturn => (PackedKind::CarriageReturn, 0),
+            };
+            arena.push(PackedEntry {
+                start: token.start,
+                end: token.end,
+                symbol,
+                source: token.source,
+                kind,
+            });
+        }
+        arena.push(PackedEntry::END);
+        Ok(TokenStream::from_arena(
+            arena,
+            sources,
+            Arc::new(symbols),
+            source,
+        ))
+    }
+}
+
+#[derive(Deserialize)]
+struct RangeData {
+    start: usize,
+    end: usize,
+}
+
+/// The parts of a serialized [`Span`] needed to find it again.
+#[derive(Deserialize)]
+struct SpanData {
+    file: String,
+    range: RangeData,
+}
+
+impl SpanData {
+    /// Finds this span in one of `sources`.
+    fn find<E: de::Error>(self, sources: &[Arc<SourceFile>]) -> Result<Span, E> {
+        let source = sources
+            .iter()
+            .find(|source| *source.id() == self.file)
+            .ok_or_else(|| E::custom(format!("no source file named {}", self.file)))?;
+        let RangeData { start, end } = self.range;
+        if start > end
+            || !source.contents.is_char_boundary(start)
+            || !source.contents.is_char_boundary(end)
+        {
+            return Err(E::custom(format!("invalid span range {start}..{end}")));
+        }
+        Ok(Span::new(start, end, Arc::clone(source)))
+    }
+}
+
+#[derive(Serialize)]
+struct LeafRef<'a, T> {
+    value: T,
+    text: &'a str,
+    span: &'a Span,
+}
+
+#[derive(Deserialize)]
+struct LeafData<T> {
+    value: T,
+    text: String,
+    span: SpanData,
+}
+
+impl<T> LeafData<T> {
+    /// Returns the value of this token, and a span covering its text in a new
+    /// source file named after the original.
+    fn into_parts(self) -> (T, Span) {
+        let len = self.text.len();
+        let source = Arc::new(SourceFile::new(self.span.file, self.text));
+        (self.value, Span::new(0, len, source))
+    }
+}
+
+/// Implements `Serialize` and `Deserialize` for a token storing its value in
+/// `$field`.
+macro_rules! leaf {
+    ( $t:ident, $field:ident : $value:ty ) => {
+        /// Tokens are serialized with their value, their text, and their span.
+        ///
+        /// A deserialized token's span covers its text in a new source file
+        /// with the name of the original, as the rest of the original file
+        /// isn't included.
+        impl Serialize for $t {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                LeafRef {
+                    value: &self.$field,
+                    text: self.span.source_text(),
+                    span: &self.span,
+                }
+                .serialize(serializer)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $t {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let ($field, span) = LeafData::<$value>::deserialize(deserializer)?.into_parts();
+                Ok($t { $field, span })
+            }
+        }
+    };
+}
+
+leaf!(LitStrDoubleQuote, string: String);
+leaf!(LitStrSingleQuote, string: String);
+leaf!(LitChar, ch: char);
+leaf!(LitInt, value: u64);
+leaf!(LitFloat, value: f64);
+
+/// Identifiers are serialized with their string, their text, and their span.
+///
+/// A deserialized identifier's span covers its text in a new source file with
+/// the name of the original, as the rest of the original file isn't included.
+impl Serialize for Ident {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        LeafRef {
+            value: self.string(),
+            text: self.span.source_text(),
+            span: &self.span,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Ident {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (string, span) = LeafData::<String>::deserialize(deserializer)?.into_parts();
+        Ok(Ident::new(&string, span))
+    }
+}
+
+#[derive(Serialize)]
+struct GroupRef<'a> {
+    open: &'a Span,
+    close: &'a Span,
+    tokens: &'a TokenStream,
+}
+
+#[derive(Deserialize)]
+struct GroupData {
+    open: SpanData,
+    close: SpanData,
+    tokens: TokenStream,
+}
+
+/// Groups are serialized with the spans of their delimiters, and the stream of
+/// tokens between them.
+///
+/// The delimiters are found again in the source files of the stream when
+/// deserializing.
+impl<D: Delimiters> Serialize for Group<D> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        GroupRef {
+            open: &self.open,
+            close: &self.close,
+            tokens: &self.token_stream,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de, D: Delimiters> Deserialize<'de> for Group<D> {
+    fn deserialize<De: Deserializer<'de>>(deserializer: De) -> Result<Self, De::Error> {
+        let data = GroupData::deserialize(deserializer)?;
+        let tokens = data.tokens;
+        let sources: Vec<_> = tokens
+            .sources
+            .iter()
+            .chain(iter::once(&tokens.source))
+            .cloned()
+            .collect();
+        let open = data.open.find(&sources)?;
+        let close = data.close.find(&sources)?;
+        if open.source != close.source {
+            return Err(de::Error::custom(
+                "group delimiters are in different source files",
+            ));
+        }
+        Ok(Group {
+            span: Span::across(&open, &close),
+            token_stream: tokens,
+            open,
+            close,
+            _marker: PhantomData,
+        })
+    }
+}
@@ -0,0 +1,362 @@
+//! Traversal of syntax trees.
+//!
+//! A [`Visitor`] is given a reference to each node of a syntax tree in turn,
+//! and a [`Folder`] is given ownership of each node and returns its
+//! replacement. The nodes of a tree implement [`Visit`] and [`Fold`], which
+//! can be derived with the `derive` feature enabled.
+//!
+//! Visitors and folders receive every node through a single generic method,
+//! and can pick out the types they are interested in with
+//! [`Visit::downcast_ref`], or with [`cast`] and [`map_as`] when folding.
+//! Unlike a visitor trait with a method for each type of node, this works for
+//! node types defined outside this crate, which a derive can't add methods
+//! for. Tokens, and derived types without lifetime parameters, can be picked
+//! out this way. Other nodes, such as ones that borrow from their input, are
+//! still traversed, but their types can't be checked.
+//!
+//! Tokens, and other values with no nodes inside them, are leaves of the tree.
+//! [`Box`], [`Vec`], [`Option`] and [`Recover`] are transparent, passing the
+//! values they contain to the visitor rather than themselves.
+//!
+//! # Example
+//! ```
+//! # #[cfg(feature = "derive")] {
+//! # use flexi_parse::parse_string;
+//! # use flexi_parse::token::LitInt;
+//! # use flexi_parse::visit::Visit;
+//! # use flexi_parse::visit::Visitor;
+//! # use flexi_parse::Parse;
+//! # use flexi_parse::Punct;
+//! #[derive(Parse, Visit)]
+//! struct Sum {
+//!     left: LitInt,
+//!     plus: Punct!["+"],
+//!     right: LitInt,
+//! }
+//!
+//! struct Total(u64);
+//!
+//! impl Visitor for Total {
+//!     fn visit<T: Visit>(&mut self, node: &T) {
+//!         if let Some(lit) = node.downcast_ref::<LitInt>() {
+//!             self.0 += lit.value();
+//!         }
+//!         node.visit_children(self);
+//!     }
+//! }
+//!
+//! let sum: Sum = parse_string("1 + 2".to_string()).unwrap();
+//! let mut total = Total(0);
+//! sum.visit_with(&mut total);
+//! assert_eq!(total.0, 3);
+//! # }
+//! ```
+
+use crate::recover::Recover;
+use crate::Span;
+
+use std::any::Any;
+
+#[cfg(feature = "derive")]
+pub use flexi_parse_derive::Fold;
+#[cfg(feature = "derive")]
+pub use flexi_parse_derive::Visit;
+
+/// Syntax tree nodes that can be traversed by a [`Visitor`].
+///
+/// With the `derive` feature enabled, this trait can be derived for structs
+/// and enums whose fields all implement it. The derived implementation visits
+/// each field in the order they are declared. If the type has no lifetime
+/// parameters, the derived implementation also implements [`Visit::as_any`],
+/// and requires each type parameter to be `'static`.
+pub trait Visit: Sized {
+    /// Passes each of the nodes directly inside `self` to `visitor`.
+    ///
+    /// The default implementation does nothing, as for a leaf.
+    fn visit_children<V: Visitor>(&self, visitor: &mut V) {
+        let _ = visitor;
+    }
+
+    /// Passes `self` to `visitor`.
+    ///
+    /// Containers override this to pass the values they contain instead.
+    fn visit_with<V: Visitor>(&self, visitor: &mut V) {
+        visitor.visit(self);
+    }
+
+    /// Returns `self` as [`Any`], so that its type can be checked, or `None`
+    /// if its type can't be checked.
+    ///
+    /// The default implementation returns `None`. Types that are `'static`
+    /// should return `Some(self)`.
+    fn as_any(&self) -> Option<&dyn Any> {
+        None
+    }
+
+    /// Returns `self` as a `U` if it is one.
+    fn downcast_ref<U: Any>(&self) -> Option<&U> {
+        self.as_any()?.downcast_ref()
+    }
+}
+
+/// A type that is given a reference to each node of a syntax tree.
+pub trait Visitor: Sized {
+    /// Visits `node`.
+    ///
+    /// The default implementation visits the children of `node`, and an
+    /// overriding implementation should usually do the same.
+    fn visit<T: Visit>(&mut self, node: &T) {
+        node.visit_children(self);
+    }
+}
+
+/// Syntax tree nodes that can be rebuilt by a [`Folder`].
+///
+/// With the `derive` feature enabled, this trait can be derived for structs
+/// and enums whose fields all implement it. The derived implementation folds
+/// each field in the order they are declared. If the type has no lifetime
+/// parameters, the derived implementation can be used with [`cast`], and
+/// requires each type parameter to be `'static`.
+pub trait Fold: Sized {
+    /// Replaces each of the nodes directly inside `self` with the result of
+    /// passing it to `folder`.
+    ///
+    /// The default implementation returns `self` unchanged, as for a leaf.
+    fn fold_children<F: Folder>(self, folder: &mut F) -> Self {
+        let _ = folder;
+        self
+    }
+
+    /// Passes `self` to `folder`, returning the replacement.
+    ///
+    /// Containers override this to pass the values they contain instead.
+    fn fold_with<F: Folder>(self, folder: &mut F) -> Self {
+        folder.fold(self)
+    }
+
+    /// Returns `slot` as [`Any`], so that [`cast`] can check the type of the
+    /// value in it, or `None` if its type can't be checked.
+    #[doc(hidden)]
+    fn any_slot(slot: &mut Option<Self>) -> Option<&mut dyn Any> {
+        let _ = slot;
+        None
+    }
+
+    /// Takes the value out of `slot` if it holds an `Option<Self>`.
+    #[doc(hidden)]
+    fn take_slot(slot: &mut dyn Any) -> Option<Self> {
+        let _ = slot;
+        None
+    }
+}
+
+/// A type that is given ownership of each node of a syntax tree, and returns
+/// its replacement.
+pub trait Folder: Sized {
+    /// Folds `node`.
+    ///
+    /// The default implementation folds the children of `node`, and an
+    /// overriding implementation should usually do the same.
+    fn fold<T: Fold>(&mut self, node: T) -> T {
+        node.fold_children(self)
+    }
+}
+
+/// Converts `value` to a `U` if it is one, or returns it unchanged if not.
+///
+/// Values whose types can't be checked, such as ones with lifetime parameters,
+/// are always returned unchanged.
+///
+/// ## Example
+/// ```
+/// # use flexi_parse::parse_string;
+/// # use flexi_parse::token::Ident;
+/// # use flexi_parse::token::LitInt;
+/// # use flexi_parse::visit::cast;
+/// let lit: LitInt = parse_string("1".to_string()).unwrap();
+/// let lit = cast::<LitInt, Ident>(lit).unwrap_err();
+/// assert_eq!(cast::<LitInt, LitInt>(lit).unwrap().value(), 1);
+/// ```
+pub fn cast<T: Fold, U: Fold>(value: T) -> Result<U, T> {
+    let mut value = Some(value);
+    match T::any_slot(&mut value).and_then(U::take_slot) {
+        Some(cast) => Ok(cast),
+        None => Err(value.unwrap()),
+    }
+}
+
+/// Replaces `value` with the result of passing it to `function` if it is a
+/// `U`, or returns it unchanged as an error if not.
+///
+/// This allows a [`Folder`] to replace nodes of a particular type:
+/// ```
+/// # use flexi_parse::token::LitInt;
+/// # use flexi_parse::visit::map_as;
+/// # use flexi_parse::visit::Fold;
+/// # use flexi_parse::visit::Folder;
+/// struct Negate;
+///
+/// impl Negate {
+///     fn negate(&mut self, lit: LitInt) -> LitInt {
+///         // ...
+/// #       lit
+///     }
+/// }
+///
+/// impl Folder for Negate {
+///     fn fold<T: Fold>(&mut self, node: T) -> T {
+///         map_as(node, |lit: LitInt| self.negate(lit))
+///             .unwrap_or_else(|node| node.fold_children(self))
+///     }
+/// }
+/// ```
+pub fn map_as<T: Fold, U: Fold, F: FnOnce(U) -> U>(value: T, function: F) -> Result<T, T> {
+    let value = cast::<T, U>(value)?;
+    let mut value = Some(function(value));
+    // `T` and `U` are the same type, so this always succeeds.
+    Ok(U::any_slot(&mut value)
+        .and_then(T::take_slot)
+        .expect("`T` is not `U`"))
+}
+
+impl<T: Visit> Visit for Box<T> {
+    fn visit_children<V: Visitor>(&self, visitor: &mut V) {
+        (**self).visit_children(visitor);
+    }
+
+    fn visit_with<V: Visitor>(&self, visitor: &mut V) {
+        (**self).visit_with(visitor);
+    }
+}
+
+impl<T: Fold> Fold for Box<T> {
+    fn fold_children<F: Folder>(self, folder: &mut F) -> Self {
+        Box::new((*self).fold_children(folder))
+    }
+
+    fn fold_with<F: Folder>(self, folder: &mut F) -> Self {
+        Box::new((*self).fold_with(folder))
+    }
+}
+
+impl<T: Visit> Visit for Vec<T> {
+    fn visit_children<V: Visitor>(&self, visitor: &mut V) {
+        self.visit_with(visitor);
+    }
+
+    fn visit_with<V: Visitor>(&self, visitor: &mut V) {
+        for value in self {
+            value.visit_with(visitor);
+        }
+    }
+}
+
+impl<T: Fold> Fold for Vec<T> {
+    fn fold_children<F: Folder>(self, folder: &mut F) -> Self {
+        self.fold_with(folder)
+    }
+
+    fn fold_with<F: Folder>(self, folder: &mut F) -> Self {
+        self.into_iter()
+            .map(|value| value.fold_with(folder))
+            .collect()
+    }
+}
+
+impl<T: Visit> Visit for Option<T> {
+    fn visit_children<V: Visitor>(&self, visitor: &mut V) {
+        self.visit_with(visitor);
+    }
+
+    fn visit_with<V: Visitor>(&self, visitor: &mut V) {
+        if let Some(value) = self {
+            value.visit_with(visitor);
+        }
+    }
+}
+
+impl<T: Fold> Fold for Option<T> {
+    fn fold_children<F: Folder>(self, folder: &mut F) -> Self {
+        self.fold_with(folder)
+    }
+
+    fn fold_with<F: Folder>(self, folder: &mut F) -> Self {
+        self.map(|value| value.fold_with(folder))
+    }
+}
+
+impl<T: Visit> Visit for Recover<T> {
+    fn visit_children<V: Visitor>(&self, visitor: &mut V) {
+        self.visit_with(visitor);
+    }
+
+    fn visit_with<V: Visitor>(&self, visitor: &mut V) {
+        if let Recover::Ok(value) = self {
+            value.visit_with(visitor);
+        }
+    }
+}
+
+impl<T: Fold> Fold for Recover<T> {
+    fn fold_children<F: Folder>(self, folder: &mut F) -> Self {
+        self.fold_with(folder)
+    }
+
+    fn fold_with<F: Folder>(self, folder: &mut F) -> Self {
+        match self {
+            Recover::Ok(value) => Recover::Ok(value.fold_with(folder)),
+            Recover::Error(span) => Recover::Error(span),
+        }
+    }
+}
+
+/// Implements [`Visit::as_any`] for a `'static` type.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __visit_any {
+    () => {
+        fn as_any(&self) -> ::std::option::Option<&dyn ::std::any::Any> {
+            ::std::option::Option::Some(self)
+        }
+    };
+}
+
+/// Implements the hooks [`cast`] uses for a `'static` type.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __fold_any {
+    () => {
+        fn any_slot(
+            slot: &mut ::std::option::Option<Self>,
+        ) -> ::std::option::Option<&mut dyn ::std::any::Any> {
+            ::std::option::Option::Some(slot)
+        }
+
+        fn take_slot(slot: &mut dyn ::std::any::Any) -> ::std::option::Option<Self> {
+            slot.downcast_mut::<::std::option::Option<Self>>()?.take()
+        }
+    };
+}
+
+/// Implements [`Visit`] and [`Fold`] for types that are leaves of a syntax
+/// tree.
+macro_rules! leaves {
+    [ $( $t:ty ),+ $(,)? ] => {
+        $(
+            impl $crate::visit::Visit for $t {
+                $crate::__visit_any!();
+            }
+
+            impl $crate::visit::Fold for $t {
+                $crate::__fold_any!();
+            }
+        )+
+    };
+}
+
+pub(crate) use leaves;
+
+leaves![
+    Span, String, bool, char, f32, f64, i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128,
+    usize
+];